@@ -0,0 +1,243 @@
+//!
+//! Request logging in the Apache Common and Combined Log Formats.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::access_log::AccessLog;
+//! use http_rs::router::Router;
+//! use std::fs::OpenOptions;
+//!
+//! let mut router = Router::new();
+//!
+//! // Common Log Format to stdout
+//! router.layer(AccessLog::new(std::io::stdout()));
+//!
+//! // Combined Log Format, with latency, appended to a file
+//! let file = OpenOptions::new().create(true).append(true).open("access.log").unwrap();
+//! router.layer(AccessLog::new(file).combined().latency(true));
+//! ```
+//!
+
+use crate::body::Body;
+use crate::date;
+use crate::middleware::{Middleware, Next};
+use crate::server::{HttpMethod, Request, Response};
+use std::{
+    io::Write,
+    sync::Mutex,
+    time::{Instant, SystemTime},
+};
+
+///
+/// Line layouts supported by [AccessLog].
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    ///
+    /// `host ident user [date] "request" status bytes`
+    ///
+    Common,
+
+    ///
+    /// [LogFormat::Common] followed by `"referer" "user-agent"`
+    ///
+    Combined,
+}
+
+///
+/// [Middleware] writing one line per request to a `Write` sink.
+///
+/// Fields that are unknown are logged as `-`, as are streamed bodies whose size isn't
+/// known upfront. Install it as the first layer so it times and logs the whole chain.
+///
+pub struct AccessLog {
+    sink: Mutex<Box<dyn Write + Send>>,
+    format: LogFormat,
+    latency: bool,
+}
+
+impl AccessLog {
+    ///
+    /// Creates the logging [Middleware] writing [LogFormat::Common] lines to `sink`.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` -> Where lines are written (e.g., stdout or a file opened for appending)
+    ///
+    pub fn new<W: Write + Send + 'static>(sink: W) -> AccessLog {
+        AccessLog {
+            sink: Mutex::new(Box::new(sink)),
+            format: LogFormat::Common,
+            latency: false,
+        }
+    }
+
+    ///
+    /// Switches to [LogFormat::Combined].
+    ///
+    pub fn combined(self) -> AccessLog {
+        self.format(LogFormat::Combined)
+    }
+
+    ///
+    /// Sets the [LogFormat] of the lines.
+    ///
+    pub fn format(mut self, format: LogFormat) -> AccessLog {
+        self.format = format;
+
+        self
+    }
+
+    ///
+    /// Appends the time taken to handle the request, in microseconds (as Apache's `%D`),
+    /// to every line.
+    ///
+    pub fn latency(mut self, latency: bool) -> AccessLog {
+        self.latency = latency;
+
+        self
+    }
+
+    ///
+    /// Formats the log line of a handled request, without the trailing newline.
+    ///
+    fn line(&self, entry: &Entry, res: &Response, micros: u128) -> String {
+        let bytes = match &res.body {
+            _ if entry.method == HttpMethod::HEAD => "-".to_string(),
+            Body::Full(bytes) if !bytes.is_empty() => bytes.len().to_string(),
+            Body::File { len, .. } if *len > 0 => len.to_string(),
+            _ => "-".to_string(),
+        };
+
+        let mut line = format!(
+            "{} - {} [{}] \"{}\" {} {}",
+            entry.host, entry.user, entry.time, entry.request_line, res.status, bytes
+        );
+
+        if self.format == LogFormat::Combined {
+            line.push_str(&format!(" \"{}\" \"{}\"", entry.referer, entry.user_agent));
+        }
+
+        if self.latency {
+            line.push_str(&format!(" {}", micros));
+        }
+
+        line
+    }
+}
+
+///
+/// Request fields captured before the [Request] is handed down the chain.
+///
+struct Entry {
+    method: HttpMethod,
+    host: String,
+    user: String,
+    time: String,
+    request_line: String,
+    referer: String,
+    user_agent: String,
+}
+
+impl Entry {
+    fn new(req: &Request) -> Entry {
+        let header = |name| req.headers.get(name).map_or("-".to_string(), escape);
+
+        Entry {
+            method: req.method.clone(),
+            host: req.client_ip().map_or("-".to_string(), |ip| ip.to_string()),
+            user: req
+                .credentials()
+                .and_then(|c| c.username().map(escape))
+                .filter(|user| !user.is_empty())
+                .unwrap_or_else(|| "-".to_string()),
+            time: date::clf(SystemTime::now()),
+            request_line: escape(&format!("{} {} {}", req.method, req.target, req.version)),
+            referer: header("Referer"),
+            user_agent: header("User-Agent"),
+        }
+    }
+}
+
+impl Middleware for AccessLog {
+    fn handle(&self, req: Request, next: Next<'_>) -> Response {
+        let entry = Entry::new(&req);
+        let start = Instant::now();
+
+        let res = next.run(req);
+        let line = self.line(&entry, &res, start.elapsed().as_micros());
+
+        let mut sink = self.sink.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = writeln!(sink, "{}", line);
+
+        res
+    }
+}
+
+///
+/// Escapes quotes, backslashes and control characters so a client can't break out of
+/// a quoted field or forge log lines.
+///
+fn escape(value: &str) -> String {
+    value
+        .chars()
+        .flat_map(|c| match c {
+            '"' | '\\' => vec!['\\', c],
+            c if c.is_control() => format!("\\x{:02x}", c as u32).chars().collect(),
+            c => vec![c],
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::router::Router;
+    use crate::server::test_request;
+    use std::sync::Arc;
+
+    ///
+    /// Sink whose contents stay readable after being moved into the middleware.
+    ///
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_access_log_combined() {
+        let sink = Shared::default();
+        let mut router = Router::new();
+
+        router
+            .layer(AccessLog::new(sink.clone()).combined())
+            .get("/users", |_| Response::new(200).text("hello"));
+
+        let mut req = test_request(HttpMethod::GET, "/users");
+        req.remote_addr = Some("127.0.0.1:4000".parse().unwrap());
+        req.headers.insert("User-Agent", "curl/8.0 \"x\"");
+
+        router.handle(req);
+        router.handle(test_request(HttpMethod::GET, "/missing"));
+
+        let log = String::from_utf8(sink.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<_> = log.lines().collect();
+
+        let (prefix, rest) = lines[0].split_once(" [").unwrap();
+        let (_, rest) = rest.split_once("] ").unwrap();
+
+        assert_eq!(prefix, "127.0.0.1 - -");
+        assert_eq!(rest, r#""GET /users HTTP/1.1" 200 5 "-" "curl/8.0 \"x\"""#);
+        assert!(lines[1].starts_with("- - - ["));
+        assert!(lines[1].contains("\"GET /missing HTTP/1.1\" 404 "));
+    }
+}
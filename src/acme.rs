@@ -0,0 +1,961 @@
+//!
+//! ACME (RFC 8555) client obtaining and renewing certificates from Let's Encrypt or any
+//! other ACME certificate authority, enabled with the `acme` feature.
+//!
+//! Domains are validated with the HTTP-01 challenge, answered by [Acme::challenges] on a
+//! plain HTTP server listening on port 80. The account key, certificate and private key
+//! are kept in a cache directory, so restarts reuse them instead of ordering new ones.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::acme::Acme;
+//! use http_rs::https_redirect::HttpsRedirect;
+//! use http_rs::router::Router;
+//! use http_rs::server::{Response, Server};
+//! use std::{thread, time::Duration};
+//!
+//! let acme = Acme::new(["example.com", "www.example.com"], "/var/lib/example/acme")
+//!     .contact("admin@example.com");
+//!
+//! // Port 80 answers the challenges and redirects everything else to HTTPS
+//! let mut http = Router::new();
+//! http.layer(acme.challenges());
+//! http.layer(HttpsRedirect::new());
+//! thread::spawn(move || Server::new("0.0.0.0:80").unwrap().serve(http));
+//!
+//! let (cert_path, key_path) = acme.obtain().unwrap();
+//! let server = Server::new_tls("0.0.0.0:443", cert_path, key_path).unwrap();
+//!
+//! // Checks twice a day, renewing once a third of the certificate's lifetime is left
+//! acme.renew(server.certificates(), Duration::from_secs(12 * 3600));
+//!
+//! server.serve(|_| Response::new(200).text("Hello, TLS!"));
+//! ```
+//!
+
+use crate::{
+    certificates::Certificates,
+    der, events,
+    header::HeaderMap,
+    middleware::{Middleware, Next},
+    server::{HttpMethod, Limits, Request, Response},
+    tls::tls_error,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ring::{
+    rand::SystemRandom,
+    signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING},
+};
+use rustls::{
+    crypto::ring as provider,
+    pki_types::{pem::PemObject, CertificateDer, ServerName},
+    ClientConfig, ClientConnection, RootCertStore, StreamOwned,
+};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufReader, Read, Write},
+    net::TcpStream,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock, RwLock},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+///
+/// Directory of the Let's Encrypt production environment.
+///
+pub const LETS_ENCRYPT: &str = "https://acme-v02.api.letsencrypt.org/directory";
+
+///
+/// Directory of the Let's Encrypt staging environment, with much higher rate limits but
+/// certificates browsers don't trust, to try a deployment out.
+///
+pub const LETS_ENCRYPT_STAGING: &str = "https://acme-staging-v02.api.letsencrypt.org/directory";
+
+///
+/// Path prefix HTTP-01 challenges are fetched from by the certificate authority.
+///
+const CHALLENGE_PATH: &str = "/.well-known/acme-challenge/";
+
+///
+/// How many times an authorization or order is polled before giving up.
+///
+const POLL_ATTEMPTS: u32 = 30;
+
+///
+/// ACME client for a set of domains, see the [module](self) documentation.
+///
+/// Cheap to clone, clones share the pending challenges answered by
+/// [Acme::challenges].
+///
+#[derive(Debug, Clone)]
+pub struct Acme {
+    directory: String,
+    domains: Vec<String>,
+    contact: Vec<String>,
+    cache: PathBuf,
+
+    ///
+    /// Key authorizations by challenge token, while an order is validated
+    ///
+    tokens: Arc<RwLock<HashMap<String, String>>>,
+}
+
+impl Acme {
+    ///
+    /// Creates an ACME client for certificates covering the given domains, ordered from
+    /// Let's Encrypt.
+    ///
+    /// # Arguments
+    ///
+    /// * `domains` -> Domains the certificate is for, the first one being its subject
+    /// * `cache` -> Directory keeping the account key, certificate and private key,
+    ///   created if missing
+    ///
+    pub fn new<I, S>(domains: I, cache: impl Into<PathBuf>) -> Acme
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Acme {
+            directory: LETS_ENCRYPT.to_string(),
+            domains: domains.into_iter().map(Into::into).collect(),
+            contact: Vec::new(),
+            cache: cache.into(),
+            tokens: Arc::default(),
+        }
+    }
+
+    ///
+    /// Orders from another ACME directory, e.g. [LETS_ENCRYPT_STAGING].
+    ///
+    pub fn directory(mut self, url: &str) -> Acme {
+        self.directory = url.to_string();
+
+        self
+    }
+
+    ///
+    /// Adds an email address the certificate authority may contact about the account,
+    /// e.g. for expiration notices.
+    ///
+    pub fn contact(mut self, email: &str) -> Acme {
+        self.contact.push(format!("mailto:{}", email));
+
+        self
+    }
+
+    ///
+    /// Returns the [Middleware] answering the HTTP-01 challenges of pending orders,
+    /// to layer on the router served on port 80.
+    ///
+    pub fn challenges(&self) -> Challenges {
+        Challenges {
+            tokens: self.tokens.clone(),
+        }
+    }
+
+    ///
+    /// Returns the paths of the PEM certificate chain and private key in the cache.
+    ///
+    pub fn paths(&self) -> (PathBuf, PathBuf) {
+        (self.cache.join("cert.pem"), self.cache.join("key.pem"))
+    }
+
+    ///
+    /// Makes sure the cache holds a certificate that isn't due for renewal, ordering one
+    /// otherwise. Blocks until the order completes, which requires [Acme::challenges]
+    /// to be served on port 80 of every domain.
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<(PathBuf, PathBuf)>` -> The paths of the PEM certificate chain and
+    ///   private key, to give to [crate::server::Server::new_tls], or an [std::io] error
+    ///   if the order failed
+    ///
+    pub fn obtain(&self) -> io::Result<(PathBuf, PathBuf)> {
+        if self.due() {
+            self.order()?;
+        }
+
+        Ok(self.paths())
+    }
+
+    ///
+    /// Checks the certificate every `interval` on a background thread, ordering a new
+    /// one once it is due for renewal and reloading the given [Certificates] with it.
+    ///
+    /// Failed orders are reported through the `tracing` or `log` features and retried
+    /// on the next check.
+    ///
+    /// # Arguments
+    ///
+    /// * `certificates` -> Certificates serving the cached files, see
+    ///   [crate::server::Server::certificates]
+    /// * `interval` -> Time between checks
+    ///
+    pub fn renew(&self, certificates: Vec<Certificates>, interval: Duration) {
+        let acme = self.clone();
+
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+
+            if !acme.due() {
+                continue;
+            }
+
+            let renewed = acme.order().and_then(|_| {
+                certificates
+                    .iter()
+                    .try_for_each(|certificates| certificates.reload())
+            });
+
+            if let Err(e) = renewed {
+                events::acme_order_failed(&e);
+            }
+        });
+    }
+
+    ///
+    /// Whether the cached certificate is missing, unreadable, or past two thirds of its
+    /// lifetime.
+    ///
+    fn due(&self) -> bool {
+        let (cert_path, _) = self.paths();
+
+        let Ok(Some(Ok(cert))) = CertificateDer::pem_file_iter(&cert_path).map(|mut c| c.next())
+        else {
+            return true;
+        };
+
+        let Some(cert) = der::Certificate::parse(&cert) else {
+            return true;
+        };
+
+        let lifetime = cert
+            .not_after
+            .duration_since(cert.not_before)
+            .unwrap_or_default();
+
+        SystemTime::now() >= cert.not_before + lifetime * 2 / 3
+    }
+
+    ///
+    /// Orders a certificate for the domains, answering their challenges, and writes it
+    /// along with its new private key to the cache.
+    ///
+    fn order(&self) -> io::Result<()> {
+        fs::create_dir_all(&self.cache)?;
+
+        let mut account = Account::new(&self.directory, self.account_key()?)?;
+
+        account.post(
+            "newAccount",
+            Some(json!({ "termsOfServiceAgreed": true, "contact": self.contact })),
+        )?;
+
+        let identifiers: Vec<Value> = self
+            .domains
+            .iter()
+            .map(|domain| json!({ "type": "dns", "value": domain }))
+            .collect();
+
+        let (order, location) =
+            account.post("newOrder", Some(json!({ "identifiers": identifiers })))?;
+        let location = location.ok_or_else(|| acme_error("order without a location"))?;
+
+        let authorizations = order["authorizations"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+
+        for authorization in authorizations.iter().filter_map(Value::as_str) {
+            self.authorize(&mut account, authorization)?;
+        }
+
+        let key = rcgen::KeyPair::generate().map_err(tls_error)?;
+        let mut params = rcgen::CertificateParams::new(self.domains.clone()).map_err(tls_error)?;
+        params.distinguished_name = rcgen::DistinguishedName::new();
+        let csr = params.serialize_request(&key).map_err(tls_error)?;
+
+        let finalize = order["finalize"]
+            .as_str()
+            .ok_or_else(|| acme_error("order without a finalize URL"))?;
+        account.post(
+            finalize,
+            Some(json!({ "csr": URL_SAFE_NO_PAD.encode(csr.der()) })),
+        )?;
+
+        let order = account.poll(&location, &["ready", "processing"])?;
+        let certificate = order["certificate"]
+            .as_str()
+            .ok_or_else(|| acme_error("order without a certificate"))?;
+        let chain = account.fetch(certificate)?;
+
+        let (cert_path, key_path) = self.paths();
+
+        // The new key is in place before the certificate that goes with it
+        write_atomic(&key_path, key.serialize_pem().as_bytes())?;
+        write_atomic(&cert_path, &chain)
+    }
+
+    ///
+    /// Answers the HTTP-01 challenge of an authorization and waits for it to be valid.
+    ///
+    fn authorize(&self, account: &mut Account, url: &str) -> io::Result<()> {
+        let (authorization, _) = account.post(url, None)?;
+
+        if authorization["status"] == "valid" {
+            return Ok(());
+        }
+
+        let challenge = authorization["challenges"]
+            .as_array()
+            .and_then(|challenges| challenges.iter().find(|c| c["type"] == "http-01"))
+            .ok_or_else(|| acme_error("no http-01 challenge offered"))?;
+
+        let (Some(token), Some(challenge_url)) =
+            (challenge["token"].as_str(), challenge["url"].as_str())
+        else {
+            return Err(acme_error("malformed http-01 challenge"));
+        };
+
+        let key_authorization = format!("{}.{}", token, account.thumbprint());
+
+        self.tokens
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(token.to_string(), key_authorization);
+
+        let validated = account
+            .post(challenge_url, Some(json!({})))
+            .and_then(|_| account.poll(url, &["pending"]));
+
+        self.tokens
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(token);
+
+        validated.map(|_| ())
+    }
+
+    ///
+    /// Loads the account key from the cache, generating and storing one the first time.
+    ///
+    fn account_key(&self) -> io::Result<EcdsaKeyPair> {
+        let path = self.cache.join("account.key");
+        let rng = SystemRandom::new();
+
+        let pkcs8 = match fs::read(&path) {
+            Ok(pkcs8) => pkcs8,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+                    .map_err(tls_error)?;
+                write_atomic(&path, pkcs8.as_ref())?;
+
+                pkcs8.as_ref().to_vec()
+            }
+            Err(e) => return Err(e),
+        };
+
+        EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &pkcs8, &rng).map_err(tls_error)
+    }
+}
+
+///
+/// [Middleware] answering the HTTP-01 challenges of an [Acme] client, created with
+/// [Acme::challenges].
+///
+pub struct Challenges {
+    tokens: Arc<RwLock<HashMap<String, String>>>,
+}
+
+impl Middleware for Challenges {
+    fn handle(&self, req: Request, next: Next<'_>) -> Response {
+        let token = match req.route.strip_prefix(CHALLENGE_PATH) {
+            Some(token) if req.method == HttpMethod::GET => token,
+            _ => return next.run(req),
+        };
+
+        let tokens = self.tokens.read().unwrap_or_else(|e| e.into_inner());
+
+        match tokens.get(token) {
+            Some(key_authorization) => Response::new(200)
+                .header("Content-Type", "application/octet-stream")
+                .body(key_authorization.clone().into_bytes()),
+            None => Response::new(404),
+        }
+    }
+}
+
+///
+/// ACME account session: the directory, the account key and the replay nonce of the
+/// next request.
+///
+struct Account {
+    directory: Value,
+    key: EcdsaKeyPair,
+    kid: Option<String>,
+    nonce: Option<String>,
+}
+
+impl Account {
+    fn new(directory: &str, key: EcdsaKeyPair) -> io::Result<Account> {
+        let reply = request("GET", directory, None)?;
+
+        Ok(Account {
+            directory: reply.json()?,
+            key,
+            kid: None,
+            nonce: None,
+        })
+    }
+
+    ///
+    /// Public account key as a JSON Web Key, with its members in the lexicographic order
+    /// its thumbprint is computed over (RFC 7638).
+    ///
+    fn jwk(&self) -> String {
+        // Uncompressed point: 0x04, then both 32 byte coordinates
+        let point = self.key.public_key().as_ref();
+
+        format!(
+            r#"{{"crv":"P-256","kty":"EC","x":"{}","y":"{}"}}"#,
+            URL_SAFE_NO_PAD.encode(&point[1..33]),
+            URL_SAFE_NO_PAD.encode(&point[33..65])
+        )
+    }
+
+    ///
+    /// Thumbprint of the account key, included in every key authorization.
+    ///
+    fn thumbprint(&self) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(self.jwk()))
+    }
+
+    ///
+    /// Sends a JWS signed request, a POST-as-GET without `payload`, and returns the
+    /// JSON reply with its `Location`. `url` may name a directory resource instead
+    /// (e.g., `newOrder`).
+    ///
+    fn post(&mut self, url: &str, payload: Option<Value>) -> io::Result<(Value, Option<String>)> {
+        let reply = self.signed(url, payload)?;
+        let location = reply.headers.get("Location").map(str::to_string);
+
+        Ok((reply.json()?, location))
+    }
+
+    ///
+    /// Downloads a resource with a POST-as-GET, e.g. the certificate chain.
+    ///
+    fn fetch(&mut self, url: &str) -> io::Result<Vec<u8>> {
+        Ok(self.signed(url, None)?.body)
+    }
+
+    ///
+    /// Polls an authorization or order with POST-as-GET while its status is one of
+    /// `waiting`, returning it once `valid`.
+    ///
+    fn poll(&mut self, url: &str, waiting: &[&str]) -> io::Result<Value> {
+        for _ in 0..POLL_ATTEMPTS {
+            let reply = self.signed(url, None)?;
+            let retry_after = reply
+                .headers
+                .get("Retry-After")
+                .and_then(|secs| secs.trim().parse().ok())
+                .map_or(Duration::from_secs(2), |secs: u64| {
+                    Duration::from_secs(secs.min(30))
+                });
+            let resource = reply.json()?;
+
+            match resource["status"].as_str() {
+                Some("valid") => return Ok(resource),
+                Some(status) if waiting.contains(&status) => thread::sleep(retry_after),
+                _ => return Err(problem(&resource)),
+            }
+        }
+
+        Err(acme_error(&format!("{} still not valid", url)))
+    }
+
+    ///
+    /// Sends a JWS signed request, fetching a fresh nonce first if needed and retrying
+    /// once when the server rejects the nonce.
+    ///
+    fn signed(&mut self, url: &str, payload: Option<Value>) -> io::Result<Reply> {
+        let url = match self.directory[url].as_str() {
+            Some(resource) => resource.to_string(),
+            None => url.to_string(),
+        };
+
+        let mut retried = false;
+
+        loop {
+            let nonce = match self.nonce.take() {
+                Some(nonce) => nonce,
+                None => {
+                    let new_nonce = self.directory["newNonce"]
+                        .as_str()
+                        .ok_or_else(|| acme_error("directory without newNonce"))?;
+
+                    request("HEAD", new_nonce, None)?
+                        .headers
+                        .get("Replay-Nonce")
+                        .ok_or_else(|| acme_error("no nonce"))?
+                        .to_string()
+                }
+            };
+
+            let body = self.jws(&url, &nonce, payload.as_ref())?;
+            let reply = request("POST", &url, Some(body.as_bytes()))?;
+
+            self.nonce = reply.headers.get("Replay-Nonce").map(str::to_string);
+
+            if reply.status < 400 {
+                if self.kid.is_none() {
+                    self.kid = reply.headers.get("Location").map(str::to_string);
+                }
+
+                return Ok(reply);
+            }
+
+            let problem_document = reply.json().unwrap_or_default();
+
+            if problem_document["type"] == "urn:ietf:params:acme:error:badNonce" && !retried {
+                retried = true;
+                continue;
+            }
+
+            return Err(problem(&problem_document));
+        }
+    }
+
+    ///
+    /// Serializes a flattened JWS (RFC 7515) signed with ES256, identifying the account
+    /// by its key until it has a URL.
+    ///
+    fn jws(&self, url: &str, nonce: &str, payload: Option<&Value>) -> io::Result<String> {
+        let key = match &self.kid {
+            Some(kid) => format!(r#""kid":{}"#, json!(kid)),
+            None => format!(r#""jwk":{}"#, self.jwk()),
+        };
+
+        let protected = format!(
+            r#"{{"alg":"ES256",{},"nonce":{},"url":{}}}"#,
+            key,
+            json!(nonce),
+            json!(url)
+        );
+
+        let protected = URL_SAFE_NO_PAD.encode(protected);
+        let payload = payload.map_or(String::new(), |payload| {
+            URL_SAFE_NO_PAD.encode(payload.to_string())
+        });
+
+        let signature = self
+            .key
+            .sign(
+                &SystemRandom::new(),
+                format!("{}.{}", protected, payload).as_bytes(),
+            )
+            .map_err(tls_error)?;
+
+        Ok(json!({
+            "protected": protected,
+            "payload": payload,
+            "signature": URL_SAFE_NO_PAD.encode(signature.as_ref()),
+        })
+        .to_string())
+    }
+}
+
+///
+/// Response of the ACME server.
+///
+struct Reply {
+    status: u16,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl Reply {
+    fn json(&self) -> io::Result<Value> {
+        serde_json::from_slice(&self.body).map_err(io::Error::from)
+    }
+}
+
+///
+/// Sends an HTTP/1.1 request to the ACME server over a new connection.
+///
+/// `http` URLs are accepted too, for test ACME servers such as Pebble.
+///
+fn request(method: &str, url: &str, body: Option<&[u8]>) -> io::Result<Reply> {
+    let (tls, rest) = match url.split_once("://") {
+        Some(("https", rest)) => (true, rest),
+        Some(("http", rest)) => (false, rest),
+        _ => return Err(acme_error(&format!("unsupported URL {}", url))),
+    };
+
+    let (authority, path) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, "/"),
+    };
+
+    let host = match authority.rsplit_once(':') {
+        Some((host, port)) if port.bytes().all(|b| b.is_ascii_digit()) => host,
+        _ => authority,
+    };
+
+    let default_port = if tls { 443 } else { 80 };
+    let addr = if host == authority {
+        format!("{}:{}", authority, default_port)
+    } else {
+        authority.to_string()
+    };
+
+    let socket = TcpStream::connect(addr)?;
+    socket.set_read_timeout(Some(Duration::from_secs(30)))?;
+    socket.set_write_timeout(Some(Duration::from_secs(30)))?;
+
+    let mut stream: Box<dyn ReadWrite> = if tls {
+        let name =
+            ServerName::try_from(host.trim_matches(['[', ']']).to_string()).map_err(tls_error)?;
+        let conn = ClientConnection::new(client_config()?, name).map_err(tls_error)?;
+
+        Box::new(StreamOwned::new(conn, socket))
+    } else {
+        Box::new(socket)
+    };
+
+    let mut head = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: http_rs\r\nConnection: close\r\n",
+        method, path, authority
+    );
+
+    if let Some(body) = body {
+        head.push_str("Content-Type: application/jose+json\r\n");
+        head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+
+    head.push_str("\r\n");
+
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body.unwrap_or_default())?;
+    stream.flush()?;
+
+    let mut reader = BufReader::new(stream);
+    let limits = Limits::default();
+
+    let line = Request::read_line(
+        &mut reader,
+        limits.header_line,
+        crate::Error::HeaderTooLarge,
+    )
+    .map_err(io::Error::other)?;
+    let status = line
+        .split(' ')
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| acme_error("malformed status line"))?;
+
+    let mut headers = HeaderMap::new();
+
+    loop {
+        let line = Request::read_line(
+            &mut reader,
+            limits.header_line,
+            crate::Error::HeaderTooLarge,
+        )
+        .map_err(io::Error::other)?;
+
+        match line.split_once(':') {
+            Some((name, value)) => headers.append(name.trim(), value.trim()),
+            None => break,
+        }
+    }
+
+    let body = if method == "HEAD" {
+        Vec::new()
+    } else if headers
+        .get("Transfer-Encoding")
+        .is_some_and(crate::server::is_chunked)
+    {
+        Request::read_chunked(&mut reader, &limits)
+            .map_err(io::Error::other)?
+            .0
+    } else if let Some(len) = headers
+        .get("Content-Length")
+        .and_then(|len| len.parse().ok())
+    {
+        let mut body = vec![0; len];
+        reader.read_exact(&mut body)?;
+        body
+    } else {
+        let mut body = Vec::new();
+
+        // Servers closing the connection without a TLS close_notify end the body too
+        match reader.read_to_end(&mut body) {
+            Err(e) if e.kind() != io::ErrorKind::UnexpectedEof => return Err(e),
+            _ => body,
+        }
+    };
+
+    Ok(Reply {
+        status,
+        headers,
+        body,
+    })
+}
+
+trait ReadWrite: Read + Write {}
+
+impl<T: Read + Write> ReadWrite for T {}
+
+///
+/// TLS configuration trusting the Mozilla root certificates, shared by every request.
+///
+fn client_config() -> io::Result<Arc<ClientConfig>> {
+    static CONFIG: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+
+    if let Some(config) = CONFIG.get() {
+        return Ok(config.clone());
+    }
+
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+
+    let config = ClientConfig::builder_with_provider(Arc::new(provider::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(tls_error)?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    Ok(CONFIG.get_or_init(|| Arc::new(config)).clone())
+}
+
+///
+/// Writes a file through a temporary sibling, so readers never see it half written.
+///
+fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+
+    fs::write(&tmp, contents)?;
+    fs::rename(tmp, path)
+}
+
+fn acme_error(message: &str) -> io::Error {
+    io::Error::other(format!("ACME: {}", message))
+}
+
+///
+/// Converts an ACME problem document (RFC 7807), or a resource that became invalid, to
+/// an [std::io] error.
+///
+fn problem(document: &Value) -> io::Error {
+    let problem = match document.get("error") {
+        Some(error) => error,
+        None => document,
+    };
+
+    acme_error(&format!(
+        "{} ({})",
+        problem["detail"].as_str().unwrap_or("request failed"),
+        problem["type"].as_str().unwrap_or("unknown problem")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Account, Acme, CHALLENGE_PATH};
+    use crate::{
+        router::Router,
+        server::{test_request, HttpMethod, Request, Response, Server},
+    };
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use ring::{
+        rand::SystemRandom,
+        signature::{self, EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING},
+    };
+    use serde_json::{json, Value};
+    use std::{
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+    };
+
+    #[test]
+    fn test_acme_jws_and_challenges() {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+            .unwrap();
+        let public = key.public_key().as_ref().to_vec();
+
+        let account = Account {
+            directory: json!({}),
+            key,
+            kid: None,
+            nonce: None,
+        };
+
+        let jws: Value = serde_json::from_str(
+            &account
+                .jws("https://ca.test/new-acct", "abc", Some(&json!({"a": 1})))
+                .unwrap(),
+        )
+        .unwrap();
+
+        let field = |name: &str| URL_SAFE_NO_PAD.decode(jws[name].as_str().unwrap()).unwrap();
+        let protected: Value = serde_json::from_slice(&field("protected")).unwrap();
+
+        assert_eq!(protected["alg"], "ES256");
+        assert_eq!(protected["nonce"], "abc");
+        assert_eq!(protected["url"], "https://ca.test/new-acct");
+        assert_eq!(protected["jwk"]["kty"], "EC");
+        assert_eq!(field("payload"), br#"{"a":1}"#);
+
+        let signed = format!(
+            "{}.{}",
+            jws["protected"].as_str().unwrap(),
+            jws["payload"].as_str().unwrap()
+        );
+        signature::UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, &public)
+            .verify(signed.as_bytes(), &field("signature"))
+            .unwrap();
+
+        let acme = Acme::new(["example.com"], std::env::temp_dir());
+        acme.tokens.write().unwrap().insert(
+            "token".to_string(),
+            format!("token.{}", account.thumbprint()),
+        );
+
+        let mut router = Router::new();
+        router.layer(acme.challenges());
+        router.get("/", |_| Response::new(200));
+
+        let res = router.handle(test_request(
+            HttpMethod::GET,
+            &format!("{}token", CHALLENGE_PATH),
+        ));
+        assert_eq!(res.status, 200);
+        assert_eq!(
+            res.body.as_bytes().unwrap(),
+            format!("token.{}", account.thumbprint()).as_bytes()
+        );
+
+        let res = router.handle(test_request(
+            HttpMethod::GET,
+            &format!("{}other", CHALLENGE_PATH),
+        ));
+        assert_eq!(res.status, 404);
+
+        let res = router.handle(test_request(HttpMethod::GET, "/"));
+        assert_eq!(res.status, 200);
+    }
+
+    #[test]
+    fn test_acme_obtain_against_mock_directory() {
+        let server = Server::new("127.0.0.1:0").unwrap().workers(1);
+        let base = format!("http://{}", server.local_addr().unwrap());
+
+        let issued = rcgen::generate_simple_self_signed(vec!["example.com".to_string()]).unwrap();
+        let chain = issued.cert.pem();
+        let validated = Arc::new(AtomicBool::new(false));
+        let orders = Arc::new(AtomicUsize::new(0));
+
+        let reply = |body: Value| {
+            Response::new(200)
+                .header("Replay-Nonce", "nonce")
+                .json(&body)
+        };
+
+        let mut router = Router::new();
+        let url = base.clone();
+        router.get("/dir", move |_| {
+            Response::new(200).json(&json!({
+                "newNonce": format!("{}/nonce", url),
+                "newAccount": format!("{}/account", url),
+                "newOrder": format!("{}/order", url),
+            }))
+        });
+        router.get("/nonce", |_| {
+            Response::new(200).header("Replay-Nonce", "nonce")
+        });
+        let url = base.clone();
+        router.post("/account", move |_| {
+            reply(json!({ "status": "valid" })).header("Location", &format!("{}/account/1", url))
+        });
+        let (url, count) = (base.clone(), orders.clone());
+        router.post("/order", move |req: Request| {
+            let order: Value = serde_json::from_slice(&req.body).unwrap();
+            let payload = URL_SAFE_NO_PAD
+                .decode(order["payload"].as_str().unwrap())
+                .unwrap();
+            let payload: Value = serde_json::from_slice(&payload).unwrap();
+            assert_eq!(payload["identifiers"][0]["value"], "example.com");
+
+            count.fetch_add(1, Ordering::SeqCst);
+            reply(json!({
+                "status": "pending",
+                "authorizations": [format!("{}/authz", url)],
+                "finalize": format!("{}/finalize", url),
+            }))
+            .header("Location", &format!("{}/order/1", url))
+        });
+        let (url, done) = (base.clone(), validated.clone());
+        router.post("/authz", move |_| match done.load(Ordering::SeqCst) {
+            true => reply(json!({ "status": "valid" })),
+            false => reply(json!({
+                "status": "pending",
+                "challenges": [{ "type": "http-01", "url": format!("{}/challenge", url), "token": "token" }],
+            })),
+        });
+        let done = validated.clone();
+        router.post("/challenge", move |_| {
+            done.store(true, Ordering::SeqCst);
+            reply(json!({ "status": "processing" }))
+        });
+        router.post("/finalize", move |_| {
+            reply(json!({ "status": "processing" }))
+        });
+        let url = base.clone();
+        router.post("/order/1", move |_| {
+            reply(json!({ "status": "valid", "certificate": format!("{}/cert", url) }))
+        });
+        router.post("/cert", move |_| {
+            Response::new(200)
+                .header("Replay-Nonce", "nonce")
+                .text(&chain)
+        });
+
+        thread::spawn(move || server.serve(router));
+
+        let dir = std::env::temp_dir().join(format!("http_rs_acme_{}", std::process::id()));
+        let acme = Acme::new(["example.com"], &dir).directory(&format!("{}/dir", base));
+
+        let (cert_path, key_path) = acme.obtain().unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&cert_path).unwrap(),
+            issued.cert.pem()
+        );
+        assert!(std::fs::read_to_string(key_path)
+            .unwrap()
+            .contains("PRIVATE KEY"));
+        assert!(dir.join("account.key").exists());
+        assert!(acme.tokens.read().unwrap().is_empty());
+
+        // The cached certificate isn't due for renewal yet
+        acme.obtain().unwrap();
+        assert_eq!(orders.load(Ordering::SeqCst), 1);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,340 @@
+//!
+//! Non-blocking server backend built on tokio, enabled with the `async` feature.
+//!
+//! The sync API stays intact, this module only adds async entrypoints sharing the
+//! same parser and response formatting.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::router::Router;
+//! use http_rs::server::{Request, Response, Server};
+//! use std::sync::Arc;
+//! use tokio::io::BufReader;
+//!
+//! async fn run() -> std::io::Result<()> {
+//!     let server = Server::new("127.0.0.1:8080")?;
+//!     let listener = server.listen_async()?;
+//!
+//!     let mut router = Router::new();
+//!     router.get("/", |_| Response::new(200).json(&"Hello"));
+//!     let router = Arc::new(router);
+//!
+//!     loop {
+//!         let (stream, _) = listener.accept().await?;
+//!         let router = Arc::clone(&router);
+//!
+//!         tokio::spawn(async move {
+//!             let (read, mut write) = stream.into_split();
+//!             let mut read = BufReader::new(read);
+//!
+//!             if let Ok(req) = Request::new_async(&mut read).await {
+//!                 let _ = router.handle(req).send_async(&mut write).await;
+//!             }
+//!         });
+//!     }
+//! }
+//! ```
+//!
+
+use crate::body::{last_chunk, Body};
+use crate::error::Error;
+use crate::header::HeaderMap;
+use crate::server::{chunk_size, is_chunked, BodyFraming, Limits, Request, Response, Server};
+use std::{
+    io::{self, Read},
+    net::SocketAddr,
+};
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+///
+/// Size of the buffer used when copying a streamed body to the connection.
+///
+const CHUNK_SIZE: usize = 8 * 1024;
+
+///
+/// Async listener returned by [Server::listen_async].
+///
+pub struct AsyncListener {
+    listener: TcpListener,
+}
+
+impl AsyncListener {
+    ///
+    /// Waits for the next incoming connection.
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<(TcpStream, SocketAddr)>` -> The tokio [TcpStream] and peer address,
+    ///   or an [std::io] error
+    ///
+    pub async fn accept(&self) -> io::Result<(TcpStream, SocketAddr)> {
+        self.listener.accept().await
+    }
+}
+
+impl Server {
+    ///
+    /// Returns an [AsyncListener] accepting connections on the server's socket, the first
+    /// one if it is bound to several addresses.
+    ///
+    /// `👉 Note:` Must be called from within a tokio runtime, and switches the socket to
+    /// non-blocking mode so it should not be mixed with [Server::listen] or [Server::serve].
+    /// Connections are plain TCP, TLS is not applied.
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<AsyncListener>` -> The async listener or an [std::io] error
+    ///
+    pub fn listen_async(&self) -> io::Result<AsyncListener> {
+        let listener = self.listeners[0].socket.try_clone()?;
+        listener.set_nonblocking(true)?;
+
+        Ok(AsyncListener {
+            listener: TcpListener::from_std(listener)?,
+        })
+    }
+}
+
+impl Request {
+    ///
+    /// Asynchronously parses the next [Request] from a buffered reader.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` -> Buffered async reader positioned at the start of a [Request]
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Request, Error>` -> A Result containing the parsed [Request] or the [Error]
+    ///   making it invalid, an [Error::Io] of kind [io::ErrorKind::UnexpectedEof] if the
+    ///   peer closed the connection
+    ///
+    pub async fn new_async<R: AsyncBufRead + Unpin>(stream: &mut R) -> Result<Request, Error> {
+        // Collect the raw head, then hand it to the sync parser
+        let mut head = Vec::new();
+
+        loop {
+            let start = head.len();
+
+            if stream.read_until(b'\n', &mut head).await? == 0 {
+                return Err(Error::Io(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Connection closed",
+                )));
+            }
+
+            if matches!(&head[start..], b"\r\n" | b"\n") {
+                break;
+            }
+        }
+
+        let limits = Limits::default();
+        let mut req = Request::read_head(&mut &head[..], &limits, None)?;
+
+        req.body = match req.body_framing()? {
+            BodyFraming::Chunked => {
+                let (body, trailers) = read_chunked_async(stream, &limits).await?;
+                req.trailers = trailers;
+
+                body
+            }
+            BodyFraming::Length(content_length) if content_length > limits.body => {
+                return Err(Error::BodyTooLarge)
+            }
+            BodyFraming::Length(content_length) => {
+                let mut body = vec![0; content_length];
+                stream.read_exact(&mut body).await?;
+
+                body
+            }
+        };
+
+        Ok(req)
+    }
+}
+
+///
+/// Asynchronously decodes a `Transfer-Encoding: chunked` body, returning it with the
+/// fields of the trailer section.
+///
+async fn read_chunked_async<R: AsyncBufRead + Unpin>(
+    stream: &mut R,
+    limits: &Limits,
+) -> Result<(Vec<u8>, HeaderMap), Error> {
+    let mut body = Vec::new();
+
+    loop {
+        let size = chunk_size(&read_line_async(stream).await?)?;
+
+        if size == 0 {
+            break;
+        }
+
+        if size > limits.body - body.len() {
+            return Err(Error::BodyTooLarge);
+        }
+
+        let start = body.len();
+        body.resize(start + size, 0);
+        stream.read_exact(&mut body[start..]).await?;
+
+        if !read_line_async(stream).await?.is_empty() {
+            return Err(Error::BadChunkedEncoding);
+        }
+    }
+
+    // Collect the raw trailer section, then hand it to the sync parser
+    let mut section = Vec::new();
+
+    loop {
+        let start = section.len();
+
+        if stream.read_until(b'\n', &mut section).await? == 0 {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Connection closed",
+            )));
+        }
+
+        if matches!(&section[start..], b"\r\n" | b"\n") {
+            break;
+        }
+    }
+
+    Ok((body, Request::read_trailers(&mut &section[..], limits)?))
+}
+
+///
+/// Asynchronously reads a single trimmed line.
+///
+async fn read_line_async<R: AsyncBufRead + Unpin>(stream: &mut R) -> io::Result<String> {
+    let mut line = String::new();
+
+    if stream.read_line(&mut line).await? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Connection closed",
+        ));
+    }
+
+    Ok(line.trim().to_string())
+}
+
+impl Response {
+    ///
+    /// Asynchronously sends the [Response] over an async writer.
+    ///
+    /// `👉 Note:` Streamed bodies are read from their (blocking) source one chunk at a
+    /// time between async writes
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` -> The async writer to send the response to
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<()>` -> Ok if the response was sent successfully or an [std::io] error
+    ///
+    pub async fn send_async<W: AsyncWrite + Unpin>(self, stream: &mut W) -> io::Result<()> {
+        let chunked = self
+            .headers
+            .get("Transfer-Encoding")
+            .is_some_and(is_chunked);
+
+        let mut head = Vec::new();
+        self.write_head(&mut head);
+        stream.write_all(&head).await?;
+
+        let mut reader: Box<dyn Read + Send> = match self.body {
+            Body::Full(bytes) => {
+                stream.write_all(&bytes).await?;
+
+                return stream.flush().await;
+            }
+            // Chunk-encoded below if `chunked`, copied as is otherwise (e.g., files)
+            body => body.into_reader(),
+        };
+
+        let mut buf = vec![0; CHUNK_SIZE];
+
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+
+            if !chunked {
+                stream.write_all(&buf[..n]).await?;
+                continue;
+            }
+
+            stream.write_all(format!("{:x}\r\n", n).as_bytes()).await?;
+            stream.write_all(&buf[..n]).await?;
+            stream.write_all(b"\r\n").await?;
+            stream.flush().await?;
+        }
+
+        if chunked {
+            stream.write_all(&last_chunk(self.trailers)).await?;
+        }
+
+        stream.flush().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::HttpMethod;
+    use tokio::io::BufReader;
+
+    #[tokio::test]
+    async fn test_async_round_trip() {
+        let server = Server::new("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let listener = server.listen_async().unwrap();
+
+        let client = tokio::spawn(async move {
+            let mut client = TcpStream::connect(addr).await.unwrap();
+
+            client
+                .write_all(b"POST /echo HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nhi\r\n0\r\n\r\n")
+                .await
+                .unwrap();
+
+            let mut raw = String::new();
+            client.read_to_string(&mut raw).await.unwrap();
+
+            raw
+        });
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let (read, mut write) = stream.into_split();
+        let mut read = BufReader::new(read);
+
+        let req = Request::new_async(&mut read).await.unwrap();
+
+        assert_eq!(req.method, HttpMethod::POST);
+        assert_eq!(req.body, b"hi");
+
+        Response::new(200)
+            .json(&String::from_utf8(req.body).unwrap())
+            .send_async(&mut write)
+            .await
+            .unwrap();
+
+        drop(write);
+        drop(read);
+
+        let raw = client.await.unwrap();
+
+        assert!(raw.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(raw.ends_with("\"hi\""));
+    }
+}
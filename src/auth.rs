@@ -0,0 +1,316 @@
+//!
+//! HTTP Basic and Bearer authentication.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::auth::{Auth, Credentials};
+//! use http_rs::router::Router;
+//! use http_rs::server::{Request, Response};
+//!
+//! let mut router = Router::new();
+//!
+//! router.layer(Auth::basic("admin", |user, password| {
+//!     user == "admin" && password == "hunter2"
+//! }));
+//!
+//! router.get("/dashboard", |req: Request| {
+//!     let user = req.extensions().get::<Credentials>().unwrap().username();
+//!
+//!     Response::new(200).json(&format!("Welcome {}", user.unwrap_or("")))
+//! });
+//! ```
+//!
+
+use crate::middleware::{Middleware, Next};
+use crate::server::{Request, Response};
+use crate::status::StatusCode;
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+///
+/// Credentials carried in the `Authorization` header of a [Request].
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum Credentials {
+    ///
+    /// `Basic` scheme, a base64 encoded `username:password` pair
+    ///
+    Basic { username: String, password: String },
+
+    ///
+    /// `Bearer` scheme, an opaque token
+    ///
+    Bearer(String),
+}
+
+impl Credentials {
+    ///
+    /// Returns the username of [Credentials::Basic] credentials.
+    ///
+    pub fn username(&self) -> Option<&str> {
+        match self {
+            Credentials::Basic { username, .. } => Some(username),
+            Credentials::Bearer(_) => None,
+        }
+    }
+
+    ///
+    /// Returns the token of [Credentials::Bearer] credentials.
+    ///
+    pub fn token(&self) -> Option<&str> {
+        match self {
+            Credentials::Basic { .. } => None,
+            Credentials::Bearer(token) => Some(token),
+        }
+    }
+
+    ///
+    /// Parses an `Authorization` header value.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Credentials>` -> The credentials, or None if the scheme is unsupported
+    ///   or the value is malformed
+    ///
+    pub fn parse(authorization: &str) -> Option<Credentials> {
+        let (scheme, value) = authorization.trim().split_once(' ')?;
+        let value = value.trim();
+
+        if scheme.eq_ignore_ascii_case("Basic") {
+            let decoded = String::from_utf8(STANDARD.decode(value).ok()?).ok()?;
+            let (username, password) = decoded.split_once(':')?;
+
+            return Some(Credentials::Basic {
+                username: username.to_string(),
+                password: password.to_string(),
+            });
+        }
+
+        if scheme.eq_ignore_ascii_case("Bearer") && !value.is_empty() {
+            return Some(Credentials::Bearer(value.to_string()));
+        }
+
+        None
+    }
+}
+
+impl Request {
+    ///
+    /// Returns the [Credentials] of the `Authorization` header.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Credentials>` -> The credentials, or None if the header is missing,
+    ///   malformed or uses an unsupported scheme
+    ///
+    pub fn credentials(&self) -> Option<Credentials> {
+        self.headers
+            .get("Authorization")
+            .and_then(Credentials::parse)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Scheme {
+    Basic,
+    Bearer,
+}
+
+///
+/// Callback deciding whether [Credentials] are valid.
+///
+type Verify = Box<dyn Fn(&Credentials) -> bool + Send + Sync>;
+
+///
+/// [Middleware] requiring valid Basic or Bearer credentials on every request.
+///
+/// Requests without valid credentials get a 401 with the `WWW-Authenticate` challenge
+/// of the scheme. Accepted [Credentials] are inserted in the [Request] extensions for
+/// handlers to read.
+///
+pub struct Auth {
+    scheme: Scheme,
+    realm: String,
+    verify: Verify,
+}
+
+impl Auth {
+    ///
+    /// Creates a [Middleware] requiring HTTP Basic credentials.
+    ///
+    /// # Arguments
+    ///
+    /// * `realm` -> Protection space shown by browsers in the login prompt
+    /// * `verify` -> Callback given the username and password, returning whether they
+    ///   are valid
+    ///
+    pub fn basic<F>(realm: &str, verify: F) -> Auth
+    where
+        F: Fn(&str, &str) -> bool + Send + Sync + 'static,
+    {
+        Auth {
+            scheme: Scheme::Basic,
+            realm: realm.to_string(),
+            verify: Box::new(move |credentials| match credentials {
+                Credentials::Basic { username, password } => verify(username, password),
+                Credentials::Bearer(_) => false,
+            }),
+        }
+    }
+
+    ///
+    /// Creates a [Middleware] requiring a Bearer token.
+    ///
+    /// # Arguments
+    ///
+    /// * `realm` -> Protection space sent in the challenge
+    /// * `verify` -> Callback given the token, returning whether it is valid
+    ///
+    pub fn bearer<F>(realm: &str, verify: F) -> Auth
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        Auth {
+            scheme: Scheme::Bearer,
+            realm: realm.to_string(),
+            verify: Box::new(move |credentials| match credentials {
+                Credentials::Bearer(token) => verify(token),
+                Credentials::Basic { .. } => false,
+            }),
+        }
+    }
+
+    ///
+    /// Builds the 401 [Response] with the challenge of the scheme.
+    ///
+    /// # Arguments
+    ///
+    /// * `invalid` -> Whether credentials were given but rejected, rather than missing
+    ///
+    fn challenge(&self, invalid: bool) -> Response {
+        let realm = self.realm.replace(['\\', '"'], "");
+
+        let challenge = match self.scheme {
+            Scheme::Basic => format!("Basic realm=\"{}\", charset=\"UTF-8\"", realm),
+            Scheme::Bearer if invalid => {
+                format!("Bearer realm=\"{}\", error=\"invalid_token\"", realm)
+            }
+            Scheme::Bearer => format!("Bearer realm=\"{}\"", realm),
+        };
+
+        let status = StatusCode::UNAUTHORIZED;
+
+        Response::new(status)
+            .header("WWW-Authenticate", &challenge)
+            .json(&status.canonical_reason())
+    }
+}
+
+impl Middleware for Auth {
+    fn handle(&self, mut req: Request, next: Next<'_>) -> Response {
+        let Some(credentials) = req.credentials() else {
+            return self.challenge(req.headers.contains_key("Authorization"));
+        };
+
+        if !(self.verify)(&credentials) {
+            return self.challenge(true);
+        }
+
+        req.extensions_mut().insert(credentials);
+
+        next.run(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::router::Router;
+    use crate::server::{test_request, HttpMethod};
+
+    fn request(authorization: Option<&str>) -> Request {
+        let mut req = test_request(HttpMethod::GET, "/");
+
+        if let Some(authorization) = authorization {
+            req.headers.insert("Authorization", authorization);
+        }
+
+        req
+    }
+
+    #[test]
+    fn test_parse_credentials() {
+        // "aladdin:open:sesame", the password may contain colons
+        assert_eq!(
+            Credentials::parse("Basic YWxhZGRpbjpvcGVuOnNlc2FtZQ=="),
+            Some(Credentials::Basic {
+                username: "aladdin".to_string(),
+                password: "open:sesame".to_string(),
+            })
+        );
+        assert_eq!(
+            Credentials::parse("bearer abc.def"),
+            Some(Credentials::Bearer("abc.def".to_string()))
+        );
+        assert_eq!(Credentials::parse("Basic !!!"), None);
+        assert_eq!(Credentials::parse("Digest x"), None);
+    }
+
+    #[test]
+    fn test_basic_auth() {
+        let mut router = Router::new();
+
+        router
+            .layer(Auth::basic("admin", |u, p| {
+                u == "aladdin" && p == "open:sesame"
+            }))
+            .get("/", |req: Request| {
+                let credentials = req.extensions().get::<Credentials>().unwrap();
+
+                Response::new(200).json(&credentials.username())
+            });
+
+        let res = router.handle(request(None));
+
+        assert_eq!(res.status, 401);
+        assert_eq!(
+            res.headers.get("WWW-Authenticate"),
+            Some("Basic realm=\"admin\", charset=\"UTF-8\"")
+        );
+
+        let res = router.handle(request(Some("Basic YWxhZGRpbjp3cm9uZw==")));
+
+        assert_eq!(res.status, 401);
+
+        let res = router.handle(request(Some("Basic YWxhZGRpbjpvcGVuOnNlc2FtZQ==")));
+
+        assert_eq!(res.status, 200);
+        assert_eq!(res.body.as_bytes(), Some(&b"\"aladdin\""[..]));
+    }
+
+    #[test]
+    fn test_bearer_auth() {
+        let mut router = Router::new();
+
+        router
+            .layer(Auth::bearer("api", |token| token == "secret"))
+            .get("/", |_| Response::new(200));
+
+        let res = router.handle(request(None));
+
+        assert_eq!(
+            res.headers.get("WWW-Authenticate"),
+            Some("Bearer realm=\"api\"")
+        );
+
+        let res = router.handle(request(Some("Bearer wrong")));
+
+        assert_eq!(res.status, 401);
+        assert_eq!(
+            res.headers.get("WWW-Authenticate"),
+            Some("Bearer realm=\"api\", error=\"invalid_token\"")
+        );
+
+        assert_eq!(router.handle(request(Some("Bearer secret"))).status, 200);
+    }
+}
@@ -0,0 +1,451 @@
+//!
+//! [Server] construction with control over the listening socket.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::server::{Request, Response, Server};
+//! use std::time::Duration;
+//!
+//! fn main() -> std::io::Result<()> {
+//!     Server::builder("0.0.0.0:8080")
+//!         .nodelay(true)
+//!         .reuse_port(true)
+//!         .backlog(4096)
+//!         .workers(16)
+//!         .keep_alive(Some(Duration::from_secs(30)))
+//!         .max_body_size(1024 * 1024)
+//!         .build()?
+//!         .serve(|_req: Request| Response::new(200))
+//! }
+//! ```
+//!
+
+use crate::server::{Limits, Overload, Server};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{
+    io,
+    net::{SocketAddr, TcpListener, ToSocketAddrs},
+    time::Duration,
+};
+
+#[cfg(feature = "tls")]
+use crate::client_cert::ClientAuth;
+#[cfg(feature = "tls")]
+use std::path::PathBuf;
+
+///
+/// Whether the kernel spreads connections across sockets sharing a port with
+/// `SO_REUSEPORT`, rather than handing them all to one.
+///
+const BALANCED_REUSE_PORT: bool = cfg!(any(target_os = "linux", target_os = "android"));
+
+///
+/// Builder for a [Server], created with [Server::builder].
+///
+/// Socket options are applied when [ServerBuilder::build] binds the listener, the other
+/// settings can also be changed afterwards with the matching [Server] methods.
+///
+#[derive(Debug, Clone)]
+pub struct ServerBuilder {
+    addr: String,
+    nodelay: bool,
+    reuse_address: bool,
+    reuse_port: bool,
+    backlog: i32,
+    acceptors: usize,
+    workers: Option<usize>,
+    keep_alive: Option<Option<Duration>>,
+    max_connections: Option<(usize, Overload)>,
+    limits: Limits,
+    #[cfg(feature = "tls")]
+    tls: Option<(PathBuf, PathBuf)>,
+    #[cfg(feature = "tls")]
+    sni_certificates: Vec<(String, PathBuf, PathBuf)>,
+    #[cfg(feature = "tls")]
+    client_auth: Option<(PathBuf, ClientAuth)>,
+}
+
+impl Server {
+    ///
+    /// Creates a [ServerBuilder] for a server bound to the specified address.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` -> Address to bind to (e.g., "0.0.0.0:8080")
+    ///
+    pub fn builder(addr: &str) -> ServerBuilder {
+        ServerBuilder {
+            addr: addr.to_string(),
+            nodelay: false,
+            // Matches `TcpListener::bind`, so restarts don't wait for TIME_WAIT sockets
+            reuse_address: cfg!(unix),
+            reuse_port: false,
+            backlog: 128,
+            acceptors: 1,
+            workers: None,
+            keep_alive: None,
+            max_connections: None,
+            limits: Limits::default(),
+            #[cfg(feature = "tls")]
+            tls: None,
+            #[cfg(feature = "tls")]
+            sni_certificates: Vec::new(),
+            #[cfg(feature = "tls")]
+            client_auth: None,
+        }
+    }
+}
+
+impl ServerBuilder {
+    ///
+    /// Sets `TCP_NODELAY` on accepted connections, sending small responses without
+    /// waiting to coalesce them (Nagle's algorithm).
+    ///
+    /// Defaults to `false`.
+    ///
+    pub fn nodelay(mut self, nodelay: bool) -> ServerBuilder {
+        self.nodelay = nodelay;
+
+        self
+    }
+
+    ///
+    /// Sets `SO_REUSEADDR` on the listening socket, allowing it to bind while
+    /// connections from a previous process linger in `TIME_WAIT`.
+    ///
+    /// Defaults to `true` on unix, as with [TcpListener::bind].
+    ///
+    pub fn reuse_address(mut self, reuse: bool) -> ServerBuilder {
+        self.reuse_address = reuse;
+
+        self
+    }
+
+    ///
+    /// Sets `SO_REUSEPORT` on the listening socket, letting several processes bind the
+    /// same address with the kernel balancing connections between them.
+    ///
+    /// `👉 Note:` Ignored on platforms without `SO_REUSEPORT` (e.g., Windows)
+    ///
+    /// Defaults to `false`.
+    ///
+    pub fn reuse_port(mut self, reuse: bool) -> ServerBuilder {
+        self.reuse_port = reuse;
+
+        self
+    }
+
+    ///
+    /// Sets the maximum number of connections queued by the kernel before they are
+    /// accepted, capped by the system limit (e.g., `net.core.somaxconn` on Linux).
+    ///
+    /// Defaults to 128.
+    ///
+    pub fn backlog(mut self, backlog: u32) -> ServerBuilder {
+        self.backlog = i32::try_from(backlog).unwrap_or(i32::MAX);
+
+        self
+    }
+
+    ///
+    /// Sets the number of threads accepting connections, each running its own accept
+    /// loop, so a single one doesn't cap the rate of new connections on many cores.
+    ///
+    /// On Linux and Android every thread gets its own socket bound with `SO_REUSEPORT`
+    /// (set on all of them, whatever [ServerBuilder::reuse_port] says), the kernel
+    /// spreading connections between them. Other platforms don't balance connections
+    /// across such sockets, so the threads accept on the same one instead.
+    ///
+    /// Defaults to 1.
+    ///
+    /// # Arguments
+    ///
+    /// * `acceptors` -> Number of accept loops, e.g. the number of cores
+    ///
+    pub fn acceptors(mut self, acceptors: usize) -> ServerBuilder {
+        self.acceptors = acceptors.max(1);
+
+        self
+    }
+
+    ///
+    /// Sets the number of worker threads, see [Server::workers].
+    ///
+    pub fn workers(mut self, workers: usize) -> ServerBuilder {
+        self.workers = Some(workers);
+
+        self
+    }
+
+    ///
+    /// Sets the idle timeout of persistent connections, see [Server::keep_alive].
+    ///
+    pub fn keep_alive(mut self, timeout: Option<Duration>) -> ServerBuilder {
+        self.keep_alive = Some(timeout);
+
+        self
+    }
+
+    ///
+    /// Limits how many connections are handled at once, see [Server::max_connections].
+    ///
+    pub fn max_connections(mut self, max: usize, overload: Overload) -> ServerBuilder {
+        self.max_connections = Some((max, overload));
+
+        self
+    }
+
+    ///
+    /// Sets the largest accepted request body, see [Server::max_body_size].
+    ///
+    pub fn max_body_size(mut self, bytes: usize) -> ServerBuilder {
+        self.limits.body = bytes;
+
+        self
+    }
+
+    ///
+    /// Sets the most header fields accepted in a request, see [Server::max_headers].
+    ///
+    pub fn max_headers(mut self, count: usize) -> ServerBuilder {
+        self.limits.headers = count;
+
+        self
+    }
+
+    ///
+    /// Sets the longest accepted header line, see [Server::max_header_line].
+    ///
+    pub fn max_header_line(mut self, bytes: usize) -> ServerBuilder {
+        self.limits.header_line = bytes;
+
+        self
+    }
+
+    ///
+    /// Sets the longest accepted request target, see [Server::max_uri_length].
+    ///
+    pub fn max_uri_length(mut self, bytes: usize) -> ServerBuilder {
+        self.limits.uri = bytes;
+
+        self
+    }
+
+    ///
+    /// Serves HTTPS with the given certificate chain and private key, as
+    /// [Server::new_tls].
+    ///
+    /// `👉 Note:` Requires the `tls` feature
+    ///
+    #[cfg(feature = "tls")]
+    pub fn tls(
+        mut self,
+        cert_path: impl Into<PathBuf>,
+        key_path: impl Into<PathBuf>,
+    ) -> ServerBuilder {
+        self.tls = Some((cert_path.into(), key_path.into()));
+
+        self
+    }
+
+    ///
+    /// Serves another certificate to clients asking for `hostname` through SNI, as
+    /// [Server::sni_certificate].
+    ///
+    /// `👉 Note:` Requires the `tls` feature
+    ///
+    #[cfg(feature = "tls")]
+    pub fn sni_certificate(
+        mut self,
+        hostname: &str,
+        cert_path: impl Into<PathBuf>,
+        key_path: impl Into<PathBuf>,
+    ) -> ServerBuilder {
+        self.sni_certificates
+            .push((hostname.to_string(), cert_path.into(), key_path.into()));
+
+        self
+    }
+
+    ///
+    /// Verifies client certificates against the given CA bundle, as
+    /// [Server::client_auth].
+    ///
+    /// `👉 Note:` Requires the `tls` feature
+    ///
+    #[cfg(feature = "tls")]
+    pub fn client_auth(mut self, ca_path: impl Into<PathBuf>, auth: ClientAuth) -> ServerBuilder {
+        self.client_auth = Some((ca_path.into(), auth));
+
+        self
+    }
+
+    ///
+    /// Binds the listening socket and creates the [Server].
+    ///
+    /// Every address `addr` resolves to is tried in turn until one binds.
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<Server>` -> The server instance or an [std::io] error
+    ///
+    pub fn build(self) -> io::Result<Server> {
+        let mut last_error = None;
+        let mut listener = None;
+
+        for addr in self.addr.to_socket_addrs()? {
+            match self.bind(addr) {
+                Ok(bound) => {
+                    listener = Some(bound);
+                    break;
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        let listener = listener.ok_or_else(|| {
+            last_error.unwrap_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "could not resolve to any addresses",
+                )
+            })
+        })?;
+
+        // Bound to the resolved port, `:0` included
+        let addr = listener.local_addr()?;
+        let mut acceptors = Vec::with_capacity(self.acceptors - 1);
+
+        for _ in 1..self.acceptors {
+            acceptors.push(match BALANCED_REUSE_PORT {
+                true => self.bind(addr)?,
+                false => listener.try_clone()?,
+            });
+        }
+
+        let mut server = Server::from_listener(listener)
+            .max_body_size(self.limits.body)
+            .max_headers(self.limits.headers)
+            .max_header_line(self.limits.header_line)
+            .max_uri_length(self.limits.uri);
+
+        server.nodelay = self.nodelay;
+
+        if let Some(workers) = self.workers {
+            server = server.workers(workers);
+        }
+
+        if let Some(timeout) = self.keep_alive {
+            server = server.keep_alive(timeout);
+        }
+
+        if let Some((max, overload)) = self.max_connections {
+            server = server.max_connections(max, overload);
+        }
+
+        #[cfg(feature = "tls")]
+        if let Some((cert_path, key_path)) = &self.tls {
+            server.listeners[0].set_tls(cert_path, key_path)?;
+        }
+
+        #[cfg(feature = "tls")]
+        for (hostname, cert_path, key_path) in &self.sni_certificates {
+            server = server.sni_certificate(hostname, cert_path, key_path)?;
+        }
+
+        #[cfg(feature = "tls")]
+        if let Some((ca_path, auth)) = self.client_auth {
+            server = server.client_auth(ca_path, auth)?;
+        }
+
+        // Sharing the first listener's TLS configuration, complete by now
+        for socket in acceptors {
+            let acceptor = server.listeners[0].sibling(socket);
+            server.listeners.push(acceptor);
+        }
+
+        Ok(server)
+    }
+
+    ///
+    /// Creates a listening socket on `addr` with the configured options.
+    ///
+    fn bind(&self, addr: SocketAddr) -> io::Result<TcpListener> {
+        let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+
+        socket.set_reuse_address(self.reuse_address)?;
+
+        #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+        socket.set_reuse_port(self.reuse_port || (BALANCED_REUSE_PORT && self.acceptors > 1))?;
+
+        socket.bind(&addr.into())?;
+        socket.listen(self.backlog)?;
+
+        Ok(socket.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::{Request, Response, Server};
+    use std::{
+        io::{Read, Write},
+        net::TcpStream,
+        thread,
+    };
+
+    #[test]
+    fn test_builder_socket_options() {
+        let server = Server::builder("127.0.0.1:0")
+            .nodelay(true)
+            .reuse_port(true)
+            .backlog(16)
+            .build()
+            .unwrap();
+
+        let addr = server.local_addr().unwrap();
+
+        // A second listener may share the port
+        #[cfg(target_os = "linux")]
+        Server::builder(&addr.to_string())
+            .reuse_port(true)
+            .build()
+            .unwrap();
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+
+        let stream = server.listen().next().unwrap().unwrap();
+
+        assert_eq!(stream.peer_addr().unwrap(), client.local_addr().unwrap());
+        assert!(matches!(&stream, crate::stream::Stream::Tcp(tcp) if tcp.nodelay().unwrap()));
+    }
+
+    #[test]
+    fn test_builder_acceptors() {
+        let server = Server::builder("127.0.0.1:0")
+            .acceptors(4)
+            .workers(2)
+            .build()
+            .unwrap();
+
+        let addr = server.local_addr().unwrap();
+        assert_eq!(server.listeners.len(), 4);
+        assert_eq!(server.local_addrs().unwrap(), vec![addr]);
+
+        thread::spawn(move || server.serve(|_req: Request| Response::new(200).text("ok")));
+
+        // Whichever acceptor the kernel picks, every connection is answered
+        for _ in 0..16 {
+            let mut client = TcpStream::connect(addr).unwrap();
+            client
+                .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+                .unwrap();
+
+            let mut raw = String::new();
+            client.read_to_string(&mut raw).unwrap();
+            assert!(raw.starts_with("HTTP/1.1 200 OK\r\n"));
+        }
+    }
+}
@@ -0,0 +1,763 @@
+//!
+//! In-memory caching of responses to `GET` requests.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::cache::Cache;
+//! use http_rs::router::Router;
+//! use http_rs::server::{Request, Response};
+//! use std::time::Duration;
+//!
+//! let mut router = Router::new();
+//!
+//! // Up to 64 MiB of responses, fresh for as long as their `Cache-Control` allows
+//! let cache = Cache::new(64 * 1024 * 1024);
+//! let purge = cache.handle();
+//! router.layer(cache);
+//!
+//! router.get("/reports/:id", |req: Request| {
+//!     Response::new(200)
+//!         .cache_for(Duration::from_secs(300))
+//!         .json(&req.params["id"])
+//! });
+//!
+//! // Drop a report once it changes
+//! router.post("/reports/:id", move |req: Request| {
+//!     purge.purge(&format!("/reports/{}", req.params["id"]));
+//!     Response::new(204)
+//! });
+//! ```
+//!
+
+use crate::{
+    date,
+    header::HeaderMap,
+    middleware::{Middleware, Next},
+    server::{HttpMethod, Request, Response},
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant, SystemTime},
+};
+
+///
+/// Statuses cacheable by default (RFC 9110, section 15.1), as long as the response gives
+/// a freshness lifetime.
+///
+const CACHEABLE: [u16; 10] = [200, 203, 204, 300, 301, 308, 404, 405, 410, 501];
+
+///
+/// A stored response, for one combination of the request headers it varies on.
+///
+#[derive(Debug)]
+struct Entry {
+    ///
+    /// `Host` and the request headers named by `Vary`, lowercase, with the values they
+    /// had
+    ///
+    vary: Vec<(String, Option<String>)>,
+    status: u16,
+    headers: HeaderMap,
+    body: Vec<u8>,
+    stored: Instant,
+    expires: Instant,
+    last_used: u64,
+}
+
+impl Entry {
+    ///
+    /// Approximate memory held, counted against [Cache::new]'s limit.
+    ///
+    fn size(&self) -> usize {
+        let headers: usize = self.headers.iter().map(|(k, v)| k.len() + v.len()).sum();
+
+        self.body.len() + headers
+    }
+
+    fn matches(&self, req: &Request) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| req.headers.get(name) == value.as_deref())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Store {
+    ///
+    /// Entries by request target (path and query)
+    ///
+    entries: HashMap<String, Vec<Entry>>,
+    size: usize,
+
+    ///
+    /// Use counter, stamped on entries to find the least recently used one
+    ///
+    clock: u64,
+}
+
+impl Store {
+    fn remove_where(&mut self, mut remove: impl FnMut(&str, &Entry) -> bool) {
+        let mut freed = 0;
+
+        self.entries.retain(|target, entries| {
+            entries.retain(|entry| {
+                let removed = remove(target, entry);
+
+                if removed {
+                    freed += entry.size();
+                }
+
+                !removed
+            });
+
+            !entries.is_empty()
+        });
+
+        self.size -= freed;
+    }
+
+    ///
+    /// Evicts the least recently used entries until `size` fits within `max`.
+    ///
+    fn evict(&mut self, max: usize) {
+        while self.size > max {
+            let oldest = self
+                .entries
+                .values()
+                .flatten()
+                .map(|entry| entry.last_used)
+                .min();
+
+            match oldest {
+                Some(oldest) => self.remove_where(|_, entry| entry.last_used == oldest),
+                None => break,
+            }
+        }
+    }
+}
+
+///
+/// [Middleware] serving `GET` and `HEAD` requests from an in-memory cache of earlier
+/// responses, answering with an `Age` header.
+///
+/// Responses are cached for the freshness lifetime their `Cache-Control` (`s-maxage`,
+/// then `max-age`) or `Expires` header gives, or [Cache::default_ttl] if they have
+/// none. Responses marked `no-store`, `no-cache` or `private`, setting cookies, with a
+/// streamed body or `Vary: *` are never stored. Requests sending `Authorization` or
+/// `Cache-Control: no-cache` / `no-store` bypass the cache.
+///
+/// Entries are keyed by path, query and the request headers the response's `Vary`
+/// names, and the least recently used ones are evicted once the cached bodies and
+/// headers exceed the size limit.
+///
+pub struct Cache {
+    store: Arc<Mutex<Store>>,
+    max_size: usize,
+    default_ttl: Option<Duration>,
+}
+
+impl Cache {
+    ///
+    /// Creates the response caching [Middleware].
+    ///
+    /// # Arguments
+    ///
+    /// * `max_size` -> Bytes of bodies and headers kept before evicting entries
+    ///
+    pub fn new(max_size: usize) -> Cache {
+        Cache {
+            store: Arc::default(),
+            max_size,
+            default_ttl: None,
+        }
+    }
+
+    ///
+    /// Caches responses without an explicit freshness lifetime for `ttl`, instead of
+    /// not caching them.
+    ///
+    pub fn default_ttl(mut self, ttl: Duration) -> Cache {
+        self.default_ttl = Some(ttl);
+
+        self
+    }
+
+    ///
+    /// Returns a [CacheHandle] to purge entries, e.g. once the resource they hold
+    /// changes.
+    ///
+    pub fn handle(&self) -> CacheHandle {
+        CacheHandle {
+            store: self.store.clone(),
+        }
+    }
+
+    fn store(&self) -> MutexGuard<'_, Store> {
+        lock(&self.store)
+    }
+
+    ///
+    /// How long a response may be served from the cache, None if it can't be stored.
+    ///
+    fn ttl(&self, res: &Response) -> Option<Duration> {
+        if !CACHEABLE.contains(&res.status)
+            || res.body.as_bytes().is_none()
+            || res.headers.contains_key("Set-Cookie")
+            || res.headers.get_all("Vary").any(|vary| vary.contains('*'))
+        {
+            return None;
+        }
+
+        let directives = directives(res.headers.get_all("Cache-Control"));
+        let directive = |name: &str| directives.iter().find(|(n, _)| n == name);
+
+        if ["no-store", "no-cache", "private"]
+            .iter()
+            .any(|name| directive(name).is_some())
+        {
+            return None;
+        }
+
+        let max_age = directive("s-maxage")
+            .or_else(|| directive("max-age"))
+            .and_then(|(_, secs)| secs.as_ref()?.parse().ok())
+            .map(Duration::from_secs);
+
+        let expires = || {
+            let expires = date::parse_http_date(res.headers.get("Expires")?)?;
+            let now = res
+                .headers
+                .get("Date")
+                .and_then(date::parse_http_date)
+                .unwrap_or_else(SystemTime::now);
+
+            // An already passed or invalid date means stale
+            Some(expires.duration_since(now).unwrap_or_default())
+        };
+
+        max_age
+            .or_else(expires)
+            .or(self.default_ttl)
+            .filter(|ttl| !ttl.is_zero())
+    }
+
+    ///
+    /// Looks up a fresh entry for the request, marking it as used.
+    ///
+    fn lookup(&self, req: &Request, target: &str, now: Instant) -> Option<Response> {
+        let mut store = self.store();
+        store.clock += 1;
+        let clock = store.clock;
+
+        let entry = store
+            .entries
+            .get_mut(target)?
+            .iter_mut()
+            .find(|entry| entry.matches(req) && entry.expires > now)?;
+
+        entry.last_used = clock;
+
+        let mut res = Response::new(entry.status).body(entry.body.clone());
+        res.headers = entry.headers.clone();
+        res.headers.insert(
+            "Age",
+            now.duration_since(entry.stored).as_secs().to_string(),
+        );
+
+        Some(res)
+    }
+
+    ///
+    /// Stores a copy of the response, replacing any entry for the same variant.
+    ///
+    fn insert(
+        &self,
+        headers: &HeaderMap,
+        target: String,
+        res: &Response,
+        ttl: Duration,
+        now: Instant,
+    ) {
+        let Some(body) = res.body.as_bytes() else {
+            return;
+        };
+
+        // Responses for different hosts of the same path are distinct resources
+        let vary = std::iter::once("host")
+            .chain(res.headers.get_all("Vary").flat_map(|vary| vary.split(',')))
+            .map(|name| name.trim().to_ascii_lowercase())
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                let value = headers.get(&name).map(str::to_string);
+                (name, value)
+            })
+            .collect();
+
+        let mut store = self.store();
+        store.clock += 1;
+
+        let entry = Entry {
+            vary,
+            status: res.status,
+            headers: res.headers.clone(),
+            body: body.to_vec(),
+            stored: now,
+            expires: now + ttl,
+            last_used: store.clock,
+        };
+
+        if entry.size() > self.max_size {
+            return;
+        }
+
+        store.remove_where(|t, stored| t == target && stored.vary == entry.vary);
+        store.size += entry.size();
+        store.entries.entry(target).or_default().push(entry);
+
+        // Expired entries go first, then the least recently used
+        if store.size > self.max_size {
+            store.remove_where(|_, entry| entry.expires <= now);
+        }
+
+        store.evict(self.max_size);
+    }
+}
+
+impl Middleware for Cache {
+    fn handle(&self, req: Request, next: Next<'_>) -> Response {
+        if !matches!(req.method, HttpMethod::GET | HttpMethod::HEAD)
+            || req.headers.contains_key("Authorization")
+        {
+            return next.run(req);
+        }
+
+        let target = match req.target.to_string().split_once('?') {
+            Some((_, query)) => format!("{}?{}", req.route, query),
+            None => req.route.clone(),
+        };
+
+        let bypass = directives(req.headers.get_all("Cache-Control"))
+            .iter()
+            .any(|(name, _)| name == "no-cache" || name == "no-store");
+
+        let now = Instant::now();
+
+        if !bypass {
+            if let Some(res) = self.lookup(&req, &target, now) {
+                return res;
+            }
+        }
+
+        let method = req.method.clone();
+        let headers = req.headers.clone();
+        let res = next.run(req);
+
+        // HEAD responses carry no body to serve later GET requests with
+        if method == HttpMethod::GET {
+            if let Some(ttl) = self.ttl(&res) {
+                self.insert(&headers, target, &res, ttl, now);
+            }
+        }
+
+        res
+    }
+}
+
+///
+/// Handle to purge the entries of a [Cache], created with [Cache::handle].
+///
+#[derive(Clone)]
+pub struct CacheHandle {
+    store: Arc<Mutex<Store>>,
+}
+
+impl CacheHandle {
+    ///
+    /// Removes the entries of a path, whatever their query.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` -> Path of the resource (e.g., "/reports/42")
+    ///
+    pub fn purge(&self, path: &str) {
+        lock(&self.store).remove_where(|target, _| {
+            target.split_once('?').map_or(target, |(path, _)| path) == path
+        });
+    }
+
+    ///
+    /// Removes every entry.
+    ///
+    pub fn clear(&self) {
+        let mut store = lock(&self.store);
+
+        store.entries.clear();
+        store.size = 0;
+    }
+
+    ///
+    /// Returns the number of cached responses, counting each variant.
+    ///
+    pub fn len(&self) -> usize {
+        lock(&self.store).entries.values().map(Vec::len).sum()
+    }
+
+    ///
+    /// Whether nothing is cached.
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn lock(store: &Mutex<Store>) -> MutexGuard<'_, Store> {
+    store.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+///
+/// Splits `Cache-Control` values into lowercase directive names and their unquoted
+/// arguments.
+///
+fn directives<'a>(values: impl Iterator<Item = &'a str>) -> Vec<(String, Option<String>)> {
+    values
+        .flat_map(|value| value.split(','))
+        .filter_map(|directive| {
+            let (name, arg) = match directive.split_once('=') {
+                Some((name, arg)) => (name, Some(arg.trim().trim_matches('"').to_string())),
+                None => (directive, None),
+            };
+            let name = name.trim().to_ascii_lowercase();
+
+            (!name.is_empty()).then_some((name, arg))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cache;
+    use crate::{
+        router::Router,
+        server::{test_request, HttpMethod, Request, Response},
+    };
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    };
+
+    fn get(router: &Router, target: &str, language: &str) -> Response {
+        let mut req = test_request(HttpMethod::GET, target);
+        req.headers.insert("Accept-Language", language);
+
+        router.handle(req)
+    }
+
+    #[test]
+    fn test_cache_hits_vary_and_purge() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let cache = Cache::new(1024);
+        let handle = cache.handle();
+
+        let mut router = Router::new();
+        router.layer(cache);
+
+        let counter = calls.clone();
+        router.get("/report", move |req: Request| {
+            let n = counter.fetch_add(1, Ordering::SeqCst);
+            let language = req.headers.get("Accept-Language").unwrap_or("").to_string();
+
+            Response::new(200)
+                .header("Cache-Control", "max-age=60")
+                .header("Vary", "Accept-Language")
+                .json(&(n, language))
+        });
+        router.get("/private", |_| {
+            Response::new(200)
+                .header("Cache-Control", "private, max-age=60")
+                .json(&"mine")
+        });
+        router.get("/large", |_| {
+            Response::new(200)
+                .header("Cache-Control", "max-age=60")
+                .body(vec![b'x'; 850])
+        });
+
+        let res = get(&router, "/report", "en");
+        assert_eq!(res.body.as_bytes().unwrap(), b"[0,\"en\"]");
+        assert!(res.headers.get("Age").is_none());
+
+        let res = get(&router, "/report", "en");
+        assert_eq!(res.body.as_bytes().unwrap(), b"[0,\"en\"]");
+        assert_eq!(res.headers.get("Age"), Some("0"));
+
+        // Another language is another variant, and another query another resource
+        assert_eq!(
+            get(&router, "/report", "fr").body.as_bytes().unwrap(),
+            b"[1,\"fr\"]"
+        );
+        assert_eq!(
+            get(&router, "/report?page=2", "en")
+                .body
+                .as_bytes()
+                .unwrap(),
+            b"[2,\"en\"]"
+        );
+        assert_eq!(handle.len(), 3);
+
+        // HEAD is answered from the GET entry
+        let mut req = test_request(HttpMethod::HEAD, "/report");
+        req.headers.insert("Accept-Language", "en");
+        let res = router.handle(req);
+        assert_eq!(res.status, 200);
+        assert_eq!(res.headers.get("Age"), Some("0"));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        get(&router, "/private", "en");
+        assert_eq!(handle.len(), 3);
+
+        let mut req = test_request(HttpMethod::GET, "/report");
+        req.headers.insert("Accept-Language", "en");
+        req.headers.insert("Cache-Control", "no-cache");
+        assert_eq!(router.handle(req).body.as_bytes().unwrap(), b"[3,\"en\"]");
+
+        handle.purge("/report");
+        assert!(handle.is_empty());
+
+        // Storing 850 more bytes evicts the least recently used entry to stay under 1024
+        get(&router, "/report", "en");
+        get(&router, "/report", "fr");
+        get(&router, "/report", "en");
+        get(&router, "/large", "en");
+        assert_eq!(handle.len(), 2);
+        assert_eq!(get(&router, "/report", "en").headers.get("Age"), Some("0"));
+
+        handle.clear();
+        assert!(handle.is_empty());
+    }
+
+    #[test]
+    fn test_cache_skips_uncacheable() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let cache = Cache::new(1024).default_ttl(Duration::from_secs(60));
+        let handle = cache.handle();
+
+        let mut router = Router::new();
+        router.layer(cache);
+
+        let mut route = |path: &str, response: fn() -> Response| {
+            let counter = calls.clone();
+            router.get(path, move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                response()
+            });
+        };
+
+        route("/cookie", || {
+            Response::new(200)
+                .header("Cache-Control", "max-age=60")
+                .header("Set-Cookie", "id=1")
+        });
+        route("/no-store", || {
+            Response::new(200).header("Cache-Control", "no-store, max-age=60")
+        });
+        route("/private", || {
+            Response::new(200).header("Cache-Control", "Private")
+        });
+        route("/vary-all", || Response::new(200).header("Vary", "*"));
+        route("/created", || Response::new(201));
+
+        let counter = calls.clone();
+        router.post("/created", move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Response::new(200).header("Cache-Control", "max-age=60")
+        });
+
+        for path in ["/cookie", "/no-store", "/private", "/vary-all", "/created"] {
+            get(&router, path, "en");
+            assert!(get(&router, path, "en").headers.get("Age").is_none());
+        }
+
+        router.handle(test_request(HttpMethod::POST, "/created"));
+        router.handle(test_request(HttpMethod::POST, "/created"));
+
+        assert!(handle.is_empty());
+        assert_eq!(calls.load(Ordering::SeqCst), 12);
+    }
+
+    #[test]
+    fn test_cache_skips_authorized_requests() {
+        let cache = Cache::new(1024);
+        let handle = cache.handle();
+
+        let mut router = Router::new();
+        router.layer(cache);
+        router.get("/report", |_| {
+            Response::new(200)
+                .header("Cache-Control", "max-age=60")
+                .body("report")
+        });
+
+        let mut req = test_request(HttpMethod::GET, "/report");
+        req.headers.insert("Authorization", "Bearer token");
+        router.handle(req);
+        assert!(handle.is_empty());
+
+        get(&router, "/report", "en");
+        assert_eq!(handle.len(), 1);
+
+        // Nor are they answered from the entries of others
+        let mut req = test_request(HttpMethod::GET, "/report");
+        req.headers.insert("Authorization", "Bearer token");
+        assert!(router.handle(req).headers.get("Age").is_none());
+    }
+
+    #[test]
+    fn test_cache_vary_variants() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let cache = Cache::new(4096);
+        let handle = cache.handle();
+
+        let mut router = Router::new();
+        router.layer(cache);
+
+        let counter = calls.clone();
+        router.get("/report", move |_| {
+            let n = counter.fetch_add(1, Ordering::SeqCst);
+
+            Response::new(200)
+                .header("Cache-Control", "max-age=60")
+                .header("Vary", "accept-language, Accept-Encoding")
+                .json(&n)
+        });
+
+        let get = |language: Option<&str>, encoding: Option<&str>, host: &str| {
+            let mut req = test_request(HttpMethod::GET, "/report");
+            req.headers.insert("Host", host);
+
+            if let Some(language) = language {
+                req.headers.insert("Accept-Language", language);
+            }
+            if let Some(encoding) = encoding {
+                req.headers.insert("Accept-Encoding", encoding);
+            }
+
+            router.handle(req).body.as_bytes().unwrap().to_vec()
+        };
+
+        assert_eq!(get(Some("en"), Some("gzip"), "a.test"), b"0");
+        assert_eq!(get(Some("en"), Some("br"), "a.test"), b"1");
+        assert_eq!(get(Some("en"), None, "a.test"), b"2");
+        assert_eq!(get(None, None, "a.test"), b"3");
+        assert_eq!(get(Some("en"), Some("gzip"), "b.test"), b"4");
+        assert_eq!(handle.len(), 5);
+
+        // Each variant is served only to requests sending the same values
+        assert_eq!(get(Some("en"), Some("gzip"), "a.test"), b"0");
+        assert_eq!(get(Some("en"), Some("br"), "a.test"), b"1");
+        assert_eq!(get(Some("en"), None, "a.test"), b"2");
+        assert_eq!(get(None, None, "a.test"), b"3");
+        assert_eq!(get(Some("en"), Some("gzip"), "b.test"), b"4");
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+    }
+
+    #[test]
+    fn test_cache_expiry() {
+        let cache = Cache::new(1024);
+        let req = test_request(HttpMethod::GET, "/report");
+        let res = Response::new(200)
+            .header("Cache-Control", "max-age=60")
+            .body("report");
+        let now = Instant::now();
+
+        let ttl = cache.ttl(&res).unwrap();
+        assert_eq!(ttl, Duration::from_secs(60));
+        cache.insert(&req.headers, "/report".to_string(), &res, ttl, now);
+
+        let later = now + Duration::from_secs(59);
+        assert_eq!(
+            cache
+                .lookup(&req, "/report", later)
+                .unwrap()
+                .headers
+                .get("Age"),
+            Some("59")
+        );
+        assert!(cache
+            .lookup(&req, "/report", now + Duration::from_secs(60))
+            .is_none());
+
+        // `s-maxage` takes precedence over `max-age`, which does over `Expires`
+        let ttl = |cache_control: &str, expires: &str| {
+            let res = Response::new(200)
+                .header("Date", "Sun, 06 Nov 1994 08:49:37 GMT")
+                .header("Cache-Control", cache_control)
+                .header("Expires", expires);
+
+            cache.ttl(&res).map(|ttl| ttl.as_secs())
+        };
+
+        let expires = "Sun, 06 Nov 1994 08:59:37 GMT";
+        assert_eq!(ttl("max-age=30, s-maxage=\"90\"", expires), Some(90));
+        assert_eq!(ttl("max-age=30", expires), Some(30));
+        assert_eq!(ttl("public", expires), Some(600));
+        assert_eq!(ttl("public", "Sun, 06 Nov 1994 08:00:00 GMT"), None);
+        assert_eq!(ttl("max-age=0", expires), None);
+        assert_eq!(ttl("public", "0"), None);
+
+        // Without a lifetime, only a default one makes a response cacheable
+        let res = Response::new(200).body("report");
+        assert_eq!(cache.ttl(&res), None);
+        assert_eq!(
+            Cache::new(1024)
+                .default_ttl(Duration::from_secs(5))
+                .ttl(&res),
+            Some(Duration::from_secs(5))
+        );
+    }
+
+    #[test]
+    fn test_cache_eviction() {
+        let cache = Cache::new(1100);
+        let handle = cache.handle();
+        let req = test_request(HttpMethod::GET, "/");
+        let res = Response::new(200).body(vec![b'x'; 300]);
+        let now = Instant::now();
+        let ttl = Duration::from_secs(60);
+
+        // With its headers, each entry takes 345 bytes, so three fit
+        cache.insert(&req.headers, "/a".to_string(), &res, ttl, now);
+        cache.insert(&req.headers, "/b".to_string(), &res, ttl, now);
+        cache.insert(&req.headers, "/c".to_string(), &res, ttl, now);
+        assert_eq!(handle.len(), 3);
+
+        // Storing the same variant again replaces it rather than counting twice
+        cache.insert(&req.headers, "/c".to_string(), &res, ttl, now);
+        assert_eq!(handle.len(), 3);
+
+        // Using `/a` leaves `/b` the least recently used
+        assert!(cache.lookup(&req, "/a", now).is_some());
+        cache.insert(&req.headers, "/d".to_string(), &res, ttl, now);
+        assert_eq!(handle.len(), 3);
+        assert!(cache.lookup(&req, "/b", now).is_none());
+        assert!(cache.lookup(&req, "/a", now).is_some());
+
+        // Expired entries are evicted first, even if used more recently
+        let later = now + Duration::from_secs(30);
+        cache.insert(&req.headers, "/e".to_string(), &res, ttl, later);
+        cache.insert(&req.headers, "/f".to_string(), &res, ttl, later);
+        cache.insert(&req.headers, "/g".to_string(), &res, ttl, now + ttl);
+        assert_eq!(handle.len(), 3);
+        assert!(cache.lookup(&req, "/a", now).is_none());
+
+        // A response larger than the whole cache is never stored
+        let large = Response::new(200).body(vec![b'x'; 1100]);
+        cache.insert(&req.headers, "/large".to_string(), &large, ttl, now);
+        assert_eq!(handle.len(), 3);
+        assert!(cache.lookup(&req, "/large", now).is_none());
+    }
+}
@@ -0,0 +1,336 @@
+//!
+//! Typed builders for the caching headers of responses: `Cache-Control`, `Expires`,
+//! `Last-Modified` and `Vary`.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::cache_control::CacheControl;
+//! use http_rs::server::{Request, Response};
+//! use std::time::{Duration, SystemTime};
+//!
+//! fn handler(req: Request) -> Response {
+//!     match req.route.as_str() {
+//!         // Fingerprinted assets never change
+//!         "/app.3f9a1c.js" => Response::new(200).cache_control(
+//!             CacheControl::new()
+//!                 .public(true)
+//!                 .max_age(Duration::from_secs(365 * 24 * 3600))
+//!                 .immutable(true),
+//!         ),
+//!         "/news" => Response::new(200)
+//!             .cache_for(Duration::from_secs(60))
+//!             .last_modified(SystemTime::now())
+//!             .vary("Accept-Language"),
+//!         _ => Response::new(200).no_store(),
+//!     }
+//! }
+//! ```
+//!
+
+use crate::{date, server::Response};
+use std::{
+    fmt,
+    time::{Duration, SystemTime},
+};
+
+///
+/// A `Cache-Control` response header value.
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CacheControl {
+    public: bool,
+    private: bool,
+    no_cache: bool,
+    no_store: bool,
+    no_transform: bool,
+    must_revalidate: bool,
+    proxy_revalidate: bool,
+    immutable: bool,
+    max_age: Option<Duration>,
+    s_maxage: Option<Duration>,
+    stale_while_revalidate: Option<Duration>,
+    stale_if_error: Option<Duration>,
+}
+
+impl CacheControl {
+    ///
+    /// Creates a [CacheControl] without directives.
+    ///
+    pub fn new() -> CacheControl {
+        CacheControl::default()
+    }
+
+    ///
+    /// Sets `public`, letting shared caches store the response even if it would
+    /// otherwise be private (e.g., answering a request with `Authorization`).
+    ///
+    pub fn public(mut self, public: bool) -> CacheControl {
+        self.public = public;
+
+        self
+    }
+
+    ///
+    /// Sets `private`, letting only the client's own cache store the response.
+    ///
+    pub fn private(mut self, private: bool) -> CacheControl {
+        self.private = private;
+
+        self
+    }
+
+    ///
+    /// Sets `no-cache`, requiring caches to revalidate the response before each use.
+    ///
+    pub fn no_cache(mut self, no_cache: bool) -> CacheControl {
+        self.no_cache = no_cache;
+
+        self
+    }
+
+    ///
+    /// Sets `no-store`, forbidding any cache from storing the response.
+    ///
+    pub fn no_store(mut self, no_store: bool) -> CacheControl {
+        self.no_store = no_store;
+
+        self
+    }
+
+    ///
+    /// Sets `no-transform`, forbidding intermediaries from altering the content.
+    ///
+    pub fn no_transform(mut self, no_transform: bool) -> CacheControl {
+        self.no_transform = no_transform;
+
+        self
+    }
+
+    ///
+    /// Sets `must-revalidate`, forbidding caches from serving the response once stale.
+    ///
+    pub fn must_revalidate(mut self, must_revalidate: bool) -> CacheControl {
+        self.must_revalidate = must_revalidate;
+
+        self
+    }
+
+    ///
+    /// Sets `proxy-revalidate`, as [CacheControl::must_revalidate] for shared caches
+    /// only.
+    ///
+    pub fn proxy_revalidate(mut self, proxy_revalidate: bool) -> CacheControl {
+        self.proxy_revalidate = proxy_revalidate;
+
+        self
+    }
+
+    ///
+    /// Sets `immutable`, telling clients the response won't change while fresh so they
+    /// don't revalidate it on reload.
+    ///
+    pub fn immutable(mut self, immutable: bool) -> CacheControl {
+        self.immutable = immutable;
+
+        self
+    }
+
+    ///
+    /// Sets `max-age`, how long the response stays fresh. Sub-second precision is
+    /// dropped.
+    ///
+    pub fn max_age(mut self, max_age: Duration) -> CacheControl {
+        self.max_age = Some(max_age);
+
+        self
+    }
+
+    ///
+    /// Sets `s-maxage`, overriding `max-age` for shared caches.
+    ///
+    pub fn s_maxage(mut self, s_maxage: Duration) -> CacheControl {
+        self.s_maxage = Some(s_maxage);
+
+        self
+    }
+
+    ///
+    /// Sets `stale-while-revalidate`, how long caches may serve the response once stale
+    /// while they revalidate it in the background.
+    ///
+    pub fn stale_while_revalidate(mut self, duration: Duration) -> CacheControl {
+        self.stale_while_revalidate = Some(duration);
+
+        self
+    }
+
+    ///
+    /// Sets `stale-if-error`, how long caches may serve the response once stale when
+    /// revalidating it fails.
+    ///
+    pub fn stale_if_error(mut self, duration: Duration) -> CacheControl {
+        self.stale_if_error = Some(duration);
+
+        self
+    }
+}
+
+///
+/// Formats the directives as a `Cache-Control` value (e.g., `public, max-age=60`).
+///
+impl fmt::Display for CacheControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flags = [
+            (self.public, "public"),
+            (self.private, "private"),
+            (self.no_cache, "no-cache"),
+            (self.no_store, "no-store"),
+            (self.no_transform, "no-transform"),
+            (self.must_revalidate, "must-revalidate"),
+            (self.proxy_revalidate, "proxy-revalidate"),
+            (self.immutable, "immutable"),
+        ];
+
+        let durations = [
+            (self.max_age, "max-age"),
+            (self.s_maxage, "s-maxage"),
+            (self.stale_while_revalidate, "stale-while-revalidate"),
+            (self.stale_if_error, "stale-if-error"),
+        ];
+
+        let directives = flags
+            .into_iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, name)| name.to_string())
+            .chain(durations.into_iter().filter_map(|(duration, name)| {
+                duration.map(|d| format!("{}={}", name, d.as_secs()))
+            }));
+
+        for (i, directive) in directives.enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+
+            f.write_str(&directive)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Response {
+    ///
+    /// Sets the `Cache-Control` header, replacing any previous one.
+    ///
+    /// # Arguments
+    ///
+    /// * `cache_control` -> The [CacheControl] directives
+    ///
+    pub fn cache_control(self, cache_control: CacheControl) -> Response {
+        self.set_header("Cache-Control", &cache_control.to_string())
+    }
+
+    ///
+    /// Lets any cache store the response and serve it for `duration`, with
+    /// `Cache-Control: public, max-age=...`.
+    ///
+    pub fn cache_for(self, duration: Duration) -> Response {
+        self.cache_control(CacheControl::new().public(true).max_age(duration))
+    }
+
+    ///
+    /// Forbids any cache from storing the response, with `Cache-Control: no-store`.
+    ///
+    pub fn no_store(self) -> Response {
+        self.cache_control(CacheControl::new().no_store(true))
+    }
+
+    ///
+    /// Sets the `Expires` header, when the response becomes stale for caches that
+    /// ignore `Cache-Control: max-age`.
+    ///
+    pub fn expires(self, time: SystemTime) -> Response {
+        self.set_header("Expires", &date::http_date(time))
+    }
+
+    ///
+    /// Sets the `Last-Modified` header, which [crate::conditional::ConditionalGet] compares
+    /// with `If-Modified-Since`.
+    ///
+    pub fn last_modified(self, time: SystemTime) -> Response {
+        self.set_header("Last-Modified", &date::http_date(time))
+    }
+
+    ///
+    /// Adds a request header the response varies on to `Vary`, unless it is already
+    /// listed or `Vary` is `*`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` -> Request header name (e.g., "Accept-Language")
+    ///
+    pub fn vary(self, name: &str) -> Response {
+        let listed = self.headers.get_all("Vary").any(|vary| {
+            vary.split(',')
+                .map(str::trim)
+                .any(|v| v == "*" || v.eq_ignore_ascii_case(name))
+        });
+
+        match listed {
+            true => self,
+            false => self.append_header("Vary", name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CacheControl;
+    use crate::server::Response;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_cache_control_headers() {
+        let cache_control = CacheControl::new()
+            .public(true)
+            .max_age(Duration::from_secs(3600))
+            .immutable(true)
+            .stale_while_revalidate(Duration::from_millis(30_500));
+
+        assert_eq!(
+            cache_control.to_string(),
+            "public, immutable, max-age=3600, stale-while-revalidate=30"
+        );
+        assert_eq!(CacheControl::new().to_string(), "");
+
+        let res = Response::new(200)
+            .cache_for(Duration::from_secs(60))
+            .expires(UNIX_EPOCH + Duration::from_secs(784_111_777))
+            .last_modified(UNIX_EPOCH)
+            .vary("Accept-Language")
+            .vary("accept-language")
+            .vary("Accept-Encoding");
+
+        assert_eq!(res.headers.get("Cache-Control"), Some("public, max-age=60"));
+        assert_eq!(
+            res.headers.get("Expires"),
+            Some("Sun, 06 Nov 1994 08:49:37 GMT")
+        );
+        assert_eq!(
+            res.headers.get("Last-Modified"),
+            Some("Thu, 01 Jan 1970 00:00:00 GMT")
+        );
+        assert_eq!(
+            res.headers.get_all("Vary").collect::<Vec<_>>(),
+            ["Accept-Language", "Accept-Encoding"]
+        );
+
+        let res = Response::new(200)
+            .header("Vary", "*")
+            .vary("Accept")
+            .no_store();
+        assert_eq!(res.headers.get("Vary"), Some("*"));
+        assert_eq!(res.headers.get("Cache-Control"), Some("no-store"));
+    }
+}
@@ -0,0 +1,307 @@
+//!
+//! Certificates of HTTPS listeners, selected by SNI hostname and reloadable from disk
+//! without restarting the server, enabled with the `tls` feature.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::server::{Response, Server};
+//! use std::time::Duration;
+//!
+//! let server = Server::new_tls("0.0.0.0:443", "default.pem", "default.key")
+//!     .and_then(|server| server.sni_certificate("api.example.com", "api.pem", "api.key"))
+//!     .and_then(|server| server.sni_certificate("*.example.com", "wildcard.pem", "wildcard.key"))
+//!     .unwrap();
+//!
+//! // Picks up renewed certificates (e.g., from certbot) within a minute
+//! for certificates in server.certificates() {
+//!     certificates.watch(Duration::from_secs(60));
+//! }
+//!
+//! server.serve(|_| Response::new(200));
+//! ```
+//!
+
+use crate::{events, tls};
+use rustls::{
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+};
+use std::{
+    collections::HashMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock, Weak},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+///
+/// Certificates of a TLS listener: a default one, and others served to clients asking
+/// for specific hostnames through SNI.
+///
+/// Cheap to clone, clones share the same certificates, see [Certificates::reload].
+///
+#[derive(Clone)]
+pub struct Certificates {
+    store: Arc<RwLock<Store>>,
+}
+
+struct Store {
+    sources: Vec<Source>,
+    default: Arc<CertifiedKey>,
+
+    ///
+    /// Certificates by lowercase hostname, possibly a `*.` wildcard
+    ///
+    named: HashMap<String, Arc<CertifiedKey>>,
+}
+
+///
+/// Files a certificate is loaded from, `hostname` being `None` for the default one.
+///
+#[derive(Clone)]
+struct Source {
+    hostname: Option<String>,
+    cert_path: PathBuf,
+    key_path: PathBuf,
+}
+
+impl Source {
+    ///
+    /// Modification times of the files, to notice renewals.
+    ///
+    fn modified(&self) -> [Option<SystemTime>; 2] {
+        [&self.cert_path, &self.key_path]
+            .map(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok())
+    }
+}
+
+impl Certificates {
+    ///
+    /// Loads the default certificate chain and private key.
+    ///
+    pub(crate) fn new(cert_path: &Path, key_path: &Path) -> io::Result<Certificates> {
+        let source = Source {
+            hostname: None,
+            cert_path: cert_path.to_path_buf(),
+            key_path: key_path.to_path_buf(),
+        };
+
+        let store = Store {
+            default: tls::certified_key(cert_path, key_path)?,
+            named: HashMap::new(),
+            sources: vec![source],
+        };
+
+        Ok(Certificates {
+            store: Arc::new(RwLock::new(store)),
+        })
+    }
+
+    ///
+    /// Loads a certificate served to clients asking for `hostname`, replacing any
+    /// previous one for it.
+    ///
+    pub(crate) fn add(&self, hostname: &str, cert_path: &Path, key_path: &Path) -> io::Result<()> {
+        let hostname = hostname.to_ascii_lowercase();
+        let key = tls::certified_key(cert_path, key_path)?;
+
+        let mut store = self.store.write().unwrap_or_else(|e| e.into_inner());
+
+        store
+            .sources
+            .retain(|source| source.hostname.as_ref() != Some(&hostname));
+        store.sources.push(Source {
+            hostname: Some(hostname.clone()),
+            cert_path: cert_path.to_path_buf(),
+            key_path: key_path.to_path_buf(),
+        });
+        store.named.insert(hostname, key);
+
+        Ok(())
+    }
+
+    ///
+    /// Reloads every certificate from disk. New connections get the reloaded
+    /// certificates, established ones keep theirs.
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<()>` -> An [std::io] error if any certificate fails to load, in
+    ///   which case all the previous ones remain in use
+    ///
+    pub fn reload(&self) -> io::Result<()> {
+        let sources = self.sources();
+        let mut default = None;
+        let mut named = HashMap::new();
+
+        for source in &sources {
+            let key = tls::certified_key(&source.cert_path, &source.key_path)?;
+
+            match &source.hostname {
+                Some(hostname) => {
+                    named.insert(hostname.clone(), key);
+                }
+                None => default = Some(key),
+            }
+        }
+
+        let mut store = self.store.write().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(default) = default {
+            store.default = default;
+        }
+
+        store.named = named;
+
+        Ok(())
+    }
+
+    ///
+    /// Checks the certificate and key files for changes every `interval` on a
+    /// background thread, and reloads them when they change.
+    ///
+    /// A failed reload (e.g., files caught mid-write) is reported through the `tracing`
+    /// or `log` features and retried on the next check. The thread stops once the server
+    /// is dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` -> Time between checks
+    ///
+    pub fn watch(&self, interval: Duration) {
+        let store = Arc::downgrade(&self.store);
+        let mut seen: Vec<_> = self.sources().iter().map(Source::modified).collect();
+
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+
+            let Some(store) = Weak::upgrade(&store) else {
+                break;
+            };
+
+            let certificates = Certificates { store };
+            let modified: Vec<_> = certificates
+                .sources()
+                .iter()
+                .map(Source::modified)
+                .collect();
+
+            if modified == seen {
+                continue;
+            }
+
+            match certificates.reload() {
+                Ok(()) => seen = modified,
+                Err(e) => events::certificates_reload_failed(&e),
+            }
+        });
+    }
+
+    fn sources(&self) -> Vec<Source> {
+        let store = self.store.read().unwrap_or_else(|e| e.into_inner());
+
+        store.sources.clone()
+    }
+
+    ///
+    /// Picks the certificate for the hostname a client asked for: an exact match, then
+    /// a wildcard covering its first label, then the default.
+    ///
+    fn select(&self, server_name: Option<&str>) -> Arc<CertifiedKey> {
+        let store = self.store.read().unwrap_or_else(|e| e.into_inner());
+
+        let Some(name) = server_name.map(str::to_ascii_lowercase) else {
+            return store.default.clone();
+        };
+
+        let wildcard = name
+            .split_once('.')
+            .map(|(_, parent)| format!("*.{}", parent));
+
+        store
+            .named
+            .get(&name)
+            .or_else(|| wildcard.and_then(|wildcard| store.named.get(&wildcard)))
+            .unwrap_or(&store.default)
+            .clone()
+    }
+}
+
+impl ResolvesServerCert for Certificates {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.select(client_hello.server_name()))
+    }
+}
+
+impl fmt::Debug for Certificates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let store = self.store.read().unwrap_or_else(|e| e.into_inner());
+        let mut hostnames: Vec<_> = store.named.keys().collect();
+        hostnames.sort();
+
+        f.debug_struct("Certificates")
+            .field("hostnames", &hostnames)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Certificates;
+    use rcgen::CertifiedKey;
+    use std::path::{Path, PathBuf};
+
+    fn write(dir: &Path, name: &str, hostname: &str) -> (PathBuf, PathBuf, CertifiedKey) {
+        let cert = rcgen::generate_simple_self_signed(vec![hostname.to_string()]).unwrap();
+        let (cert_path, key_path) = (
+            dir.join(format!("{}.pem", name)),
+            dir.join(format!("{}.key", name)),
+        );
+
+        std::fs::write(&cert_path, cert.cert.pem()).unwrap();
+        std::fs::write(&key_path, cert.key_pair.serialize_pem()).unwrap();
+
+        (cert_path, key_path, cert)
+    }
+
+    #[test]
+    fn test_certificates_select_and_reload() {
+        let dir = std::env::temp_dir().join(format!("http_rs_certs_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let (cert_path, key_path, default) = write(&dir, "default", "localhost");
+        let (api_cert, api_key, api) = write(&dir, "api", "api.example.com");
+        let (wildcard_cert, wildcard_key, wildcard) = write(&dir, "wildcard", "*.example.com");
+
+        let certificates = Certificates::new(&cert_path, &key_path).unwrap();
+        certificates
+            .add("API.example.com", &api_cert, &api_key)
+            .unwrap();
+        certificates
+            .add("*.example.com", &wildcard_cert, &wildcard_key)
+            .unwrap();
+
+        let served = |name: Option<&str>| certificates.select(name).cert[0].to_vec();
+
+        assert_eq!(served(None), default.cert.der().to_vec());
+        assert_eq!(served(Some("api.example.com")), api.cert.der().to_vec());
+        assert_eq!(
+            served(Some("www.example.com")),
+            wildcard.cert.der().to_vec()
+        );
+        assert_eq!(served(Some("a.b.example.com")), default.cert.der().to_vec());
+
+        // A broken renewal keeps the previous certificates
+        std::fs::write(&api_key, "").unwrap();
+        assert!(certificates.reload().is_err());
+        assert_eq!(served(Some("api.example.com")), api.cert.der().to_vec());
+
+        let (_, _, renewed) = write(&dir, "api", "api.example.com");
+        certificates.reload().unwrap();
+        assert_eq!(served(Some("api.example.com")), renewed.cert.der().to_vec());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
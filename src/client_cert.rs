@@ -0,0 +1,570 @@
+//!
+//! Client certificate (mutual TLS) authentication, enabled with the `tls` feature.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::client_cert::{ClientAuth, SubjectAltName};
+//! use http_rs::server::{Request, Response, Server};
+//!
+//! let server = Server::new_tls("0.0.0.0:8443", "cert.pem", "key.pem")
+//!     .and_then(|server| server.client_auth("ca.pem", ClientAuth::Required))
+//!     .unwrap();
+//!
+//! server.serve(|req: Request| match req.client_certificate() {
+//!     Some(cert) if cert.subject_alt_names().contains(&SubjectAltName::Dns("billing".into())) => {
+//!         Response::new(200).json(&cert.subject())
+//!     }
+//!     _ => Response::new(403),
+//! });
+//! ```
+//!
+
+use crate::{
+    der::{Certificate, Der, OBJECT_IDENTIFIER, OCTET_STRING, SEQUENCE, SET},
+    server::Request,
+};
+use sha2::{Digest, Sha256};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+///
+/// Whether TLS clients must present a certificate, see
+/// [crate::server::Server::client_auth].
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientAuth {
+    ///
+    /// Handshakes without a valid certificate fail
+    ///
+    Required,
+
+    ///
+    /// Clients may connect without a certificate, but one they present must be valid
+    ///
+    Optional,
+}
+
+///
+/// Name of a subject alternative name extension entry.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubjectAltName {
+    Dns(String),
+    Email(String),
+    Uri(String),
+    Ip(IpAddr),
+}
+
+///
+/// Certificate a client authenticated with during the TLS handshake, already verified
+/// against the configured CA bundle.
+///
+#[derive(Debug, Clone)]
+pub struct ClientCertificate {
+    subject: String,
+    common_name: Option<String>,
+    subject_alt_names: Vec<SubjectAltName>,
+    fingerprint: String,
+    der: Vec<u8>,
+}
+
+impl ClientCertificate {
+    ///
+    /// Reads the subject and subject alternative names of a DER encoded X.509
+    /// certificate.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<ClientCertificate>` -> The certificate or `None` if it is malformed
+    ///
+    pub(crate) fn from_der(der: Vec<u8>) -> Option<ClientCertificate> {
+        let cert = Certificate::parse(&der)?;
+        let (subject, common_name) = distinguished_name(cert.subject)?;
+
+        let subject_alt_names = match cert.extensions {
+            Some(extensions) => alt_names(extensions)?,
+            None => Vec::new(),
+        };
+
+        let fingerprint = Sha256::digest(&der)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+
+        Some(ClientCertificate {
+            subject,
+            common_name,
+            subject_alt_names,
+            fingerprint,
+            der,
+        })
+    }
+
+    ///
+    /// Returns the subject distinguished name in RFC 4514 form (e.g.,
+    /// `CN=billing,O=Example`).
+    ///
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    ///
+    /// Returns the common name (`CN`) of the subject, if any.
+    ///
+    pub fn common_name(&self) -> Option<&str> {
+        self.common_name.as_deref()
+    }
+
+    ///
+    /// Returns the names of the subject alternative name extension.
+    ///
+    pub fn subject_alt_names(&self) -> &[SubjectAltName] {
+        &self.subject_alt_names
+    }
+
+    ///
+    /// Returns the SHA-256 fingerprint of the certificate as lowercase hex, e.g. to pin
+    /// known clients.
+    ///
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    ///
+    /// Returns the DER encoded certificate.
+    ///
+    pub fn der(&self) -> &[u8] {
+        &self.der
+    }
+}
+
+impl Request {
+    ///
+    /// Returns the certificate the client authenticated with on a TLS connection, see
+    /// [crate::server::Server::client_auth].
+    ///
+    /// # Example
+    ///
+    /// ```rust, no_run
+    /// use http_rs::server::{Request, Response};
+    ///
+    /// fn handler(req: Request) -> Response {
+    ///     match req.client_certificate().and_then(|cert| cert.common_name()) {
+    ///         Some("admin") => Response::new(200),
+    ///         _ => Response::new(403),
+    ///     }
+    /// }
+    /// ```
+    ///
+    pub fn client_certificate(&self) -> Option<&ClientCertificate> {
+        self.extensions.get::<ClientCertificate>()
+    }
+}
+
+const SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+
+///
+/// Formats an X.501 `Name` per RFC 4514, most significant RDN last, also returning its
+/// first common name.
+///
+fn distinguished_name(mut name: Der) -> Option<(String, Option<String>)> {
+    let mut rdns = Vec::new();
+    let mut common_name = None;
+
+    while let Some((SET, rdn)) = name.next() {
+        let mut rdn = Der::new(rdn);
+        let mut attributes = Vec::new();
+
+        while let Some((SEQUENCE, attribute)) = rdn.next() {
+            let mut attribute = Der::new(attribute);
+            let (OBJECT_IDENTIFIER, oid) = attribute.next()? else {
+                return None;
+            };
+            let (tag, raw) = attribute.next()?;
+            let value = string(tag, raw);
+
+            let name = match oid {
+                [0x55, 0x04, 0x03] => "CN".to_string(),
+                [0x55, 0x04, 0x06] => "C".to_string(),
+                [0x55, 0x04, 0x07] => "L".to_string(),
+                [0x55, 0x04, 0x08] => "ST".to_string(),
+                [0x55, 0x04, 0x09] => "STREET".to_string(),
+                [0x55, 0x04, 0x0a] => "O".to_string(),
+                [0x55, 0x04, 0x0b] => "OU".to_string(),
+                [0x09, 0x92, 0x26, 0x89, 0x93, 0xf2, 0x2c, 0x64, 0x01, 0x01] => "UID".to_string(),
+                [0x09, 0x92, 0x26, 0x89, 0x93, 0xf2, 0x2c, 0x64, 0x01, 0x19] => "DC".to_string(),
+                _ => dotted(oid)?,
+            };
+
+            if name == "CN" && common_name.is_none() {
+                common_name = value.clone();
+            }
+
+            let value = match value {
+                Some(value) => escape(&value),
+                // Values that aren't strings are written as the hex of their encoding
+                None => std::iter::once(format!("#{:02x}{:02x}", tag, raw.len()))
+                    .chain(raw.iter().map(|b| format!("{:02x}", b)))
+                    .collect(),
+            };
+
+            attributes.push(format!("{}={}", name, value));
+        }
+
+        rdns.push(attributes.join("+"));
+    }
+
+    rdns.reverse();
+
+    Some((rdns.join(","), common_name))
+}
+
+///
+/// Decodes the ASN.1 string types used in names, `None` for other types.
+///
+fn string(tag: u8, value: &[u8]) -> Option<String> {
+    match tag {
+        // UTF8String, PrintableString, IA5String
+        0x0c | 0x13 | 0x16 => String::from_utf8(value.to_vec()).ok(),
+        // TeletexString, in practice Latin-1
+        0x14 => Some(value.iter().map(|&b| b as char).collect()),
+        // BMPString
+        0x1e => char::decode_utf16(
+            value
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]])),
+        )
+        .collect::<Result<_, _>>()
+        .ok(),
+        _ => None,
+    }
+}
+
+///
+/// Escapes the characters RFC 4514 reserves in attribute values.
+///
+fn escape(value: &str) -> String {
+    let last = value.chars().count().saturating_sub(1);
+
+    value
+        .chars()
+        .enumerate()
+        .map(|(i, c)| match c {
+            ',' | '+' | '"' | '\\' | '<' | '>' | ';' => format!("\\{}", c),
+            '#' if i == 0 => "\\#".to_string(),
+            ' ' if i == 0 || i == last => "\\ ".to_string(),
+            '\0' => "\\00".to_string(),
+            _ => c.to_string(),
+        })
+        .collect()
+}
+
+///
+/// Formats an object identifier of an unknown attribute type in dotted decimal form.
+///
+fn dotted(oid: &[u8]) -> Option<String> {
+    let mut arcs = Vec::new();
+    let mut arc: u64 = 0;
+
+    for &b in oid {
+        arc = arc.checked_mul(128)? | (b & 0x7f) as u64;
+
+        if b & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (arc / 40).min(2);
+                arcs.push(first);
+                arcs.push(arc - first * 40);
+            } else {
+                arcs.push(arc);
+            }
+
+            arc = 0;
+        }
+    }
+
+    let arcs: Vec<String> = arcs.iter().map(u64::to_string).collect();
+
+    Some(arcs.join("."))
+}
+
+///
+/// Reads the subject alternative names out of the certificate extensions.
+///
+fn alt_names(mut extensions: Der) -> Option<Vec<SubjectAltName>> {
+    while let Some((SEQUENCE, extension)) = extensions.next() {
+        let mut extension = Der::new(extension);
+
+        if extension.expect(OBJECT_IDENTIFIER)?.bytes != SUBJECT_ALT_NAME {
+            continue;
+        }
+
+        // The optional critical flag precedes the value
+        let value = loop {
+            match extension.next()? {
+                (OCTET_STRING, value) => break value,
+                _ => continue,
+            }
+        };
+
+        let mut names = Der::new(value).expect(SEQUENCE)?;
+        let mut alt_names = Vec::new();
+
+        while let Some((tag, value)) = names.next() {
+            let text = || String::from_utf8(value.to_vec()).ok();
+
+            let name = match tag {
+                0x81 => SubjectAltName::Email(text()?),
+                0x82 => SubjectAltName::Dns(text()?),
+                0x86 => SubjectAltName::Uri(text()?),
+                0x87 => match value.len() {
+                    4 => {
+                        SubjectAltName::Ip(Ipv4Addr::from(<[u8; 4]>::try_from(value).ok()?).into())
+                    }
+                    16 => {
+                        SubjectAltName::Ip(Ipv6Addr::from(<[u8; 16]>::try_from(value).ok()?).into())
+                    }
+                    _ => continue,
+                },
+                // Other names, directory names and registered IDs aren't exposed
+                _ => continue,
+            };
+
+            alt_names.push(name);
+        }
+
+        return Some(alt_names);
+    }
+
+    Some(Vec::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClientAuth, ClientCertificate, SubjectAltName};
+    use crate::der::{Der, OBJECT_IDENTIFIER, SEQUENCE, SET};
+    use crate::server::{Request, Response, Server};
+    use rcgen::{
+        BasicConstraints, CertificateParams, DistinguishedName, DnType, ExtendedKeyUsagePurpose,
+        IsCa, KeyPair, SanType,
+    };
+    use rustls::{
+        crypto::ring,
+        pki_types::{PrivateKeyDer, ServerName},
+        ClientConfig, ClientConnection, RootCertStore,
+    };
+    use std::{
+        io::{Read, Write},
+        net::{IpAddr, Ipv4Addr, Ipv6Addr, TcpStream},
+        sync::Arc,
+        thread,
+    };
+
+    #[test]
+    fn test_client_certificate_from_der() {
+        let mut params = CertificateParams::new(vec!["billing.internal".to_string()]).unwrap();
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "billing");
+        params
+            .distinguished_name
+            .push(DnType::OrganizationName, "Example, Inc.");
+        params
+            .subject_alt_names
+            .push(SanType::IpAddress(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+        params
+            .subject_alt_names
+            .push(SanType::Rfc822Name("ops@example.com".try_into().unwrap()));
+
+        let key = KeyPair::generate().unwrap();
+        let cert = params.self_signed(&key).unwrap();
+
+        let parsed = ClientCertificate::from_der(cert.der().to_vec()).unwrap();
+
+        assert_eq!(parsed.subject(), "O=Example\\, Inc.,CN=billing");
+        assert_eq!(parsed.common_name(), Some("billing"));
+        assert_eq!(
+            parsed.subject_alt_names(),
+            [
+                SubjectAltName::Dns("billing.internal".to_string()),
+                SubjectAltName::Ip(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                SubjectAltName::Email("ops@example.com".to_string()),
+            ]
+        );
+        assert_eq!(parsed.fingerprint().len(), 64);
+        assert_eq!(parsed.der(), cert.der().as_ref());
+
+        assert_eq!(
+            super::dotted(&[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d]).unwrap(),
+            "1.2.840.113549"
+        );
+        assert!(ClientCertificate::from_der(vec![0x30, 0x05, 0x01]).is_none());
+    }
+
+    /// DER value with a short form length
+    fn tlv(tag: u8, value: &[u8]) -> Vec<u8> {
+        [&[tag, value.len() as u8][..], value].concat()
+    }
+
+    /// Relative distinguished name of (attribute type, string tag, value) attributes
+    fn rdn(attributes: &[(&[u8], u8, &[u8])]) -> Vec<u8> {
+        let attributes: Vec<u8> = attributes
+            .iter()
+            .flat_map(|(oid, tag, value)| {
+                tlv(
+                    SEQUENCE,
+                    &[tlv(OBJECT_IDENTIFIER, oid), tlv(*tag, value)].concat(),
+                )
+            })
+            .collect();
+
+        tlv(SET, &attributes)
+    }
+
+    #[test]
+    fn test_distinguished_name() {
+        const DC: &[u8] = &[0x09, 0x92, 0x26, 0x89, 0x93, 0xf2, 0x2c, 0x64, 0x01, 0x19];
+        const UID: &[u8] = &[0x09, 0x92, 0x26, 0x89, 0x93, 0xf2, 0x2c, 0x64, 0x01, 0x01];
+        const EMAIL: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x01];
+
+        let name = [
+            rdn(&[(DC, 0x16, b"com")]),
+            // Multi-valued, with characters to escape
+            rdn(&[(&[0x55, 0x04, 0x0a], 0x0c, b"#1 a+b "), (UID, 0x0c, b"u1")]),
+            // BMPString
+            rdn(&[(
+                &[0x55, 0x04, 0x03],
+                0x1e,
+                &[0x00, b'Z', 0x00, b'o', 0x00, 0xeb],
+            )]),
+            // Unknown type holding an INTEGER rather than a string
+            rdn(&[(EMAIL, 0x02, &[0x05])]),
+        ]
+        .concat();
+
+        let (subject, common_name) = super::distinguished_name(Der::new(&name)).unwrap();
+
+        assert_eq!(
+            subject,
+            "1.2.840.113549.1.9.1=#020105,CN=Zoë,O=\\#1 a\\+b\\ +UID=u1,DC=com"
+        );
+        assert_eq!(common_name.as_deref(), Some("Zoë"));
+
+        // TeletexString is read as Latin-1, other types aren't strings
+        assert_eq!(super::string(0x14, b"caf\xe9").as_deref(), Some("café"));
+        assert_eq!(super::string(0x04, b"raw"), None);
+        assert_eq!(super::escape("a,b;c\0"), "a\\,b\\;c\\00");
+
+        // An attribute without an object identifier is malformed
+        let bad = tlv(SET, &tlv(SEQUENCE, &tlv(0x0c, b"x")));
+        assert!(super::distinguished_name(Der::new(&bad)).is_none());
+    }
+
+    #[test]
+    fn test_subject_alt_names() {
+        let mut params = CertificateParams::new(Vec::new()).unwrap();
+        params.subject_alt_names.push(SanType::URI(
+            "spiffe://example.org/billing".try_into().unwrap(),
+        ));
+        params
+            .subject_alt_names
+            .push(SanType::IpAddress(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+
+        let cert = params.self_signed(&KeyPair::generate().unwrap()).unwrap();
+        let parsed = ClientCertificate::from_der(cert.der().to_vec()).unwrap();
+
+        assert_eq!(
+            parsed.subject_alt_names(),
+            [
+                SubjectAltName::Uri("spiffe://example.org/billing".to_string()),
+                SubjectAltName::Ip(IpAddr::V6(Ipv6Addr::LOCALHOST)),
+            ]
+        );
+
+        // Without the extension there are no alternative names, nor a common name
+        let mut params = CertificateParams::new(Vec::new()).unwrap();
+        params.distinguished_name = DistinguishedName::new();
+
+        let cert = params.self_signed(&KeyPair::generate().unwrap()).unwrap();
+        let parsed = ClientCertificate::from_der(cert.der().to_vec()).unwrap();
+
+        assert!(parsed.subject_alt_names().is_empty());
+        assert_eq!(parsed.common_name(), None);
+    }
+
+    #[test]
+    fn test_client_auth_round_trip() {
+        let mut ca = CertificateParams::new(Vec::new()).unwrap();
+        ca.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        ca.distinguished_name.push(DnType::CommonName, "Test CA");
+        let ca_key = KeyPair::generate().unwrap();
+        let ca = ca.self_signed(&ca_key).unwrap();
+
+        let server_cert =
+            rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+
+        let mut client = CertificateParams::new(vec!["billing.internal".to_string()]).unwrap();
+        client
+            .distinguished_name
+            .push(DnType::CommonName, "billing");
+        client.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+        let client_key = KeyPair::generate().unwrap();
+        let client = client.signed_by(&client_key, &ca, &ca_key).unwrap();
+
+        let dir = std::env::temp_dir().join(format!("http_rs_mtls_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("cert.pem"), server_cert.cert.pem()).unwrap();
+        std::fs::write(dir.join("key.pem"), server_cert.key_pair.serialize_pem()).unwrap();
+        std::fs::write(dir.join("ca.pem"), ca.pem()).unwrap();
+
+        let server = Server::new_tls("127.0.0.1:0", dir.join("cert.pem"), dir.join("key.pem"))
+            .and_then(|server| server.client_auth(dir.join("ca.pem"), ClientAuth::Required))
+            .unwrap()
+            .workers(1);
+        let addr = server.local_addr().unwrap();
+
+        thread::spawn(move || {
+            server.serve(|req: Request| {
+                let cert = req.client_certificate().unwrap();
+                Response::new(200).json(&(cert.subject(), cert.subject_alt_names().len()))
+            })
+        });
+
+        let mut roots = RootCertStore::empty();
+        roots.add(server_cert.cert.der().clone()).unwrap();
+
+        let builder = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots);
+
+        let get = |config: ClientConfig| {
+            let conn =
+                ClientConnection::new(Arc::new(config), ServerName::try_from("localhost").unwrap())
+                    .unwrap();
+            let mut client = rustls::StreamOwned::new(conn, TcpStream::connect(addr).unwrap());
+            let mut raw = Vec::new();
+
+            let _ = client
+                .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+                .and_then(|_| client.read_to_end(&mut raw));
+
+            String::from_utf8_lossy(&raw).into_owned()
+        };
+
+        let raw = get(builder
+            .clone()
+            .with_client_auth_cert(
+                vec![client.der().clone()],
+                PrivateKeyDer::try_from(client_key.serialize_der()).unwrap(),
+            )
+            .unwrap());
+
+        assert!(raw.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(raw.ends_with("[\"CN=billing\",1]"));
+
+        // Without a certificate the handshake is refused
+        assert!(get(builder.with_no_client_auth()).is_empty());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
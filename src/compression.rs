@@ -0,0 +1,472 @@
+//!
+//! Response compression negotiated with the client's `Accept-Encoding`.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::compression::Compression;
+//! use http_rs::router::Router;
+//!
+//! let mut router = Router::new();
+//!
+//! // Only compress bodies of at least 1 KiB
+//! router.layer(Compression::new().min_size(1024));
+//! ```
+//!
+
+use crate::body::{Body, Producer};
+use crate::middleware::{Middleware, Next};
+use crate::server::{Request, Response};
+use flate2::{
+    read::{GzEncoder, ZlibEncoder},
+    write,
+};
+use std::io::{Read, Write};
+
+///
+/// Default minimum body size, in bytes, for a response to be compressed.
+///
+const DEFAULT_MIN_SIZE: usize = 1024;
+
+///
+/// Content codings supported by [Compression].
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Gzip,
+
+    ///
+    /// The zlib format, which is what `deflate` means in HTTP
+    ///
+    Deflate,
+}
+
+impl Encoding {
+    ///
+    /// Returns the `Content-Encoding` token of the coding.
+    ///
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+}
+
+///
+/// [Middleware] compressing response bodies with gzip or deflate.
+///
+/// The coding is picked from the request's `Accept-Encoding` (preferring gzip on ties)
+/// and announced with `Content-Encoding`, while `Vary: Accept-Encoding` is added to
+/// every eligible response so caches keep the variants apart.
+/// Bodies smaller than [Compression::min_size], responses that already carry a
+/// `Content-Encoding`, partial (`206`) responses, whose `Content-Range` counts bytes of
+/// the uncompressed body, and already compressed media types are left untouched.
+/// Streamed bodies are always compressed on the fly.
+///
+/// A compressed response gets its own `ETag`, the coding appended to the tag (e.g.,
+/// `"v1-gzip"`), as its bytes differ from the uncompressed one's. `If-None-Match` tags
+/// naming the negotiated coding are passed on without it, so handlers keep answering
+/// `304`, while `If-Range` ones no longer match and get the whole body.
+///
+pub struct Compression {
+    min_size: usize,
+    level: flate2::Compression,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Compression {
+            min_size: DEFAULT_MIN_SIZE,
+            level: flate2::Compression::default(),
+        }
+    }
+}
+
+impl Compression {
+    ///
+    /// Creates the compression [Middleware] with a 1 KiB threshold and the default level.
+    ///
+    pub fn new() -> Compression {
+        Compression::default()
+    }
+
+    ///
+    /// Sets the minimum body size in bytes below which responses are sent uncompressed.
+    ///
+    pub fn min_size(mut self, min_size: usize) -> Compression {
+        self.min_size = min_size;
+
+        self
+    }
+
+    ///
+    /// Sets the compression level, from 0 (none) to 9 (best).
+    ///
+    pub fn level(mut self, level: u32) -> Compression {
+        self.level = flate2::Compression::new(level.min(9));
+
+        self
+    }
+
+    ///
+    /// Whether the [Response] is worth compressing at all.
+    ///
+    fn is_compressible(&self, res: &Response) -> bool {
+        if matches!(res.status, 100..=199 | 204 | 206 | 304)
+            || res.headers.get("Content-Encoding").is_some()
+            || res.headers.get("Content-Range").is_some()
+            || res
+                .headers
+                .get("Content-Type")
+                .is_some_and(is_compressed_type)
+        {
+            return false;
+        }
+
+        match &res.body {
+            Body::Full(bytes) => bytes.len() >= self.min_size,
+            Body::File { len, .. } => *len >= self.min_size as u64,
+            Body::Stream(_) | Body::Producer(_) => true,
+        }
+    }
+
+    ///
+    /// Wraps a streamed body in an encoder compressing it as it is read.
+    ///
+    fn encoder(&self, reader: Box<dyn Read + Send>, encoding: Encoding) -> Body {
+        match encoding {
+            Encoding::Gzip => Body::Stream(Box::new(GzEncoder::new(reader, self.level))),
+            Encoding::Deflate => Body::Stream(Box::new(ZlibEncoder::new(reader, self.level))),
+        }
+    }
+
+    ///
+    /// Wraps a produced body in an encoder compressing it as it is written.
+    ///
+    fn write_encoder(&self, produce: Producer, encoding: Encoding) -> Body {
+        let level = self.level;
+
+        Body::from_fn(move |out| match encoding {
+            Encoding::Gzip => {
+                let mut encoder = write::GzEncoder::new(out, level);
+                produce(&mut encoder)?;
+                encoder.finish().map(drop)
+            }
+            Encoding::Deflate => {
+                let mut encoder = write::ZlibEncoder::new(out, level);
+                produce(&mut encoder)?;
+                encoder.finish().map(drop)
+            }
+        })
+    }
+
+    ///
+    /// Compresses a full body, returning None if compression doesn't shrink it.
+    ///
+    fn compress(&self, bytes: &[u8], encoding: Encoding) -> Option<Vec<u8>> {
+        let compressed = match encoding {
+            Encoding::Gzip => {
+                let mut encoder = write::GzEncoder::new(Vec::new(), self.level);
+                encoder.write_all(bytes).ok()?;
+                encoder.finish().ok()?
+            }
+            Encoding::Deflate => {
+                let mut encoder = write::ZlibEncoder::new(Vec::new(), self.level);
+                encoder.write_all(bytes).ok()?;
+                encoder.finish().ok()?
+            }
+        };
+
+        (compressed.len() < bytes.len()).then_some(compressed)
+    }
+}
+
+impl Middleware for Compression {
+    fn handle(&self, mut req: Request, next: Next<'_>) -> Response {
+        let encoding = req.headers.get("Accept-Encoding").and_then(negotiate);
+
+        // Tags of the compressed variant name the one handlers know, for revalidating it
+        let if_none_match = match (encoding, req.headers.get("If-None-Match")) {
+            (Some(encoding), Some(tags)) => {
+                let tags = tags.to_string();
+                let uncoded = tags
+                    .split(',')
+                    .map(|tag| uncoded_tag(tag.trim(), encoding))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                req.headers.insert("If-None-Match", uncoded);
+
+                Some(tags)
+            }
+            _ => None,
+        };
+
+        let mut res = next.run(req);
+
+        if res.status == 304 {
+            if let (Some(encoding), Some(tags), Some(tag)) =
+                (encoding, &if_none_match, res.headers.get("ETag"))
+            {
+                let coded = coded_tag(tag, encoding);
+
+                let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+
+                if tags.split(',').any(|tag| opaque(tag) == opaque(&coded)) {
+                    res.headers.insert("ETag", coded);
+                }
+            }
+        }
+
+        if !self.is_compressible(&res) {
+            return res;
+        }
+
+        // The representation depends on Accept-Encoding even if this client gets identity
+        res = res.vary("Accept-Encoding");
+
+        let Some(encoding) = encoding else {
+            return res;
+        };
+
+        match std::mem::take(&mut res.body) {
+            Body::Full(bytes) => match self.compress(&bytes, encoding) {
+                Some(compressed) => {
+                    res.headers
+                        .insert("Content-Length", compressed.len().to_string());
+                    res.body = Body::Full(compressed);
+                }
+                None => {
+                    res.body = Body::Full(bytes);
+
+                    return res;
+                }
+            },
+            Body::Stream(reader) => res.body = self.encoder(reader, encoding),
+            Body::Producer(produce) => res.body = self.write_encoder(produce, encoding),
+            // The compressed length isn't known up front, so the file is streamed chunked
+            Body::File { file, len } => {
+                res = res.body(self.encoder(Box::new(file.take(len)), encoding));
+            }
+        }
+
+        res.headers.insert("Content-Encoding", encoding.as_str());
+
+        if let Some(tag) = res.headers.get("ETag") {
+            let coded = coded_tag(tag, encoding);
+            res.headers.insert("ETag", coded);
+        }
+
+        // Ranges are served from the uncompressed body only
+        res.headers.remove("Accept-Ranges");
+
+        res
+    }
+}
+
+///
+/// Appends the coding to an entity tag (e.g., `"v1"` to `"v1-gzip"`), keeping it weak
+/// if it was.
+///
+fn coded_tag(tag: &str, encoding: Encoding) -> String {
+    match tag.strip_suffix('"') {
+        Some(opaque) => format!("{}-{}\"", opaque, encoding.as_str()),
+        None => tag.to_string(),
+    }
+}
+
+///
+/// Removes the coding [coded_tag] appended, leaving other tags unchanged.
+///
+fn uncoded_tag(tag: &str, encoding: Encoding) -> String {
+    let suffix = format!("-{}\"", encoding.as_str());
+
+    match tag.strip_suffix(&suffix) {
+        Some(opaque) => format!("{}\"", opaque),
+        None => tag.to_string(),
+    }
+}
+
+///
+/// Picks the preferred supported coding from an `Accept-Encoding` value.
+///
+/// # Returns
+///
+/// * `Option<Encoding>` -> The coding with the highest non-zero quality, gzip winning
+///   ties, or None if the client accepts neither
+///
+pub fn negotiate(accept_encoding: &str) -> Option<Encoding> {
+    let mut gzip = None;
+    let mut deflate = None;
+    let mut wildcard = None;
+
+    for item in accept_encoding.split(',') {
+        let mut parts = item.split(';');
+        let coding = parts.next().unwrap_or("").trim();
+
+        let quality = parts
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+
+        match coding.to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => gzip = Some(quality),
+            "deflate" => deflate = Some(quality),
+            "*" => wildcard = Some(quality),
+            _ => {}
+        }
+    }
+
+    let gzip = gzip.or(wildcard).unwrap_or(0.0);
+    let deflate = deflate.or(wildcard).unwrap_or(0.0);
+
+    match (gzip, deflate) {
+        (g, d) if g > 0.0 && g >= d => Some(Encoding::Gzip),
+        (_, d) if d > 0.0 => Some(Encoding::Deflate),
+        _ => None,
+    }
+}
+
+///
+/// Whether a `Content-Type` is already compressed, so compressing again only wastes CPU,
+/// or must reach the client unbuffered.
+///
+fn is_compressed_type(content_type: &str) -> bool {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase();
+
+    match mime.split_once('/') {
+        Some(("image", subtype)) => subtype != "svg+xml",
+        Some(("audio" | "video", _)) => true,
+        _ => matches!(
+            mime.as_str(),
+            "application/zip" | "application/gzip" | "application/pdf" | "font/woff2"
+            // Not compressed, but encoders buffer output and would hold events back
+            | "text/event-stream"
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conditional;
+    use crate::router::Router;
+    use crate::server::{test_request, HttpMethod};
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    fn router() -> Router {
+        let mut router = Router::new();
+
+        router
+            .layer(Compression::new().min_size(64))
+            .get("/big", |_| Response::new(200).json(&"a".repeat(1000)))
+            .get("/small", |_| Response::new(200).json(&"a"))
+            .get("/tagged", |req: Request| {
+                let res = Response::new(200)
+                    .header("ETag", "\"v1\"")
+                    .json(&"a".repeat(1000));
+
+                conditional::evaluate(&req, res)
+            })
+            .get("/partial", |_| {
+                Response::new(206)
+                    .header("Content-Range", "bytes 0-999/2000")
+                    .json(&"a".repeat(1000))
+            });
+
+        router
+    }
+
+    fn request(route: &str, accept_encoding: &str) -> Request {
+        let mut req = test_request(HttpMethod::GET, route);
+        req.headers
+            .insert("Accept-Encoding".to_string(), accept_encoding.to_string());
+
+        req
+    }
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(negotiate("gzip, deflate, br"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("deflate, gzip;q=0.5"), Some(Encoding::Deflate));
+        assert_eq!(negotiate("*;q=0.1"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("gzip;q=0, *"), Some(Encoding::Deflate));
+        assert_eq!(negotiate("br, identity"), None);
+    }
+
+    #[test]
+    fn test_compression_gzip() {
+        let res = router().handle(request("/big", "gzip"));
+
+        assert_eq!(res.headers.get("Content-Encoding"), Some("gzip"));
+        assert_eq!(res.headers.get("Vary"), Some("Accept-Encoding"));
+
+        let compressed = res.body.as_bytes().unwrap();
+        assert_eq!(
+            res.headers.get("Content-Length"),
+            Some(compressed.len().to_string().as_str())
+        );
+
+        let mut body = String::new();
+        GzDecoder::new(compressed)
+            .read_to_string(&mut body)
+            .unwrap();
+
+        assert_eq!(body, format!("\"{}\"", "a".repeat(1000)));
+    }
+
+    #[test]
+    fn test_compression_skipped() {
+        let res = router().handle(request("/small", "gzip"));
+
+        assert_eq!(res.headers.get("Content-Encoding"), None);
+        assert_eq!(res.body.as_bytes(), Some(&b"\"a\""[..]));
+
+        let res = router().handle(request("/big", "br"));
+
+        assert_eq!(res.headers.get("Content-Encoding"), None);
+        assert_eq!(res.headers.get("Vary"), Some("Accept-Encoding"));
+    }
+
+    #[test]
+    fn test_compression_etag() {
+        let res = router().handle(request("/tagged", "gzip"));
+        assert_eq!(res.headers.get("Content-Encoding"), Some("gzip"));
+        assert_eq!(res.headers.get("ETag"), Some("\"v1-gzip\""));
+
+        let res = router().handle(request("/tagged", "deflate"));
+        assert_eq!(res.headers.get("ETag"), Some("\"v1-deflate\""));
+
+        let res = router().handle(request("/tagged", "identity"));
+        assert_eq!(res.headers.get("ETag"), Some("\"v1\""));
+
+        // Revalidating the compressed variant is answered by the handler
+        let mut req = request("/tagged", "gzip");
+        req.headers.insert("If-None-Match", "\"v0\", W/\"v1-gzip\"");
+        let res = router().handle(req);
+        assert_eq!(res.status, 304);
+        assert_eq!(res.headers.get("ETag"), Some("\"v1-gzip\""));
+
+        // The tag of another coding isn't the variant this client gets
+        let mut req = request("/tagged", "gzip");
+        req.headers.insert("If-None-Match", "\"v1-deflate\"");
+        assert_eq!(router().handle(req).status, 200);
+    }
+
+    #[test]
+    fn test_compression_partial_skipped() {
+        let res = router().handle(request("/partial", "gzip"));
+
+        assert_eq!(res.status, 206);
+        assert_eq!(res.headers.get("Content-Encoding"), None);
+        assert_eq!(res.headers.get("Content-Range"), Some("bytes 0-999/2000"));
+        assert_eq!(res.body.as_bytes().unwrap().len(), 1002);
+    }
+}
@@ -0,0 +1,264 @@
+//!
+//! `ETag` generation and conditional `GET` handling with `304 Not Modified`.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::conditional::ConditionalGet;
+//! use http_rs::router::Router;
+//! use http_rs::server::{Request, Response};
+//!
+//! let mut router = Router::new();
+//!
+//! router.layer(ConditionalGet::new());
+//!
+//! // Clients sending back the ETag of this body get a 304 without it
+//! router.get("/users", |_req: Request| Response::new(200).json(&["alice", "bob"]));
+//! ```
+//!
+
+use crate::body::Body;
+use crate::date;
+use crate::middleware::{Middleware, Next};
+use crate::server::{HttpMethod, Request, Response};
+use sha2::{Digest, Sha256};
+
+///
+/// Headers kept on a `304`, as they describe the representation the client has cached.
+///
+const NOT_MODIFIED_HEADERS: [&str; 7] = [
+    "Cache-Control",
+    "Content-Location",
+    "Date",
+    "ETag",
+    "Expires",
+    "Last-Modified",
+    "Vary",
+];
+
+///
+/// [Middleware] adding an `ETag` to buffered `GET` and `HEAD` responses and answering
+/// `304 Not Modified` when the request's `If-None-Match` or `If-Modified-Since` shows
+/// the client's cached copy is still valid.
+///
+/// The `ETag` is a hash of the body, handlers may set their own (e.g., a version
+/// number) to skip hashing, and `If-Modified-Since` is checked against the
+/// `Last-Modified` header set by the handler.
+///
+#[derive(Debug, Default)]
+pub struct ConditionalGet;
+
+impl ConditionalGet {
+    ///
+    /// Creates the conditional request [Middleware].
+    ///
+    pub fn new() -> ConditionalGet {
+        ConditionalGet
+    }
+}
+
+impl Middleware for ConditionalGet {
+    fn handle(&self, req: Request, next: Next<'_>) -> Response {
+        let preconditions = Preconditions::new(&req);
+        let mut res = next.run(req);
+
+        if !preconditions.applies(&res) {
+            return res;
+        }
+
+        if let (None, Body::Full(bytes)) = (res.headers.get("ETag"), &res.body) {
+            let tag = etag(bytes);
+            res.headers.insert("ETag", tag);
+        }
+
+        preconditions.evaluate(res)
+    }
+}
+
+///
+/// Computes a strong `ETag` from the bytes of a body.
+///
+pub fn etag(bytes: &[u8]) -> String {
+    let digest = Sha256::digest(bytes);
+    let hex: String = digest[..16].iter().map(|b| format!("{:02x}", b)).collect();
+
+    format!("\"{}\"", hex)
+}
+
+///
+/// Answers `304 Not Modified` in place of a successful `GET` or `HEAD` [Response] if the
+/// [Request]'s preconditions show the client's cached copy is still valid.
+///
+/// `If-None-Match` is compared with the response's `ETag` (weakly, as RFC 9110 requires
+/// for it), and only when it is absent is `If-Modified-Since` compared with
+/// `Last-Modified`.
+///
+/// # Returns
+///
+/// * `Response` -> A `304` keeping the caching headers, or `res` unchanged
+///
+pub fn evaluate(req: &Request, res: Response) -> Response {
+    Preconditions::new(req).evaluate(res)
+}
+
+///
+/// The parts of a [Request] that decide whether it is conditional, kept so the
+/// [Request] itself can be handed down the middleware chain.
+///
+struct Preconditions {
+    method: HttpMethod,
+    if_none_match: Option<String>,
+    if_modified_since: Option<String>,
+}
+
+impl Preconditions {
+    fn new(req: &Request) -> Preconditions {
+        Preconditions {
+            method: req.method.clone(),
+            if_none_match: req.headers.get("If-None-Match").map(str::to_string),
+            if_modified_since: req.headers.get("If-Modified-Since").map(str::to_string),
+        }
+    }
+
+    ///
+    /// Whether the [Response] is one preconditions are evaluated against.
+    ///
+    fn applies(&self, res: &Response) -> bool {
+        matches!(self.method, HttpMethod::GET | HttpMethod::HEAD) && res.status == 200
+    }
+
+    fn evaluate(&self, res: Response) -> Response {
+        if !self.applies(&res) {
+            return res;
+        }
+
+        let not_modified = match &self.if_none_match {
+            Some(if_none_match) => res
+                .headers
+                .get("ETag")
+                .is_some_and(|tag| matches_any(if_none_match, tag)),
+            None => {
+                let since = self
+                    .if_modified_since
+                    .as_deref()
+                    .and_then(date::parse_http_date);
+                let modified = res
+                    .headers
+                    .get("Last-Modified")
+                    .and_then(date::parse_http_date);
+
+                matches!((since, modified), (Some(since), Some(modified)) if modified <= since)
+            }
+        };
+
+        if !not_modified {
+            return res;
+        }
+
+        let mut not_modified = Response::new(304);
+        not_modified.headers = res
+            .headers
+            .iter()
+            .filter(|(name, _)| {
+                NOT_MODIFIED_HEADERS
+                    .iter()
+                    .any(|kept| kept.eq_ignore_ascii_case(name))
+            })
+            .collect();
+
+        not_modified
+    }
+}
+
+///
+/// Whether an `If-None-Match` value lists `tag`, ignoring weakness.
+///
+fn matches_any(if_none_match: &str, tag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+
+    if_none_match.trim() == "*"
+        || if_none_match
+            .split(',')
+            .any(|candidate| opaque(candidate) == opaque(tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::router::Router;
+    use crate::server::test_request;
+
+    fn router() -> Router {
+        let mut router = Router::new();
+
+        router
+            .layer(ConditionalGet::new())
+            .get("/users", |_| Response::new(200).json(&["alice"]))
+            .get("/report", |_| {
+                Response::new(200)
+                    .header("Last-Modified", "Sun, 06 Nov 1994 08:49:37 GMT")
+                    .header("ETag", "W/\"v1\"")
+                    .text("report")
+            });
+
+        router
+    }
+
+    fn request(route: &str, header: (&str, &str)) -> Request {
+        let mut req = test_request(HttpMethod::GET, route);
+        req.headers.insert(header.0, header.1);
+
+        req
+    }
+
+    #[test]
+    fn test_etag_if_none_match() {
+        let router = router();
+
+        let res = router.handle(test_request(HttpMethod::GET, "/users"));
+        let tag = res.headers.get("ETag").unwrap().to_string();
+
+        assert_eq!(tag, etag(b"[\"alice\"]"));
+
+        let res = router.handle(request(
+            "/users",
+            ("If-None-Match", &format!("\"x\", {}", tag)),
+        ));
+
+        assert_eq!(res.status, 304);
+        assert_eq!(res.headers.get("ETag"), Some(tag.as_str()));
+        assert_eq!(res.headers.get("Content-Length"), None);
+        assert_eq!(res.body.as_bytes(), Some(&b""[..]));
+
+        let res = router.handle(request("/users", ("If-None-Match", "\"stale\"")));
+
+        assert_eq!(res.status, 200);
+    }
+
+    #[test]
+    fn test_if_modified_since() {
+        let router = router();
+
+        let res = router.handle(request(
+            "/report",
+            ("If-Modified-Since", "Sun, 06 Nov 1994 08:49:37 GMT"),
+        ));
+
+        assert_eq!(res.status, 304);
+        assert_eq!(res.headers.get("ETag"), Some("W/\"v1\""));
+
+        let res = router.handle(request(
+            "/report",
+            ("If-Modified-Since", "Sat, 05 Nov 1994 08:49:37 GMT"),
+        ));
+
+        assert_eq!(res.status, 200);
+
+        // If-None-Match takes precedence
+        let mut req = request("/report", ("If-None-Match", "\"v2\""));
+        req.headers
+            .insert("If-Modified-Since", "Sun, 06 Nov 1994 08:49:37 GMT");
+
+        assert_eq!(router.handle(req).status, 200);
+    }
+}
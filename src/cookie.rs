@@ -0,0 +1,289 @@
+//!
+//! Cookie parsing for requests and `Set-Cookie` building for responses.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::cookie::{Cookie, SameSite};
+//! use http_rs::server::{Request, Response};
+//! use std::time::Duration;
+//!
+//! fn handler(req: Request) -> Response {
+//!     let visits = req
+//!         .cookies()
+//!         .get("visits")
+//!         .and_then(|v| v.parse::<u32>().ok())
+//!         .unwrap_or(0);
+//!
+//!     Response::new(200).json(&visits).set_cookie(
+//!         Cookie::new("visits", &(visits + 1).to_string())
+//!             .path("/")
+//!             .max_age(Duration::from_secs(3600))
+//!             .http_only(true)
+//!             .same_site(SameSite::Lax),
+//!     )
+//! }
+//! ```
+//!
+
+use crate::server::{Request, Response};
+use std::{collections::HashMap, fmt, time::Duration};
+
+///
+/// Alias for request cookies as KV pairs.
+///
+pub type Cookies = HashMap<String, String>;
+
+///
+/// Values of the `SameSite` cookie attribute.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+
+    ///
+    /// Sent on cross-site requests too, browsers require [Cookie::secure] with it
+    ///
+    None,
+}
+
+///
+/// A cookie to send to the client with `Set-Cookie`.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    domain: Option<String>,
+    max_age: Option<Duration>,
+    http_only: bool,
+    secure: bool,
+    same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    ///
+    /// Creates a [Cookie] with the given name and value and no attributes.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` -> Cookie name
+    /// * `value` -> Cookie value
+    ///
+    pub fn new(name: &str, value: &str) -> Cookie {
+        Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            path: None,
+            domain: None,
+            max_age: None,
+            http_only: false,
+            secure: false,
+            same_site: None,
+        }
+    }
+
+    ///
+    /// Sets the `Path` the cookie is sent for.
+    ///
+    pub fn path(mut self, path: &str) -> Cookie {
+        self.path = Some(path.to_string());
+
+        self
+    }
+
+    ///
+    /// Sets the `Domain` the cookie is sent to.
+    ///
+    pub fn domain(mut self, domain: &str) -> Cookie {
+        self.domain = Some(domain.to_string());
+
+        self
+    }
+
+    ///
+    /// Sets `Max-Age`, how long the client keeps the cookie. A zero duration deletes it.
+    ///
+    pub fn max_age(mut self, max_age: Duration) -> Cookie {
+        self.max_age = Some(max_age);
+
+        self
+    }
+
+    ///
+    /// Sets `HttpOnly`, hiding the cookie from scripts.
+    ///
+    pub fn http_only(mut self, http_only: bool) -> Cookie {
+        self.http_only = http_only;
+
+        self
+    }
+
+    ///
+    /// Sets `Secure`, only sending the cookie over HTTPS.
+    ///
+    pub fn secure(mut self, secure: bool) -> Cookie {
+        self.secure = secure;
+
+        self
+    }
+
+    ///
+    /// Sets the [SameSite] policy.
+    ///
+    pub fn same_site(mut self, same_site: SameSite) -> Cookie {
+        self.same_site = Some(same_site);
+
+        self
+    }
+
+    ///
+    /// Returns the cookie name.
+    ///
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    ///
+    /// Returns the cookie value.
+    ///
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+///
+/// Formats the cookie as a `Set-Cookie` header value.
+///
+impl fmt::Display for Cookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+
+        if let Some(path) = &self.path {
+            write!(f, "; Path={}", path)?;
+        }
+
+        if let Some(domain) = &self.domain {
+            write!(f, "; Domain={}", domain)?;
+        }
+
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age.as_secs())?;
+        }
+
+        if self.http_only {
+            f.write_str("; HttpOnly")?;
+        }
+
+        if self.secure {
+            f.write_str("; Secure")?;
+        }
+
+        match self.same_site {
+            Some(SameSite::Strict) => f.write_str("; SameSite=Strict"),
+            Some(SameSite::Lax) => f.write_str("; SameSite=Lax"),
+            Some(SameSite::None) => f.write_str("; SameSite=None"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Request {
+    ///
+    /// Parses the `Cookie` header into name/value pairs.
+    ///
+    /// # Returns
+    ///
+    /// * `Cookies` -> The request [Cookies], empty if the header is missing
+    ///
+    pub fn cookies(&self) -> Cookies {
+        self.headers
+            .get_all("Cookie")
+            .flat_map(parse_cookies)
+            .collect()
+    }
+}
+
+impl Response {
+    ///
+    /// Adds a `Set-Cookie` header for the [Cookie], keeping any cookies set before it.
+    ///
+    /// # Arguments
+    ///
+    /// * `cookie` -> The [Cookie] to send
+    ///
+    pub fn set_cookie(self, cookie: Cookie) -> Response {
+        self.append_header("Set-Cookie", &cookie.to_string())
+    }
+}
+
+///
+/// Parses a `Cookie` header value (e.g., `a=1; b=2`) into name/value pairs.
+///
+fn parse_cookies(header: &str) -> impl Iterator<Item = (String, String)> + '_ {
+    header.split(';').filter_map(|pair| {
+        let (name, value) = pair.split_once('=')?;
+        let name = name.trim();
+
+        if name.is_empty() {
+            return None;
+        }
+
+        // Values may be wrapped in double quotes
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value);
+
+        Some((name.to_string(), value.to_string()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::{test_request, HttpMethod};
+
+    #[test]
+    fn test_request_cookies() {
+        let mut req = test_request(HttpMethod::GET, "/");
+        req.headers
+            .insert("Cookie".to_string(), "a=1; b=\"two\";  c=".to_string());
+
+        let cookies = req.cookies();
+
+        assert_eq!(cookies.get("a"), Some(&"1".to_string()));
+        assert_eq!(cookies.get("b"), Some(&"two".to_string()));
+        assert_eq!(cookies.get("c"), Some(&"".to_string()));
+    }
+
+    #[test]
+    fn test_set_cookie_attributes() {
+        let res = Response::new(200)
+            .set_cookie(Cookie::new("session", "abc").path("/").http_only(true))
+            .set_cookie(
+                Cookie::new("theme", "dark")
+                    .max_age(Duration::from_secs(60))
+                    .secure(true)
+                    .same_site(SameSite::None),
+            );
+
+        let cookies: Vec<_> = res
+            .headers
+            .iter()
+            .filter(|(k, _)| *k == "Set-Cookie")
+            .map(|(_, v)| v)
+            .collect();
+
+        assert_eq!(
+            cookies,
+            [
+                "session=abc; Path=/; HttpOnly",
+                "theme=dark; Max-Age=60; Secure; SameSite=None"
+            ]
+        );
+    }
+}
@@ -0,0 +1,248 @@
+//!
+//! Calendar conversion of [SystemTime] for the date formats used on the wire and in logs.
+//!
+
+use std::{
+    cell::RefCell,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const DAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+///
+/// A UTC date and time broken into calendar fields.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct DateTime {
+    pub(crate) year: i64,
+    pub(crate) month: u32,
+    pub(crate) day: u32,
+    pub(crate) hour: u32,
+    pub(crate) minute: u32,
+    pub(crate) second: u32,
+    pub(crate) weekday: u32,
+}
+
+impl DateTime {
+    ///
+    /// Converts a [SystemTime] to UTC calendar fields, clamping times before the epoch.
+    ///
+    pub(crate) fn from_system_time(time: SystemTime) -> DateTime {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64);
+
+        let (days, rem) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+        let (year, month, day) = civil_from_days(days);
+
+        DateTime {
+            year,
+            month,
+            day,
+            hour: (rem / 3600) as u32,
+            minute: (rem % 3600 / 60) as u32,
+            second: (rem % 60) as u32,
+            // 1970-01-01 was a Thursday
+            weekday: (days + 4).rem_euclid(7) as u32,
+        }
+    }
+
+    ///
+    /// Returns the English abbreviation of the month (e.g., `Oct`).
+    ///
+    pub(crate) fn month_name(&self) -> &'static str {
+        MONTHS[self.month as usize - 1]
+    }
+}
+
+///
+/// Formats a time as in Apache access logs (e.g., `10/Oct/2000:13:55:36 +0000`).
+///
+pub(crate) fn clf(time: SystemTime) -> String {
+    let dt = DateTime::from_system_time(time);
+
+    format!(
+        "{:02}/{}/{}:{:02}:{:02}:{:02} +0000",
+        dt.day,
+        dt.month_name(),
+        dt.year,
+        dt.hour,
+        dt.minute,
+        dt.second
+    )
+}
+
+///
+/// Formats a time as an HTTP date (IMF-fixdate, e.g., `Sun, 06 Nov 1994 08:49:37 GMT`).
+///
+pub(crate) fn http_date(time: SystemTime) -> String {
+    let dt = DateTime::from_system_time(time);
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        DAYS[dt.weekday as usize],
+        dt.day,
+        dt.month_name(),
+        dt.year,
+        dt.hour,
+        dt.minute,
+        dt.second
+    )
+}
+
+thread_local! {
+    ///
+    /// The second [now] was last formatted for, with its formatted value.
+    ///
+    static NOW: RefCell<(u64, String)> = const { RefCell::new((u64::MAX, String::new())) };
+}
+
+///
+/// Returns the current time as an HTTP date, for the `Date` header of responses.
+///
+/// The value only changes once per second, so it is formatted at most once per second
+/// and thread, every other call copying the cached string.
+///
+pub(crate) fn now() -> String {
+    let time = SystemTime::now();
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+
+    NOW.with(|cached| {
+        let mut cached = cached.borrow_mut();
+
+        if cached.0 != secs {
+            *cached = (secs, http_date(time));
+        }
+
+        cached.1.clone()
+    })
+}
+
+///
+/// Parses an HTTP date in any of the three formats recipients must accept: IMF-fixdate,
+/// the obsolete RFC 850 format and asctime.
+///
+/// # Returns
+///
+/// * `Option<SystemTime>` -> The time, or None if the date is malformed or before the
+///   epoch
+///
+pub(crate) fn parse_http_date(date: &str) -> Option<SystemTime> {
+    let parts: Vec<&str> = date.split_whitespace().collect();
+
+    let (day, month, year, time) = match parts.as_slice() {
+        // Sun, 06 Nov 1994 08:49:37 GMT
+        [_, day, month, year, time, "GMT"] => (*day, *month, year.parse().ok()?, *time),
+        // Sunday, 06-Nov-94 08:49:37 GMT
+        [_, date, time, "GMT"] => {
+            let mut fields = date.split('-');
+            let (day, month, year) = (fields.next()?, fields.next()?, fields.next()?);
+            let year: i64 = year.parse().ok()?;
+
+            // Two digit years are read as 1970 to 2069
+            let year = if year < 70 { year + 2000 } else { year + 1900 };
+
+            (day, month, year, *time)
+        }
+        // Sun Nov  6 08:49:37 1994
+        [_, month, day, time, year] => (*day, *month, year.parse().ok()?, *time),
+        _ => return None,
+    };
+
+    let day: i64 = day.parse().ok()?;
+    let month = MONTHS.iter().position(|m| *m == month)? as i64 + 1;
+
+    let mut clock = time.split(':').map(|f| f.parse::<i64>().ok());
+    let (hour, minute, second) = (clock.next()??, clock.next()??, clock.next()??);
+
+    if !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let secs = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second;
+
+    Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(secs).ok()?))
+}
+
+///
+/// Converts a civil date to days since 1970-01-01, the inverse of [civil_from_days].
+///
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146_097 + doe - 719_468
+}
+
+///
+/// Converts days since 1970-01-01 to a (year, month, day) civil date.
+///
+/// Howard Hinnant's `civil_from_days` algorithm, exact over the whole proleptic
+/// Gregorian calendar.
+///
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_clf_date() {
+        let time = UNIX_EPOCH + Duration::from_secs(971_186_136);
+
+        assert_eq!(clf(time), "10/Oct/2000:13:55:36 +0000");
+        assert_eq!(clf(UNIX_EPOCH), "01/Jan/1970:00:00:00 +0000");
+
+        // Leap day
+        let time = UNIX_EPOCH + Duration::from_secs(1_709_164_800);
+
+        assert_eq!(clf(time), "29/Feb/2024:00:00:00 +0000");
+    }
+
+    #[test]
+    fn test_http_date() {
+        let time = UNIX_EPOCH + Duration::from_secs(784_111_777);
+
+        assert_eq!(http_date(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+
+        for date in [
+            "Sun, 06 Nov 1994 08:49:37 GMT",
+            "Sunday, 06-Nov-94 08:49:37 GMT",
+            "Sun Nov  6 08:49:37 1994",
+        ] {
+            assert_eq!(parse_http_date(date), Some(time), "{}", date);
+        }
+
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49 GMT"), None);
+        assert_eq!(parse_http_date("yesterday"), None);
+    }
+
+    #[test]
+    fn test_now() {
+        let date = parse_http_date(&now()).unwrap();
+        let elapsed = SystemTime::now().duration_since(date).unwrap();
+
+        assert!(elapsed < Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,236 @@
+//!
+//! Minimal DER reader for the parts of X.509 certificates the crate looks at.
+//!
+
+use crate::date;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub(crate) const SEQUENCE: u8 = 0x30;
+pub(crate) const SET: u8 = 0x31;
+pub(crate) const OBJECT_IDENTIFIER: u8 = 0x06;
+pub(crate) const OCTET_STRING: u8 = 0x04;
+
+const UTC_TIME: u8 = 0x17;
+const GENERALIZED_TIME: u8 = 0x18;
+
+///
+/// Context specific tags of the optional `TBSCertificate` fields
+///
+const VERSION: u8 = 0xa0;
+const EXTENSIONS: u8 = 0xa3;
+
+///
+/// Reader over consecutive DER encoded values.
+///
+pub(crate) struct Der<'a> {
+    pub(crate) bytes: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Der<'a> {
+        Der { bytes }
+    }
+
+    ///
+    /// Reads the next value, returning its tag and contents.
+    ///
+    pub(crate) fn next(&mut self) -> Option<(u8, &'a [u8])> {
+        let (&tag, rest) = self.bytes.split_first()?;
+        let (&first, rest) = rest.split_first()?;
+
+        let (len, rest) = match first {
+            0..=0x7f => (first as usize, rest),
+            0x81..=0x84 => {
+                let count = (first & 0x7f) as usize;
+                let (len, rest) = rest.split_at_checked(count)?;
+
+                (len.iter().fold(0, |acc, &b| acc << 8 | b as usize), rest)
+            }
+            _ => return None,
+        };
+
+        let (value, rest) = rest.split_at_checked(len)?;
+        self.bytes = rest;
+
+        Some((tag, value))
+    }
+
+    ///
+    /// Reads the next value, which must have the given tag, returning a reader over its
+    /// contents.
+    ///
+    pub(crate) fn expect(&mut self, tag: u8) -> Option<Der<'a>> {
+        match self.next()? {
+            (found, value) if found == tag => Some(Der::new(value)),
+            _ => None,
+        }
+    }
+}
+
+///
+/// Fields of a certificate's `TBSCertificate`.
+///
+pub(crate) struct Certificate<'a> {
+    #[cfg_attr(not(feature = "acme"), allow(dead_code))]
+    pub(crate) not_before: SystemTime,
+    #[cfg_attr(not(feature = "acme"), allow(dead_code))]
+    pub(crate) not_after: SystemTime,
+    pub(crate) subject: Der<'a>,
+    pub(crate) extensions: Option<Der<'a>>,
+}
+
+impl<'a> Certificate<'a> {
+    ///
+    /// Reads a DER encoded X.509 certificate, `None` if it is malformed.
+    ///
+    pub(crate) fn parse(der: &'a [u8]) -> Option<Certificate<'a>> {
+        let mut tbs = Der::new(der).expect(SEQUENCE)?.expect(SEQUENCE)?;
+
+        // version, serialNumber, signature and issuer precede the validity
+        if tbs.bytes.first() == Some(&VERSION) {
+            tbs.next()?;
+        }
+
+        for _ in 0..3 {
+            tbs.next()?;
+        }
+
+        let mut validity = tbs.expect(SEQUENCE)?;
+        let (tag, value) = validity.next()?;
+        let not_before = time(tag, value)?;
+        let (tag, value) = validity.next()?;
+        let not_after = time(tag, value)?;
+
+        let subject = tbs.expect(SEQUENCE)?;
+
+        // subjectPublicKeyInfo, then the optional unique identifiers and extensions
+        tbs.next()?;
+
+        let mut extensions = None;
+
+        while let Some((tag, value)) = tbs.next() {
+            if tag == EXTENSIONS {
+                extensions = Some(Der::new(value).expect(SEQUENCE)?);
+            }
+        }
+
+        Some(Certificate {
+            not_before,
+            not_after,
+            subject,
+            extensions,
+        })
+    }
+}
+
+///
+/// Decodes a `UTCTime` or `GeneralizedTime` in the `YYMMDDHHMMSSZ` and
+/// `YYYYMMDDHHMMSSZ` forms certificates use.
+///
+fn time(tag: u8, value: &[u8]) -> Option<SystemTime> {
+    let value = std::str::from_utf8(value).ok()?.strip_suffix('Z')?;
+
+    let (year, rest) = match tag {
+        // Two digit years are read as 1950 to 2049
+        UTC_TIME if value.len() == 12 => {
+            let year: i64 = value.get(..2)?.parse().ok()?;
+            (
+                if year < 50 { year + 2000 } else { year + 1900 },
+                &value[2..],
+            )
+        }
+        GENERALIZED_TIME if value.len() == 14 => (value.get(..4)?.parse().ok()?, &value[4..]),
+        _ => return None,
+    };
+
+    if !rest.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let field = |i: usize| rest[i..i + 2].parse::<i64>().unwrap_or(0);
+    let (month, day) = (field(0), field(2));
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let secs = date::days_from_civil(year, month, day) * 86_400
+        + field(4) * 3600
+        + field(6) * 60
+        + field(8);
+
+    Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(secs).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{time, Certificate, Der, GENERALIZED_TIME, OCTET_STRING, SEQUENCE, UTC_TIME};
+    use rcgen::{CertificateParams, KeyPair};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_der_reader() {
+        // Short and long form lengths, one after the other
+        let mut long = vec![OCTET_STRING, 0x82, 0x01, 0x00];
+        long.extend([7; 256]);
+
+        let mut bytes = vec![SEQUENCE, 0x03, OCTET_STRING, 0x01, 0xaa];
+        bytes.extend(&long);
+
+        let mut der = Der::new(&bytes);
+        let mut inner = der.expect(SEQUENCE).unwrap();
+
+        assert_eq!(inner.next(), Some((OCTET_STRING, &[0xaa][..])));
+        assert_eq!(inner.next(), None);
+
+        let (tag, value) = der.next().unwrap();
+        assert_eq!((tag, value.len()), (OCTET_STRING, 256));
+        assert!(der.bytes.is_empty());
+
+        // Wrong tag, value longer than the input, indefinite and oversized lengths
+        assert!(Der::new(&[OCTET_STRING, 0x00]).expect(SEQUENCE).is_none());
+        assert!(Der::new(&[SEQUENCE, 0x05, 0x01]).next().is_none());
+        assert!(Der::new(&[SEQUENCE, 0x80, 0x00, 0x00]).next().is_none());
+        assert!(Der::new(&[SEQUENCE, 0x85, 0, 0, 0, 0, 1, 0])
+            .next()
+            .is_none());
+        assert!(Der::new(&[SEQUENCE, 0x82, 0x01]).next().is_none());
+    }
+
+    #[test]
+    fn test_der_certificate() {
+        let mut params = CertificateParams::new(vec!["example.com".to_string()]).unwrap();
+        params.not_before = rcgen::date_time_ymd(2024, 1, 1);
+        params.not_after = rcgen::date_time_ymd(2051, 6, 30);
+
+        let cert = params.self_signed(&KeyPair::generate().unwrap()).unwrap();
+        let parsed = Certificate::parse(cert.der()).unwrap();
+
+        // 2024-01-01 and 2051-06-30, the latter as a GeneralizedTime past 2049
+        assert_eq!(
+            parsed.not_before,
+            UNIX_EPOCH + Duration::from_secs(1_704_067_200)
+        );
+        assert_eq!(
+            parsed.not_after,
+            UNIX_EPOCH + Duration::from_secs(2_571_696_000)
+        );
+        assert!(parsed.extensions.is_some());
+
+        // Truncated anywhere, the certificate is rejected
+        for len in [0, 10, cert.der().len() / 2] {
+            assert!(Certificate::parse(&cert.der()[..len]).is_none());
+        }
+    }
+
+    #[test]
+    fn test_der_time() {
+        let expected = UNIX_EPOCH + Duration::from_secs(784_111_777);
+
+        assert_eq!(time(UTC_TIME, b"941106084937Z"), Some(expected));
+        assert_eq!(time(GENERALIZED_TIME, b"19941106084937Z"), Some(expected));
+        assert!(time(UTC_TIME, b"491231235959Z").is_some());
+        assert_eq!(time(UTC_TIME, b"941306084937Z"), None);
+        assert_eq!(time(GENERALIZED_TIME, b"19941106084937"), None);
+    }
+}
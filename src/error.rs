@@ -0,0 +1,306 @@
+//!
+//! The error type of request parsing and body decoding, with the status code each
+//! failure is answered with.
+//!
+//! # Example
+//!
+//! ```rust
+//! use http_rs::server::Request;
+//! use http_rs::status::StatusCode;
+//! use http_rs::Error;
+//!
+//! let err = Request::new(&b"GET /path HTTP/2.0\r\n\r\n"[..]).unwrap_err();
+//!
+//! assert!(matches!(err, Error::UnsupportedVersion));
+//! assert_eq!(err.status(), StatusCode::HTTP_VERSION_NOT_SUPPORTED);
+//! ```
+//!
+
+use crate::{response::ResponseError, server::Response, status::StatusCode};
+use std::{fmt, io};
+
+///
+/// Alias for results failing with an [Error].
+///
+pub type Result<T> = std::result::Result<T, Error>;
+
+///
+/// Reasons a request can't be read or decoded.
+///
+/// Every variant maps to the status code a server should answer with, see
+/// [Error::status]. Converting into an [io::Error] is lossless for [Error::Io], so `?`
+/// keeps working in functions returning [io::Result].
+///
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    ///
+    /// The request line isn't `method target version`, or the version isn't `HTTP/x.y`
+    ///
+    BadRequestLine,
+
+    ///
+    /// The method isn't a valid token
+    ///
+    InvalidMethod,
+
+    ///
+    /// The request target is malformed or badly percent-encoded
+    ///
+    InvalidTarget,
+
+    ///
+    /// The request target exceeds the configured limit
+    ///
+    UriTooLong,
+
+    ///
+    /// The request line advertises a well-formed version other than HTTP/1.x (e.g.,
+    /// `HTTP/2.0`)
+    ///
+    UnsupportedVersion,
+
+    ///
+    /// A header line isn't `name: value`, or is folded onto the previous one (obs-fold)
+    ///
+    BadHeader,
+
+    ///
+    /// Too many header fields, or a header line exceeding the configured limit
+    ///
+    HeaderTooLarge,
+
+    ///
+    /// `Content-Length` isn't a number, or repeated with different values
+    ///
+    InvalidContentLength,
+
+    ///
+    /// Both `Transfer-Encoding` and `Content-Length` are sent, leaving the body length
+    /// ambiguous
+    ///
+    ConflictingFraming,
+
+    ///
+    /// `Transfer-Encoding` lists a coding other than `chunked`
+    ///
+    UnsupportedTransferCoding,
+
+    ///
+    /// A `Transfer-Encoding: chunked` body is malformed
+    ///
+    BadChunkedEncoding,
+
+    ///
+    /// The body exceeds the configured limit
+    ///
+    BodyTooLarge,
+
+    ///
+    /// The `Expect` header can't be met, or was refused by
+    /// [crate::server::Server::expect_continue]
+    ///
+    ExpectationFailed,
+
+    ///
+    /// The request head wasn't received in time
+    ///
+    Timeout,
+
+    ///
+    /// Reading from the connection failed, or it was closed
+    ///
+    Io(io::Error),
+
+    ///
+    /// The `Content-Type` of the body isn't the expected one, which is given
+    ///
+    UnsupportedMediaType(&'static str),
+
+    ///
+    /// The body isn't valid UTF-8 where text was expected
+    ///
+    InvalidUtf8,
+
+    ///
+    /// The body isn't valid `JSON` for the expected type
+    ///
+    Json(serde_json::Error),
+
+    ///
+    /// The body or query isn't a valid form for the expected type
+    ///
+    Form(serde_urlencoded::de::Error),
+
+    ///
+    /// The body isn't valid `XML` for the expected type
+    ///
+    #[cfg(feature = "xml")]
+    Xml(quick_xml::DeError),
+}
+
+impl Error {
+    ///
+    /// Returns the status code to answer the failed request with.
+    ///
+    /// [Error::Io] maps to `408` for socket timeouts, to `400` for invalid data (e.g., a
+    /// request line or header that isn't UTF-8) and to `500` otherwise, though the
+    /// connection is usually gone by then.
+    ///
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Error::BadRequestLine
+            | Error::InvalidMethod
+            | Error::InvalidTarget
+            | Error::BadHeader
+            | Error::InvalidContentLength
+            | Error::ConflictingFraming
+            | Error::BadChunkedEncoding
+            | Error::InvalidUtf8
+            | Error::Form(_) => StatusCode::BAD_REQUEST,
+            #[cfg(feature = "xml")]
+            Error::Xml(_) => StatusCode::BAD_REQUEST,
+            // Well-formed `JSON` that doesn't match the expected type
+            Error::Json(e) if e.is_data() => StatusCode::UNPROCESSABLE_CONTENT,
+            Error::Json(_) => StatusCode::BAD_REQUEST,
+            Error::UriTooLong => StatusCode::URI_TOO_LONG,
+            Error::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Error::UnsupportedVersion => StatusCode::HTTP_VERSION_NOT_SUPPORTED,
+            Error::HeaderTooLarge => StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+            Error::UnsupportedTransferCoding => StatusCode::NOT_IMPLEMENTED,
+            Error::BodyTooLarge => StatusCode::CONTENT_TOO_LARGE,
+            Error::ExpectationFailed => StatusCode::EXPECTATION_FAILED,
+            Error::Timeout => StatusCode::REQUEST_TIMEOUT,
+            Error::Io(e) if is_timeout(e) => StatusCode::REQUEST_TIMEOUT,
+            Error::Io(e) if e.kind() == io::ErrorKind::InvalidData => StatusCode::BAD_REQUEST,
+            Error::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+///
+/// Whether an error comes from a socket timeout, which is reported as either
+/// [io::ErrorKind::WouldBlock] or [io::ErrorKind::TimedOut] depending on the platform.
+///
+pub(crate) fn is_timeout(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::BadRequestLine => f.write_str("Malformed request line"),
+            Error::InvalidMethod => f.write_str("Invalid HTTP method"),
+            Error::InvalidTarget => f.write_str("Invalid request target"),
+            Error::UriTooLong => f.write_str("Request target too long"),
+            Error::UnsupportedVersion => f.write_str("Unsupported HTTP version"),
+            Error::BadHeader => f.write_str("Malformed header line"),
+            Error::HeaderTooLarge => f.write_str("Request header fields too large"),
+            Error::InvalidContentLength => f.write_str("Invalid Content-Length"),
+            Error::ConflictingFraming => {
+                f.write_str("Both Transfer-Encoding and Content-Length sent")
+            }
+            Error::UnsupportedTransferCoding => f.write_str("Unsupported transfer coding"),
+            Error::BadChunkedEncoding => f.write_str("Invalid chunked encoding"),
+            Error::BodyTooLarge => f.write_str("Request body too large"),
+            Error::ExpectationFailed => f.write_str("Expectation failed"),
+            Error::Timeout => f.write_str("Request header timeout"),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::UnsupportedMediaType(expected) => {
+                write!(f, "Unsupported media type, expected {}", expected)
+            }
+            Error::InvalidUtf8 => f.write_str("Body isn't valid UTF-8"),
+            Error::Json(e) => write!(f, "Invalid JSON: {}", e),
+            Error::Form(e) => write!(f, "Invalid form: {}", e),
+            #[cfg(feature = "xml")]
+            Error::Xml(e) => write!(f, "Invalid XML: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Json(e) => Some(e),
+            Error::Form(e) => Some(e),
+            #[cfg(feature = "xml")]
+            Error::Xml(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Error {
+        Error::Json(e)
+    }
+}
+
+impl From<serde_urlencoded::de::Error> for Error {
+    fn from(e: serde_urlencoded::de::Error) -> Error {
+        Error::Form(e)
+    }
+}
+
+#[cfg(feature = "xml")]
+impl From<quick_xml::DeError> for Error {
+    fn from(e: quick_xml::DeError) -> Error {
+        Error::Xml(e)
+    }
+}
+
+///
+/// Answers with the [Error::status] and a `JSON` body describing the failure (e.g.,
+/// `{"error": "Invalid JSON: missing field `name` at line 1 column 12"}`).
+///
+impl From<Error> for Response {
+    fn from(e: Error) -> Response {
+        e.error_response()
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> io::Error {
+        match e {
+            Error::Io(e) => e,
+            Error::Timeout => io::Error::new(io::ErrorKind::TimedOut, e),
+            Error::UnsupportedVersion => io::Error::new(io::ErrorKind::Unsupported, e),
+            e => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_status_and_io_conversion() {
+        assert_eq!(Error::HeaderTooLarge.status().as_u16(), 431);
+        assert_eq!(
+            Error::Io(io::ErrorKind::WouldBlock.into())
+                .status()
+                .as_u16(),
+            408
+        );
+
+        let io_error = io::Error::from(Error::UnsupportedVersion);
+
+        assert_eq!(io_error.kind(), io::ErrorKind::Unsupported);
+        assert_eq!(io_error.to_string(), "Unsupported HTTP version");
+
+        let io_error = io::Error::from(Error::Io(io::ErrorKind::UnexpectedEof.into()));
+
+        assert_eq!(io_error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
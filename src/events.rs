@@ -0,0 +1,135 @@
+//!
+//! Diagnostics emitted while serving connections, as `tracing` events and/or `log`
+//! records depending on the enabled features.
+//!
+//! Without either feature, failures to accept or answer a connection are still printed
+//! to stderr and the other events are dropped.
+//!
+
+use crate::error::Error;
+use std::{any::Any, io, net::SocketAddr};
+
+///
+/// Target of every event and record emitted by the crate.
+///
+#[cfg(any(feature = "tracing", feature = "log"))]
+pub(crate) const TARGET: &str = "http_rs";
+
+///
+/// Accepting a connection failed.
+///
+pub(crate) fn accept_failed(e: &io::Error) {
+    #[cfg(feature = "tracing")]
+    tracing::error!(target: TARGET, error = %e, "connection failed");
+
+    #[cfg(feature = "log")]
+    log::error!(target: TARGET, "connection failed: {}", e);
+
+    #[cfg(not(any(feature = "tracing", feature = "log")))]
+    eprintln!("Connection failed: {}", e);
+}
+
+///
+/// Writing a response to the connection failed.
+///
+pub(crate) fn send_failed(e: &io::Error, peer: Option<SocketAddr>) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(target: TARGET, error = %e, peer = ?peer, "failed to send response");
+
+    #[cfg(feature = "log")]
+    log::warn!(target: TARGET, "failed to send response to {:?}: {}", peer, e);
+
+    #[cfg(not(any(feature = "tracing", feature = "log")))]
+    {
+        let _ = peer;
+        eprintln!("Failed to send response: {}", e);
+    }
+}
+
+///
+/// Reloading the certificates of a TLS listener after their files changed failed.
+///
+#[cfg(feature = "tls")]
+pub(crate) fn certificates_reload_failed(e: &io::Error) {
+    #[cfg(feature = "tracing")]
+    tracing::error!(target: TARGET, error = %e, "certificates reload failed");
+
+    #[cfg(feature = "log")]
+    log::error!(target: TARGET, "certificates reload failed: {}", e);
+
+    #[cfg(not(any(feature = "tracing", feature = "log")))]
+    eprintln!("Certificates reload failed: {}", e);
+}
+
+///
+/// Ordering a certificate from the ACME server to renew the current one failed.
+///
+#[cfg(feature = "acme")]
+pub(crate) fn acme_order_failed(e: &io::Error) {
+    #[cfg(feature = "tracing")]
+    tracing::error!(target: TARGET, error = %e, "certificate renewal failed");
+
+    #[cfg(feature = "log")]
+    log::error!(target: TARGET, "certificate renewal failed: {}", e);
+
+    #[cfg(not(any(feature = "tracing", feature = "log")))]
+    eprintln!("Certificate renewal failed: {}", e);
+}
+
+///
+/// A request couldn't be parsed and was answered with the error's status.
+///
+pub(crate) fn request_rejected(e: &Error, peer: Option<SocketAddr>) {
+    #[cfg(feature = "tracing")]
+    tracing::info!(
+        target: TARGET,
+        error = %e,
+        status = e.status().as_u16(),
+        peer = ?peer,
+        "request rejected"
+    );
+
+    #[cfg(feature = "log")]
+    log::info!(
+        target: TARGET,
+        "request from {:?} rejected with {}: {}",
+        peer,
+        e.status().as_u16(),
+        e
+    );
+
+    #[cfg(not(any(feature = "tracing", feature = "log")))]
+    let _ = (e, peer);
+}
+
+///
+/// A handler panicked and the request was answered with a `500`.
+///
+pub(crate) fn handler_panicked(panic: &(dyn Any + Send), method: &str, path: &str) {
+    let message = panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Box<dyn Any>");
+
+    #[cfg(feature = "tracing")]
+    tracing::error!(
+        target: TARGET,
+        method,
+        path,
+        panic = message,
+        "handler panicked"
+    );
+
+    #[cfg(feature = "log")]
+    log::error!(
+        target: TARGET,
+        "handler panicked on {} {}: {}",
+        method,
+        path,
+        message
+    );
+
+    #[cfg(not(any(feature = "tracing", feature = "log")))]
+    let _ = (message, method, path);
+}
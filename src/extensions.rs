@@ -0,0 +1,187 @@
+//!
+//! Typed per-request data attached by middleware.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::middleware::Next;
+//! use http_rs::router::Router;
+//! use http_rs::server::{Request, Response};
+//!
+//! struct AuthUser {
+//!     name: String,
+//! }
+//!
+//! let mut router = Router::new();
+//!
+//! router.layer(|mut req: Request, next: Next| {
+//!     let name = req.headers.get("X-User").unwrap_or("guest").to_string();
+//!     req.extensions_mut().insert(AuthUser { name });
+//!
+//!     next.run(req)
+//! });
+//!
+//! router.get("/me", |req: Request| match req.extensions().get::<AuthUser>() {
+//!     Some(user) => Response::new(200).json(&user.name),
+//!     None => Response::new(401).json(&"Unauthorized"),
+//! });
+//! ```
+//!
+
+use crate::server::Request;
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt,
+};
+
+///
+/// Map holding at most one value per type.
+///
+#[derive(Default)]
+pub struct Extensions {
+    map: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    ///
+    /// Creates an empty [Extensions] map.
+    ///
+    pub fn new() -> Extensions {
+        Extensions::default()
+    }
+
+    ///
+    /// Stores a value, replacing any previous value of the same type.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<T>` -> The replaced value, if any
+    ///
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|old| old.downcast().ok())
+            .map(|old| *old)
+    }
+
+    ///
+    /// Returns a reference to the value of type `T`.
+    ///
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.map.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    ///
+    /// Returns a mutable reference to the value of type `T`.
+    ///
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.map.get_mut(&TypeId::of::<T>())?.downcast_mut()
+    }
+
+    ///
+    /// Removes and returns the value of type `T`.
+    ///
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.map
+            .remove(&TypeId::of::<T>())?
+            .downcast()
+            .ok()
+            .map(|value| *value)
+    }
+
+    ///
+    /// Whether a value of type `T` is stored.
+    ///
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    ///
+    /// Returns the number of stored values.
+    ///
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    ///
+    /// Whether no values are stored.
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.map.len())
+            .finish()
+    }
+}
+
+impl Request {
+    ///
+    /// Returns the [Extensions] of the request, holding typed data attached by
+    /// middleware.
+    ///
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    ///
+    /// Returns the [Extensions] of the request mutably, to attach typed data for
+    /// handlers further down the chain.
+    ///
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::Next;
+    use crate::router::Router;
+    use crate::server::{test_request, HttpMethod, Response};
+
+    #[derive(Debug, PartialEq)]
+    struct AuthUser(String);
+
+    #[test]
+    fn test_extensions_map() {
+        let mut ext = Extensions::new();
+
+        assert_eq!(ext.insert(AuthUser("a".to_string())), None);
+        assert_eq!(
+            ext.insert(AuthUser("b".to_string())),
+            Some(AuthUser("a".to_string()))
+        );
+
+        ext.insert(7u32);
+        *ext.get_mut::<u32>().unwrap() += 1;
+
+        assert_eq!(ext.get::<u32>(), Some(&8));
+        assert_eq!(ext.remove::<AuthUser>(), Some(AuthUser("b".to_string())));
+        assert!(!ext.contains::<AuthUser>());
+        assert_eq!(ext.len(), 1);
+    }
+
+    #[test]
+    fn test_extensions_from_middleware() {
+        let mut router = Router::new();
+
+        router
+            .layer(|mut req: Request, next: Next| {
+                req.extensions_mut().insert(AuthUser("alice".to_string()));
+                next.run(req)
+            })
+            .get("/me", |req: Request| {
+                Response::new(200).json(&req.extensions().get::<AuthUser>().unwrap().0)
+            });
+
+        let res = router.handle(test_request(HttpMethod::GET, "/me"));
+
+        assert_eq!(res.body.as_bytes(), Some(&b"\"alice\""[..]));
+    }
+}
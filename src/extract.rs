@@ -0,0 +1,268 @@
+//!
+//! Typed extraction of request bodies and queries, failing with an [Error] that
+//! converts into a `400`, `415` or `422` [crate::server::Response] describing what went
+//! wrong.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::extract::{Json, Query};
+//! use http_rs::server::{Request, Response};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct User {
+//!     id: u32,
+//!     name: String,
+//! }
+//!
+//! #[derive(Deserialize)]
+//! struct Notify {
+//!     email: bool,
+//! }
+//!
+//! fn create_user(req: Request) -> Response {
+//!     let (Json(user), Query(notify)) = match req.extract::<(Json<User>, Query<Notify>)>() {
+//!         Ok(extracted) => extracted,
+//!         // e.g. 415 `{"error": "Unsupported media type, expected application/json"}`
+//!         Err(e) => return e.into(),
+//!     };
+//!
+//!     Response::new(201).json(&user)
+//! }
+//! ```
+//!
+
+use crate::{
+    error::{Error, Result},
+    media_type,
+    server::Request,
+};
+use serde::Deserialize;
+
+///
+/// Types that can be extracted from a [Request], see [Request::extract].
+///
+/// Implemented for [Json], [Form], [Query], `String` and `Vec<u8>` bodies, and tuples
+/// of up to four extractors.
+///
+pub trait FromRequest: Sized {
+    ///
+    /// Extracts the value from the [Request].
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self>` -> The value, or the [Error] to answer the request with
+    ///
+    fn from_request(req: &Request) -> Result<Self>;
+}
+
+///
+/// A `JSON` body deserialized into `T`, requiring `Content-Type: application/json` (or
+/// a `+json` type such as `application/merge-patch+json`).
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Json<T>(pub T);
+
+///
+/// An `application/x-www-form-urlencoded` body deserialized into `T`.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Form<T>(pub T);
+
+///
+/// The query parameters deserialized into `T`, with values coerced into the field types
+/// and missing parameters allowed for `Option` and `#[serde(default)]` fields.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query<T>(pub T);
+
+///
+/// Whether the `Content-Type` of the [Request] is `media_type`, ignoring parameters
+/// such as `charset`.
+///
+pub(crate) fn has_media_type(req: &Request, expected: &str) -> bool {
+    media_type(req).is_some_and(|media_type| media_type.eq_ignore_ascii_case(expected))
+}
+
+///
+/// Returns the media type of the [Request] body, without parameters.
+///
+pub(crate) fn media_type(req: &Request) -> Option<&str> {
+    let value = req.headers.get("Content-Type")?;
+
+    Some(value.split(';').next().unwrap_or("").trim())
+}
+
+impl<T: for<'a> Deserialize<'a>> FromRequest for Json<T> {
+    fn from_request(req: &Request) -> Result<Json<T>> {
+        let is_json = media_type(req).is_some_and(|media_type| {
+            let media_type = media_type.to_ascii_lowercase();
+
+            media_type == "application/json"
+                || (media_type.starts_with("application/") && media_type.ends_with("+json"))
+        });
+
+        if !is_json {
+            return Err(Error::UnsupportedMediaType("application/json"));
+        }
+
+        Ok(Json(serde_json::from_slice(&req.body)?))
+    }
+}
+
+impl<T: for<'a> Deserialize<'a>> FromRequest for Form<T> {
+    fn from_request(req: &Request) -> Result<Form<T>> {
+        if !has_media_type(req, "application/x-www-form-urlencoded") {
+            return Err(Error::UnsupportedMediaType(
+                "application/x-www-form-urlencoded",
+            ));
+        }
+
+        Ok(Form(serde_urlencoded::from_bytes(&req.body)?))
+    }
+}
+
+impl<T: for<'a> Deserialize<'a>> FromRequest for Query<T> {
+    fn from_request(req: &Request) -> Result<Query<T>> {
+        // Re-encoding the decoded params lets serde_urlencoded handle the coercion
+        let query = serde_urlencoded::to_string(&req.query_params)
+            .map_err(|e| Error::Form(serde::de::Error::custom(e)))?;
+
+        Ok(Query(serde_urlencoded::from_str(&query)?))
+    }
+}
+
+///
+/// The body as text, whatever its `Content-Type`, decoded from the `charset` it declares
+/// (`UTF-8` by default), see [crate::media_type::MediaType::decode].
+///
+impl FromRequest for String {
+    fn from_request(req: &Request) -> Result<String> {
+        let media_type = req.media_type();
+
+        media_type::decode(&req.body, media_type.as_ref().and_then(|m| m.charset()))
+    }
+}
+
+///
+/// The raw body, whatever its `Content-Type`.
+///
+impl FromRequest for Vec<u8> {
+    fn from_request(req: &Request) -> Result<Vec<u8>> {
+        Ok(req.body.clone())
+    }
+}
+
+macro_rules! tuple_from_request {
+    ($($name:ident),+) => {
+        ///
+        /// Every extractor in turn, failing with the first [Error].
+        ///
+        impl<$($name: FromRequest),+> FromRequest for ($($name,)+) {
+            fn from_request(req: &Request) -> Result<($($name,)+)> {
+                Ok(($($name::from_request(req)?,)+))
+            }
+        }
+    };
+}
+
+tuple_from_request!(A, B);
+tuple_from_request!(A, B, C);
+tuple_from_request!(A, B, C, D);
+
+impl Request {
+    ///
+    /// Extracts a typed value from the [Request], such as a [Json] or [Form] body or the
+    /// [Query] parameters.
+    ///
+    /// # Parameters
+    ///
+    /// * `T` -> The [FromRequest] type to extract (e.g., `Json<User>`)
+    ///
+    /// # Returns
+    ///
+    /// * `Result<T>` -> The value, or an [Error] which converts into a `4xx`
+    ///   [crate::server::Response] with a `JSON` description (e.g., `return e.into()`)
+    ///
+    pub fn extract<T: FromRequest>(&self) -> Result<T> {
+        T::from_request(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Form, Json, Query};
+    use crate::{
+        error::Error,
+        server::{test_request, HttpMethod, Request, Response},
+    };
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct User {
+        id: u32,
+        name: String,
+    }
+
+    fn post(content_type: Option<&str>, body: &str) -> Request {
+        let mut req = test_request(HttpMethod::POST, "/users?id=7&name=Bob");
+        req.body = body.as_bytes().to_vec();
+
+        if let Some(content_type) = content_type {
+            req.headers.insert("Content-Type", content_type);
+        }
+
+        req
+    }
+
+    #[test]
+    fn test_extract() {
+        let req = post(
+            Some("application/json; charset=utf-8"),
+            r#"{"id":1,"name":"Alice"}"#,
+        );
+        let (Json(user), Query(query)) = req.extract::<(Json<User>, Query<User>)>().unwrap();
+
+        assert_eq!(user.name, "Alice");
+        assert_eq!(
+            query,
+            User {
+                id: 7,
+                name: "Bob".to_string()
+            }
+        );
+        assert_eq!(
+            req.extract::<String>().unwrap(),
+            r#"{"id":1,"name":"Alice"}"#
+        );
+
+        let req = post(Some("application/x-www-form-urlencoded"), "id=2&name=Eve+A");
+        let Form(user) = req.extract::<Form<User>>().unwrap();
+        assert_eq!(user.name, "Eve A");
+
+        let err = post(None, "{}").extract::<Json<User>>().unwrap_err();
+        assert!(matches!(
+            err,
+            Error::UnsupportedMediaType("application/json")
+        ));
+
+        let res = Response::from(err);
+        assert_eq!(res.status, 415);
+        assert_eq!(
+            res.body.as_bytes().unwrap(),
+            br#"{"error":"Unsupported media type, expected application/json"}"#
+        );
+
+        let err = post(Some("application/json"), r#"{"id":1}"#)
+            .extract::<Json<User>>()
+            .unwrap_err();
+        assert!(matches!(err, Error::Json(_)));
+        assert_eq!(Response::from(err).status, 422);
+
+        let mut req = post(None, "");
+        req.body = vec![0xff];
+        assert_eq!(req.extract::<Vec<u8>>().unwrap(), [0xff]);
+        assert!(matches!(req.extract::<String>(), Err(Error::InvalidUtf8)));
+    }
+}
@@ -0,0 +1,669 @@
+//!
+//! Forward proxy for plain HTTP, e.g. to capture and inspect the traffic of the
+//! application under test in integration tests.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::forward_proxy::ForwardProxy;
+//! use http_rs::router::Router;
+//! use http_rs::server::Server;
+//!
+//! // Clients configured with `http_proxy=http://127.0.0.1:3128` send
+//! // `GET http://api.example.com/users HTTP/1.1`, forwarded to api.example.com:80
+//! let mut router = Router::new();
+//! router.layer(ForwardProxy::new().allow("api.example.com").allow("*.test"));
+//!
+//! Server::new("127.0.0.1:3128").unwrap().serve(router);
+//! ```
+//!
+
+use crate::{
+    error::{is_timeout, Error},
+    header::HeaderMap,
+    middleware::{Middleware, Next},
+    server::{HttpMethod, Limits, Request, RequestTarget, Response},
+};
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+///
+/// Headers describing a single connection (RFC 9110 Section 7.6.1), never forwarded.
+///
+const HOP_BY_HOP: [&str; 9] = [
+    "Connection",
+    "Keep-Alive",
+    "Proxy-Authenticate",
+    "Proxy-Authorization",
+    "Proxy-Connection",
+    "TE",
+    "Trailer",
+    "Transfer-Encoding",
+    "Upgrade",
+];
+
+///
+/// Added to the `Via` header of forwarded requests and responses.
+///
+const VIA: &str = "1.1 http_rs";
+
+///
+/// [Middleware] forwarding requests with an absolute-form target (e.g.,
+/// `GET http://example.com/users HTTP/1.1`) to that server, and passing other requests
+/// on, so the same [crate::router::Router] can also serve routes of its own.
+///
+/// Hop-by-hop headers (`Connection` and the headers it lists, `Proxy-Authorization`,
+/// `Transfer-Encoding`, ...) are stripped both ways and a `Via` header added. Each request
+/// opens its own upstream connection.
+///
+/// Answers `403` for hosts not allowed, `400` for non `http://` targets (`CONNECT`
+/// tunnels aren't supported), `502` when the upstream server can't be reached or answers
+/// garbage, and `504` when it is too slow.
+///
+/// Layer other middleware before it to record the requests and responses going through.
+///
+pub struct ForwardProxy {
+    allowed: Vec<String>,
+    timeout: Option<Duration>,
+    limits: Limits,
+}
+
+impl ForwardProxy {
+    ///
+    /// Creates the proxying [Middleware], allowing no host until [ForwardProxy::allow]ed.
+    ///
+    pub fn new() -> ForwardProxy {
+        ForwardProxy {
+            allowed: Vec::new(),
+            timeout: Some(Duration::from_secs(30)),
+            limits: Limits::default(),
+        }
+    }
+
+    ///
+    /// Allows forwarding to a host, on any port.
+    ///
+    /// `👉 Note:` `*` allows every host, making an open proxy anyone reaching the server
+    /// can use, including to reach internal services
+    ///
+    /// # Arguments
+    ///
+    /// * `host` -> Host name or IP address (e.g., "example.com", "127.0.0.1", "::1"),
+    ///   `*.example.com` for its subdomains, or `*` for any host
+    ///
+    pub fn allow(mut self, host: &str) -> ForwardProxy {
+        self.allowed.push(host.to_ascii_lowercase());
+
+        self
+    }
+
+    ///
+    /// Sets how long to wait for connecting to the upstream server and on each read or
+    /// write, `None` waits forever.
+    ///
+    /// Defaults to 30 seconds.
+    ///
+    pub fn timeout(mut self, timeout: Option<Duration>) -> ForwardProxy {
+        self.timeout = timeout.filter(|t| !t.is_zero());
+
+        self
+    }
+
+    fn is_allowed(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+
+        self.allowed
+            .iter()
+            .any(|allowed| match allowed.strip_prefix('*') {
+                Some("") => true,
+                Some(suffix) => suffix.starts_with('.') && host.ends_with(suffix),
+                None => *allowed == host,
+            })
+    }
+
+    ///
+    /// Sends the [Request] to the upstream server and reads its [Response].
+    ///
+    fn forward(&self, req: &Request, upstream: &Upstream) -> Result<Response, Error> {
+        let mut stream = self.connect(upstream)?;
+        stream.set_read_timeout(self.timeout)?;
+        stream.set_write_timeout(self.timeout)?;
+
+        let mut head = format!("{} {} HTTP/1.1\r\n", req.method, upstream.path);
+        head.push_str(&format!("Host: {}\r\n", upstream.authority));
+
+        for (name, value) in forwardable(&req.headers) {
+            if !["Host", "Content-Length", "Expect", "Via"]
+                .iter()
+                .any(|skipped| name.eq_ignore_ascii_case(skipped))
+            {
+                head.push_str(&format!("{}: {}\r\n", name, value));
+            }
+        }
+
+        head.push_str(&format!("Via: {}\r\n", via(&req.headers)));
+        head.push_str("Connection: close\r\n");
+
+        if !req.body.is_empty()
+            || matches!(
+                req.method,
+                HttpMethod::POST | HttpMethod::PUT | HttpMethod::PATCH
+            )
+        {
+            head.push_str(&format!("Content-Length: {}\r\n", req.body.len()));
+        }
+
+        head.push_str("\r\n");
+
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(&req.body)?;
+        stream.flush()?;
+
+        self.read_response(&mut BufReader::new(stream), req.method == HttpMethod::HEAD)
+    }
+
+    fn connect(&self, upstream: &Upstream) -> Result<TcpStream, Error> {
+        let mut last = io::Error::new(io::ErrorKind::NotFound, "Host not found");
+
+        for addr in (upstream.host.as_str(), upstream.port).to_socket_addrs()? {
+            let connected = match self.timeout {
+                Some(timeout) => TcpStream::connect_timeout(&addr, timeout),
+                None => TcpStream::connect(addr),
+            };
+
+            match connected {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last = e,
+            }
+        }
+
+        Err(Error::Io(last))
+    }
+
+    ///
+    /// Reads the upstream [Response], skipping interim (`1xx`) ones.
+    ///
+    fn read_response<R: BufRead>(&self, stream: &mut R, head: bool) -> Result<Response, Error> {
+        let invalid = |what: &str| Error::Io(io::Error::new(io::ErrorKind::InvalidData, what));
+        let line = self.limits.header_line;
+
+        let (status, reason, headers) = loop {
+            let status_line = Request::read_line(stream, line, invalid("Status line too long"))?;
+
+            let mut parts = status_line.splitn(3, ' ');
+            let status = match (parts.next(), parts.next()) {
+                (Some(version), Some(status)) if version.starts_with("HTTP/1.") => status
+                    .parse::<u16>()
+                    .ok()
+                    .filter(|status| (100..1000).contains(status))
+                    .ok_or_else(|| invalid("Invalid status"))?,
+                _ => return Err(invalid("Invalid status line")),
+            };
+            let reason = parts.next().unwrap_or("").to_string();
+
+            let mut headers = HeaderMap::new();
+
+            loop {
+                let header = Request::read_line(stream, line, Error::HeaderTooLarge)?;
+
+                if header.is_empty() {
+                    break;
+                }
+
+                if headers.len() == self.limits.headers {
+                    return Err(Error::HeaderTooLarge);
+                }
+
+                match header.split_once(':') {
+                    Some((name, value)) if !name.is_empty() && !name.ends_with(' ') => {
+                        headers.append(name, value.trim())
+                    }
+                    _ => return Err(invalid("Invalid header")),
+                }
+            }
+
+            if status >= 200 {
+                break (status, reason, headers);
+            }
+        };
+
+        let chunked = headers
+            .get("Transfer-Encoding")
+            .is_some_and(crate::server::is_chunked);
+
+        let body = if head || status == 204 || status == 304 {
+            None
+        } else if chunked {
+            Some(Request::read_chunked(stream, &self.limits)?.0)
+        } else {
+            let limit = match headers.get("Content-Length") {
+                Some(len) => Some(
+                    len.trim()
+                        .parse::<usize>()
+                        .map_err(|_| invalid("Invalid Content-Length"))?,
+                ),
+                None => None,
+            };
+
+            let mut body = Vec::new();
+            let max = limit.unwrap_or(self.limits.body).min(self.limits.body);
+            stream.take(max as u64 + 1).read_to_end(&mut body)?;
+
+            match limit {
+                _ if body.len() > self.limits.body => return Err(Error::BodyTooLarge),
+                Some(len) if body.len() != len => return Err(invalid("Body ended early")),
+                _ => Some(body),
+            }
+        };
+
+        let mut res = Response::new(status);
+        res.headers.remove("Content-Type");
+
+        if !reason.is_empty() {
+            res = res.reason(&reason);
+        }
+
+        // A read body gets its length set again, unread ones (`HEAD`, `304`) keep it
+        for (name, value) in forwardable(&headers) {
+            let skipped = name.eq_ignore_ascii_case("Via")
+                || (body.is_some() && name.eq_ignore_ascii_case("Content-Length"));
+
+            if !skipped {
+                res.headers.append(name, value);
+            }
+        }
+
+        res.headers.insert("Via", via(&headers));
+
+        Ok(match body {
+            Some(body) => res.body(body),
+            None => res,
+        })
+    }
+}
+
+impl Default for ForwardProxy {
+    fn default() -> ForwardProxy {
+        ForwardProxy::new()
+    }
+}
+
+impl Middleware for ForwardProxy {
+    fn handle(&self, req: Request, next: Next<'_>) -> Response {
+        let RequestTarget::Absolute(uri) = &req.target else {
+            return next.run(req);
+        };
+
+        let Some(upstream) = Upstream::parse(uri) else {
+            return Response::new(400).json(&"Only http:// targets can be forwarded");
+        };
+
+        if !self.is_allowed(&upstream.host) {
+            return Response::new(403).json(&"Destination not allowed");
+        }
+
+        match self.forward(&req, &upstream) {
+            Ok(res) => res,
+            Err(Error::Io(e)) if is_timeout(&e) => {
+                Response::new(504).json(&"Upstream server timed out")
+            }
+            Err(e) => Response::new(502).json(&format!("Upstream server failed: {}", e)),
+        }
+    }
+}
+
+///
+/// Where an absolute-form target points to.
+///
+#[derive(Debug, PartialEq)]
+struct Upstream {
+    ///
+    /// Host and port as in the target, for the `Host` header
+    ///
+    authority: String,
+
+    ///
+    /// Host name or IP address, without the brackets of IPv6 literals
+    ///
+    host: String,
+    port: u16,
+
+    ///
+    /// Path and query, in origin-form
+    ///
+    path: String,
+}
+
+impl Upstream {
+    ///
+    /// Parses a `http://` URI, None for other schemes or a missing host.
+    ///
+    fn parse(uri: &str) -> Option<Upstream> {
+        let scheme = uri.get(..7)?;
+        if !scheme.eq_ignore_ascii_case("http://") {
+            return None;
+        }
+
+        let rest = &uri[7..];
+        let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+
+        // User info is for the origin server's authentication, not sent as part of Host
+        let authority = rest[..end]
+            .rsplit_once('@')
+            .map_or(&rest[..end], |(_, a)| a);
+
+        let (host, port) = match authority.strip_prefix('[') {
+            Some(literal) => {
+                let (host, port) = literal.split_once(']')?;
+                (host, port.strip_prefix(':'))
+            }
+            None => match authority.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            },
+        };
+
+        let port = match port {
+            Some("") | None => 80,
+            Some(port) => port.parse().ok()?,
+        };
+
+        if host.is_empty() {
+            return None;
+        }
+
+        let path = rest[end..].split('#').next().unwrap_or("");
+        let path = match path.starts_with('?') || path.is_empty() {
+            true => format!("/{}", path),
+            false => path.to_string(),
+        };
+
+        Some(Upstream {
+            authority: authority.to_string(),
+            host: host.to_string(),
+            port,
+            path,
+        })
+    }
+}
+
+///
+/// The headers that aren't hop-by-hop, nor listed in `Connection`.
+///
+fn forwardable(headers: &HeaderMap) -> impl Iterator<Item = (&str, &str)> {
+    let listed: Vec<&str> = headers
+        .get_all("Connection")
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+
+    headers.iter().filter(move |(name, _)| {
+        !HOP_BY_HOP
+            .iter()
+            .chain(listed.iter())
+            .any(|hop| name.eq_ignore_ascii_case(hop))
+    })
+}
+
+///
+/// The `Via` header with this proxy appended.
+///
+fn via(headers: &HeaderMap) -> String {
+    headers
+        .get_all("Via")
+        .chain([VIA])
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ForwardProxy, Upstream};
+    use crate::{
+        error::Error,
+        router::Router,
+        server::{test_request, HttpMethod, Request, Response, Server},
+    };
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::{SocketAddr, TcpListener},
+        sync::mpsc::{self, Receiver},
+        thread,
+        time::Duration,
+    };
+
+    ///
+    /// Answers one connection with `response` after `delay`, sending back the head of
+    /// the request it got.
+    ///
+    fn upstream(response: &'static [u8], delay: Duration) -> (SocketAddr, Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut head = String::new();
+
+            loop {
+                let mut line = String::new();
+
+                if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                    break;
+                }
+
+                head.push_str(&line);
+            }
+
+            let _ = tx.send(head);
+
+            thread::sleep(delay);
+            let _ = (&stream).write_all(response);
+        });
+
+        (addr, rx)
+    }
+
+    #[test]
+    fn test_forward_proxy() {
+        let upstream = Server::new("127.0.0.1:0").unwrap().workers(1);
+        let addr = upstream.local_addr().unwrap();
+
+        thread::spawn(move || {
+            upstream.serve(|req: Request| {
+                let headers: Vec<String> = req
+                    .headers
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", name, value))
+                    .collect();
+
+                Response::new(201)
+                    .header("X-Upstream", "yes")
+                    .header("Keep-Alive", "timeout=5")
+                    .json(&(
+                        req.method.as_str(),
+                        &req.target.to_string(),
+                        headers,
+                        req.body,
+                    ))
+            })
+        });
+
+        let mut router = Router::new();
+        router.layer(ForwardProxy::new().allow("127.0.0.1"));
+        router.get("/local", |_| Response::new(200).text("local"));
+
+        let mut req = test_request(HttpMethod::POST, &format!("http://{}/echo?q=1#top", addr));
+        req.headers.insert("Connection", "X-Secret");
+        req.headers.insert("X-Secret", "hop");
+        req.headers
+            .insert("Proxy-Authorization", "Basic Zm9vOmJhcg==");
+        req.headers.insert("X-Kept", "end-to-end");
+        req.body = b"hi".to_vec();
+
+        let res = router.handle(req);
+        assert_eq!(res.status, 201);
+        assert_eq!(res.headers.get("X-Upstream"), Some("yes"));
+        assert_eq!(res.headers.get("Keep-Alive"), None);
+        assert_eq!(res.headers.get("Via"), Some("1.1 http_rs"));
+
+        let (method, target, headers, body): (String, String, Vec<String>, Vec<u8>) =
+            serde_json::from_slice(res.body.as_bytes().unwrap()).unwrap();
+        assert_eq!((method.as_str(), target.as_str()), ("POST", "/echo?q=1"));
+        assert!(headers.contains(&format!("Host={}", addr)));
+        assert!(headers.contains(&"X-Kept=end-to-end".to_string()));
+        assert!(headers.contains(&"Via=1.1 http_rs".to_string()));
+        assert!(!headers.iter().any(|h| h.starts_with("X-Secret")));
+        assert!(!headers.iter().any(|h| h.starts_with("Proxy-Authorization")));
+        assert_eq!(body, b"hi");
+
+        let status = |target: &str| router.handle(test_request(HttpMethod::GET, target)).status;
+        assert_eq!(status("http://example.com/"), 403);
+        assert_eq!(status("https://127.0.0.1/"), 400);
+        assert_eq!(status("/local"), 200);
+
+        // Nothing listens on port 9 (discard) locally
+        assert_eq!(status("http://127.0.0.1:9/"), 502);
+    }
+
+    #[test]
+    fn test_parse_upstream() {
+        let upstream = Upstream::parse("HTTP://user@[::1]:8080?a=b").unwrap();
+        assert_eq!(upstream.authority, "[::1]:8080");
+        assert_eq!((upstream.host.as_str(), upstream.port), ("::1", 8080));
+        assert_eq!(upstream.path, "/?a=b");
+
+        let upstream = Upstream::parse("http://example.com").unwrap();
+        assert_eq!((upstream.port, upstream.path.as_str()), (80, "/"));
+
+        assert_eq!(Upstream::parse("http://:80/"), None);
+        assert_eq!(Upstream::parse("ftp://example.com/"), None);
+
+        let proxy = ForwardProxy::new().allow("*.test").allow("Example.com");
+        assert!(proxy.is_allowed("api.test"));
+        assert!(!proxy.is_allowed("test"));
+        assert!(proxy.is_allowed("EXAMPLE.com"));
+        assert!(!proxy.is_allowed("other.com"));
+    }
+
+    #[test]
+    fn test_forward_proxy_strips_request_hops() {
+        let (addr, head) = upstream(b"HTTP/1.1 204 No Content\r\n\r\n", Duration::ZERO);
+
+        let mut router = Router::new();
+        router.layer(ForwardProxy::new().allow("127.0.0.1"));
+
+        let mut req = test_request(HttpMethod::GET, &format!("http://{}/", addr));
+        req.headers.insert("Connection", "keep-alive, X-Session");
+        req.headers.append("Connection", "x-trace");
+        req.headers.insert("X-Session", "1");
+        req.headers.insert("X-Trace", "2");
+        req.headers.insert("Keep-Alive", "timeout=5");
+        req.headers.insert("Proxy-Connection", "keep-alive");
+        req.headers.insert("TE", "trailers");
+        req.headers.insert("Upgrade", "websocket");
+        req.headers.insert("Via", "1.0 gateway");
+        req.headers.insert("X-Kept", "3");
+
+        assert_eq!(router.handle(req).status, 204);
+
+        let head = head.recv().unwrap();
+        let mut lines: Vec<&str> = head.lines().collect();
+        lines.sort();
+        assert_eq!(
+            lines,
+            [
+                "Connection: close",
+                "GET / HTTP/1.1",
+                &format!("Host: {}", addr),
+                "Via: 1.0 gateway, 1.1 http_rs",
+                "X-Kept: 3",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_forward_proxy_reads_response() {
+        let proxy = ForwardProxy::new();
+        let read = |raw: &[u8], head: bool| proxy.read_response(&mut &raw[..], head);
+
+        let res = read(
+            b"HTTP/1.1 100 Continue\r\n\r\n\
+              HTTP/1.1 200 Fine\r\n\
+              Connection: keep-alive, X-Hop\r\n\
+              X-Hop: 1\r\n\
+              Transfer-Encoding: chunked\r\n\
+              Trailer: X-Sum\r\n\
+              Proxy-Authenticate: Basic\r\n\
+              Via: 1.0 cdn\r\n\
+              X-End: 2\r\n\r\n\
+              5\r\nhello\r\n6\r\n world\r\n0\r\nX-Sum: 1\r\n\r\n",
+            false,
+        )
+        .unwrap();
+
+        // The chunked body is passed on whole, with its length
+        assert_eq!(res.status, 200);
+        assert_eq!(res.body.as_bytes().unwrap(), b"hello world");
+
+        let mut headers: Vec<(&str, &str)> = res.headers.iter().collect();
+        headers.sort();
+        assert_eq!(
+            headers,
+            [
+                ("Content-Length", "11"),
+                ("Via", "1.0 cdn, 1.1 http_rs"),
+                ("X-End", "2"),
+            ]
+        );
+
+        // Without a body to read, the upstream length is kept
+        let res = read(b"HTTP/1.1 200 OK\r\nContent-Length: 42\r\n\r\n", true).unwrap();
+        assert_eq!(res.headers.get("Content-Length"), Some("42"));
+        assert!(res.body.as_bytes().unwrap().is_empty());
+
+        let res = read(b"HTTP/1.0 200 OK\r\n\r\nuntil closed", false).unwrap();
+        assert_eq!(res.body.as_bytes().unwrap(), b"until closed");
+
+        for raw in [
+            &b"SSH-2.0-OpenSSH\r\n\r\n"[..],
+            b"HTTP/1.1 2000 OK\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nBad Header\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort",
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhel",
+        ] {
+            assert!(matches!(read(raw, false), Err(Error::Io(_))));
+        }
+    }
+
+    #[test]
+    fn test_forward_proxy_upstream_errors() {
+        let mut router = Router::new();
+        router.layer(
+            ForwardProxy::new()
+                .allow("127.0.0.1")
+                .timeout(Some(Duration::from_millis(200))),
+        );
+
+        let status = |addr: SocketAddr| {
+            let req = test_request(HttpMethod::GET, &format!("http://{}/", addr));
+            router.handle(req).status
+        };
+
+        let (addr, _) = upstream(b"garbage\r\n\r\n", Duration::ZERO);
+        assert_eq!(status(addr), 502);
+
+        let (addr, _) = upstream(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort",
+            Duration::ZERO,
+        );
+        assert_eq!(status(addr), 502);
+
+        let (addr, _) = upstream(b"HTTP/1.1 200 OK\r\n\r\n", Duration::from_secs(2));
+        assert_eq!(status(addr), 504);
+    }
+}
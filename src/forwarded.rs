@@ -0,0 +1,178 @@
+//!
+//! Client address resolution behind trusted reverse proxies.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::server::{Request, Response, Server};
+//!
+//! fn main() -> std::io::Result<()> {
+//!     Server::new("127.0.0.1:8080")?
+//!         // Only the local load balancer may set `X-Forwarded-For`/`Forwarded`
+//!         .trusted_proxies(["127.0.0.1".parse().unwrap()])
+//!         .serve(|req: Request| Response::new(200).json(&req.client_ip()))
+//! }
+//! ```
+//!
+
+use crate::header::HeaderMap;
+use crate::server::{Request, Server};
+use std::net::{IpAddr, SocketAddr};
+
+impl Server {
+    ///
+    /// Trusts the given proxies to report the client address, enabling
+    /// [Request::client_ip] to read it from the `Forwarded` or `X-Forwarded-For` headers
+    /// of requests they relay.
+    ///
+    /// `👉 Note:` Requests from any other peer keep their socket address, as the headers
+    /// are trivially forged by clients
+    ///
+    /// # Arguments
+    ///
+    /// * `proxies` -> Addresses of the reverse proxies in front of the server
+    ///
+    pub fn trusted_proxies<I: IntoIterator<Item = IpAddr>>(mut self, proxies: I) -> Server {
+        self.trusted_proxies = proxies.into_iter().collect();
+
+        self
+    }
+}
+
+impl Request {
+    ///
+    /// Returns the IP address of the client.
+    ///
+    /// This is the peer address, unless the peer is one of the [Server::trusted_proxies],
+    /// in which case the forwarding chain is walked from the nearest hop back to the
+    /// first address not belonging to a trusted proxy.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<IpAddr>` -> The address, or None if the request wasn't read by
+    ///   [Server::serve] and [Request::remote_addr] isn't set
+    ///
+    pub fn client_ip(&self) -> Option<IpAddr> {
+        self.client_ip
+            .or_else(|| self.remote_addr.map(|addr| addr.ip()))
+    }
+}
+
+///
+/// Resolves the client address of a request received from `peer`.
+///
+/// `Forwarded` is used when present, `X-Forwarded-For` otherwise. Walking stops at an
+/// entry that isn't an IP address (e.g., `unknown` or an obfuscated identifier),
+/// returning the last trusted hop.
+///
+pub(crate) fn client_ip(headers: &HeaderMap, peer: IpAddr, trusted: &[IpAddr]) -> IpAddr {
+    if !trusted.contains(&peer) {
+        return peer;
+    }
+
+    let chain: Vec<&str> = match headers.contains_key("Forwarded") {
+        true => headers
+            .get_all("Forwarded")
+            .flat_map(|value| value.split(','))
+            .filter_map(forwarded_for)
+            .collect(),
+        false => headers
+            .get_all("X-Forwarded-For")
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .collect(),
+    };
+
+    let mut client = peer;
+
+    for hop in chain.iter().rev() {
+        let Some(ip) = parse_node(hop) else {
+            break;
+        };
+
+        client = ip;
+
+        if !trusted.contains(&ip) {
+            break;
+        }
+    }
+
+    client
+}
+
+///
+/// Extracts the `for=` parameter of one `Forwarded` element.
+///
+fn forwarded_for(element: &str) -> Option<&str> {
+    element.split(';').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+
+        key.trim()
+            .eq_ignore_ascii_case("for")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+///
+/// Parses a forwarded node, an IP address optionally bracketed (IPv6) and with a port.
+///
+fn parse_node(node: &str) -> Option<IpAddr> {
+    if let Ok(ip) = node.parse::<IpAddr>() {
+        return Some(ip);
+    }
+
+    if let Ok(addr) = node.parse::<SocketAddr>() {
+        return Some(addr.ip());
+    }
+
+    node.strip_prefix('[')?.split_once(']')?.0.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_client_ip_trusted_proxies() {
+        let trusted = [ip("10.0.0.1"), ip("10.0.0.2")];
+
+        let headers: HeaderMap = [("X-Forwarded-For", "6.6.6.6, 203.0.113.7, 10.0.0.2")]
+            .into_iter()
+            .collect();
+
+        // The spoofed leftmost entry is ignored, the first untrusted hop wins
+        assert_eq!(
+            client_ip(&headers, ip("10.0.0.1"), &trusted),
+            ip("203.0.113.7")
+        );
+
+        // Untrusted peers can't pick their address
+        assert_eq!(
+            client_ip(&headers, ip("198.51.100.1"), &trusted),
+            ip("198.51.100.1")
+        );
+
+        let headers: HeaderMap = [(
+            "Forwarded",
+            "for=\"[2001:db8::1]:4711\";proto=https, for=10.0.0.2",
+        )]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            client_ip(&headers, ip("10.0.0.1"), &trusted),
+            ip("2001:db8::1")
+        );
+
+        let headers: HeaderMap = [("Forwarded", "for=unknown")].into_iter().collect();
+
+        assert_eq!(
+            client_ip(&headers, ip("10.0.0.1"), &trusted),
+            ip("10.0.0.1")
+        );
+    }
+}
@@ -0,0 +1,276 @@
+//!
+//! GraphQL endpoint serving a `juniper` schema, with a GraphiQL page to explore it.
+//!
+//! `👉 Note:` Requires the `graphql` feature
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::graphql::GraphQL;
+//! use http_rs::router::Router;
+//! use http_rs::server::Request;
+//! use juniper::{graphql_object, EmptyMutation, EmptySubscription, RootNode};
+//!
+//! struct Context {
+//!     user: Option<String>,
+//! }
+//!
+//! impl juniper::Context for Context {}
+//!
+//! struct Query;
+//!
+//! #[graphql_object]
+//! #[graphql(context = Context)]
+//! impl Query {
+//!     fn whoami(context: &Context) -> Option<String> {
+//!         context.user.clone()
+//!     }
+//! }
+//!
+//! let schema = RootNode::new(Query, EmptyMutation::new(), EmptySubscription::new());
+//! let mut router = Router::new();
+//!
+//! // POST/GET /graphql runs queries, GET /graphiql serves the explorer page
+//! GraphQL::new(schema, |req: &Request| Context {
+//!     user: req.headers.get("X-User").map(str::to_string),
+//! })
+//! .mount(&mut router);
+//! ```
+//!
+
+use crate::{
+    extract::{has_media_type, media_type},
+    router::Router,
+    server::{HttpMethod, Request, Response},
+};
+use juniper::{
+    http::{graphiql::graphiql_source, GraphQLBatchRequest, GraphQLBatchResponse, GraphQLRequest},
+    DefaultScalarValue, GraphQLType, InputValue, RootNode,
+};
+use std::sync::Arc;
+
+///
+/// Executes operations against the schema, with the context built from the [Request].
+///
+type Executor = dyn Fn(&Request, &GraphQLBatchRequest) -> GraphQLBatchResponse + Send + Sync;
+
+///
+/// A GraphQL endpoint for a `juniper` schema, following the GraphQL over HTTP
+/// conventions:
+///
+/// * `POST` with an `application/json` body `{"query": ..., "variables": ...,
+///   "operationName": ...}`, or an array of them for a batch
+/// * `POST` with an `application/graphql` body holding the query
+/// * `GET` with `query`, `variables` (`JSON`) and `operationName` query parameters
+///
+/// Results are answered as `JSON` with `200`, or `400` when the query couldn't be
+/// executed (e.g., a syntax or validation error), and other content types with `415`.
+///
+pub struct GraphQL {
+    executor: Arc<Executor>,
+    endpoint: String,
+    graphiql: Option<String>,
+}
+
+impl GraphQL {
+    ///
+    /// Creates an endpoint at `/graphql`, with GraphiQL at `/graphiql`.
+    ///
+    /// # Arguments
+    ///
+    /// * `schema` -> The `juniper` schema
+    /// * `context` -> Builds the resolvers' context from each [Request] (e.g., the
+    ///   authenticated user, a database handle)
+    ///
+    pub fn new<Q, M, S, F>(schema: RootNode<Q, M, S>, context: F) -> GraphQL
+    where
+        Q: GraphQLType<DefaultScalarValue>,
+        M: GraphQLType<DefaultScalarValue, Context = Q::Context>,
+        S: GraphQLType<DefaultScalarValue, Context = Q::Context>,
+        RootNode<Q, M, S>: Send + Sync + 'static,
+        F: Fn(&Request) -> Q::Context + Send + Sync + 'static,
+    {
+        let executor = move |req: &Request, operations: &GraphQLBatchRequest| {
+            operations.execute_sync(&schema, &context(req))
+        };
+
+        GraphQL {
+            executor: Arc::new(executor),
+            endpoint: "/graphql".to_string(),
+            graphiql: Some("/graphiql".to_string()),
+        }
+    }
+
+    ///
+    /// Sets the paths of the endpoint and of the GraphiQL page, None to not serve it
+    /// (e.g., in production).
+    ///
+    pub fn paths(mut self, endpoint: &str, graphiql: Option<&str>) -> GraphQL {
+        self.endpoint = endpoint.to_string();
+        self.graphiql = graphiql.map(str::to_string);
+
+        self
+    }
+
+    ///
+    /// Registers the endpoint and GraphiQL routes on a [Router].
+    ///
+    /// The GraphiQL page loads its assets from the `jsDelivr` CDN.
+    ///
+    pub fn mount(self, router: &mut Router) {
+        let executor = self.executor.clone();
+        router.get(&self.endpoint, move |req: Request| {
+            execute(&*executor, &req)
+        });
+
+        let executor = self.executor;
+        router.post(&self.endpoint, move |req: Request| {
+            execute(&*executor, &req)
+        });
+
+        if let Some(graphiql) = &self.graphiql {
+            let page = graphiql_source(&self.endpoint, None);
+
+            router.get(graphiql, move |_| Response::new(200).html(&page));
+        }
+    }
+}
+
+///
+/// Runs the operations of a [Request].
+///
+fn execute(executor: &Executor, req: &Request) -> Response {
+    let operations = match parse(req) {
+        Ok(operations) => operations,
+        Err((status, message)) => {
+            return Response::new(status)
+                .json(&serde_json::json!({ "errors": [{ "message": message }] }))
+        }
+    };
+
+    let result = executor(req, &operations);
+
+    match result.is_ok() {
+        true => Response::new(200).json(&result),
+        false => Response::new(400).json(&result),
+    }
+}
+
+///
+/// Reads the operations from the query parameters of a `GET`, or the body of a `POST`.
+///
+fn parse(req: &Request) -> Result<GraphQLBatchRequest, (u16, String)> {
+    if req.method == HttpMethod::GET {
+        let query = req
+            .query_params
+            .get("query")
+            .ok_or((400, "Missing `query` parameter".to_string()))?;
+
+        let variables = match req.query_params.get("variables") {
+            Some(variables) => Some(
+                serde_json::from_str::<InputValue>(variables)
+                    .map_err(|e| (400, format!("Invalid `variables`: {}", e)))?,
+            ),
+            None => None,
+        };
+
+        let operation_name = req.query_params.get("operationName").cloned();
+
+        return Ok(GraphQLBatchRequest::Single(GraphQLRequest::new(
+            query.clone(),
+            operation_name,
+            variables,
+        )));
+    }
+
+    if has_media_type(req, "application/graphql") {
+        let query = String::from_utf8(req.body.clone())
+            .map_err(|_| (400, "Body isn't valid UTF-8".to_string()))?;
+
+        return Ok(GraphQLBatchRequest::Single(GraphQLRequest::new(
+            query, None, None,
+        )));
+    }
+
+    match media_type(req) {
+        Some(media_type) if media_type.eq_ignore_ascii_case("application/json") => {
+            serde_json::from_slice(&req.body).map_err(|e| (400, format!("Invalid JSON: {}", e)))
+        }
+        _ => Err((
+            415,
+            "Expected application/json or application/graphql".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GraphQL;
+    use crate::{
+        router::Router,
+        server::{test_request, HttpMethod, Request, Response},
+    };
+    use juniper::{graphql_object, EmptyMutation, EmptySubscription, RootNode};
+
+    struct Context {
+        user: String,
+    }
+
+    impl juniper::Context for Context {}
+
+    struct Query;
+
+    #[graphql_object]
+    #[graphql(context = Context)]
+    impl Query {
+        fn greet(context: &Context, greeting: String) -> String {
+            format!("{} {}", greeting, context.user)
+        }
+    }
+
+    fn body(res: &Response) -> String {
+        String::from_utf8_lossy(res.body.as_bytes().unwrap()).to_string()
+    }
+
+    #[test]
+    fn test_graphql_endpoint() {
+        let schema = RootNode::new(Query, EmptyMutation::new(), EmptySubscription::new());
+        let mut router = Router::new();
+
+        GraphQL::new(schema, |req: &Request| Context {
+            user: req.headers.get("X-User").unwrap_or("anonymous").to_string(),
+        })
+        .mount(&mut router);
+
+        let mut req = test_request(HttpMethod::POST, "/graphql");
+        req.headers.insert("Content-Type", "application/json");
+        req.headers.insert("X-User", "Alice");
+        req.body =
+            br#"{"query": "query($g: String!) { greet(greeting: $g) }", "variables": {"g": "Hi"}}"#
+                .to_vec();
+
+        let res = router.handle(req);
+        assert_eq!(res.status, 200);
+        assert_eq!(body(&res), r#"{"data":{"greet":"Hi Alice"}}"#);
+
+        let res = router.handle(test_request(
+            HttpMethod::GET,
+            "/graphql?query=%7B%20greet(greeting%3A%20%22Yo%22)%20%7D",
+        ));
+        assert_eq!(body(&res), r#"{"data":{"greet":"Yo anonymous"}}"#);
+
+        let mut req = test_request(HttpMethod::POST, "/graphql");
+        req.headers.insert("Content-Type", "application/graphql");
+        req.body = b"{ nope }".to_vec();
+
+        let res = router.handle(req);
+        assert_eq!(res.status, 400);
+        assert!(body(&res).contains("Unknown field \\\"nope\\\""));
+
+        let res = router.handle(test_request(HttpMethod::POST, "/graphql"));
+        assert_eq!(res.status, 415);
+
+        let res = router.handle(test_request(HttpMethod::GET, "/graphiql"));
+        assert!(body(&res).contains("GraphiQL"));
+    }
+}
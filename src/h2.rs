@@ -0,0 +1,2049 @@
+//!
+//! HTTP/2 (RFC 9113) on TLS connections that negotiated `h2` with ALPN.
+//!
+//! The frames of every stream are read as they arrive, on the connection's thread, and
+//! each request is handed to the [Handler] on a worker thread of its own once complete.
+//! Responses are sent concurrently, their DATA frames interleaved as the client's flow
+//! control windows allow, so a slow or never ending one (e.g., server-sent events)
+//! holds back no other stream. Server push isn't supported.
+//!
+
+use crate::{
+    body::Body,
+    error::{is_timeout, Error},
+    events,
+    header::HeaderMap,
+    hpack::{self, DecodeError, Decoder},
+    router::{Handler, RouteLimits},
+    server::{
+        attach, dispatch, error_response, stamp, HttpMethod, HttpVersion, Limits, Peer, Request,
+        Response, Shared,
+    },
+    stream::Stream,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, BufRead, BufReader, Read, Write},
+    net::Shutdown,
+    sync::{Condvar, Mutex, MutexGuard},
+    thread,
+    time::{Duration, Instant},
+};
+
+///
+/// Connection preface every client starts with.
+///
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+// Frame types
+const DATA: u8 = 0x0;
+const HEADERS: u8 = 0x1;
+const PRIORITY: u8 = 0x2;
+const RST_STREAM: u8 = 0x3;
+const SETTINGS: u8 = 0x4;
+const PUSH_PROMISE: u8 = 0x5;
+const PING: u8 = 0x6;
+const GOAWAY: u8 = 0x7;
+const WINDOW_UPDATE: u8 = 0x8;
+const CONTINUATION: u8 = 0x9;
+
+// Frame flags
+const END_STREAM: u8 = 0x1;
+const ACK: u8 = 0x1;
+const END_HEADERS: u8 = 0x4;
+const PADDED: u8 = 0x8;
+const PRIORITY_FLAG: u8 = 0x20;
+
+// Error codes
+const NO_ERROR: u32 = 0x0;
+const PROTOCOL_ERROR: u32 = 0x1;
+const INTERNAL_ERROR: u32 = 0x2;
+const FLOW_CONTROL_ERROR: u32 = 0x3;
+const STREAM_CLOSED: u32 = 0x5;
+const FRAME_SIZE_ERROR: u32 = 0x6;
+const REFUSED_STREAM: u32 = 0x7;
+const COMPRESSION_ERROR: u32 = 0x9;
+const ENHANCE_YOUR_CALM: u32 = 0xb;
+
+// Settings
+const SETTINGS_ENABLE_PUSH: u16 = 0x2;
+const SETTINGS_MAX_CONCURRENT_STREAMS: u16 = 0x3;
+const SETTINGS_INITIAL_WINDOW_SIZE: u16 = 0x4;
+const SETTINGS_MAX_FRAME_SIZE: u16 = 0x5;
+const SETTINGS_MAX_HEADER_LIST_SIZE: u16 = 0x6;
+
+///
+/// Initial flow control window of the connection and of every stream.
+///
+const DEFAULT_WINDOW: i64 = 65_535;
+
+///
+/// Largest flow control window allowed.
+///
+const MAX_WINDOW: i64 = (1 << 31) - 1;
+
+///
+/// Largest frame payload received and sent, the default `SETTINGS_MAX_FRAME_SIZE`.
+///
+const FRAME_SIZE: usize = 16_384;
+
+///
+/// Streams a client may have open at once, advertised with
+/// `SETTINGS_MAX_CONCURRENT_STREAMS`.
+///
+const MAX_STREAMS: u32 = 100;
+
+///
+/// Header fields specific to HTTP/1 connections, which HTTP/2 messages mustn't carry.
+///
+const CONNECTION_SPECIFIC: [&str; 5] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+];
+
+///
+/// Why a connection ended before the client closed it.
+///
+#[derive(Debug)]
+enum Failure {
+    ///
+    /// Reading or writing the connection failed
+    ///
+    Io(io::Error),
+
+    ///
+    /// The client broke the protocol, answered with a `GOAWAY` carrying this error code
+    ///
+    Protocol(u32),
+}
+
+impl From<io::Error> for Failure {
+    fn from(e: io::Error) -> Failure {
+        Failure::Io(e)
+    }
+}
+
+///
+/// A frame as read from the connection.
+///
+struct Frame {
+    kind: u8,
+    flags: u8,
+    stream: u32,
+    payload: Vec<u8>,
+}
+
+impl Frame {
+    ///
+    /// Returns the payload without its padding, and without the priority fields of a
+    /// `HEADERS` frame.
+    ///
+    fn data(&self) -> Result<&[u8], Failure> {
+        let mut data = &self.payload[..];
+
+        if self.flags & PADDED != 0 {
+            let (&padding, rest) = data
+                .split_first()
+                .ok_or(Failure::Protocol(PROTOCOL_ERROR))?;
+            let len = rest.len().checked_sub(padding as usize);
+
+            data = &rest[..len.ok_or(Failure::Protocol(PROTOCOL_ERROR))?];
+        }
+
+        if self.kind == HEADERS && self.flags & PRIORITY_FLAG != 0 {
+            data = data.get(5..).ok_or(Failure::Protocol(PROTOCOL_ERROR))?;
+        }
+
+        Ok(data)
+    }
+
+    ///
+    /// Reads the 4 byte payload of `RST_STREAM` and `WINDOW_UPDATE` frames.
+    ///
+    fn word(&self) -> Result<u32, Failure> {
+        match self.payload[..] {
+            [a, b, c, d] => Ok(u32::from_be_bytes([a, b, c, d])),
+            _ => Err(Failure::Protocol(FRAME_SIZE_ERROR)),
+        }
+    }
+}
+
+///
+/// A request stream whose header block was received.
+///
+#[derive(Debug)]
+struct Incoming {
+    ///
+    /// The request of the header block, without its body, or why it is malformed
+    ///
+    head: Result<Request, Error>,
+    body: Vec<u8>,
+
+    ///
+    /// Fields of a header block ending the stream after the body
+    ///
+    trailers: Vec<(String, String)>,
+
+    ///
+    /// Whether the client ended the stream, its request being complete
+    ///
+    ended: bool,
+
+    ///
+    /// Whether the body outgrew the limit, the stream being answered right away
+    ///
+    too_large: bool,
+
+    ///
+    /// Whether the client paused the body for longer than the read timeout, the stream
+    /// being answered right away
+    ///
+    timed_out: bool,
+
+    ///
+    /// Largest body accepted and longest pause allowed while it is received, the
+    /// route's (see [crate::router::Router::limits]) or the server's
+    ///
+    max_body: usize,
+    read_timeout: Option<Duration>,
+
+    ///
+    /// When the stream last received a frame
+    ///
+    last_read: Instant,
+}
+
+///
+/// Reading half of an HTTP/2 connection, run on the connection's thread.
+///
+struct Connection<'a> {
+    reader: BufReader<Stream>,
+    output: &'a Output,
+    shared: &'a Shared,
+
+    ///
+    /// Limits of the route a request goes to, see [Handler::route_limits]
+    ///
+    route_limits: &'a dyn Fn(&Request) -> Option<RouteLimits>,
+    decoder: Decoder,
+
+    ///
+    /// Streams still receiving their request
+    ///
+    open: HashMap<u32, Incoming>,
+
+    ///
+    /// Streams whose request completed, waiting for a worker to answer them
+    ///
+    ready: VecDeque<(u32, Incoming)>,
+
+    ///
+    /// Highest stream opened by the client, later ones being idle
+    ///
+    last_stream: u32,
+
+    ///
+    /// Header block waiting for `CONTINUATION` frames: its stream, the `HEADERS` flags
+    /// and the fragments received so far
+    ///
+    partial: Option<(u32, u8, Vec<u8>)>,
+
+    ///
+    /// Whether the client sent `GOAWAY`
+    ///
+    going_away: bool,
+}
+
+///
+/// Writing half of an HTTP/2 connection, shared by the connection's thread and the
+/// workers answering its streams.
+///
+struct Output {
+    state: Mutex<Outgoing>,
+
+    ///
+    /// Notified when flow control windows grow, streams close or the connection ends
+    ///
+    changed: Condvar,
+}
+
+///
+/// What [Output] guards: the connection, so frames are written whole, and the state
+/// deciding what may be written.
+///
+struct Outgoing {
+    writer: Stream,
+
+    ///
+    /// Bytes the client lets us send on the connection
+    ///
+    send_window: i64,
+
+    ///
+    /// Bytes the client lets us send on each stream not answered yet
+    ///
+    windows: HashMap<u32, i64>,
+
+    ///
+    /// Window of new streams, as set by the client's `SETTINGS_INITIAL_WINDOW_SIZE`
+    ///
+    initial_window: i64,
+
+    ///
+    /// Workers answering a stream
+    ///
+    running: usize,
+
+    ///
+    /// When the last worker finished, None until one did
+    ///
+    idle_since: Option<Instant>,
+
+    ///
+    /// Whether the connection ended, workers giving up on their streams
+    ///
+    closed: bool,
+}
+
+///
+/// How often the connection's thread checks whether its workers finished, while it
+/// waits for frames from a client waiting for responses.
+///
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+///
+/// Serves an HTTP/2 connection until the client closes it, stays idle longer than the
+/// keep-alive timeout, or breaks the protocol.
+///
+pub(crate) fn serve<H: Handler>(stream: Stream, handler: &H, shared: &Shared) {
+    let Ok(reader) = stream.try_clone() else {
+        return;
+    };
+
+    let peer = Peer::of(&stream);
+    let output = Output {
+        state: Mutex::new(Outgoing {
+            writer: stream,
+            send_window: DEFAULT_WINDOW,
+            windows: HashMap::new(),
+            initial_window: DEFAULT_WINDOW,
+            running: 0,
+            idle_since: None,
+            closed: false,
+        }),
+        changed: Condvar::new(),
+    };
+
+    let route_limits = |req: &Request| handler.route_limits(req);
+
+    let mut conn = Connection {
+        reader: BufReader::new(reader),
+        output: &output,
+        shared,
+        route_limits: &route_limits,
+        decoder: Decoder::new(max_header_list_size(&shared.limits)),
+        open: HashMap::new(),
+        ready: VecDeque::new(),
+        last_stream: 0,
+        partial: None,
+        going_away: false,
+    };
+
+    let (output, peer) = (&output, &peer);
+
+    // Waits for the workers before the connection is dropped
+    thread::scope(|scope| {
+        let answer = |id, incoming| {
+            let running = output.start();
+
+            scope.spawn(move || {
+                respond(handler, output, shared, peer, id, incoming);
+                drop(running);
+            });
+        };
+
+        if let Err(Failure::Protocol(code)) = conn.run(answer) {
+            let _ = conn.go_away(code);
+        }
+
+        output.close();
+    });
+}
+
+impl Connection<'_> {
+    ///
+    /// Exchanges the prefaces, then reads frames, handing each request to `answer` once
+    /// complete.
+    ///
+    fn run(&mut self, mut answer: impl FnMut(u32, Incoming)) -> Result<(), Failure> {
+        let timeouts = self.shared.timeouts;
+
+        // Read within the header timeout, like the head of an HTTP/1 request
+        let mut preface = [0; PREFACE.len()];
+        self.reader.read_exact(&mut preface)?;
+
+        if preface != PREFACE {
+            return Err(Failure::Protocol(PROTOCOL_ERROR));
+        }
+
+        let list_size = max_header_list_size(&self.shared.limits);
+
+        let mut settings = Vec::new();
+        settings.extend_from_slice(&SETTINGS_MAX_CONCURRENT_STREAMS.to_be_bytes());
+        settings.extend_from_slice(&MAX_STREAMS.to_be_bytes());
+        settings.extend_from_slice(&SETTINGS_MAX_HEADER_LIST_SIZE.to_be_bytes());
+        settings.extend_from_slice(&u32::try_from(list_size).unwrap_or(u32::MAX).to_be_bytes());
+
+        self.write_frame(SETTINGS, 0, 0, &settings)?;
+        self.flush()?;
+
+        // Like an HTTP/1 connection's, timeouts run from the last frame or response
+        let mut last_frame = Instant::now();
+
+        loop {
+            while let Some((id, incoming)) = self.ready.pop_front() {
+                answer(id, incoming);
+            }
+
+            let receiving = !self.open.is_empty() || self.partial.is_some();
+            let (running, idle_since) = {
+                let out = self.output.lock();
+                (out.running, out.idle_since)
+            };
+
+            if !receiving && running == 0 && self.going_away {
+                return Ok(());
+            }
+
+            // Connections are kept open between requests as HTTP/1 ones are
+            let timeout = match (receiving, running, idle_since) {
+                (true, ..) => self.receive_timeout(),
+                (false, 1.., _) => Some(POLL_INTERVAL),
+                (false, 0, None) => left(timeouts.header, last_frame),
+                (false, 0, Some(_)) if timeouts.keep_alive.is_none() => {
+                    return self.go_away(NO_ERROR)
+                }
+                (false, 0, Some(since)) => left(timeouts.keep_alive, since.max(last_frame)),
+            };
+
+            if timeout.is_some_and(|timeout| timeout.is_zero()) {
+                return self.go_away(NO_ERROR);
+            }
+
+            // Waits for the start of a frame, then reads the rest within the read timeout
+            self.reader.get_ref().set_read_timeout(timeout)?;
+
+            match self.reader.fill_buf().map(|buf| buf.is_empty()) {
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                // Checked again, as a worker may have finished meanwhile
+                Err(e) if !receiving && is_timeout(&e) => continue,
+                // Streams too slow to send their body are answered, others keep waiting
+                Err(e) if is_timeout(&e) && self.expire() => continue,
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(e) => return Err(e.into()),
+            }
+
+            self.reader.get_ref().set_read_timeout(timeouts.read)?;
+
+            let frame = match self.read_frame() {
+                Err(Failure::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                frame => frame?,
+            };
+
+            self.process(frame)?;
+            self.flush()?;
+
+            last_frame = Instant::now();
+        }
+    }
+
+    ///
+    /// How long to wait for the next frame while requests are received: until the
+    /// first stream pausing its body longer than its read timeout, or the read timeout
+    /// for a header block in progress.
+    ///
+    fn receive_timeout(&self) -> Option<Duration> {
+        let partial = self.partial.as_ref().and(self.shared.timeouts.read);
+
+        self.open
+            .values()
+            .filter_map(|incoming| left(incoming.read_timeout, incoming.last_read))
+            .chain(partial)
+            .min()
+    }
+
+    ///
+    /// Hands the streams that paused their body longer than their read timeout over to
+    /// be answered with a `408`.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` -> Whether a stream timed out
+    ///
+    fn expire(&mut self) -> bool {
+        let expired: Vec<u32> = self
+            .open
+            .iter()
+            .filter(|(_, incoming)| {
+                left(incoming.read_timeout, incoming.last_read).is_some_and(|left| left.is_zero())
+            })
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in &expired {
+            if let Some(mut incoming) = self.open.remove(id) {
+                incoming.timed_out = true;
+                self.ready.push_back((*id, incoming));
+            }
+        }
+
+        !expired.is_empty()
+    }
+
+    ///
+    /// Handles a frame received from the client.
+    ///
+    fn process(&mut self, frame: Frame) -> Result<(), Failure> {
+        // A header block must be continued before anything else
+        if let Some((id, _, _)) = &self.partial {
+            if frame.kind != CONTINUATION || frame.stream != *id {
+                return Err(Failure::Protocol(PROTOCOL_ERROR));
+            }
+        }
+
+        let misplaced = match frame.kind {
+            SETTINGS | PING | GOAWAY => frame.stream != 0,
+            DATA | HEADERS | PRIORITY | RST_STREAM | PUSH_PROMISE | CONTINUATION => {
+                frame.stream == 0
+            }
+            _ => false,
+        };
+
+        if misplaced {
+            return Err(Failure::Protocol(PROTOCOL_ERROR));
+        }
+
+        match frame.kind {
+            DATA => self.on_data(frame),
+            HEADERS => self.on_headers(frame),
+            CONTINUATION => self.on_continuation(frame),
+            RST_STREAM => self.on_reset(frame),
+            SETTINGS => self.on_settings(frame),
+            PING => self.on_ping(frame),
+            WINDOW_UPDATE => self.on_window_update(frame),
+            GOAWAY => {
+                self.going_away = true;
+                Ok(())
+            }
+            PUSH_PROMISE => Err(Failure::Protocol(PROTOCOL_ERROR)),
+            // Priorities are advisory and deprecated
+            PRIORITY => Ok(()),
+            // Unknown frame types must be ignored
+            _ => Ok(()),
+        }
+    }
+
+    fn on_data(&mut self, frame: Frame) -> Result<(), Failure> {
+        let id = frame.stream;
+
+        if id > self.last_stream {
+            return Err(Failure::Protocol(PROTOCOL_ERROR));
+        }
+
+        // Padding counts against flow control too, the whole frame is given back
+        let flow = frame.payload.len() as u32;
+
+        if flow > 0 {
+            self.write_frame(WINDOW_UPDATE, 0, 0, &flow.to_be_bytes())?;
+        }
+
+        // Data of a stream that was answered or reset is dropped
+        let Some(incoming) = self.open.get_mut(&id) else {
+            return Ok(());
+        };
+
+        let data = frame.data()?;
+        incoming.last_read = Instant::now();
+
+        if incoming.body.len() + data.len() > incoming.max_body {
+            incoming.too_large = true;
+        } else {
+            incoming.body.extend_from_slice(data);
+        }
+
+        incoming.ended = frame.flags & END_STREAM != 0;
+
+        if incoming.ended || incoming.too_large {
+            if let Some(incoming) = self.open.remove(&id) {
+                self.ready.push_back((id, incoming));
+            }
+        } else if flow > 0 {
+            self.write_frame(WINDOW_UPDATE, 0, id, &flow.to_be_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    fn on_headers(&mut self, frame: Frame) -> Result<(), Failure> {
+        if frame.stream.is_multiple_of(2) {
+            return Err(Failure::Protocol(PROTOCOL_ERROR));
+        }
+
+        let fragment = frame.data()?;
+
+        if frame.flags & END_HEADERS != 0 {
+            self.on_header_block(frame.stream, frame.flags, fragment)
+        } else {
+            self.partial = Some((frame.stream, frame.flags, fragment.to_vec()));
+            Ok(())
+        }
+    }
+
+    fn on_continuation(&mut self, frame: Frame) -> Result<(), Failure> {
+        let (id, flags, mut block) = self
+            .partial
+            .take()
+            .ok_or(Failure::Protocol(PROTOCOL_ERROR))?;
+
+        let limits = self.shared.limits;
+
+        block.extend_from_slice(&frame.payload);
+
+        // Bounds the memory a never ending header block can take
+        if block.len() > limits.headers * limits.header_line {
+            return Err(Failure::Protocol(ENHANCE_YOUR_CALM));
+        }
+
+        if frame.flags & END_HEADERS != 0 {
+            self.on_header_block(id, flags, &block)
+        } else {
+            self.partial = Some((id, flags, block));
+            Ok(())
+        }
+    }
+
+    ///
+    /// Handles a complete header block, which opens a stream or carries its trailers.
+    ///
+    fn on_header_block(&mut self, id: u32, flags: u8, block: &[u8]) -> Result<(), Failure> {
+        // Decoded even if the stream is refused, to keep the dynamic table in sync
+        let headers = self.decoder.decode(block).map_err(|e| match e {
+            DecodeError::Malformed => Failure::Protocol(COMPRESSION_ERROR),
+            // Stopping halfway leaves the dynamic table out of sync as well
+            DecodeError::TooLarge => Failure::Protocol(ENHANCE_YOUR_CALM),
+        })?;
+
+        let ended = flags & END_STREAM != 0;
+
+        // A block on an open stream holds trailers, which end it
+        if let Some(mut incoming) = self.open.remove(&id) {
+            if !ended {
+                return Err(Failure::Protocol(PROTOCOL_ERROR));
+            }
+
+            incoming.trailers = headers;
+            incoming.ended = true;
+            self.ready.push_back((id, incoming));
+
+            return Ok(());
+        }
+
+        if id <= self.last_stream {
+            return Err(Failure::Protocol(STREAM_CLOSED));
+        }
+
+        self.last_stream = id;
+
+        let mut out = self.output.lock();
+
+        if self.open.len() + self.ready.len() + out.running >= MAX_STREAMS as usize {
+            drop(out);
+            return self.reset(id, REFUSED_STREAM);
+        }
+
+        let window = out.initial_window;
+        out.windows.insert(id, window);
+        drop(out);
+
+        let head = head(headers, &self.shared.limits);
+
+        // The body is received within the limits of the route it goes to
+        let route = match &head {
+            Ok(req) if !ended => (self.route_limits)(req).unwrap_or_default(),
+            _ => RouteLimits::default(),
+        };
+
+        let incoming = Incoming {
+            head,
+            body: Vec::new(),
+            trailers: Vec::new(),
+            ended,
+            too_large: false,
+            timed_out: false,
+            max_body: route.max_body_size.unwrap_or(self.shared.limits.body),
+            read_timeout: route.read_timeout.or(self.shared.timeouts.read),
+            last_read: Instant::now(),
+        };
+
+        if ended {
+            self.ready.push_back((id, incoming));
+        } else {
+            self.open.insert(id, incoming);
+        }
+
+        Ok(())
+    }
+
+    fn on_reset(&mut self, frame: Frame) -> Result<(), Failure> {
+        frame.word()?;
+
+        if frame.stream > self.last_stream {
+            return Err(Failure::Protocol(PROTOCOL_ERROR));
+        }
+
+        self.close(frame.stream);
+
+        Ok(())
+    }
+
+    fn on_settings(&mut self, frame: Frame) -> Result<(), Failure> {
+        if frame.flags & ACK != 0 {
+            return match frame.payload.len() {
+                0 => Ok(()),
+                _ => Err(Failure::Protocol(FRAME_SIZE_ERROR)),
+            };
+        }
+
+        if !frame.payload.len().is_multiple_of(6) {
+            return Err(Failure::Protocol(FRAME_SIZE_ERROR));
+        }
+
+        for setting in frame.payload.chunks(6) {
+            let id = u16::from_be_bytes([setting[0], setting[1]]);
+            let value = u32::from_be_bytes([setting[2], setting[3], setting[4], setting[5]]);
+
+            match id {
+                SETTINGS_ENABLE_PUSH if value > 1 => {
+                    return Err(Failure::Protocol(PROTOCOL_ERROR));
+                }
+                SETTINGS_INITIAL_WINDOW_SIZE => {
+                    let value = i64::from(value);
+
+                    if value > MAX_WINDOW {
+                        return Err(Failure::Protocol(FLOW_CONTROL_ERROR));
+                    }
+
+                    let mut out = self.output.lock();
+
+                    // Applies to the streams already open as well
+                    let delta = value - out.initial_window;
+
+                    for window in out.windows.values_mut() {
+                        *window += delta;
+
+                        if *window > MAX_WINDOW {
+                            return Err(Failure::Protocol(FLOW_CONTROL_ERROR));
+                        }
+                    }
+
+                    out.initial_window = value;
+                    drop(out);
+
+                    self.output.changed.notify_all();
+                }
+                // Frames are never sent larger than the default anyway
+                SETTINGS_MAX_FRAME_SIZE if !(16_384..=16_777_215).contains(&value) => {
+                    return Err(Failure::Protocol(PROTOCOL_ERROR));
+                }
+                _ => {}
+            }
+        }
+
+        self.write_frame(SETTINGS, ACK, 0, &[])?;
+
+        Ok(())
+    }
+
+    fn on_ping(&mut self, frame: Frame) -> Result<(), Failure> {
+        if frame.payload.len() != 8 {
+            return Err(Failure::Protocol(FRAME_SIZE_ERROR));
+        }
+
+        if frame.flags & ACK == 0 {
+            self.write_frame(PING, ACK, 0, &frame.payload)?;
+        }
+
+        Ok(())
+    }
+
+    fn on_window_update(&mut self, frame: Frame) -> Result<(), Failure> {
+        let increment = i64::from(frame.word()? & 0x7fff_ffff);
+        let id = frame.stream;
+
+        if id == 0 {
+            let mut out = self.output.lock();
+            out.send_window += increment;
+            let window = out.send_window;
+            drop(out);
+
+            self.output.changed.notify_all();
+
+            return match increment {
+                0 => Err(Failure::Protocol(PROTOCOL_ERROR)),
+                _ if window > MAX_WINDOW => Err(Failure::Protocol(FLOW_CONTROL_ERROR)),
+                _ => Ok(()),
+            };
+        }
+
+        if id > self.last_stream {
+            return Err(Failure::Protocol(PROTOCOL_ERROR));
+        }
+
+        let mut out = self.output.lock();
+
+        let Some(window) = out.windows.get_mut(&id) else {
+            return Ok(());
+        };
+
+        *window += increment;
+        let window = *window;
+        drop(out);
+
+        self.output.changed.notify_all();
+
+        match increment {
+            0 => self.reset(id, PROTOCOL_ERROR),
+            _ if window > MAX_WINDOW => self.reset(id, FLOW_CONTROL_ERROR),
+            _ => Ok(()),
+        }
+    }
+
+    ///
+    /// Resets a stream with an error code and forgets it.
+    ///
+    fn reset(&mut self, id: u32, code: u32) -> Result<(), Failure> {
+        self.close(id);
+        self.write_frame(RST_STREAM, 0, id, &code.to_be_bytes())?;
+
+        Ok(())
+    }
+
+    ///
+    /// Forgets a stream, whether it is receiving, waiting or being answered, the worker
+    /// answering it giving up.
+    ///
+    fn close(&mut self, id: u32) {
+        self.open.remove(&id);
+        self.ready.retain(|(ready, _)| *ready != id);
+
+        self.output.lock().windows.remove(&id);
+        self.output.changed.notify_all();
+    }
+
+    ///
+    /// Tells the client the connection is closing, with the last stream it opened.
+    ///
+    fn go_away(&mut self, code: u32) -> Result<(), Failure> {
+        let mut payload = self.last_stream.to_be_bytes().to_vec();
+        payload.extend_from_slice(&code.to_be_bytes());
+
+        self.write_frame(GOAWAY, 0, 0, &payload)?;
+        self.flush()?;
+
+        Ok(())
+    }
+
+    fn read_frame(&mut self) -> Result<Frame, Failure> {
+        let mut head = [0; 9];
+        self.reader.read_exact(&mut head)?;
+
+        let len = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+
+        if len > FRAME_SIZE {
+            return Err(Failure::Protocol(FRAME_SIZE_ERROR));
+        }
+
+        let mut payload = vec![0; len];
+        self.reader.read_exact(&mut payload)?;
+
+        Ok(Frame {
+            kind: head[3],
+            flags: head[4],
+            stream: u32::from_be_bytes([head[5], head[6], head[7], head[8]]) & 0x7fff_ffff,
+            payload,
+        })
+    }
+
+    fn write_frame(&mut self, kind: u8, flags: u8, stream: u32, payload: &[u8]) -> io::Result<()> {
+        self.output.lock().write_frame(kind, flags, stream, payload)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.lock().writer.flush()
+    }
+}
+
+impl Output {
+    fn lock(&self) -> MutexGuard<'_, Outgoing> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    ///
+    /// Counts a worker as running until the returned guard is dropped.
+    ///
+    fn start(&self) -> Running<'_> {
+        self.lock().running += 1;
+
+        Running(self)
+    }
+
+    ///
+    /// Ends the connection, waking the workers waiting for flow control window so they
+    /// give up on their streams.
+    ///
+    fn close(&self) {
+        let mut out = self.lock();
+        out.closed = true;
+        let _ = out.writer.shutdown(Shutdown::Both);
+        drop(out);
+
+        self.changed.notify_all();
+    }
+
+    ///
+    /// Waits until a stream may send DATA, returning the lock with how many bytes it may
+    /// send, or None once the stream is reset.
+    ///
+    /// A client that grants no window within `timeout` is treated as one that doesn't
+    /// read, failing the connection.
+    ///
+    fn window(
+        &self,
+        id: u32,
+        timeout: Option<Duration>,
+    ) -> io::Result<Option<(MutexGuard<'_, Outgoing>, usize)>> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut out = self.lock();
+
+        loop {
+            if out.closed {
+                return Err(io::ErrorKind::BrokenPipe.into());
+            }
+
+            let Some(&window) = out.windows.get(&id) else {
+                return Ok(None);
+            };
+
+            let window = window.min(out.send_window);
+
+            if window > 0 {
+                return Ok(Some((out, window as usize)));
+            }
+
+            out = match deadline {
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+
+                    if left.is_zero() {
+                        return Err(io::Error::new(
+                            io::ErrorKind::TimedOut,
+                            "No flow control window granted",
+                        ));
+                    }
+
+                    self.changed
+                        .wait_timeout(out, left)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0
+                }
+                None => self.changed.wait(out).unwrap_or_else(|e| e.into_inner()),
+            };
+        }
+    }
+}
+
+impl Outgoing {
+    ///
+    /// Writes a header block as a HEADERS frame and as many CONTINUATION frames as needed,
+    /// ending the stream with it if `end_stream`.
+    ///
+    fn write_block(&mut self, id: u32, block: &[u8], end_stream: bool) -> io::Result<()> {
+        let fragments: Vec<&[u8]> = block.chunks(FRAME_SIZE).collect();
+
+        for (i, fragment) in fragments.iter().enumerate() {
+            let mut flags = 0;
+
+            if i == fragments.len() - 1 {
+                flags |= END_HEADERS;
+            }
+
+            if i == 0 && end_stream {
+                flags |= END_STREAM;
+            }
+
+            let kind = if i == 0 { HEADERS } else { CONTINUATION };
+            self.write_frame(kind, flags, id, fragment)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_frame(&mut self, kind: u8, flags: u8, stream: u32, payload: &[u8]) -> io::Result<()> {
+        let mut frame = Vec::with_capacity(9 + payload.len());
+
+        frame.extend_from_slice(&(payload.len() as u32).to_be_bytes()[1..]);
+        frame.extend_from_slice(&[kind, flags]);
+        frame.extend_from_slice(&stream.to_be_bytes());
+        frame.extend_from_slice(payload);
+
+        self.writer.write_all(&frame)
+    }
+}
+
+///
+/// Guard counting a worker as running, see [Output::start].
+///
+struct Running<'a>(&'a Output);
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        let mut out = self.0.lock();
+        out.running -= 1;
+
+        if out.running == 0 {
+            out.idle_since = Some(Instant::now());
+        }
+    }
+}
+
+///
+/// Answers a stream on its worker: runs the [Handler] on its request, or answers the
+/// error that made the request invalid.
+///
+/// A connection failing to send the response is closed, for every stream.
+///
+fn respond<H: Handler>(
+    handler: &H,
+    output: &Output,
+    shared: &Shared,
+    peer: &Peer,
+    id: u32,
+    incoming: Incoming,
+) {
+    let errors = shared.errors.as_ref();
+    let ended = incoming.ended;
+
+    let (response, head) = match request(incoming, &shared.limits) {
+        Ok(mut req) => {
+            attach(&mut req, peer, shared);
+
+            let head = req.method == HttpMethod::HEAD;
+            let response = dispatch(handler, req).unwrap_or_else(|| error_response(errors, 500));
+
+            (response, head)
+        }
+        Err(e) => {
+            events::request_rejected(&e, peer.remote_addr);
+            (error_response(errors, e.status().as_u16()), false)
+        }
+    };
+
+    let sent = send(output, shared, id, stamp(response, shared), head).and_then(|sent| {
+        // The rest of a body answered early (e.g., too large) is no longer needed
+        if sent && !ended {
+            let mut out = output.lock();
+            out.write_frame(RST_STREAM, 0, id, &NO_ERROR.to_be_bytes())?;
+            out.writer.flush()?;
+        }
+
+        Ok(())
+    });
+
+    if sent.is_err() {
+        output.close();
+    }
+}
+
+///
+/// Sends a [Response] on a stream, its DATA frames interleaved with those of the other
+/// streams as the flow control windows allow.
+///
+/// # Returns
+///
+/// * `io::Result<bool>` -> Whether the response was sent completely, rather than the
+///   stream being reset by the client or failing to produce its body
+///
+fn send(
+    output: &Output,
+    shared: &Shared,
+    id: u32,
+    mut response: Response,
+    head: bool,
+) -> io::Result<bool> {
+    let status = response.status;
+    let timeouts = shared.timeouts;
+
+    let fields: Vec<(String, String)> = response
+        .headers
+        .iter()
+        .map(|(name, value)| (name.to_ascii_lowercase(), value.to_string()))
+        .filter(|(name, _)| !CONNECTION_SPECIFIC.contains(&name.as_str()))
+        .collect();
+
+    let block = hpack::encode(status, fields.iter().map(|(n, v)| (n.as_str(), v.as_str())));
+
+    let body = std::mem::take(&mut response.body);
+    let bodiless = head
+        || status == 204
+        || status == 304
+        || matches!(&body, Body::Full(bytes) if bytes.is_empty());
+
+    {
+        let mut out = output.lock();
+
+        if out.closed {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+
+        // Reset by the client while the handler ran
+        if !out.windows.contains_key(&id) {
+            return Ok(false);
+        }
+
+        out.write_block(id, &block, bodiless)?;
+        out.writer.flush()?;
+
+        if bodiless {
+            out.windows.remove(&id);
+            return Ok(true);
+        }
+    }
+
+    let mut reader = body.into_reader();
+
+    let mut buf = vec![0; FRAME_SIZE];
+
+    loop {
+        // Read without the lock, a slow body holding back no other stream
+        let n = match reader.read(&mut buf) {
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => {
+                let mut out = output.lock();
+
+                if out.windows.remove(&id).is_some() {
+                    out.write_frame(RST_STREAM, 0, id, &INTERNAL_ERROR.to_be_bytes())?;
+                    out.writer.flush()?;
+                }
+
+                return Ok(false);
+            }
+        };
+
+        if n == 0 {
+            let trailers: Vec<(String, String)> = response
+                .trailers
+                .take()
+                .map(|trailers| trailers.fields())
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(name, value)| (name.to_ascii_lowercase(), value))
+                .collect();
+
+            let mut out = output.lock();
+
+            if out.windows.remove(&id).is_none() {
+                return Ok(false);
+            }
+
+            // Trailers end the stream in place of an empty DATA frame
+            match trailers.is_empty() {
+                true => out.write_frame(DATA, END_STREAM, id, &[])?,
+                false => {
+                    let block = hpack::encode_trailers(
+                        trailers.iter().map(|(n, v)| (n.as_str(), v.as_str())),
+                    );
+                    out.write_block(id, &block, true)?;
+                }
+            }
+
+            out.writer.flush()?;
+
+            return Ok(true);
+        }
+
+        let mut sent = 0;
+
+        // One frame per lock, letting the other streams' frames in between
+        while sent < n {
+            let Some((mut out, window)) = output.window(id, timeouts.write.or(timeouts.read))?
+            else {
+                return Ok(false);
+            };
+
+            let len = (n - sent).min(window);
+
+            out.write_frame(DATA, 0, id, &buf[sent..sent + len])?;
+            out.send_window -= len as i64;
+
+            if let Some(window) = out.windows.get_mut(&id) {
+                *window -= len as i64;
+            }
+
+            // Every chunk goes out as soon as it is read, for slow producers
+            out.writer.flush()?;
+
+            sent += len;
+        }
+    }
+}
+
+///
+/// What is left of a timeout started at `since`.
+///
+fn left(timeout: Option<Duration>, since: Instant) -> Option<Duration> {
+    timeout.map(|timeout| timeout.saturating_sub(since.elapsed()))
+}
+
+///
+/// Completes the [Request] of a stream with its body and trailers.
+///
+fn request(incoming: Incoming, limits: &Limits) -> Result<Request, Error> {
+    if incoming.too_large {
+        return Err(Error::BodyTooLarge);
+    }
+
+    if incoming.timed_out {
+        return Err(Error::Timeout);
+    }
+
+    let mut req = incoming.head?;
+
+    if let Some(len) = req.headers.get("content-length") {
+        if len.parse::<usize>().ok() != Some(incoming.body.len()) {
+            return Err(Error::BadHeader);
+        }
+    }
+
+    req.body = incoming.body;
+
+    for (name, value) in incoming.trailers {
+        // Trailers carry no pseudo-header fields
+        if name.starts_with(':') {
+            return Err(Error::BadHeader);
+        }
+
+        check_field(&req.trailers, &name, &value, limits)?;
+        req.trailers.append(name, value.trim());
+    }
+
+    Ok(req)
+}
+
+///
+/// Builds the bodiless [Request] of a stream from the fields of its header block.
+///
+/// Malformed requests (RFC 9113 section 8.1.1) fail with a `400` error rather than
+/// resetting the stream, which lets the client see why.
+///
+fn head(fields: Vec<(String, String)>, limits: &Limits) -> Result<Request, Error> {
+    let (mut method, mut scheme, mut path, mut authority) = (None, None, None, None);
+    let mut headers = HeaderMap::new();
+    let mut cookies = Vec::new();
+
+    for (name, value) in fields {
+        if let Some(pseudo) = name.strip_prefix(':') {
+            let field = match pseudo {
+                "method" => &mut method,
+                "scheme" => &mut scheme,
+                "path" => &mut path,
+                "authority" => &mut authority,
+                _ => return Err(Error::BadHeader),
+            };
+
+            // Pseudo-header fields come first, once each
+            if field.is_some() || !headers.is_empty() || !cookies.is_empty() {
+                return Err(Error::BadHeader);
+            }
+
+            *field = Some(value);
+            continue;
+        }
+
+        check_field(&headers, &name, &value, limits)?;
+
+        match name.as_str() {
+            _ if CONNECTION_SPECIFIC.contains(&name.as_str()) => return Err(Error::BadHeader),
+            "te" if value != "trailers" => return Err(Error::BadHeader),
+            // Split to compress better, joined back as a single field
+            "cookie" => cookies.push(value),
+            _ => headers.append(name, value.trim()),
+        }
+    }
+
+    if !cookies.is_empty() {
+        headers.append("cookie", cookies.join("; "));
+    }
+
+    let method: HttpMethod = method.ok_or(Error::BadRequestLine)?.parse()?;
+
+    // CONNECT only carries the authority it tunnels to
+    let raw_target = match (&method, path, scheme) {
+        (HttpMethod::CONNECT, None, None) => authority.clone(),
+        (HttpMethod::CONNECT, ..) => None,
+        (_, Some(path), Some(_)) if !path.is_empty() => Some(path),
+        _ => None,
+    }
+    .ok_or(Error::BadRequestLine)?;
+
+    // `:authority` stands for `Host`, which HTTP/1 handlers rely on
+    if let Some(authority) = authority {
+        if !headers.contains_key("host") {
+            headers.insert("host", authority);
+        }
+    }
+
+    let target = Request::split_target(&raw_target, limits)?;
+
+    Ok(Request::from_head(
+        method,
+        HttpVersion::Http2,
+        target,
+        headers,
+    ))
+}
+
+///
+/// Largest header list decoded, advertised as `SETTINGS_MAX_HEADER_LIST_SIZE`: as many
+/// fields as the [Limits] allow, each as long as they allow.
+///
+fn max_header_list_size(limits: &Limits) -> usize {
+    limits.headers * (limits.header_line + hpack::ENTRY_OVERHEAD)
+}
+
+///
+/// Checks a regular field against the [Limits] and the HTTP/2 rules for names and values.
+///
+fn check_field(fields: &HeaderMap, name: &str, value: &str, limits: &Limits) -> Result<(), Error> {
+    if fields.len() == limits.headers || name.len() + value.len() > limits.header_line {
+        return Err(Error::HeaderTooLarge);
+    }
+
+    let lowercase = name
+        .bytes()
+        .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b"!#$%&'*+-.^_`|~".contains(&b));
+
+    if name.is_empty() || !lowercase || value.contains(['\r', '\n', '\0']) {
+        return Err(Error::BadHeader);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::router::Router;
+    use crate::server::Server;
+    use rustls::{pki_types::ServerName, ClientConfig, ClientConnection, RootCertStore};
+    use std::{
+        net::{TcpListener, TcpStream},
+        sync::{mpsc, Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    fn frame(kind: u8, flags: u8, stream: u32, payload: &[u8]) -> Vec<u8> {
+        let mut frame = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+        frame.extend_from_slice(&[kind, flags]);
+        frame.extend_from_slice(&stream.to_be_bytes());
+        frame.extend_from_slice(payload);
+
+        frame
+    }
+
+    /// Header block of literals without indexing, with short names and values
+    fn block(fields: &[(&str, &str)]) -> Vec<u8> {
+        let mut block = Vec::new();
+
+        for (name, value) in fields {
+            block.push(0);
+            block.push(name.len() as u8);
+            block.extend_from_slice(name.as_bytes());
+            block.push(value.len() as u8);
+            block.extend_from_slice(value.as_bytes());
+        }
+
+        block
+    }
+
+    /// Header block of a bodiless request
+    fn get(path: &str) -> Vec<u8> {
+        block(&[
+            (":method", "GET"),
+            (":scheme", "https"),
+            (":path", path),
+            (":authority", "localhost"),
+        ])
+    }
+
+    /// Serves HTTP/2 over plain TCP, returning the client end once its preface and
+    /// `SETTINGS` are sent
+    fn connect(server: Server, router: Router, settings: &[(u16, u32)]) -> TcpStream {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+
+        let shared = server.shared();
+        thread::spawn(move || serve(Stream::from(stream), &router, &shared));
+
+        let payload: Vec<u8> = settings
+            .iter()
+            .flat_map(|(id, value)| [&id.to_be_bytes()[..], &value.to_be_bytes()].concat())
+            .collect();
+
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        client.write_all(PREFACE).unwrap();
+        client.write_all(&frame(SETTINGS, 0, 0, &payload)).unwrap();
+
+        client
+    }
+
+    /// Reads the next frame sent by the server
+    fn next(client: &mut TcpStream) -> Frame {
+        let mut head = [0; 9];
+        client.read_exact(&mut head).unwrap();
+
+        let mut payload = vec![0; u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize];
+        client.read_exact(&mut payload).unwrap();
+
+        Frame {
+            kind: head[3],
+            flags: head[4],
+            stream: u32::from_be_bytes([head[5], head[6], head[7], head[8]]),
+            payload,
+        }
+    }
+
+    /// Skips frames until one of `kind`
+    fn next_of(client: &mut TcpStream, kind: u8) -> Frame {
+        loop {
+            let frame = next(client);
+
+            if frame.kind == kind {
+                return frame;
+            }
+        }
+    }
+
+    /// Reads frames until the `GOAWAY`, returning its error code
+    fn go_away_code(client: &mut TcpStream) -> u32 {
+        next_of(client, GOAWAY).payload[4..]
+            .try_into()
+            .map(u32::from_be_bytes)
+            .unwrap()
+    }
+
+    fn hello() -> Router {
+        let mut router = Router::new();
+
+        router
+            .get("/", |_| Response::new(200).text("hello world"))
+            .post("/upload", |_| Response::new(204));
+
+        router
+    }
+
+    #[test]
+    fn test_h2_multiplexed_streams() {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+
+        let dir = std::env::temp_dir().join(format!("http_rs_h2_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("cert.pem"), cert.cert.pem()).unwrap();
+        std::fs::write(dir.join("key.pem"), cert.key_pair.serialize_pem()).unwrap();
+
+        let server = Server::new_tls("127.0.0.1:0", dir.join("cert.pem"), dir.join("key.pem"))
+            .unwrap()
+            .workers(1);
+        let addr = server.local_addr().unwrap();
+
+        let mut router = Router::new();
+
+        router
+            .get("/", |req: Request| {
+                let host = req.headers.get("Host").unwrap_or("").to_string();
+                Response::new(200).text(&format!("{} {}", req.version, host))
+            })
+            .post("/echo", |req: Request| Response::new(201).body(req.body));
+
+        let (release, released) = mpsc::channel::<()>();
+        let released = Arc::new(Mutex::new(released));
+
+        router.get("/slow", move |_| {
+            let released = released.clone();
+
+            Response::new(200).body(Body::from_fn(move |out| {
+                out.write_all(b"first ")?;
+                out.flush()?;
+
+                let _ = released.lock().unwrap().recv();
+
+                out.write_all(b"last")
+            }))
+        });
+
+        thread::spawn(move || server.serve(router));
+
+        let mut roots = RootCertStore::empty();
+        roots.add(cert.cert.der().clone()).unwrap();
+
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let mut config = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        config.alpn_protocols = vec![b"h2".to_vec()];
+
+        let conn =
+            ClientConnection::new(Arc::new(config), ServerName::try_from("localhost").unwrap())
+                .unwrap();
+        let tcp = TcpStream::connect(addr).unwrap();
+        tcp.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut client = rustls::StreamOwned::new(conn, tcp);
+
+        // Stream 3 is opened before stream 1 completes, both answered while stream 5
+        // waits on its body, the connection waiting for the client meanwhile
+        let mut out = PREFACE.to_vec();
+        out.extend(frame(SETTINGS, 0, 0, &[]));
+        out.extend(frame(
+            HEADERS,
+            END_HEADERS,
+            1,
+            &block(&[
+                (":method", "POST"),
+                (":scheme", "https"),
+                (":path", "/echo"),
+                (":authority", "localhost"),
+            ]),
+        ));
+        out.extend(frame(
+            HEADERS,
+            END_HEADERS | END_STREAM,
+            3,
+            &block(&[
+                (":method", "GET"),
+                (":scheme", "https"),
+                (":path", "/"),
+                (":authority", "localhost"),
+            ]),
+        ));
+        out.extend(frame(
+            HEADERS,
+            END_HEADERS | END_STREAM,
+            5,
+            &block(&[
+                (":method", "GET"),
+                (":scheme", "https"),
+                (":path", "/slow"),
+                (":authority", "localhost"),
+            ]),
+        ));
+        out.extend(frame(DATA, 0, 1, b"hello "));
+        out.extend(frame(DATA, END_STREAM, 1, b"world"));
+
+        client.write_all(&out).unwrap();
+        let sent = Instant::now();
+
+        let mut decoder = Decoder::new(usize::MAX);
+        let mut responses: Vec<(u32, String, Vec<u8>)> = Vec::new();
+        let mut ended = Vec::new();
+
+        while ended.len() < 3 {
+            if ended.len() == 2 && !ended.contains(&5) {
+                // Not only once the connection's thread stops waiting for frames
+                assert!(sent.elapsed() < POLL_INTERVAL);
+                release.send(()).unwrap();
+            }
+
+            let mut head = [0; 9];
+            client.read_exact(&mut head).unwrap();
+
+            let len = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+            let stream = u32::from_be_bytes([head[5], head[6], head[7], head[8]]);
+            let mut payload = vec![0; len];
+            client.read_exact(&mut payload).unwrap();
+
+            match head[3] {
+                HEADERS => {
+                    let headers = decoder.decode(&payload).unwrap();
+                    assert_eq!(headers[0].0, ":status");
+                    responses.push((stream, headers[0].1.clone(), Vec::new()));
+                }
+                DATA => {
+                    let response = responses.iter_mut().find(|r| r.0 == stream).unwrap();
+                    response.2.extend_from_slice(&payload);
+                }
+                _ => continue,
+            }
+
+            if head[4] & END_STREAM != 0 {
+                ended.push(stream);
+            }
+        }
+
+        assert_eq!(ended[2], 5);
+
+        responses.sort();
+        assert_eq!(
+            responses,
+            [
+                (1, "201".to_string(), b"hello world".to_vec()),
+                (3, "200".to_string(), b"HTTP/2.0 localhost".to_vec()),
+                (5, "200".to_string(), b"first last".to_vec()),
+            ]
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_h2_request_trailers() {
+        let fields = |fields: &[(&str, &str)]| {
+            fields
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        let incoming = |trailers| Incoming {
+            head: head(
+                fields(&[
+                    (":method", "POST"),
+                    (":scheme", "https"),
+                    (":path", "/upload"),
+                ]),
+                &Limits::default(),
+            ),
+            body: b"data".to_vec(),
+            trailers,
+            ended: true,
+            too_large: false,
+            timed_out: false,
+            max_body: Limits::default().body,
+            read_timeout: None,
+            last_read: Instant::now(),
+        };
+
+        let req = request(
+            incoming(fields(&[("digest", "sha-256=abc")])),
+            &Limits::default(),
+        );
+        assert_eq!(req.unwrap().trailers.get("Digest"), Some("sha-256=abc"));
+
+        let req = request(incoming(fields(&[(":path", "/")])), &Limits::default());
+        assert!(matches!(req, Err(Error::BadHeader)));
+
+        // Encoded trailer blocks carry no `:status`
+        let block = hpack::encode_trailers([("server-timing", "total;dur=1")].into_iter());
+        assert_eq!(
+            Decoder::new(usize::MAX).decode(&block).unwrap(),
+            fields(&[("server-timing", "total;dur=1")])
+        );
+    }
+
+    #[test]
+    fn test_h2_settings_ack() {
+        let server = Server::new("127.0.0.1:0").unwrap().max_header_line(64);
+        let mut client = connect(server, hello(), &[(SETTINGS_ENABLE_PUSH, 0)]);
+
+        let settings = next(&mut client);
+        let values: Vec<(u16, u32)> = settings
+            .payload
+            .chunks(6)
+            .map(|s| {
+                let value = u32::from_be_bytes([s[2], s[3], s[4], s[5]]);
+                (u16::from_be_bytes([s[0], s[1]]), value)
+            })
+            .collect();
+
+        assert_eq!((settings.kind, settings.flags), (SETTINGS, 0));
+        assert_eq!(
+            values,
+            [
+                (SETTINGS_MAX_CONCURRENT_STREAMS, MAX_STREAMS),
+                (SETTINGS_MAX_HEADER_LIST_SIZE, 100 * (64 + 32)),
+            ]
+        );
+
+        // The client's settings are acknowledged, and its acknowledgement taken silently
+        let ack = next(&mut client);
+        assert_eq!((ack.kind, ack.flags, ack.payload.len()), (SETTINGS, ACK, 0));
+
+        client.write_all(&frame(SETTINGS, ACK, 0, &[])).unwrap();
+        client.write_all(&frame(PING, 0, 0, b"12345678")).unwrap();
+
+        let ping = next(&mut client);
+        assert_eq!((ping.kind, ping.flags), (PING, ACK));
+        assert_eq!(ping.payload, b"12345678");
+
+        // An acknowledgement must be empty
+        client.write_all(&frame(SETTINGS, ACK, 0, &[0; 6])).unwrap();
+        assert_eq!(go_away_code(&mut client), FRAME_SIZE_ERROR);
+    }
+
+    #[test]
+    fn test_h2_flow_control() {
+        let server = Server::new("127.0.0.1:0").unwrap();
+        let mut client = connect(server, hello(), &[(SETTINGS_INITIAL_WINDOW_SIZE, 4)]);
+
+        client
+            .write_all(&frame(HEADERS, END_HEADERS | END_STREAM, 1, &get("/")))
+            .unwrap();
+
+        next_of(&mut client, HEADERS);
+
+        // Only the 4 bytes the stream's window allows, until the client grants more
+        let data = next(&mut client);
+        assert_eq!(
+            (data.kind, data.stream, &data.payload[..]),
+            (DATA, 1, &b"hell"[..])
+        );
+
+        client
+            .write_all(&frame(WINDOW_UPDATE, 0, 1, &100u32.to_be_bytes()))
+            .unwrap();
+
+        let mut body = data.payload;
+
+        loop {
+            let data = next_of(&mut client, DATA);
+            body.extend(data.payload);
+
+            if data.flags & END_STREAM != 0 {
+                break;
+            }
+        }
+
+        assert_eq!(body, b"hello world");
+
+        // Received data is given back to the connection and to the open stream
+        let upload = block(&[
+            (":method", "POST"),
+            (":scheme", "https"),
+            (":path", "/upload"),
+        ]);
+        client
+            .write_all(&frame(HEADERS, END_HEADERS, 3, &upload))
+            .unwrap();
+        client.write_all(&frame(DATA, 0, 3, b"abc")).unwrap();
+
+        for stream in [0, 3] {
+            let update = next(&mut client);
+
+            assert_eq!((update.kind, update.stream), (WINDOW_UPDATE, stream));
+            assert_eq!(update.payload, 3u32.to_be_bytes());
+        }
+
+        client
+            .write_all(&frame(DATA, END_STREAM, 3, b"de"))
+            .unwrap();
+        assert_eq!(next_of(&mut client, HEADERS).stream, 3);
+
+        // The connection window can't exceed 2^31 - 1
+        client
+            .write_all(&frame(WINDOW_UPDATE, 0, 0, &0x7fff_ffffu32.to_be_bytes()))
+            .unwrap();
+        assert_eq!(go_away_code(&mut client), FLOW_CONTROL_ERROR);
+    }
+
+    #[test]
+    fn test_h2_continuation_ordering() {
+        let server = Server::new("127.0.0.1:0").unwrap();
+        let mut client = connect(server, hello(), &[]);
+
+        // A header block split over HEADERS and CONTINUATION
+        let request = get("/");
+        let (first, rest) = request.split_at(10);
+
+        client
+            .write_all(&frame(HEADERS, END_STREAM, 1, first))
+            .unwrap();
+        client
+            .write_all(&frame(CONTINUATION, END_HEADERS, 1, rest))
+            .unwrap();
+
+        let headers = next_of(&mut client, HEADERS);
+        assert_eq!(headers.stream, 1);
+        assert_eq!(
+            Decoder::new(usize::MAX).decode(&headers.payload).unwrap()[0],
+            (":status".to_string(), "200".to_string())
+        );
+
+        // Nothing may come between a HEADERS frame and its CONTINUATION frames
+        client
+            .write_all(&frame(HEADERS, END_STREAM, 3, first))
+            .unwrap();
+        client.write_all(&frame(PING, 0, 0, b"12345678")).unwrap();
+
+        // Stream 3, whose header block never completed, wasn't opened
+        let go_away = next_of(&mut client, GOAWAY);
+        assert_eq!(go_away.payload[..4], 1u32.to_be_bytes());
+        assert_eq!(go_away.payload[4..], PROTOCOL_ERROR.to_be_bytes());
+    }
+
+    #[test]
+    fn test_h2_rst_stream() {
+        let server = Server::new("127.0.0.1:0").unwrap();
+        let mut client = connect(server, hello(), &[]);
+
+        // A stream reset while sending its request is never answered
+        let upload = block(&[
+            (":method", "POST"),
+            (":scheme", "https"),
+            (":path", "/upload"),
+        ]);
+        client
+            .write_all(&frame(HEADERS, END_HEADERS, 1, &upload))
+            .unwrap();
+        client
+            .write_all(&frame(RST_STREAM, 0, 1, &0x8u32.to_be_bytes()))
+            .unwrap();
+        client
+            .write_all(&frame(DATA, END_STREAM, 1, b"late"))
+            .unwrap();
+        client
+            .write_all(&frame(HEADERS, END_HEADERS | END_STREAM, 3, &get("/")))
+            .unwrap();
+
+        assert_eq!(next_of(&mut client, HEADERS).stream, 3);
+
+        // Resetting a stream the client never opened is a connection error
+        client
+            .write_all(&frame(RST_STREAM, 0, 7, &0x8u32.to_be_bytes()))
+            .unwrap();
+
+        loop {
+            let frame = next(&mut client);
+            assert_ne!((frame.kind, frame.stream), (HEADERS, 1));
+
+            if frame.kind == GOAWAY {
+                assert_eq!(frame.payload[4..], PROTOCOL_ERROR.to_be_bytes());
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn test_h2_header_list_limit() {
+        let server = || Server::new("127.0.0.1:0").unwrap().max_header_line(64);
+
+        // 150 one byte references to an 85 byte entry, over the 9600 bytes allowed
+        let mut block = vec![0x40, 3];
+        block.extend_from_slice(b"x-a");
+        block.push(50);
+        block.extend_from_slice(&[b'v'; 50]);
+        block.extend([0xbe; 150]);
+
+        let mut client = connect(server(), hello(), &[]);
+        client
+            .write_all(&frame(HEADERS, END_HEADERS | END_STREAM, 1, &block))
+            .unwrap();
+
+        assert_eq!(go_away_code(&mut client), ENHANCE_YOUR_CALM);
+
+        // Huffman coded value padded with zeros
+        let mut client = connect(server(), hello(), &[]);
+        client
+            .write_all(&frame(
+                HEADERS,
+                END_HEADERS | END_STREAM,
+                1,
+                &[0x00, 0x01, b'a', 0x81, 0x18],
+            ))
+            .unwrap();
+
+        assert_eq!(go_away_code(&mut client), COMPRESSION_ERROR);
+    }
+
+    #[test]
+    fn test_h2_concurrent_streams() {
+        let (release, released) = mpsc::channel::<()>();
+        let released = Arc::new(Mutex::new(released));
+
+        let mut router = hello();
+        router.get("/events", move |_| {
+            let released = released.clone();
+
+            Response::new(200).body(Body::from_fn(move |out| {
+                out.write_all(b"first")?;
+                out.flush()?;
+
+                let _ = released.lock().unwrap().recv();
+
+                out.write_all(b"last")
+            }))
+        });
+
+        let server = Server::new("127.0.0.1:0").unwrap();
+        let mut client = connect(server, router, &[]);
+
+        client
+            .write_all(&frame(
+                HEADERS,
+                END_HEADERS | END_STREAM,
+                1,
+                &get("/events"),
+            ))
+            .unwrap();
+
+        assert_eq!(next_of(&mut client, HEADERS).stream, 1);
+        let data = next_of(&mut client, DATA);
+        assert_eq!((data.stream, &data.payload[..]), (1, &b"first"[..]));
+
+        // While stream 1 waits on its body, pings and other streams are answered
+        client.write_all(&frame(PING, 0, 0, b"12345678")).unwrap();
+        assert_eq!(next_of(&mut client, PING).flags, ACK);
+
+        client
+            .write_all(&frame(HEADERS, END_HEADERS | END_STREAM, 3, &get("/")))
+            .unwrap();
+
+        assert_eq!(next_of(&mut client, HEADERS).stream, 3);
+        let data = next_of(&mut client, DATA);
+        assert_eq!((data.stream, &data.payload[..]), (3, &b"hello world"[..]));
+
+        release.send(()).unwrap();
+
+        let mut body = Vec::new();
+
+        loop {
+            // Skipping the empty frame ending stream 3
+            let data = next_of(&mut client, DATA);
+
+            if data.stream != 1 {
+                continue;
+            }
+
+            body.extend(data.payload);
+
+            if data.flags & END_STREAM != 0 {
+                break;
+            }
+        }
+
+        assert_eq!(body, b"last");
+    }
+
+    #[test]
+    fn test_h2_interleaved_data() {
+        let mut router = Router::new();
+        router.get("/big", |_| {
+            Response::new(200).body(vec![b'x'; 3 * FRAME_SIZE])
+        });
+
+        // Streams may send 100 bytes at first, each waiting for the client
+        let server = Server::new("127.0.0.1:0").unwrap();
+        let mut client = connect(server, router, &[(SETTINGS_INITIAL_WINDOW_SIZE, 100)]);
+
+        for id in [1, 3] {
+            client
+                .write_all(&frame(HEADERS, END_HEADERS | END_STREAM, id, &get("/big")))
+                .unwrap();
+        }
+
+        let mut received = HashMap::new();
+
+        while received.len() < 2 || received.values().any(|len| *len < 100) {
+            let data = next_of(&mut client, DATA);
+            *received.entry(data.stream).or_insert(0) += data.payload.len();
+        }
+
+        // Granting one stream more lets it finish while the other still waits
+        client
+            .write_all(&frame(WINDOW_UPDATE, 0, 3, &(1u32 << 20).to_be_bytes()))
+            .unwrap();
+        client
+            .write_all(&frame(WINDOW_UPDATE, 0, 0, &(1u32 << 20).to_be_bytes()))
+            .unwrap();
+
+        loop {
+            let data = next_of(&mut client, DATA);
+            assert_eq!(data.stream, 3);
+            *received.get_mut(&3).unwrap() += data.payload.len();
+
+            if data.flags & END_STREAM != 0 {
+                break;
+            }
+        }
+
+        assert_eq!(received[&1], 100);
+        assert_eq!(received[&3], 3 * FRAME_SIZE);
+    }
+
+    #[test]
+    fn test_h2_route_limits() {
+        let mut router = hello();
+        router
+            .limits(
+                "/upload",
+                RouteLimits::new()
+                    .max_body_size(16)
+                    .read_timeout(Duration::from_millis(200)),
+            )
+            .post("/small", |req: Request| {
+                Response::new(200).text(&req.body.len().to_string())
+            });
+
+        let server = Server::new("127.0.0.1:0").unwrap().max_body_size(4);
+        let mut client = connect(server, router, &[]);
+
+        let post =
+            |path: &str| block(&[(":method", "POST"), (":scheme", "https"), (":path", path)]);
+
+        let status = |client: &mut TcpStream, id: u32| {
+            let headers = next_of(client, HEADERS);
+            assert_eq!(headers.stream, id);
+
+            Decoder::new(usize::MAX).decode(&headers.payload).unwrap()[0]
+                .1
+                .clone()
+        };
+
+        // The route raises the server's limit, other routes keep it
+        for (id, path, body, expected) in [
+            (1, "/upload", &b"12345678"[..], "204"),
+            (3, "/upload", &[b'x'; 20][..], "413"),
+            (5, "/small", &b"12345678"[..], "413"),
+        ] {
+            client
+                .write_all(&frame(HEADERS, END_HEADERS, id, &post(path)))
+                .unwrap();
+            client
+                .write_all(&frame(DATA, END_STREAM, id, body))
+                .unwrap();
+
+            assert_eq!(status(&mut client, id), expected);
+        }
+
+        // A body paused longer than the route allows is answered, the connection kept
+        client
+            .write_all(&frame(HEADERS, END_HEADERS, 7, &post("/upload")))
+            .unwrap();
+        client.write_all(&frame(DATA, 0, 7, b"abc")).unwrap();
+
+        assert_eq!(status(&mut client, 7), "408");
+
+        client
+            .write_all(&frame(HEADERS, END_HEADERS | END_STREAM, 9, &get("/")))
+            .unwrap();
+        assert_eq!(status(&mut client, 9), "200");
+    }
+}
@@ -0,0 +1,272 @@
+//!
+//! Case-insensitive, multi-value header storage.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::header::HeaderMap;
+//!
+//! let mut headers = HeaderMap::new();
+//!
+//! headers.append("Accept", "text/html");
+//! headers.append("accept", "application/json");
+//! headers.insert("Content-Length", "0");
+//!
+//! assert_eq!(headers.get("ACCEPT"), Some("text/html"));
+//! assert_eq!(headers.get_all("Accept").count(), 2);
+//! ```
+//!
+
+use crate::server::is_token;
+use std::fmt;
+
+///
+/// Header fields as name/value pairs, kept in the order they were added.
+///
+/// Lookups ignore ASCII case, and a name may carry several values (e.g., `Set-Cookie`),
+/// while names are stored with the case they were given in.
+///
+/// As fields are written out verbatim, a name which isn't a token is rejected and CR,
+/// LF and NUL are stripped from values, so neither can split a message.
+///
+#[derive(Clone, Default, PartialEq)]
+pub struct HeaderMap {
+    entries: Vec<(String, String)>,
+}
+
+impl HeaderMap {
+    ///
+    /// Creates an empty [HeaderMap].
+    ///
+    pub fn new() -> HeaderMap {
+        HeaderMap::default()
+    }
+
+    ///
+    /// Returns the first value of a header.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&str>` -> The value, or None if the header is missing
+    ///
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    ///
+    /// Returns every value of a header, in the order they were added.
+    ///
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.entries
+            .iter()
+            .filter(move |(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    ///
+    /// Whether the header is present.
+    ///
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    ///
+    /// Sets a header, replacing its first value in place so the original position is
+    /// kept and dropping any further values of that name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` -> Header name, matched case-insensitively, ignored if not a token
+    /// * `value` -> Header value
+    ///
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let Some((name, value)) = field(name.into(), value.into()) else {
+            return;
+        };
+        let mut value = Some(value);
+
+        self.entries.retain_mut(|(k, v)| {
+            if !k.eq_ignore_ascii_case(&name) {
+                return true;
+            }
+
+            match value.take() {
+                Some(value) => {
+                    *v = value;
+
+                    true
+                }
+                None => false,
+            }
+        });
+
+        if let Some(value) = value {
+            self.entries.push((name, value));
+        }
+    }
+
+    ///
+    /// Adds a header value, keeping any existing values of that name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` -> Header name, ignored if not a token
+    /// * `value` -> Header value
+    ///
+    pub fn append(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        if let Some(field) = field(name.into(), value.into()) {
+            self.entries.push(field);
+        }
+    }
+
+    ///
+    /// Removes every value of a header.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<String>` -> The first removed value, or None if the header was missing
+    ///
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let mut removed = None;
+
+        self.entries.retain_mut(|(k, v)| {
+            if !k.eq_ignore_ascii_case(name) {
+                return true;
+            }
+
+            if removed.is_none() {
+                removed = Some(std::mem::take(v));
+            }
+
+            false
+        });
+
+        removed
+    }
+
+    ///
+    /// Returns an iterator over every name/value pair, in insertion order.
+    ///
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.into_iter()
+    }
+
+    ///
+    /// Returns the number of name/value pairs, counting every value of a name.
+    ///
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    ///
+    /// Whether there are no headers.
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+///
+/// Checks a field before it is stored: None if the name isn't a token, otherwise the
+/// field with CR, LF and NUL stripped from its value.
+///
+fn field(name: String, mut value: String) -> Option<(String, String)> {
+    if !is_token(&name) {
+        return None;
+    }
+
+    if value.contains(['\r', '\n', '\0']) {
+        value.retain(|c| !matches!(c, '\r' | '\n' | '\0'));
+    }
+
+    Some((name, value))
+}
+
+impl fmt::Debug for HeaderMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'a> IntoIterator for &'a HeaderMap {
+    type Item = (&'a str, &'a str);
+    type IntoIter = std::iter::Map<
+        std::slice::Iter<'a, (String, String)>,
+        fn(&'a (String, String)) -> (&'a str, &'a str),
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for HeaderMap {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.extend(iter);
+
+        headers
+    }
+}
+
+///
+/// Appends every pair, keeping existing values.
+///
+impl<K: Into<String>, V: Into<String>> Extend<(K, V)> for HeaderMap {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (name, value) in iter {
+            self.append(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_map_case_insensitive_multi_value() {
+        let mut headers: HeaderMap = [("Set-Cookie", "a=1"), ("Host", "example.com")]
+            .into_iter()
+            .collect();
+
+        headers.append("set-cookie", "b=2");
+
+        assert_eq!(headers.get("HOST"), Some("example.com"));
+        assert_eq!(
+            headers.get_all("Set-Cookie").collect::<Vec<_>>(),
+            ["a=1", "b=2"]
+        );
+
+        // Insert replaces in place, keeping the first position
+        headers.insert("SET-COOKIE", "c=3");
+
+        assert_eq!(
+            headers.iter().collect::<Vec<_>>(),
+            [("Set-Cookie", "c=3"), ("Host", "example.com")]
+        );
+
+        assert_eq!(headers.remove("host"), Some("example.com".to_string()));
+        assert!(!headers.contains_key("Host"));
+        assert_eq!(headers.len(), 1);
+    }
+
+    #[test]
+    fn test_header_map_rejects_splitting() {
+        let mut headers = HeaderMap::new();
+
+        headers.insert("Location", "/a\r\nSet-Cookie: admin=1");
+        headers.append("X-Null", "a\0b");
+        headers.append("Bad\r\nName", "x");
+        headers.insert("Bad Name", "x");
+        headers.append("", "x");
+
+        assert_eq!(
+            headers.iter().collect::<Vec<_>>(),
+            [("Location", "/aSet-Cookie: admin=1"), ("X-Null", "ab")]
+        );
+    }
+}
@@ -0,0 +1,252 @@
+//!
+//! Liveness and readiness endpoints for orchestrators such as Kubernetes.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::health::Health;
+//! use http_rs::router::Router;
+//!
+//! let mut router = Router::new();
+//!
+//! // GET /healthz answers 200 while the server is up, GET /readyz runs the probes
+//! router.layer(
+//!     Health::new()
+//!         .readiness("database", || Ok(()))
+//!         .readiness("cache", || Err("connection refused".to_string())),
+//! );
+//! ```
+//!
+
+use crate::middleware::{Middleware, Next};
+use crate::server::{HttpMethod, Request, Response};
+use crate::status::StatusCode;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+///
+/// Callback reporting whether a dependency is ready, with the reason if it isn't.
+///
+type Probe = Box<dyn Fn() -> Result<(), String> + Send + Sync>;
+
+///
+/// Outcome of a health endpoint or of one of its checks.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Ok,
+    Fail,
+}
+
+///
+/// Result of a readiness probe, as reported in the `/readyz` body.
+///
+#[derive(Debug, Serialize)]
+struct Check {
+    status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+///
+/// Body of the health endpoints.
+///
+#[derive(Debug, Serialize)]
+struct Report {
+    status: Status,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    checks: BTreeMap<String, Check>,
+}
+
+///
+/// [Middleware] answering the liveness and readiness endpoints, passing every other
+/// request through.
+///
+/// The liveness endpoint (`/healthz`) answers `200` as long as the server handles
+/// requests. The readiness endpoint (`/readyz`) runs every registered probe and answers
+/// `200` if all pass or `503` otherwise, with each probe's result in the `JSON` body:
+///
+/// ```json
+/// {"status":"fail","checks":{"cache":{"status":"fail","error":"connection refused"},"database":{"status":"ok"}}}
+/// ```
+///
+/// Install it before authentication or rate limiting layers so probes aren't refused.
+///
+pub struct Health {
+    liveness_path: String,
+    readiness_path: String,
+    probes: Vec<(String, Probe)>,
+}
+
+impl Health {
+    ///
+    /// Creates the health [Middleware] on `/healthz` and `/readyz`, without probes.
+    ///
+    pub fn new() -> Health {
+        Health {
+            liveness_path: "/healthz".to_string(),
+            readiness_path: "/readyz".to_string(),
+            probes: Vec::new(),
+        }
+    }
+
+    ///
+    /// Sets the paths of the liveness and readiness endpoints.
+    ///
+    /// # Arguments
+    ///
+    /// * `liveness` -> Path of the liveness endpoint (e.g., "/livez")
+    /// * `readiness` -> Path of the readiness endpoint (e.g., "/ready")
+    ///
+    pub fn paths(mut self, liveness: &str, readiness: &str) -> Health {
+        self.liveness_path = liveness.to_string();
+        self.readiness_path = readiness.to_string();
+
+        self
+    }
+
+    ///
+    /// Registers a readiness probe, run on every request to the readiness endpoint.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` -> Name of the check in the report (e.g., "database")
+    /// * `probe` -> Callback returning Ok if the dependency is ready, or the reason it
+    ///   isn't
+    ///
+    pub fn readiness<F>(mut self, name: &str, probe: F) -> Health
+    where
+        F: Fn() -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.probes.push((name.to_string(), Box::new(probe)));
+
+        self
+    }
+
+    ///
+    /// Runs every probe and builds the readiness [Report].
+    ///
+    fn readiness_report(&self) -> Report {
+        let checks: BTreeMap<String, Check> = self
+            .probes
+            .iter()
+            .map(|(name, probe)| {
+                let check = match probe() {
+                    Ok(()) => Check {
+                        status: Status::Ok,
+                        error: None,
+                    },
+                    Err(error) => Check {
+                        status: Status::Fail,
+                        error: Some(error),
+                    },
+                };
+
+                (name.clone(), check)
+            })
+            .collect();
+
+        let status = if checks.values().all(|check| check.status == Status::Ok) {
+            Status::Ok
+        } else {
+            Status::Fail
+        };
+
+        Report { status, checks }
+    }
+}
+
+impl Default for Health {
+    fn default() -> Health {
+        Health::new()
+    }
+}
+
+impl Middleware for Health {
+    fn handle(&self, req: Request, next: Next<'_>) -> Response {
+        if !matches!(req.method, HttpMethod::GET | HttpMethod::HEAD) {
+            return next.run(req);
+        }
+
+        let report = if req.route == self.liveness_path {
+            Report {
+                status: Status::Ok,
+                checks: BTreeMap::new(),
+            }
+        } else if req.route == self.readiness_path {
+            self.readiness_report()
+        } else {
+            return next.run(req);
+        };
+
+        let status = match report.status {
+            Status::Ok => StatusCode::OK,
+            Status::Fail => StatusCode::SERVICE_UNAVAILABLE,
+        };
+
+        // Probes must see the current state, not a cached one
+        Response::new(status).no_store().json(&report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::router::Router;
+    use crate::server::test_request;
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    #[test]
+    fn test_health_endpoints() {
+        let cache_up = Arc::new(AtomicBool::new(false));
+        let probe = Arc::clone(&cache_up);
+
+        let mut router = Router::new();
+
+        router
+            .layer(
+                Health::new()
+                    .readiness("database", || Ok(()))
+                    .readiness("cache", move || {
+                        if probe.load(Ordering::SeqCst) {
+                            Ok(())
+                        } else {
+                            Err("connection refused".to_string())
+                        }
+                    }),
+            )
+            .get("/", |_| Response::new(200));
+
+        let res = router.handle(test_request(HttpMethod::GET, "/healthz"));
+
+        assert_eq!(res.status, 200);
+        assert_eq!(res.body.as_bytes(), Some(&br#"{"status":"ok"}"#[..]));
+
+        let res = router.handle(test_request(HttpMethod::GET, "/readyz"));
+
+        assert_eq!(res.status, 503);
+        assert_eq!(
+            res.body.as_bytes(),
+            Some(
+                &br#"{"status":"fail","checks":{"cache":{"status":"fail","error":"connection refused"},"database":{"status":"ok"}}}"#[..]
+            )
+        );
+
+        cache_up.store(true, Ordering::SeqCst);
+
+        assert_eq!(
+            router
+                .handle(test_request(HttpMethod::GET, "/readyz"))
+                .status,
+            200
+        );
+        assert_eq!(
+            router.handle(test_request(HttpMethod::GET, "/")).status,
+            200
+        );
+    }
+}
@@ -0,0 +1,659 @@
+//!
+//! HPACK header compression (RFC 7541) for HTTP/2 connections.
+//!
+//! Requests are decoded with the full static and dynamic tables and Huffman coding.
+//! Responses are encoded as literals without indexing, which keeps the encoder stateless
+//! at the cost of a few bytes per header.
+//!
+
+use std::{collections::HashMap, collections::VecDeque, sync::OnceLock};
+
+///
+/// The HPACK static table, index 1 being the first entry.
+///
+const STATIC_TABLE: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+///
+/// Huffman code of every byte (RFC 7541 Appendix B), aligned to the least significant
+/// bit, with its length in [HUFFMAN_LENGTHS].
+///
+const HUFFMAN_CODES: [u32; 256] = [
+    0x1ff8, 0x7fffd8, 0xfffffe2, 0xfffffe3, 0xfffffe4, 0xfffffe5, 0xfffffe6, 0xfffffe7, 0xfffffe8,
+    0xffffea, 0x3ffffffc, 0xfffffe9, 0xfffffea, 0x3ffffffd, 0xfffffeb, 0xfffffec, 0xfffffed,
+    0xfffffee, 0xfffffef, 0xffffff0, 0xffffff1, 0xffffff2, 0x3ffffffe, 0xffffff3, 0xffffff4,
+    0xffffff5, 0xffffff6, 0xffffff7, 0xffffff8, 0xffffff9, 0xffffffa, 0xffffffb, 0x14, 0x3f8,
+    0x3f9, 0xffa, 0x1ff9, 0x15, 0xf8, 0x7fa, 0x3fa, 0x3fb, 0xf9, 0x7fb, 0xfa, 0x16, 0x17, 0x18,
+    0x0, 0x1, 0x2, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x1f, 0x5c, 0xfb, 0x7ffc, 0x20, 0xffb,
+    0x3fc, 0x1ffa, 0x21, 0x5d, 0x5e, 0x5f, 0x60, 0x61, 0x62, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68,
+    0x69, 0x6a, 0x6b, 0x6c, 0x6d, 0x6e, 0x6f, 0x70, 0x71, 0x72, 0xfc, 0x73, 0xfd, 0x1ffb, 0x7fff0,
+    0x1ffc, 0x3ffc, 0x22, 0x7ffd, 0x3, 0x23, 0x4, 0x24, 0x5, 0x25, 0x26, 0x27, 0x6, 0x74, 0x75,
+    0x28, 0x29, 0x2a, 0x7, 0x2b, 0x76, 0x2c, 0x8, 0x9, 0x2d, 0x77, 0x78, 0x79, 0x7a, 0x7b, 0x7ffe,
+    0x7fc, 0x3ffd, 0x1ffd, 0xffffffc, 0xfffe6, 0x3fffd2, 0xfffe7, 0xfffe8, 0x3fffd3, 0x3fffd4,
+    0x3fffd5, 0x7fffd9, 0x3fffd6, 0x7fffda, 0x7fffdb, 0x7fffdc, 0x7fffdd, 0x7fffde, 0xffffeb,
+    0x7fffdf, 0xffffec, 0xffffed, 0x3fffd7, 0x7fffe0, 0xffffee, 0x7fffe1, 0x7fffe2, 0x7fffe3,
+    0x7fffe4, 0x1fffdc, 0x3fffd8, 0x7fffe5, 0x3fffd9, 0x7fffe6, 0x7fffe7, 0xffffef, 0x3fffda,
+    0x1fffdd, 0xfffe9, 0x3fffdb, 0x3fffdc, 0x7fffe8, 0x7fffe9, 0x1fffde, 0x7fffea, 0x3fffdd,
+    0x3fffde, 0xfffff0, 0x1fffdf, 0x3fffdf, 0x7fffeb, 0x7fffec, 0x1fffe0, 0x1fffe1, 0x3fffe0,
+    0x1fffe2, 0x7fffed, 0x3fffe1, 0x7fffee, 0x7fffef, 0xfffea, 0x3fffe2, 0x3fffe3, 0x3fffe4,
+    0x7ffff0, 0x3fffe5, 0x3fffe6, 0x7ffff1, 0x3ffffe0, 0x3ffffe1, 0xfffeb, 0x7fff1, 0x3fffe7,
+    0x7ffff2, 0x3fffe8, 0x1ffffec, 0x3ffffe2, 0x3ffffe3, 0x3ffffe4, 0x7ffffde, 0x7ffffdf,
+    0x3ffffe5, 0xfffff1, 0x1ffffed, 0x7fff2, 0x1fffe3, 0x3ffffe6, 0x7ffffe0, 0x7ffffe1, 0x3ffffe7,
+    0x7ffffe2, 0xfffff2, 0x1fffe4, 0x1fffe5, 0x3ffffe8, 0x3ffffe9, 0xffffffd, 0x7ffffe3, 0x7ffffe4,
+    0x7ffffe5, 0xfffec, 0xfffff3, 0xfffed, 0x1fffe6, 0x3fffe9, 0x1fffe7, 0x1fffe8, 0x7ffff3,
+    0x3fffea, 0x3fffeb, 0x1ffffee, 0x1ffffef, 0xfffff4, 0xfffff5, 0x3ffffea, 0x7ffff4, 0x3ffffeb,
+    0x7ffffe6, 0x3ffffec, 0x3ffffed, 0x7ffffe7, 0x7ffffe8, 0x7ffffe9, 0x7ffffea, 0x7ffffeb,
+    0xffffffe, 0x7ffffec, 0x7ffffed, 0x7ffffee, 0x7ffffef, 0x7fffff0, 0x3ffffee,
+];
+
+///
+/// Length in bits of each code of [HUFFMAN_CODES].
+///
+const HUFFMAN_LENGTHS: [u8; 256] = [
+    13, 23, 28, 28, 28, 28, 28, 28, 28, 24, 30, 28, 28, 30, 28, 28, 28, 28, 28, 28, 28, 28, 30, 28,
+    28, 28, 28, 28, 28, 28, 28, 28, 6, 10, 10, 12, 13, 6, 8, 11, 10, 10, 8, 11, 8, 6, 6, 6, 5, 5,
+    5, 6, 6, 6, 6, 6, 6, 6, 7, 8, 15, 6, 12, 10, 13, 6, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+    7, 7, 7, 7, 7, 7, 7, 7, 8, 7, 8, 13, 19, 13, 14, 6, 15, 5, 6, 5, 6, 5, 6, 6, 6, 5, 7, 7, 6, 6,
+    6, 5, 6, 7, 6, 5, 5, 6, 7, 7, 7, 7, 7, 15, 11, 14, 13, 28, 20, 22, 20, 20, 22, 22, 22, 23, 22,
+    23, 23, 23, 23, 23, 24, 23, 24, 24, 22, 23, 24, 23, 23, 23, 23, 21, 22, 23, 22, 23, 23, 24, 22,
+    21, 20, 22, 22, 23, 23, 21, 23, 22, 22, 24, 21, 22, 23, 23, 21, 21, 22, 21, 23, 22, 23, 23, 20,
+    22, 22, 22, 23, 22, 22, 23, 26, 26, 20, 19, 22, 23, 22, 25, 26, 26, 26, 27, 27, 26, 24, 25, 19,
+    21, 26, 27, 27, 26, 27, 24, 21, 21, 26, 26, 28, 27, 27, 27, 20, 24, 20, 21, 22, 21, 21, 23, 22,
+    22, 25, 25, 24, 24, 26, 23, 26, 27, 26, 26, 27, 27, 27, 27, 27, 28, 27, 27, 27, 27, 27, 26,
+];
+
+///
+/// Overhead added to the length of name and value when sizing a dynamic table entry or
+/// a header list.
+///
+pub(crate) const ENTRY_OVERHEAD: usize = 32;
+
+///
+/// Dynamic table size the decoder starts with and allows, the HTTP/2 default of
+/// `SETTINGS_HEADER_TABLE_SIZE`.
+///
+pub(crate) const TABLE_SIZE: usize = 4096;
+
+///
+/// A header block couldn't be decoded, which is fatal to the connection as the dynamic
+/// table is out of sync with the client's.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum DecodeError {
+    ///
+    /// The block isn't valid HPACK
+    ///
+    Malformed,
+
+    ///
+    /// The decoded header list outgrew the limit, see [Decoder::new]
+    ///
+    TooLarge,
+}
+
+///
+/// Decoder of the header blocks sent by one client, holding its dynamic table.
+///
+#[derive(Debug)]
+pub(crate) struct Decoder {
+    table: VecDeque<(String, String)>,
+    size: usize,
+    max_size: usize,
+
+    ///
+    /// Largest decoded header list, sized as the dynamic table sizes its entries
+    ///
+    max_list_size: usize,
+}
+
+impl Decoder {
+    ///
+    /// Creates a decoder with an empty dynamic table of [TABLE_SIZE] bytes, decoding
+    /// header lists of up to `max_list_size` bytes.
+    ///
+    /// The limit bounds what a small block can expand to, as each one byte reference
+    /// to a table entry repeats the whole entry.
+    ///
+    pub(crate) fn new(max_list_size: usize) -> Decoder {
+        Decoder {
+            table: VecDeque::new(),
+            size: 0,
+            max_size: TABLE_SIZE,
+            max_list_size,
+        }
+    }
+
+    ///
+    /// Decodes a complete header block (the fragments of a `HEADERS` frame and its
+    /// `CONTINUATION` frames) into (name, value) pairs, in order.
+    ///
+    pub(crate) fn decode(
+        &mut self,
+        mut block: &[u8],
+    ) -> Result<Vec<(String, String)>, DecodeError> {
+        let mut headers = Vec::new();
+        let mut leading = true;
+        let mut list_size = 0;
+
+        while let Some(&first) = block.first() {
+            let header = if first & 0x80 != 0 {
+                // Indexed header field
+                let index = integer(&mut block, 7)?;
+                self.entry(index)?
+            } else if first & 0x40 != 0 {
+                // Literal header field with incremental indexing
+                let header = self.literal(&mut block, 6)?;
+                self.insert(header.clone());
+                header
+            } else if first & 0x20 != 0 {
+                // Dynamic table size update, only allowed before the first field
+                let size = integer(&mut block, 5)?;
+
+                if !leading || size > TABLE_SIZE {
+                    return Err(DecodeError::Malformed);
+                }
+
+                self.max_size = size;
+                self.evict(0);
+                continue;
+            } else {
+                // Literal header field without indexing or never indexed
+                self.literal(&mut block, 4)?
+            };
+
+            list_size += header.0.len() + header.1.len() + ENTRY_OVERHEAD;
+
+            if list_size > self.max_list_size {
+                return Err(DecodeError::TooLarge);
+            }
+
+            headers.push(header);
+            leading = false;
+        }
+
+        Ok(headers)
+    }
+
+    ///
+    /// Looks up an entry of the static table, or of the dynamic table past it.
+    ///
+    fn entry(&self, index: usize) -> Result<(String, String), DecodeError> {
+        match index {
+            0 => Err(DecodeError::Malformed),
+            1..=61 => {
+                let (name, value) = STATIC_TABLE[index - 1];
+                Ok((name.to_string(), value.to_string()))
+            }
+            _ => self
+                .table
+                .get(index - 62)
+                .cloned()
+                .ok_or(DecodeError::Malformed),
+        }
+    }
+
+    ///
+    /// Reads a literal field whose name is indexed, or sent as a string when the index
+    /// (with a `prefix` bits prefix) is 0.
+    ///
+    fn literal(&self, block: &mut &[u8], prefix: u8) -> Result<(String, String), DecodeError> {
+        let name = match integer(block, prefix)? {
+            0 => string(block)?,
+            index => self.entry(index)?.0,
+        };
+
+        Ok((name, string(block)?))
+    }
+
+    ///
+    /// Adds an entry to the dynamic table, evicting the oldest ones to make room. An entry
+    /// larger than the whole table empties it and isn't added.
+    ///
+    fn insert(&mut self, header: (String, String)) {
+        let size = header.0.len() + header.1.len() + ENTRY_OVERHEAD;
+
+        self.evict(size);
+
+        if size <= self.max_size {
+            self.size += size;
+            self.table.push_front(header);
+        }
+    }
+
+    ///
+    /// Evicts entries until `room` more bytes fit in the dynamic table.
+    ///
+    fn evict(&mut self, room: usize) {
+        while self.size + room > self.max_size {
+            match self.table.pop_back() {
+                Some((name, value)) => self.size -= name.len() + value.len() + ENTRY_OVERHEAD,
+                None => break,
+            }
+        }
+    }
+}
+
+///
+/// Reads an integer with an N-bit prefix (RFC 7541 section 5.1).
+///
+fn integer(block: &mut &[u8], prefix: u8) -> Result<usize, DecodeError> {
+    let (&first, rest) = block.split_first().ok_or(DecodeError::Malformed)?;
+    let max = (1 << prefix) - 1;
+    let mut value = (first & max) as usize;
+
+    *block = rest;
+
+    if value < max as usize {
+        return Ok(value);
+    }
+
+    for shift in (0..28).step_by(7) {
+        let (&byte, rest) = block.split_first().ok_or(DecodeError::Malformed)?;
+        *block = rest;
+        value += ((byte & 0x7f) as usize) << shift;
+
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    // Longer integers are no legitimate length or index
+    Err(DecodeError::Malformed)
+}
+
+///
+/// Reads a string literal, Huffman coded or not (RFC 7541 section 5.2).
+///
+fn string(block: &mut &[u8]) -> Result<String, DecodeError> {
+    let huffman = block.first().ok_or(DecodeError::Malformed)? & 0x80 != 0;
+    let len = integer(block, 7)?;
+
+    if len > block.len() {
+        return Err(DecodeError::Malformed);
+    }
+
+    let (raw, rest) = block.split_at(len);
+    *block = rest;
+
+    let bytes = if huffman {
+        huffman_decode(raw)?
+    } else {
+        raw.to_vec()
+    };
+
+    String::from_utf8(bytes).map_err(|_| DecodeError::Malformed)
+}
+
+///
+/// Maps every (length, code) pair of [HUFFMAN_CODES] to its byte.
+///
+fn huffman_table() -> &'static HashMap<(u8, u32), u8> {
+    static TABLE: OnceLock<HashMap<(u8, u32), u8>> = OnceLock::new();
+
+    TABLE.get_or_init(|| {
+        (0..=255u8)
+            .map(|byte| {
+                let i = byte as usize;
+                ((HUFFMAN_LENGTHS[i], HUFFMAN_CODES[i]), byte)
+            })
+            .collect()
+    })
+}
+
+///
+/// Decodes a Huffman coded string, whose padding must be the most significant bits of
+/// the end-of-string code (all ones) and shorter than a byte.
+///
+fn huffman_decode(raw: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let table = huffman_table();
+    let mut out = Vec::with_capacity(raw.len() * 8 / 5);
+    let (mut code, mut len) = (0u32, 0u8);
+
+    for byte in raw {
+        for shift in (0..8).rev() {
+            code = (code << 1) | u32::from(byte >> shift & 1);
+            len += 1;
+
+            if let Some(&decoded) = table.get(&(len, code)) {
+                out.push(decoded);
+                (code, len) = (0, 0);
+            } else if len >= 30 {
+                // Longest code, or the end-of-string code which mustn't appear
+                return Err(DecodeError::Malformed);
+            }
+        }
+    }
+
+    if len >= 8 || code != (1 << len) - 1 {
+        return Err(DecodeError::Malformed);
+    }
+
+    Ok(out)
+}
+
+///
+/// Encodes a response header block: `:status` followed by `headers`, whose names must
+/// be lowercase.
+///
+pub(crate) fn encode<'a>(
+    status: u16,
+    headers: impl Iterator<Item = (&'a str, &'a str)>,
+) -> Vec<u8> {
+    let mut block = Vec::new();
+    let status = status.to_string();
+
+    match STATIC_TABLE[7..14].iter().position(|(_, s)| *s == status) {
+        Some(i) => put_integer(&mut block, 0x80, 7, 8 + i),
+        None => put_literal(&mut block, 8, ":status", &status),
+    }
+
+    put_fields(&mut block, headers);
+
+    block
+}
+
+///
+/// Encodes a trailer block of `fields`, whose names must be lowercase.
+///
+pub(crate) fn encode_trailers<'a>(fields: impl Iterator<Item = (&'a str, &'a str)>) -> Vec<u8> {
+    let mut block = Vec::new();
+    put_fields(&mut block, fields);
+
+    block
+}
+
+///
+/// Writes regular fields as literals, their names indexed where the static table has them.
+///
+fn put_fields<'a>(block: &mut Vec<u8>, fields: impl Iterator<Item = (&'a str, &'a str)>) {
+    for (name, value) in fields {
+        let index = STATIC_TABLE
+            .iter()
+            .position(|(n, _)| *n == name)
+            .map_or(0, |i| i + 1);
+
+        put_literal(block, index, name, value);
+    }
+}
+
+///
+/// Writes a literal field without indexing, its name indexed unless `index` is 0.
+///
+fn put_literal(block: &mut Vec<u8>, index: usize, name: &str, value: &str) {
+    put_integer(block, 0x00, 4, index);
+
+    if index == 0 {
+        put_string(block, name);
+    }
+
+    put_string(block, value);
+}
+
+///
+/// Writes a string literal without Huffman coding.
+///
+fn put_string(block: &mut Vec<u8>, s: &str) {
+    put_integer(block, 0x00, 7, s.len());
+    block.extend_from_slice(s.as_bytes());
+}
+
+///
+/// Writes an integer with an N-bit prefix, `flags` filling the bits above it.
+///
+fn put_integer(block: &mut Vec<u8>, flags: u8, prefix: u8, mut value: usize) {
+    let max = (1 << prefix) - 1;
+
+    if value < max {
+        block.push(flags | value as u8);
+        return;
+    }
+
+    block.push(flags | max as u8);
+    value -= max;
+
+    while value >= 0x80 {
+        block.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+
+    block.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        let s: String = s.split_whitespace().collect();
+
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn hex_str(s: &str) -> String {
+        s.bytes().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn pairs(headers: &[(&str, &str)]) -> Vec<(String, String)> {
+        headers
+            .iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_hpack_decode_huffman_requests() {
+        // RFC 7541 C.4, three requests sharing the dynamic table
+        let mut decoder = Decoder::new(usize::MAX);
+
+        let first = decoder
+            .decode(&hex("8286 8441 8cf1 e3c2 e5f2 3a6b a0ab 90f4 ff"))
+            .unwrap();
+
+        assert_eq!(
+            first,
+            pairs(&[
+                (":method", "GET"),
+                (":scheme", "http"),
+                (":path", "/"),
+                (":authority", "www.example.com"),
+            ])
+        );
+
+        let second = decoder
+            .decode(&hex("8286 84be 5886 a8eb 1064 9cbf"))
+            .unwrap();
+
+        assert_eq!(
+            second[4],
+            ("cache-control".to_string(), "no-cache".to_string())
+        );
+        assert_eq!(second[3].1, "www.example.com");
+
+        let third = decoder
+            .decode(&hex(
+                "8287 85bf 4088 25a8 49e9 5ba9 7d7f 8925 a849 e95b b8e8 b4bf",
+            ))
+            .unwrap();
+
+        assert_eq!(
+            third,
+            pairs(&[
+                (":method", "GET"),
+                (":scheme", "https"),
+                (":path", "/index.html"),
+                (":authority", "www.example.com"),
+                ("custom-key", "custom-value"),
+            ])
+        );
+        assert_eq!(decoder.size, 164);
+
+        // Index past the dynamic table, and a size update after the first field
+        assert_eq!(decoder.decode(&hex("c5")), Err(DecodeError::Malformed));
+        assert_eq!(
+            decoder.decode(&hex("82 3f e1 1f")),
+            Err(DecodeError::Malformed)
+        );
+    }
+
+    #[test]
+    fn test_hpack_encode_round_trip() {
+        let long = "x".repeat(300);
+        let block = encode(
+            404,
+            [("content-type", "text/plain"), ("x-trace", long.as_str())].into_iter(),
+        );
+
+        assert_eq!(block[0], 0x80 | 13);
+
+        let headers = Decoder::new(usize::MAX).decode(&block).unwrap();
+
+        assert_eq!(
+            headers,
+            pairs(&[
+                (":status", "404"),
+                ("content-type", "text/plain"),
+                ("x-trace", &long),
+            ])
+        );
+
+        let block = encode(418, std::iter::empty());
+
+        assert_eq!(
+            Decoder::new(usize::MAX).decode(&block).unwrap(),
+            pairs(&[(":status", "418")])
+        );
+    }
+
+    #[test]
+    fn test_hpack_dynamic_table_eviction() {
+        let mut decoder = Decoder::new(usize::MAX);
+
+        // Table shrunk to 100 bytes, room for one 63 byte entry
+        let first = format!("3f45 40 01{} 1e{}", hex_str("a"), hex_str(&"1".repeat(30)));
+        decoder.decode(&hex(&first)).unwrap();
+
+        assert_eq!(decoder.size, 63);
+        assert_eq!(decoder.decode(&hex("be")).unwrap()[0].0, "a");
+
+        // Adding a second entry evicts the first
+        let second = format!("40 01{} 1e{}", hex_str("b"), hex_str(&"2".repeat(30)));
+        decoder.decode(&hex(&second)).unwrap();
+
+        assert_eq!(decoder.table.len(), 1);
+        assert_eq!(decoder.decode(&hex("be")).unwrap()[0].0, "b");
+        assert_eq!(decoder.decode(&hex("bf")), Err(DecodeError::Malformed));
+
+        // An entry larger than the table empties it
+        let large = format!("40 01{} 46{}", hex_str("c"), hex_str(&"3".repeat(70)));
+        decoder.decode(&hex(&large)).unwrap();
+
+        assert_eq!((decoder.size, decoder.table.len()), (0, 0));
+
+        // Growing the table past the advertised size is an error
+        assert_eq!(decoder.decode(&hex("3fe2 1f")), Err(DecodeError::Malformed));
+    }
+
+    #[test]
+    fn test_hpack_huffman_errors() {
+        // `a` is 00011, padded with ones
+        assert_eq!(huffman_decode(&[0x1f]).unwrap(), b"a");
+
+        // Padding of zeros, padding of a whole byte, and the end-of-string code
+        assert_eq!(huffman_decode(&[0x18]), Err(DecodeError::Malformed));
+        assert_eq!(huffman_decode(&[0x1f, 0xff]), Err(DecodeError::Malformed));
+        assert_eq!(
+            huffman_decode(&[0xff, 0xff, 0xff, 0xff]),
+            Err(DecodeError::Malformed)
+        );
+
+        // Through a literal value, Huffman coded
+        let mut decoder = Decoder::new(usize::MAX);
+
+        assert_eq!(decoder.decode(&hex("00 01 61 81 1f")).unwrap()[0].1, "a");
+        assert_eq!(
+            decoder.decode(&hex("00 01 61 81 18")),
+            Err(DecodeError::Malformed)
+        );
+    }
+
+    #[test]
+    fn test_hpack_header_list_limit() {
+        // One 85 byte entry, then referenced again and again with single bytes
+        let entry = format!("40 03{} 32{}", hex_str("x-a"), hex_str(&"v".repeat(50)));
+        let mut block = hex(&entry);
+        block.extend([0xbe; 3]);
+
+        assert_eq!(Decoder::new(4 * 85).decode(&block).unwrap().len(), 4);
+
+        block.push(0xbe);
+
+        assert_eq!(
+            Decoder::new(4 * 85).decode(&block),
+            Err(DecodeError::TooLarge)
+        );
+    }
+}
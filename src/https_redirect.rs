@@ -0,0 +1,245 @@
+//!
+//! Redirection of plain HTTP requests to their HTTPS equivalent.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::https_redirect::HttpsRedirect;
+//! use http_rs::router::Router;
+//! use http_rs::server::Server;
+//!
+//! // Port 80 only redirects, e.g. `http://example.com/a?b` to `https://example.com/a?b`,
+//! // while the HTTPS listener serves the application
+//! let mut router = Router::new();
+//! router.layer(HttpsRedirect::new().exempt("/.well-known/acme-challenge/"));
+//!
+//! Server::new("0.0.0.0:80").unwrap().serve(router);
+//! ```
+//!
+
+use crate::{
+    middleware::{Middleware, Next},
+    server::{HttpMethod, Request, RequestTarget, Response},
+};
+
+///
+/// [Middleware] redirecting requests that didn't arrive over TLS to the same host, path
+/// and query over HTTPS.
+///
+/// `GET` and `HEAD` requests get a `301 Moved Permanently`, others a
+/// `308 Permanent Redirect` so clients repeat them with the same method and body.
+/// Requests without a `Host` header can't be redirected and get a `400`.
+///
+pub struct HttpsRedirect {
+    port: Option<u16>,
+    exempt: Vec<String>,
+    trust_forwarded_proto: bool,
+}
+
+impl HttpsRedirect {
+    ///
+    /// Creates the redirecting [Middleware], to the default HTTPS port.
+    ///
+    pub fn new() -> HttpsRedirect {
+        HttpsRedirect {
+            port: None,
+            exempt: Vec::new(),
+            trust_forwarded_proto: false,
+        }
+    }
+
+    ///
+    /// Redirects to another port than 443 (e.g., 8443 in development).
+    ///
+    pub fn port(mut self, port: u16) -> HttpsRedirect {
+        self.port = Some(port).filter(|&port| port != 443);
+
+        self
+    }
+
+    ///
+    /// Lets requests whose path starts with `prefix` through over plain HTTP, e.g. the
+    /// ACME HTTP-01 challenges (`/.well-known/acme-challenge/`) certificate authorities
+    /// only fetch that way.
+    ///
+    pub fn exempt(mut self, prefix: &str) -> HttpsRedirect {
+        self.exempt.push(prefix.to_string());
+
+        self
+    }
+
+    ///
+    /// Treats requests with `X-Forwarded-Proto: https` or `Forwarded: proto=https` as
+    /// secure, for servers behind a TLS terminating proxy.
+    ///
+    /// `👉 Note:` Only enable this when every request goes through a proxy that sets or
+    /// strips these headers, clients can send them too
+    ///
+    pub fn trust_forwarded_proto(mut self, trust: bool) -> HttpsRedirect {
+        self.trust_forwarded_proto = trust;
+
+        self
+    }
+
+    fn is_secure(&self, req: &Request) -> bool {
+        if req.is_tls() {
+            return true;
+        }
+
+        if !self.trust_forwarded_proto {
+            return false;
+        }
+
+        let forwarded = req
+            .headers
+            .get_all("Forwarded")
+            .flat_map(|value| value.split([',', ';']))
+            .filter_map(|pair| pair.split_once('='))
+            .filter(|(name, _)| name.trim().eq_ignore_ascii_case("proto"))
+            .map(|(_, proto)| proto.trim().trim_matches('"'));
+
+        // The first entry comes from the proxy facing the client
+        let proto = forwarded
+            .chain(
+                req.headers
+                    .get("X-Forwarded-Proto")
+                    .map(|proto| proto.split(',').next().unwrap_or("").trim()),
+            )
+            .next();
+
+        proto.is_some_and(|proto| proto.eq_ignore_ascii_case("https"))
+    }
+
+    ///
+    /// Builds the HTTPS URL of the request, None without a `Host` header.
+    ///
+    fn location(&self, req: &Request) -> Option<String> {
+        let host = req.headers.get("Host")?.trim();
+
+        // Drop the plain HTTP port, keeping IPv6 literals intact
+        let host = match host.rsplit_once(':') {
+            Some((name, port)) if !port.contains(']') => name,
+            _ => host,
+        };
+
+        if host.is_empty() {
+            return None;
+        }
+
+        let target = match &req.target {
+            RequestTarget::Origin(target) => target.clone(),
+            RequestTarget::Absolute(uri) => {
+                let rest = uri.split_once("://").map_or(uri.as_str(), |(_, rest)| rest);
+
+                match rest.find(['/', '?']) {
+                    Some(i) if rest[i..].starts_with('?') => format!("/{}", &rest[i..]),
+                    Some(i) => rest[i..].to_string(),
+                    None => "/".to_string(),
+                }
+            }
+            RequestTarget::Authority(_) | RequestTarget::Asterisk => "/".to_string(),
+        };
+
+        Some(match self.port {
+            Some(port) => format!("https://{}:{}{}", host, port, target),
+            None => format!("https://{}{}", host, target),
+        })
+    }
+}
+
+impl Default for HttpsRedirect {
+    fn default() -> HttpsRedirect {
+        HttpsRedirect::new()
+    }
+}
+
+impl Middleware for HttpsRedirect {
+    fn handle(&self, req: Request, next: Next<'_>) -> Response {
+        if self.is_secure(&req)
+            || self
+                .exempt
+                .iter()
+                .any(|prefix| req.route.starts_with(prefix.as_str()))
+        {
+            return next.run(req);
+        }
+
+        let Some(location) = self.location(&req) else {
+            return Response::new(400).json(&"Missing Host header");
+        };
+
+        let status = match req.method {
+            HttpMethod::GET | HttpMethod::HEAD => 301,
+            _ => 308,
+        };
+
+        Response::new(status).header("Location", &location)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HttpsRedirect;
+    use crate::{
+        router::Router,
+        server::{test_request, HttpMethod, Request, Response},
+    };
+
+    fn request(method: HttpMethod, target: &str, host: Option<&str>) -> Request {
+        let mut req = test_request(method, target);
+
+        if let Some(host) = host {
+            req.headers.insert("Host", host);
+        }
+
+        req
+    }
+
+    #[test]
+    fn test_https_redirect() {
+        let mut router = Router::new();
+        router.layer(
+            HttpsRedirect::new()
+                .exempt("/.well-known/acme-challenge/")
+                .trust_forwarded_proto(true),
+        );
+        router.get("/*path", |_| Response::new(200));
+        router.post("/*path", |_| Response::new(200));
+
+        let res = router.handle(request(HttpMethod::GET, "/a/b?c=1", Some("example.com:80")));
+        assert_eq!(res.status, 301);
+        assert_eq!(
+            res.headers.get("Location"),
+            Some("https://example.com/a/b?c=1")
+        );
+
+        let res = router.handle(request(HttpMethod::POST, "/form", Some("[::1]")));
+        assert_eq!(res.status, 308);
+        assert_eq!(res.headers.get("Location"), Some("https://[::1]/form"));
+
+        let res = router.handle(request(HttpMethod::GET, "/", None));
+        assert_eq!(res.status, 400);
+
+        let res = router.handle(request(
+            HttpMethod::GET,
+            "/.well-known/acme-challenge/token",
+            Some("example.com"),
+        ));
+        assert_eq!(res.status, 200);
+
+        let mut req = request(HttpMethod::GET, "/", Some("example.com"));
+        req.headers.insert("X-Forwarded-Proto", "https");
+        assert_eq!(router.handle(req).status, 200);
+
+        let mut req = request(HttpMethod::GET, "/", Some("example.com"));
+        req.headers.insert("Forwarded", "for=1.2.3.4;proto=https");
+        assert_eq!(router.handle(req).status, 200);
+
+        let location = HttpsRedirect::new().port(8443).location(&request(
+            HttpMethod::GET,
+            "http://other/x?y",
+            Some("example.com:8080"),
+        ));
+        assert_eq!(location.as_deref(), Some("https://example.com:8443/x?y"));
+    }
+}
@@ -0,0 +1,245 @@
+//!
+//! Conversions between this crate's [Request]/[Response] and the ecosystem-standard
+//! [http] crate types, enabled with the `http-interop` feature.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::server::Response;
+//!
+//! let response = Response::new(200).json(&"Hello");
+//! let converted = http::Response::<Vec<u8>>::try_from(response).unwrap();
+//!
+//! assert_eq!(converted.status(), http::StatusCode::OK);
+//! ```
+//!
+
+use crate::body::Body;
+use crate::extensions::Extensions;
+use crate::header::HeaderMap;
+use crate::server::{parse_url, HttpMethod, HttpVersion, Params, Request, RequestTarget, Response};
+use crate::state::AppState;
+use std::io::{self, Read};
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+impl TryFrom<http::Request<Vec<u8>>> for Request {
+    type Error = io::Error;
+
+    fn try_from(req: http::Request<Vec<u8>>) -> io::Result<Request> {
+        let (parts, body) = req.into_parts();
+
+        let method = parts.method.as_str().parse::<HttpMethod>()?;
+
+        let version = match parts.version {
+            http::Version::HTTP_10 => HttpVersion::Http10,
+            http::Version::HTTP_11 => HttpVersion::Http11,
+            http::Version::HTTP_2 => HttpVersion::Http2,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "Unsupported HTTP version",
+                ))
+            }
+        };
+
+        let uri = &parts.uri;
+        let target = if uri.scheme().is_some() {
+            RequestTarget::Absolute(uri.to_string())
+        } else if uri.path() == "*" {
+            RequestTarget::Asterisk
+        } else if let (Some(authority), None) = (uri.authority(), uri.path_and_query()) {
+            RequestTarget::Authority(authority.to_string())
+        } else {
+            RequestTarget::Origin(
+                uri.path_and_query()
+                    .map_or("/", |pq| pq.as_str())
+                    .to_string(),
+            )
+        };
+
+        let (route, query_params) = match &target {
+            RequestTarget::Origin(_) | RequestTarget::Absolute(_) => {
+                parse_url(uri.path_and_query().map_or("/", |pq| pq.as_str()))?
+            }
+            _ => Default::default(),
+        };
+
+        let mut headers = HeaderMap::new();
+
+        for (name, value) in parts.headers.iter() {
+            let value = value
+                .to_str()
+                .map_err(|_| invalid("Non UTF-8 header value"))?;
+
+            headers.append(name.as_str(), value);
+        }
+
+        Ok(Request {
+            route,
+            target,
+            method,
+            version,
+            headers,
+            query_params,
+            params: Params::new(),
+            body,
+            trailers: HeaderMap::new(),
+            session: None,
+            remote_addr: None,
+            local_addr: None,
+            tls: false,
+            client_ip: None,
+            state: AppState::default(),
+            extensions: Extensions::new(),
+        })
+    }
+}
+
+impl TryFrom<Request> for http::Request<Vec<u8>> {
+    type Error = io::Error;
+
+    fn try_from(req: Request) -> io::Result<http::Request<Vec<u8>>> {
+        let method = http::Method::from_bytes(req.method.as_str().as_bytes())
+            .map_err(|_| invalid("Invalid HTTP method"))?;
+
+        let version = match req.version {
+            HttpVersion::Http10 => http::Version::HTTP_10,
+            HttpVersion::Http11 => http::Version::HTTP_11,
+            HttpVersion::Http2 => http::Version::HTTP_2,
+        };
+
+        let uri = match &req.target {
+            RequestTarget::Origin(raw)
+            | RequestTarget::Absolute(raw)
+            | RequestTarget::Authority(raw) => raw.as_str(),
+            RequestTarget::Asterisk => "*",
+        };
+
+        let mut builder = http::Request::builder()
+            .method(method)
+            .version(version)
+            .uri(uri);
+
+        for (name, value) in &req.headers {
+            builder = builder.header(name, value);
+        }
+
+        builder.body(req.body).map_err(|e| invalid(&e.to_string()))
+    }
+}
+
+impl TryFrom<http::Response<Vec<u8>>> for Response {
+    type Error = io::Error;
+
+    fn try_from(res: http::Response<Vec<u8>>) -> io::Result<Response> {
+        let (parts, body) = res.into_parts();
+
+        let mut headers = HeaderMap::new();
+
+        for (name, value) in parts.headers.iter() {
+            let value = value
+                .to_str()
+                .map_err(|_| invalid("Non UTF-8 header value"))?;
+
+            headers.append(name.as_str(), value);
+        }
+
+        Ok(Response {
+            status: parts.status.as_u16(),
+            reason: None,
+            headers,
+            body: Body::Full(body),
+            trailers: None,
+        })
+    }
+}
+
+impl TryFrom<Response> for http::Response<Vec<u8>> {
+    type Error = io::Error;
+
+    fn try_from(res: Response) -> io::Result<http::Response<Vec<u8>>> {
+        let streamed = res.body.is_stream();
+        let mut builder = http::Response::builder().status(res.status);
+
+        for (name, value) in &res.headers {
+            // Streamed bodies are buffered below, so their chunked framing no longer applies
+            if streamed && name.eq_ignore_ascii_case("Transfer-Encoding") {
+                continue;
+            }
+
+            builder = builder.header(name, value);
+        }
+
+        let body = match res.body {
+            Body::Full(bytes) => bytes,
+            Body::File { file, len } => {
+                let mut bytes = Vec::new();
+                file.take(len).read_to_end(&mut bytes)?;
+
+                bytes
+            }
+            Body::Stream(mut reader) => {
+                let mut bytes = Vec::new();
+                reader.read_to_end(&mut bytes)?;
+
+                builder = builder.header("Content-Length", bytes.len());
+
+                bytes
+            }
+            Body::Producer(produce) => {
+                let mut bytes = Vec::new();
+                produce(&mut bytes)?;
+
+                builder = builder.header("Content-Length", bytes.len());
+
+                bytes
+            }
+        };
+
+        builder.body(body).map_err(|e| invalid(&e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_round_trip() {
+        let req = http::Request::builder()
+            .method(http::Method::POST)
+            .uri("/users?page=2")
+            .header("content-type", "application/json")
+            .body(b"{}".to_vec())
+            .unwrap();
+
+        let req = Request::try_from(req).unwrap();
+
+        assert_eq!(req.method, HttpMethod::POST);
+        assert_eq!(req.route, "/users");
+        assert_eq!(req.query_params.get("page"), Some(&"2".to_string()));
+
+        let req = http::Request::<Vec<u8>>::try_from(req).unwrap();
+
+        assert_eq!(req.uri(), "/users?page=2");
+        assert_eq!(req.headers()["content-type"], "application/json");
+        assert_eq!(req.body(), b"{}");
+    }
+
+    #[test]
+    fn test_response_round_trip() {
+        let res = Response::new(201).json(&"Created");
+        let res = http::Response::<Vec<u8>>::try_from(res).unwrap();
+
+        assert_eq!(res.status(), http::StatusCode::CREATED);
+        assert_eq!(res.headers()["content-length"], "9");
+
+        let res = Response::try_from(res).unwrap();
+
+        assert_eq!(res.status, 201);
+        assert_eq!(res.body.as_bytes(), Some(&b"\"Created\""[..]));
+    }
+}
@@ -1 +1,67 @@
-pub mod server;
\ No newline at end of file
+pub mod access_log;
+#[cfg(feature = "acme")]
+pub mod acme;
+pub mod auth;
+pub mod body;
+pub mod builder;
+pub mod cache;
+pub mod cache_control;
+#[cfg(feature = "tls")]
+pub mod certificates;
+#[cfg(feature = "tls")]
+pub mod client_cert;
+pub mod compression;
+pub mod conditional;
+pub mod cookie;
+pub mod error;
+pub mod extensions;
+pub mod extract;
+pub mod forward_proxy;
+pub mod header;
+pub mod health;
+pub mod https_redirect;
+pub mod json;
+pub mod long_poll;
+pub mod media_type;
+pub mod method_override;
+pub mod middleware;
+pub mod negotiation;
+pub mod rate_limit;
+pub mod render;
+pub mod response;
+pub mod router;
+pub mod server;
+pub mod session;
+pub mod sse;
+pub mod state;
+pub mod static_files;
+pub mod status;
+pub mod stream;
+
+#[cfg(feature = "async")]
+pub mod async_io;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+#[cfg(feature = "http-interop")]
+pub mod interop;
+#[cfg(feature = "openapi")]
+pub mod openapi;
+#[cfg(any(feature = "tracing", feature = "log"))]
+pub mod trace;
+#[cfg(feature = "xml")]
+pub mod xml;
+
+pub use error::{Error, Result};
+
+mod date;
+#[cfg(feature = "tls")]
+mod der;
+mod events;
+mod forwarded;
+#[cfg(feature = "http2")]
+mod h2;
+#[cfg(feature = "http2")]
+mod hpack;
+mod pool;
+#[cfg(feature = "tls")]
+mod tls;
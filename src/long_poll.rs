@@ -0,0 +1,286 @@
+//!
+//! Long polling: a handler waits for something to happen, up to a deadline, answering
+//! `204 No Content` when nothing did so the client simply polls again.
+//!
+//! The connection's timeouts only run while the server reads a request or writes a
+//! response, never while a handler waits, so a long poll outlasting the keep-alive, header
+//! or read timeouts isn't cut off. Each waiting request holds a worker thread though, see
+//! [crate::server::Server::workers].
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::long_poll::{LongPoll, Topic};
+//! use http_rs::router::Router;
+//! use http_rs::server::{Request, Response};
+//! use std::{sync::Arc, time::Duration};
+//!
+//! let messages = Arc::new(Topic::new());
+//! let poll = LongPoll::new(Duration::from_secs(25));
+//! let mut router = Router::new();
+//!
+//! // The client passes back the version it last saw, `204` means poll again
+//! let topic = messages.clone();
+//! router.get("/messages", move |req: Request| {
+//!     let after = req.query_params.get("after").and_then(|v| v.parse().ok()).unwrap_or(0);
+//!
+//!     poll.wait(&topic, after, |version, message: String| {
+//!         Response::new(200).json(&(version, message))
+//!     })
+//! });
+//!
+//! router.post("/messages", move |req: Request| {
+//!     messages.publish(String::from_utf8_lossy(&req.body).to_string());
+//!
+//!     Response::new(202)
+//! });
+//! ```
+//!
+
+use crate::{response::IntoResponse, server::Response};
+use std::{
+    sync::{mpsc::Receiver, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+///
+/// How long a long-poll [Request](crate::server::Request) waits before answering
+/// `204 No Content`.
+///
+/// `👉 Note:` Keep the deadline below the timeouts of clients and proxies in between
+/// (often 30 to 60 seconds), or they give up before the server answers
+///
+#[derive(Debug, Clone, Copy)]
+pub struct LongPoll {
+    deadline: Duration,
+}
+
+impl LongPoll {
+    ///
+    /// Creates a [LongPoll] waiting up to `deadline`.
+    ///
+    pub fn new(deadline: Duration) -> LongPoll {
+        LongPoll { deadline }
+    }
+
+    ///
+    /// Waits for a value from a channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `receiver` -> Channel the value comes from
+    /// * `respond` -> Builds the response from the value
+    ///
+    /// # Returns
+    ///
+    /// * `Response` -> The response to the value, or `204` at the deadline (or if the
+    ///   channel is closed)
+    ///
+    pub fn recv<T, R, F>(&self, receiver: &Receiver<T>, respond: F) -> Response
+    where
+        R: IntoResponse,
+        F: FnOnce(T) -> R,
+    {
+        match receiver.recv_timeout(self.deadline) {
+            Ok(value) => respond(value).into_response(),
+            Err(_) => timed_out(),
+        }
+    }
+
+    ///
+    /// Waits for a [Topic] to be published a version other than `after`.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` -> The [Topic] waited on
+    /// * `after` -> Version the client last saw, `0` for none
+    /// * `respond` -> Builds the response from the version and value
+    ///
+    /// # Returns
+    ///
+    /// * `Response` -> The response to the value, or `204` at the deadline
+    ///
+    pub fn wait<T, R, F>(&self, topic: &Topic<T>, after: u64, respond: F) -> Response
+    where
+        T: Clone,
+        R: IntoResponse,
+        F: FnOnce(u64, T) -> R,
+    {
+        match topic.wait(after, self.deadline) {
+            Some((version, value)) => respond(version, value).into_response(),
+            None => timed_out(),
+        }
+    }
+}
+
+///
+/// The answer when nothing happened before the deadline.
+///
+fn timed_out() -> Response {
+    Response::new(204).set_header("Cache-Control", "no-store")
+}
+
+///
+/// The latest value of something long polls wait on (e.g., a chat room's last message,
+/// a job's status), numbered by a version increasing on every [Topic::publish].
+///
+/// Clients send back the version they last saw, so values published between two polls
+/// aren't missed: the next poll answers right away.
+///
+#[derive(Debug, Default)]
+pub struct Topic<T> {
+    ///
+    /// The version and value, None until the first publish
+    ///
+    latest: Mutex<(u64, Option<T>)>,
+    changed: Condvar,
+}
+
+impl<T: Clone> Topic<T> {
+    ///
+    /// Creates a [Topic] without a value, at version `0`.
+    ///
+    pub fn new() -> Topic<T> {
+        Topic {
+            latest: Mutex::new((0, None)),
+            changed: Condvar::new(),
+        }
+    }
+
+    ///
+    /// Replaces the value and wakes every waiting request.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` -> The new version
+    ///
+    pub fn publish(&self, value: T) -> u64 {
+        let mut latest = self.latest.lock().unwrap_or_else(|e| e.into_inner());
+        *latest = (latest.0 + 1, Some(value));
+        self.changed.notify_all();
+
+        latest.0
+    }
+
+    ///
+    /// Returns the current version, `0` if nothing was published yet.
+    ///
+    pub fn version(&self) -> u64 {
+        self.latest.lock().unwrap_or_else(|e| e.into_inner()).0
+    }
+
+    ///
+    /// Blocks until the version differs from `after`, or `timeout` passes.
+    ///
+    /// A version other than the current one (e.g., from before a restart) answers right
+    /// away with the current value.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<(u64, T)>` -> The version and value, or None at the timeout
+    ///
+    pub fn wait(&self, after: u64, timeout: Duration) -> Option<(u64, T)> {
+        let deadline = Instant::now() + timeout;
+        let mut latest = self.latest.lock().unwrap_or_else(|e| e.into_inner());
+
+        loop {
+            if let (version, Some(value)) = &*latest {
+                if *version != after {
+                    return Some((*version, value.clone()));
+                }
+            }
+
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return None;
+            }
+
+            latest = self
+                .changed
+                .wait_timeout(latest, left)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LongPoll, Topic};
+    use crate::{router::Router, server::Server};
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpStream,
+        sync::{mpsc, Arc},
+        thread,
+        time::Duration,
+    };
+
+    #[test]
+    fn test_long_poll() {
+        let topic = Arc::new(Topic::new());
+        let poll = LongPoll::new(Duration::from_millis(50));
+
+        assert_eq!(poll.wait(&topic, 0, |_, v: u32| v.to_string()).status, 204);
+
+        let publisher = topic.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            publisher.publish(7);
+        });
+        assert_eq!(topic.wait(0, Duration::from_secs(5)), Some((1, 7)));
+
+        // Already seen, then a stale version answers right away
+        assert_eq!(topic.wait(1, Duration::from_millis(10)), None);
+        assert_eq!(topic.wait(9, Duration::ZERO), Some((1, 7)));
+
+        let (sender, receiver) = mpsc::channel();
+        let res = poll.recv(&receiver, |v: &str| v);
+        assert_eq!(res.status, 204);
+        assert_eq!(res.headers.get("Cache-Control"), Some("no-store"));
+
+        sender.send("done").unwrap();
+        let res = poll.recv(&receiver, |v| v);
+        assert_eq!(res.body.as_bytes().unwrap(), b"done");
+    }
+
+    #[test]
+    fn test_long_poll_outlasts_timeouts() {
+        let short = Some(Duration::from_millis(50));
+        let server = Server::new("127.0.0.1:0")
+            .unwrap()
+            .workers(1)
+            .keep_alive(short)
+            .header_timeout(short)
+            .read_timeout(short);
+        let addr = server.local_addr().unwrap();
+
+        let topic = Arc::new(Topic::<String>::new());
+        let poll = LongPoll::new(Duration::from_millis(300));
+        let mut router = Router::new();
+        router.get("/poll", move |_| poll.wait(&topic, 0, |_, v| v));
+
+        thread::spawn(move || server.serve(router));
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(client.try_clone().unwrap());
+
+        client.write_all(b"GET /poll HTTP/1.1\r\n\r\n").unwrap();
+
+        let mut head = String::new();
+        while !head.ends_with("\r\n\r\n") {
+            reader.read_line(&mut head).unwrap();
+        }
+        assert!(head.starts_with("HTTP/1.1 204 No Content\r\n"));
+        assert!(head.contains("Connection: keep-alive\r\n"));
+
+        // The connection is still usable for the next poll
+        client
+            .write_all(b"GET /missing HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .unwrap();
+
+        let mut raw = String::new();
+        reader.read_to_string(&mut raw).unwrap();
+        assert!(raw.starts_with("HTTP/1.1 404"));
+    }
+}
@@ -1,6 +1,8 @@
-use std::io::{self, BufReader};
+use std::io;
 
-use http_rs::server::{HttpMethod, Request, Response, Server};
+use http_rs::extract::Json;
+use http_rs::router::Router;
+use http_rs::server::{Request, Response, Server};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
@@ -11,48 +13,39 @@ struct User {
 
 fn main() -> io::Result<()> {
     let server = Server::new("127.0.0.1:6969")?;
+    let mut router = Router::new();
+
+    router.get("/users", |_| {
+        let users = vec![
+            User {
+                id: 1,
+                name: "Alice".to_string(),
+            },
+            User {
+                id: 2,
+                name: "Bob".to_string(),
+            },
+        ];
+
+        Response::new(200).json(&users)
+    });
+
+    router.get("/users/:id", |req: Request| {
+        match req.params.get("id").and_then(|id| id.parse::<u32>().ok()) {
+            Some(id) => Response::new(200).json(&User {
+                id,
+                name: "Alice".to_string(),
+            }),
+            None => Response::new(400).json(&"Invalid user id"),
+        }
+    });
 
-    println!("Server running on http://127.0.0.1:6969");
+    router.post("/users", |req: Request| match req.extract::<Json<User>>() {
+        Ok(Json(user)) => Response::new(201).json(&user),
+        Err(e) => e.into(),
+    });
 
-    for stream in server.listen() {
-        match stream {
-            Ok(mut stream) => {
-                let buf = BufReader::new(stream.try_clone().unwrap());
-
-                if let Ok(req) = Request::new(buf) {
-                    let response = match (req.method, req.route.as_str()) {
-                        (HttpMethod::GET, "/users") => {
-                            let users = vec![
-                                User {
-                                    id: 1,
-                                    name: "Alice".to_string(),
-                                },
-                                User {
-                                    id: 2,
-                                    name: "Bob".to_string(),
-                                },
-                            ];
-
-                            Response::new(200).json(&users)
-                        }
-                        (HttpMethod::POST, "/users") => {
-                            if let Some(user) = req.get_json::<User>() {
-                                Response::new(201).json(&user)
-                            } else {
-                                Response::new(400).json(&"Invalid JSON")
-                            }
-                        }
-                        _ => Response::new(404).json(&"Not Found"),
-                    };
-
-                    if let Err(e) = response.send(&mut stream) {
-                        eprintln!("Failed to send response: {}", e);
-                    }
-                }
-            }
-            Err(e) => eprintln!("Connection failed: {}", e),
-        }
-    }
+    println!("Server running on http://127.0.0.1:6969");
 
-    Ok(())
+    server.serve(router)
 }
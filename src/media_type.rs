@@ -0,0 +1,297 @@
+//!
+//! Parsing of `Content-Type` values into a [MediaType], and decoding of text bodies
+//! according to their `charset`.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::server::{Request, Response};
+//!
+//! fn upload(req: Request) -> Response {
+//!     let Some(media_type) = req.media_type() else {
+//!         return Response::new(415);
+//!     };
+//!
+//!     if media_type.is("multipart/form-data") {
+//!         let boundary = media_type.boundary().unwrap_or_default();
+//!
+//!         return Response::new(200).text(&format!("Parts split on {}", boundary));
+//!     }
+//!
+//!     // Decoded from the declared charset (e.g., `text/plain; charset=ISO-8859-1`)
+//!     match req.extract::<String>() {
+//!         Ok(text) => Response::new(200).text(&text),
+//!         Err(e) => e.into(),
+//!     }
+//! }
+//! ```
+//!
+
+use crate::{
+    error::{Error, Result},
+    server::{is_token, Request},
+};
+use std::fmt;
+
+///
+/// A media type such as `text/html; charset=utf-8`: a type, a subtype and parameters.
+///
+/// Type, subtype and parameter names are lowercased, as they are case-insensitive.
+/// Parameter values are kept as sent, unquoted.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaType {
+    main_type: String,
+    subtype: String,
+    params: Vec<(String, String)>,
+}
+
+impl MediaType {
+    ///
+    /// Parses a `Content-Type` value.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<MediaType>` -> The media type, or None if the value is malformed
+    ///
+    pub fn parse(value: &str) -> Option<MediaType> {
+        let (essence, mut rest) = value.split_once(';').unwrap_or((value, ""));
+        let (main_type, subtype) = essence.trim().split_once('/')?;
+
+        if !is_token(main_type) || !is_token(subtype) {
+            return None;
+        }
+
+        let mut params = Vec::new();
+
+        loop {
+            rest = rest.trim_start_matches([' ', '\t', ';']);
+
+            if rest.is_empty() {
+                break;
+            }
+
+            let (name, after) = rest.split_once('=')?;
+
+            if !is_token(name) {
+                return None;
+            }
+
+            // A quoted value may hold `;`, so it is scanned rather than split
+            let (value, after) = match after.strip_prefix('"') {
+                Some(quoted) => unquote(quoted)?,
+                None => {
+                    let end = after.find(';').unwrap_or(after.len());
+                    (after[..end].trim_end().to_string(), &after[end..])
+                }
+            };
+
+            params.push((name.to_ascii_lowercase(), value));
+            rest = after;
+        }
+
+        Some(MediaType {
+            main_type: main_type.to_ascii_lowercase(),
+            subtype: subtype.to_ascii_lowercase(),
+            params,
+        })
+    }
+
+    ///
+    /// Returns the type (e.g., "text" for `text/html`).
+    ///
+    pub fn main_type(&self) -> &str {
+        &self.main_type
+    }
+
+    ///
+    /// Returns the subtype (e.g., "html" for `text/html`).
+    ///
+    pub fn subtype(&self) -> &str {
+        &self.subtype
+    }
+
+    ///
+    /// Whether this is the `type/subtype` media type, ignoring case and parameters.
+    ///
+    pub fn is(&self, essence: &str) -> bool {
+        essence.split_once('/').is_some_and(|(main_type, subtype)| {
+            main_type.eq_ignore_ascii_case(&self.main_type)
+                && subtype.eq_ignore_ascii_case(&self.subtype)
+        })
+    }
+
+    ///
+    /// Returns the value of the parameter `name`, matched case-insensitively.
+    ///
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    ///
+    /// Returns the `charset` parameter (e.g., "utf-8").
+    ///
+    pub fn charset(&self) -> Option<&str> {
+        self.param("charset")
+    }
+
+    ///
+    /// Returns the `boundary` parameter separating the parts of a `multipart` body.
+    ///
+    pub fn boundary(&self) -> Option<&str> {
+        self.param("boundary")
+    }
+
+    ///
+    /// Decodes a body of this media type as text, according to its `charset`.
+    ///
+    /// `UTF-8` (the default), `US-ASCII` and `ISO-8859-1` (latin-1) are supported.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<String>` -> The text, [Error::InvalidUtf8] if it doesn't match a
+    ///   `UTF-8` charset, or [Error::UnsupportedMediaType] for another charset
+    ///
+    pub fn decode(&self, body: &[u8]) -> Result<String> {
+        decode(body, self.charset())
+    }
+}
+
+///
+/// Reads a quoted-string value, its opening quote already consumed, returning it
+/// unescaped with what follows the closing quote.
+///
+fn unquote(quoted: &str) -> Option<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = quoted.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &quoted[i + 1..])),
+            '\\' => value.push(chars.next()?.1),
+            c => value.push(c),
+        }
+    }
+
+    // Missing the closing quote
+    None
+}
+
+///
+/// Decodes `body` as text in `charset`, `UTF-8` when None.
+///
+pub(crate) fn decode(body: &[u8], charset: Option<&str>) -> Result<String> {
+    let charset = charset.unwrap_or("utf-8").to_ascii_lowercase();
+
+    match charset.as_str() {
+        // ASCII is a subset of UTF-8, so it is decoded the same way
+        "utf-8" | "utf8" | "us-ascii" | "ascii" => {
+            String::from_utf8(body.to_vec()).map_err(|_| Error::InvalidUtf8)
+        }
+        // Every byte is the code point of the same value
+        "iso-8859-1" | "iso_8859-1" | "latin1" | "latin-1" | "l1" => {
+            Ok(body.iter().map(|&b| char::from(b)).collect())
+        }
+        _ => Err(Error::UnsupportedMediaType(
+            "a UTF-8, US-ASCII or ISO-8859-1 charset",
+        )),
+    }
+}
+
+impl fmt::Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.main_type, self.subtype)?;
+
+        for (name, value) in &self.params {
+            match is_token(value) {
+                true => write!(f, "; {}={}", name, value)?,
+                false => write!(
+                    f,
+                    "; {}=\"{}\"",
+                    name,
+                    value.replace('\\', "\\\\").replace('"', "\\\"")
+                )?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Request {
+    ///
+    /// Parses the `Content-Type` header.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<MediaType>` -> The media type of the body, or None if the header is
+    ///   missing or malformed
+    ///
+    pub fn media_type(&self) -> Option<MediaType> {
+        MediaType::parse(self.headers.get("Content-Type")?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MediaType;
+    use crate::{
+        error::Error,
+        server::{test_request, HttpMethod},
+    };
+
+    #[test]
+    fn test_media_type() {
+        let media_type =
+            MediaType::parse(r#"Multipart/Form-Data; Boundary="a;b \"c\""; charset=UTF-8"#)
+                .unwrap();
+
+        assert_eq!(
+            (media_type.main_type(), media_type.subtype()),
+            ("multipart", "form-data")
+        );
+        assert!(media_type.is("multipart/FORM-DATA"));
+        assert_eq!(media_type.boundary(), Some(r#"a;b "c""#));
+        assert_eq!(media_type.charset(), Some("UTF-8"));
+        assert_eq!(
+            media_type.to_string(),
+            r#"multipart/form-data; boundary="a;b \"c\""; charset=UTF-8"#
+        );
+
+        assert_eq!(MediaType::parse("text"), None);
+        assert_eq!(MediaType::parse("text/plain; charset"), None);
+        assert_eq!(MediaType::parse("text/plain; a=\"open"), None);
+        assert_eq!(
+            MediaType::parse("text/plain;").unwrap().to_string(),
+            "text/plain"
+        );
+    }
+
+    #[test]
+    fn test_decode_charset() {
+        let text = |content_type: &str, body: &[u8]| {
+            let mut req = test_request(HttpMethod::POST, "/");
+            req.headers.insert("Content-Type", content_type);
+            req.body = body.to_vec();
+
+            req.extract::<String>()
+        };
+
+        assert_eq!(
+            text("text/plain; charset=ISO-8859-1", b"caf\xe9").unwrap(),
+            "café"
+        );
+        assert_eq!(text("text/plain", "café".as_bytes()).unwrap(), "café");
+        assert!(matches!(
+            text("text/plain; charset=utf-8", b"caf\xe9"),
+            Err(Error::InvalidUtf8)
+        ));
+        assert!(matches!(
+            text("text/plain; charset=shift_jis", b"x"),
+            Err(Error::UnsupportedMediaType(_))
+        ));
+    }
+}
@@ -0,0 +1,170 @@
+//!
+//! Method override, letting HTML forms and clients limited to `GET` and `POST` reach
+//! `PUT`, `PATCH` and `DELETE` routes.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::method_override::MethodOverride;
+//! use http_rs::router::Router;
+//! use http_rs::server::Response;
+//!
+//! let mut router = Router::new();
+//!
+//! // `<form method="post" action="/posts/1"><input type="hidden" name="_method" value="DELETE">`
+//! // or `POST /posts/1` with `X-HTTP-Method-Override: DELETE` both reach this route
+//! router.layer(MethodOverride::new());
+//! router.delete("/posts/:id", |_| Response::new(204));
+//! ```
+//!
+
+use crate::{
+    extract::has_media_type,
+    middleware::{Middleware, Next},
+    server::{HttpMethod, Request, Response},
+};
+
+///
+/// [Middleware] rewriting the method of `POST` requests from the
+/// `X-HTTP-Method-Override` header or a `_method` field of
+/// `application/x-www-form-urlencoded` bodies, before routing.
+///
+/// Only `PUT`, `PATCH` and `DELETE` (case-insensitively) are accepted, other values
+/// leave the request a `POST`. The header wins when both are sent.
+///
+pub struct MethodOverride {
+    header: bool,
+    form: bool,
+}
+
+impl MethodOverride {
+    ///
+    /// Creates the [Middleware], reading both the header and the form field.
+    ///
+    pub fn new() -> MethodOverride {
+        MethodOverride {
+            header: true,
+            form: true,
+        }
+    }
+
+    ///
+    /// Whether to read the `X-HTTP-Method-Override` header.
+    ///
+    pub fn header(mut self, header: bool) -> MethodOverride {
+        self.header = header;
+
+        self
+    }
+
+    ///
+    /// Whether to read the `_method` field of form bodies.
+    ///
+    pub fn form(mut self, form: bool) -> MethodOverride {
+        self.form = form;
+
+        self
+    }
+
+    fn requested(&self, req: &Request) -> Option<HttpMethod> {
+        let header = self
+            .header
+            .then(|| req.headers.get("X-HTTP-Method-Override"))
+            .flatten()
+            .map(str::to_string);
+
+        let is_form = has_media_type(req, "application/x-www-form-urlencoded");
+
+        let field = || {
+            serde_urlencoded::from_bytes::<Vec<(String, String)>>(&req.body)
+                .ok()?
+                .into_iter()
+                .find(|(name, _)| name == "_method")
+                .map(|(_, method)| method)
+        };
+
+        let method = header.or_else(|| (self.form && is_form).then(field).flatten())?;
+
+        match method.trim().to_ascii_uppercase().as_str() {
+            "PUT" => Some(HttpMethod::PUT),
+            "PATCH" => Some(HttpMethod::PATCH),
+            "DELETE" => Some(HttpMethod::DELETE),
+            _ => None,
+        }
+    }
+}
+
+impl Default for MethodOverride {
+    fn default() -> MethodOverride {
+        MethodOverride::new()
+    }
+}
+
+impl Middleware for MethodOverride {
+    fn handle(&self, mut req: Request, next: Next<'_>) -> Response {
+        if req.method == HttpMethod::POST {
+            if let Some(method) = self.requested(&req) {
+                req.method = method;
+            }
+        }
+
+        next.run(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MethodOverride;
+    use crate::{
+        router::Router,
+        server::{test_request, HttpMethod, Request, Response},
+    };
+
+    fn post(headers: &[(&str, &str)], body: &str) -> Request {
+        let mut req = test_request(HttpMethod::POST, "/posts/1");
+        req.body = body.as_bytes().to_vec();
+
+        for &(name, value) in headers {
+            req.headers.insert(name, value);
+        }
+
+        req
+    }
+
+    fn routed(router: &Router, req: Request) -> String {
+        let res = router.handle(req);
+
+        String::from_utf8_lossy(res.body.as_bytes().unwrap()).to_string()
+    }
+
+    #[test]
+    fn test_method_override() {
+        let mut router = Router::new();
+        router.layer(MethodOverride::new());
+        router.get("/posts/:id", |_| Response::new(200).json(&"get"));
+        router.post("/posts/:id", |_| Response::new(200).json(&"post"));
+        router.put("/posts/:id", |_| Response::new(200).json(&"put"));
+        router.delete("/posts/:id", |_| Response::new(200).json(&"delete"));
+
+        let form = ("Content-Type", "application/x-www-form-urlencoded");
+        let header = |method| ("X-HTTP-Method-Override", method);
+
+        assert_eq!(routed(&router, post(&[header("put")], "")), "\"put\"");
+        assert_eq!(
+            routed(&router, post(&[form], "title=a&_method=DELETE")),
+            "\"delete\""
+        );
+        assert_eq!(
+            routed(&router, post(&[form, header("PUT")], "_method=DELETE")),
+            "\"put\""
+        );
+
+        // Only to PUT, PATCH and DELETE, from POST, and the field only from forms
+        assert_eq!(routed(&router, post(&[header("GET")], "")), "\"post\"");
+        assert_eq!(routed(&router, post(&[], "_method=DELETE")), "\"post\"");
+
+        let mut req = test_request(HttpMethod::GET, "/posts/1");
+        req.headers.insert("X-HTTP-Method-Override", "DELETE");
+        assert_eq!(routed(&router, req), "\"get\"");
+    }
+}
@@ -0,0 +1,115 @@
+//!
+//! Composable middleware wrapping request handling.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::middleware::Next;
+//! use http_rs::router::Router;
+//! use http_rs::server::{Request, Response};
+//!
+//! let mut router = Router::new();
+//!
+//! router.layer(|req: Request, next: Next| {
+//!     if req.headers.contains_key("Authorization") {
+//!         next.run(req)
+//!     } else {
+//!         Response::new(401).json(&"Unauthorized")
+//!     }
+//! });
+//! ```
+//!
+
+use crate::server::{Request, Response};
+
+///
+/// Logic wrapped around request handling, able to inspect or replace the [Request]
+/// before it reaches the handler and the [Response] after it.
+///
+/// Implemented for every `Fn(Request, Next) -> Response` closure or function.
+///
+pub trait Middleware: Send + Sync + 'static {
+    ///
+    /// Handles the [Request], calling [Next::run] to continue down the chain or
+    /// returning a [Response] directly to short-circuit it.
+    ///
+    fn handle(&self, req: Request, next: Next<'_>) -> Response;
+}
+
+impl<F> Middleware for F
+where
+    F: Fn(Request, Next<'_>) -> Response + Send + Sync + 'static,
+{
+    fn handle(&self, req: Request, next: Next<'_>) -> Response {
+        self(req, next)
+    }
+}
+
+///
+/// The remainder of a middleware chain, ending in the route handler.
+///
+pub struct Next<'a> {
+    middleware: &'a [Box<dyn Middleware>],
+    endpoint: &'a dyn Fn(Request) -> Response,
+}
+
+impl<'a> Next<'a> {
+    ///
+    /// Creates the chain running `middleware` in order, then `endpoint`.
+    ///
+    pub(crate) fn new(
+        middleware: &'a [Box<dyn Middleware>],
+        endpoint: &'a dyn Fn(Request) -> Response,
+    ) -> Next<'a> {
+        Next {
+            middleware,
+            endpoint,
+        }
+    }
+
+    ///
+    /// Passes the [Request] to the next middleware, or to the handler at the end of
+    /// the chain.
+    ///
+    pub fn run(self, req: Request) -> Response {
+        match self.middleware.split_first() {
+            Some((first, rest)) => first.handle(req, Next::new(rest, self.endpoint)),
+            None => (self.endpoint)(req),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::router::Router;
+    use crate::server::{test_request, HttpMethod};
+
+    #[test]
+    fn test_middleware_order_and_short_circuit() {
+        let mut router = Router::new();
+
+        router
+            .layer(|req: Request, next: Next| next.run(req).set_header("X-Outer", "1"))
+            .layer(|req: Request, next: Next| {
+                if req.route == "/blocked" {
+                    return Response::new(403);
+                }
+
+                next.run(req).set_header("X-Inner", "1")
+            })
+            .get("/open", |_| Response::new(200))
+            .get("/blocked", |_| Response::new(200));
+
+        let res = router.handle(test_request(HttpMethod::GET, "/open"));
+        let names: Vec<_> = res.headers.iter().map(|(k, _)| k).collect();
+
+        assert_eq!(res.status, 200);
+        assert_eq!(names, ["Content-Type", "X-Inner", "X-Outer"]);
+
+        let res = router.handle(test_request(HttpMethod::GET, "/blocked"));
+
+        assert_eq!(res.status, 403);
+        assert!(res.headers.contains_key("X-Outer"));
+    }
+}
@@ -0,0 +1,417 @@
+//!
+//! Content negotiation with the client's `Accept`, `Accept-Language` and
+//! `Accept-Charset` headers.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::negotiation::Representations;
+//! use http_rs::router::Router;
+//! use http_rs::server::Request;
+//!
+//! let mut router = Router::new();
+//!
+//! // JSON, HTML or plain text depending on the client, 406 for anything else
+//! router.get("/users", |req: Request| {
+//!     let users = ["alice", "bob"];
+//!
+//!     Representations::new()
+//!         .json(&users)
+//!         .html(&format!("<ul><li>{}</li></ul>", users.join("</li><li>")))
+//!         .text(&users.join("\n"))
+//!         .respond(&req)
+//! });
+//! ```
+//!
+
+use crate::server::{Request, Response};
+use crate::status::StatusCode;
+use serde::Serialize;
+
+///
+/// One entry of an `Accept`-style header with its quality value.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preference {
+    ///
+    /// The media range, language range or charset, without its parameters
+    ///
+    pub value: String,
+
+    ///
+    /// The `q` parameter, from 0 (not acceptable) to 1 (the default)
+    ///
+    pub quality: f32,
+}
+
+///
+/// Parses an `Accept`, `Accept-Language` or `Accept-Charset` value.
+///
+/// # Returns
+///
+/// * `Vec<Preference>` -> The entries ordered from most to least preferred, ties kept in
+///   the order the client listed them
+///
+pub fn parse(header: &str) -> Vec<Preference> {
+    let mut preferences: Vec<Preference> = header
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let value = parts.next().unwrap_or("").trim();
+
+            if value.is_empty() {
+                return None;
+            }
+
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0)
+                .clamp(0.0, 1.0);
+
+            Some(Preference {
+                value: value.to_ascii_lowercase(),
+                quality,
+            })
+        })
+        .collect();
+
+    // Stable, so equally preferred entries stay in the client's order
+    preferences.sort_by(|a, b| b.quality.total_cmp(&a.quality));
+
+    preferences
+}
+
+///
+/// Which of `offers` the client prefers.
+///
+/// Each offer takes the quality of the most specific entry matching it, as decided by
+/// `specificity`, and ties go to the offer listed first. A missing header accepts
+/// anything, so the first offer is chosen.
+///
+fn preferred<'a>(
+    header: Option<&str>,
+    offers: &[&'a str],
+    specificity: fn(&str, &str) -> Option<u8>,
+) -> Option<&'a str> {
+    let Some(header) = header else {
+        return offers.first().copied();
+    };
+
+    let preferences = parse(header);
+    let mut best: Option<(&str, f32)> = None;
+
+    for &offer in offers {
+        let offered = offer.to_ascii_lowercase();
+
+        let quality = preferences
+            .iter()
+            .filter_map(|p| specificity(&p.value, &offered).map(|s| (s, p.quality)))
+            // The first entry wins among equally specific ones
+            .fold(None, |most: Option<(u8, f32)>, (s, q)| match most {
+                Some((m, _)) if m >= s => most,
+                _ => Some((s, q)),
+            })
+            .map_or(0.0, |(_, q)| q);
+
+        if quality > 0.0 && best.is_none_or(|(_, q)| quality > q) {
+            best = Some((offer, quality));
+        }
+    }
+
+    best.map(|(offer, _)| offer)
+}
+
+///
+/// How specifically a media range (e.g., `text/*`) matches a media type.
+///
+fn media_range(range: &str, media_type: &str) -> Option<u8> {
+    let essence = |value: &str| value.split(';').next().unwrap_or("").trim().to_string();
+    let media_type = essence(media_type);
+    let (kind, _) = media_type.split_once('/')?;
+
+    match range.split_once('/')? {
+        ("*", "*") => Some(0),
+        (range_kind, "*") if range_kind == kind => Some(1),
+        _ if range == media_type => Some(2),
+        _ => None,
+    }
+}
+
+///
+/// How specifically a language range (e.g., `en`) matches a language tag (e.g.,
+/// `en-us`), by RFC 4647 basic filtering.
+///
+fn language_range(range: &str, tag: &str) -> Option<u8> {
+    if range == "*" {
+        return Some(0);
+    }
+
+    let matches = tag == range
+        || tag
+            .strip_prefix(range)
+            .is_some_and(|rest| rest.starts_with('-'));
+
+    // Longer ranges are more specific
+    matches.then(|| range.split('-').count().min(u8::MAX as usize) as u8)
+}
+
+///
+/// How specifically a charset entry matches a charset.
+///
+fn charset(entry: &str, charset: &str) -> Option<u8> {
+    match entry {
+        "*" => Some(0),
+        _ if entry == charset => Some(1),
+        _ => None,
+    }
+}
+
+impl Request {
+    ///
+    /// Returns the media ranges of the `Accept` header, most preferred first.
+    ///
+    pub fn accepted_types(&self) -> Vec<Preference> {
+        self.headers.get("Accept").map(parse).unwrap_or_default()
+    }
+
+    ///
+    /// Returns the language ranges of the `Accept-Language` header, most preferred
+    /// first.
+    ///
+    pub fn accepted_languages(&self) -> Vec<Preference> {
+        self.headers
+            .get("Accept-Language")
+            .map(parse)
+            .unwrap_or_default()
+    }
+
+    ///
+    /// Returns the charsets of the `Accept-Charset` header, most preferred first.
+    ///
+    pub fn accepted_charsets(&self) -> Vec<Preference> {
+        self.headers
+            .get("Accept-Charset")
+            .map(parse)
+            .unwrap_or_default()
+    }
+
+    ///
+    /// Whether the client accepts a media type, honoring wildcards (e.g., `text/*`)
+    /// and ranges refused with `q=0`.
+    ///
+    /// # Arguments
+    ///
+    /// * `media_type` -> Media type of the representation (e.g., "application/json")
+    ///
+    /// # Returns
+    ///
+    /// * `bool` -> True if accepted, or if the request has no `Accept` header
+    ///
+    pub fn accepts(&self, media_type: &str) -> bool {
+        self.preferred_type(&[media_type]).is_some()
+    }
+
+    ///
+    /// Picks the media type the client prefers among the ones available.
+    ///
+    /// # Arguments
+    ///
+    /// * `offers` -> Available media types, in the server's order of preference
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&str>` -> The preferred offer, or None if the client accepts none
+    ///
+    pub fn preferred_type<'a>(&self, offers: &[&'a str]) -> Option<&'a str> {
+        preferred(self.headers.get("Accept"), offers, media_range)
+    }
+
+    ///
+    /// Picks the language the client prefers among the ones available, see
+    /// [Request::preferred_type].
+    ///
+    pub fn preferred_language<'a>(&self, offers: &[&'a str]) -> Option<&'a str> {
+        preferred(self.headers.get("Accept-Language"), offers, language_range)
+    }
+
+    ///
+    /// Picks the charset the client prefers among the ones available, see
+    /// [Request::preferred_type].
+    ///
+    pub fn preferred_charset<'a>(&self, offers: &[&'a str]) -> Option<&'a str> {
+        preferred(self.headers.get("Accept-Charset"), offers, charset)
+    }
+}
+
+///
+/// Alternative representations of a resource, answered with the one the client's
+/// `Accept` header prefers.
+///
+/// Representations are tried in the order they are added when the client likes several
+/// equally, and `406 Not Acceptable` is answered when it accepts none.
+///
+#[derive(Default)]
+pub struct Representations {
+    responses: Vec<(String, Response)>,
+}
+
+impl Representations {
+    ///
+    /// Creates an empty set of representations.
+    ///
+    pub fn new() -> Representations {
+        Representations::default()
+    }
+
+    ///
+    /// Adds a `JSON` representation, see [Response::json].
+    ///
+    pub fn json<T: Serialize>(self, data: &T) -> Representations {
+        self.with("application/json", Response::new(200).json(data))
+    }
+
+    ///
+    /// Adds an HTML representation, see [Response::html].
+    ///
+    pub fn html(self, html: &str) -> Representations {
+        self.with("text/html", Response::new(200).html(html))
+    }
+
+    ///
+    /// Adds a plain text representation, see [Response::text].
+    ///
+    pub fn text(self, text: &str) -> Representations {
+        self.with("text/plain", Response::new(200).text(text))
+    }
+
+    ///
+    /// Adds a representation of any media type.
+    ///
+    /// # Arguments
+    ///
+    /// * `media_type` -> Media type matched against `Accept` (e.g., "text/csv")
+    /// * `response` -> Response answered when this representation is chosen
+    ///
+    pub fn with(mut self, media_type: &str, response: Response) -> Representations {
+        self.responses.push((media_type.to_string(), response));
+
+        self
+    }
+
+    ///
+    /// Answers with the representation the [Request] prefers.
+    ///
+    /// # Returns
+    ///
+    /// * `Response` -> The chosen representation with `Vary: Accept`, or a `406`
+    ///
+    pub fn respond(self, req: &Request) -> Response {
+        let offers: Vec<&str> = self.responses.iter().map(|(t, _)| t.as_str()).collect();
+
+        let chosen = req
+            .preferred_type(&offers)
+            .and_then(|chosen| offers.iter().position(|offer| *offer == chosen));
+
+        let res = chosen
+            .and_then(|index| self.responses.into_iter().nth(index))
+            .map(|(_, res)| res);
+
+        res.unwrap_or_else(|| {
+            let status = StatusCode::NOT_ACCEPTABLE;
+
+            Response::new(status).json(&status.canonical_reason())
+        })
+        .vary("Accept")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::{test_request, HttpMethod};
+
+    fn request(name: &str, value: &str) -> Request {
+        let mut req = test_request(HttpMethod::GET, "/");
+        req.headers.insert(name, value);
+
+        req
+    }
+
+    #[test]
+    fn test_parse_quality_order() {
+        let values: Vec<(String, f32)> =
+            parse("text/html;q=0.8, application/json, */*;q=0.1, text/plain")
+                .into_iter()
+                .map(|p| (p.value, p.quality))
+                .collect();
+
+        assert_eq!(
+            values,
+            [
+                ("application/json".to_string(), 1.0),
+                ("text/plain".to_string(), 1.0),
+                ("text/html".to_string(), 0.8),
+                ("*/*".to_string(), 0.1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_preferred() {
+        let req = request("Accept", "text/*;q=0.5, application/json, image/png;q=0");
+
+        assert!(req.accepts("application/json"));
+        assert!(req.accepts("text/csv"));
+        assert!(!req.accepts("image/png"));
+        assert!(!req.accepts("image/gif"));
+        assert_eq!(
+            req.preferred_type(&["text/html", "application/json"]),
+            Some("application/json")
+        );
+
+        // No Accept header accepts anything
+        assert!(test_request(HttpMethod::GET, "/").accepts("image/png"));
+
+        let req = request("Accept-Language", "fr-CH, fr;q=0.9, en;q=0.8, *;q=0.5");
+
+        assert_eq!(req.preferred_language(&["en-US", "fr-FR"]), Some("fr-FR"));
+        assert_eq!(req.preferred_language(&["de", "en-GB"]), Some("en-GB"));
+
+        let req = request("Accept-Charset", "iso-8859-1, utf-8;q=0.7");
+
+        assert_eq!(
+            req.preferred_charset(&["UTF-8", "ISO-8859-1"]),
+            Some("ISO-8859-1")
+        );
+        assert_eq!(req.preferred_charset(&["us-ascii"]), None);
+    }
+
+    #[test]
+    fn test_representations() {
+        let representations = || {
+            Representations::new()
+                .json(&["alice"])
+                .html("<p>alice</p>")
+                .text("alice")
+        };
+
+        let res = representations().respond(&request("Accept", "text/html, */*;q=0.1"));
+
+        assert_eq!(res.status, 200);
+        assert_eq!(
+            res.headers.get("Content-Type"),
+            Some("text/html; charset=utf-8")
+        );
+        assert_eq!(res.headers.get("Vary"), Some("Accept"));
+
+        // Ties go to the first representation
+        let res = representations().respond(&request("Accept", "*/*"));
+
+        assert_eq!(res.headers.get("Content-Type"), Some("application/json"));
+
+        let res = representations().respond(&request("Accept", "image/*"));
+
+        assert_eq!(res.status, 406);
+    }
+}
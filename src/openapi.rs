@@ -0,0 +1,473 @@
+//!
+//! OpenAPI 3 document generated from the routes of a [Router], served with a Swagger UI
+//! page.
+//!
+//! `👉 Note:` Requires the `openapi` feature
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::openapi::{OpenApi, Operation};
+//! use http_rs::router::Router;
+//! use http_rs::server::{HttpMethod, Response};
+//! use schemars::JsonSchema;
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize, JsonSchema)]
+//! struct User {
+//!     id: u64,
+//!     name: String,
+//! }
+//!
+//! let mut router = Router::new();
+//!
+//! router.get("/users/:id", |_| Response::new(200));
+//!
+//! // GET /openapi.json answers the document, GET /docs the Swagger UI page
+//! OpenApi::new("Users", "1.0.0")
+//!     .operation(
+//!         HttpMethod::GET,
+//!         "/users/:id",
+//!         Operation::new().summary("Get a user").response::<User>(200),
+//!     )
+//!     .mount(&mut router);
+//! ```
+//!
+
+use crate::router::{normalize_pattern, Router};
+use crate::server::{HttpMethod, Response};
+use crate::status::StatusCode;
+use schemars::{generate::SchemaSettings, JsonSchema, Schema, SchemaGenerator};
+use serde_json::{json, Map, Value};
+
+///
+/// Generates the schema of a type, registering the types it refers to as components.
+///
+type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+///
+/// Documentation of one route: what it does and the shapes of its bodies.
+///
+/// Schemas are derived with `schemars` from the types given to [Operation::request] and
+/// [Operation::response], and shared through `components/schemas`.
+///
+#[derive(Debug, Clone, Default)]
+pub struct Operation {
+    summary: Option<String>,
+    description: Option<String>,
+    tags: Vec<String>,
+    request: Option<SchemaFn>,
+    responses: Vec<(u16, Option<SchemaFn>)>,
+}
+
+impl Operation {
+    ///
+    /// Creates an operation without documentation, listed with a bare `200` response.
+    ///
+    pub fn new() -> Operation {
+        Operation::default()
+    }
+
+    ///
+    /// Sets the one line summary of the operation.
+    ///
+    pub fn summary(mut self, summary: &str) -> Operation {
+        self.summary = Some(summary.to_string());
+
+        self
+    }
+
+    ///
+    /// Sets the longer description of the operation (CommonMark).
+    ///
+    pub fn description(mut self, description: &str) -> Operation {
+        self.description = Some(description.to_string());
+
+        self
+    }
+
+    ///
+    /// Adds a tag, used by Swagger UI to group operations.
+    ///
+    pub fn tag(mut self, tag: &str) -> Operation {
+        self.tags.push(tag.to_string());
+
+        self
+    }
+
+    ///
+    /// Documents a required `application/json` request body of type `T`.
+    ///
+    pub fn request<T: JsonSchema>(mut self) -> Operation {
+        self.request = Some(SchemaGenerator::subschema_for::<T>);
+
+        self
+    }
+
+    ///
+    /// Documents an `application/json` response body of type `T`.
+    ///
+    /// # Arguments
+    ///
+    /// * `status` -> Status code the body is answered with (e.g., 200)
+    ///
+    pub fn response<T: JsonSchema>(mut self, status: u16) -> Operation {
+        self.responses
+            .push((status, Some(SchemaGenerator::subschema_for::<T>)));
+
+        self
+    }
+
+    ///
+    /// Documents a response without body (e.g., `204` or `404`).
+    ///
+    pub fn empty_response(mut self, status: u16) -> Operation {
+        self.responses.push((status, None));
+
+        self
+    }
+
+    ///
+    /// Builds the OpenAPI operation object, `params` being the route's path parameters.
+    ///
+    fn document(&self, params: &[String], generator: &mut SchemaGenerator) -> Value {
+        let mut operation = Map::new();
+
+        if let Some(summary) = &self.summary {
+            operation.insert("summary".into(), json!(summary));
+        }
+
+        if let Some(description) = &self.description {
+            operation.insert("description".into(), json!(description));
+        }
+
+        if !self.tags.is_empty() {
+            operation.insert("tags".into(), json!(self.tags));
+        }
+
+        if !params.is_empty() {
+            let params: Vec<Value> = params
+                .iter()
+                .map(|name| {
+                    json!({
+                        "name": name,
+                        "in": "path",
+                        "required": true,
+                        "schema": { "type": "string" },
+                    })
+                })
+                .collect();
+
+            operation.insert("parameters".into(), json!(params));
+        }
+
+        if let Some(schema) = self.request {
+            operation.insert(
+                "requestBody".into(),
+                json!({
+                    "required": true,
+                    "content": { "application/json": { "schema": schema(generator) } },
+                }),
+            );
+        }
+
+        let mut responses = Map::new();
+        let documented: &[(u16, Option<SchemaFn>)] = if self.responses.is_empty() {
+            &[(200, None)]
+        } else {
+            &self.responses
+        };
+
+        for (status, schema) in documented {
+            let reason = StatusCode::from(*status).canonical_reason().unwrap_or("");
+            let mut response = Map::new();
+
+            response.insert("description".into(), json!(reason));
+
+            if let Some(schema) = schema {
+                response.insert(
+                    "content".into(),
+                    json!({ "application/json": { "schema": schema(generator) } }),
+                );
+            }
+
+            responses.insert(status.to_string(), Value::Object(response));
+        }
+
+        operation.insert("responses".into(), Value::Object(responses));
+
+        Value::Object(operation)
+    }
+}
+
+///
+/// OpenAPI 3 document describing a [Router].
+///
+/// Every registered route is listed with its path parameters. Routes documented with
+/// [OpenApi::operation] also get a summary and request/response schemas, the others a
+/// bare `200` response. Catch-all segments (e.g., `*path`) are listed as a single
+/// parameter, as OpenAPI can't express a parameter spanning several segments.
+///
+#[derive(Debug, Clone)]
+pub struct OpenApi {
+    title: String,
+    version: String,
+    description: Option<String>,
+    spec_path: String,
+    docs_path: String,
+    operations: Vec<(HttpMethod, String, Operation)>,
+}
+
+impl OpenApi {
+    ///
+    /// Creates a document served on `/openapi.json`, with Swagger UI on `/docs`.
+    ///
+    /// # Arguments
+    ///
+    /// * `title` -> Name of the API
+    /// * `version` -> Version of the API (not of the OpenAPI specification)
+    ///
+    pub fn new(title: &str, version: &str) -> OpenApi {
+        OpenApi {
+            title: title.to_string(),
+            version: version.to_string(),
+            description: None,
+            spec_path: "/openapi.json".to_string(),
+            docs_path: "/docs".to_string(),
+            operations: Vec::new(),
+        }
+    }
+
+    ///
+    /// Sets the description of the API (CommonMark).
+    ///
+    pub fn description(mut self, description: &str) -> OpenApi {
+        self.description = Some(description.to_string());
+
+        self
+    }
+
+    ///
+    /// Sets the paths the document and the Swagger UI page are served on.
+    ///
+    /// # Arguments
+    ///
+    /// * `spec` -> Path of the `JSON` document (e.g., "/api/openapi.json")
+    /// * `docs` -> Path of the Swagger UI page (e.g., "/api/docs")
+    ///
+    pub fn paths(mut self, spec: &str, docs: &str) -> OpenApi {
+        self.spec_path = spec.to_string();
+        self.docs_path = docs.to_string();
+
+        self
+    }
+
+    ///
+    /// Documents a route.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` -> Method of the route
+    /// * `pattern` -> Pattern the route was registered with (e.g., "/users/:id"),
+    ///   including the prefix of nested routers
+    /// * `operation` -> Documentation of the route
+    ///
+    pub fn operation(mut self, method: HttpMethod, pattern: &str, operation: Operation) -> OpenApi {
+        self.operations
+            .push((method, normalize_pattern(pattern), operation));
+
+        self
+    }
+
+    ///
+    /// Generates the document for the routes of a [Router].
+    ///
+    pub fn document(&self, router: &Router) -> Value {
+        let mut generator = SchemaGenerator::new(SchemaSettings::openapi3());
+        let mut paths = Map::new();
+
+        for (method, pattern) in router.routes() {
+            // OpenAPI has no operation for CONNECT or extension methods
+            let key = match method {
+                HttpMethod::CONNECT | HttpMethod::Other(_) => continue,
+                _ => method.as_str().to_ascii_lowercase(),
+            };
+
+            let (path, params) = openapi_path(&pattern);
+
+            let operation = self
+                .operations
+                .iter()
+                .find(|(m, p, _)| m == method && *p == pattern)
+                .map(|(_, _, operation)| operation.document(&params, &mut generator))
+                .unwrap_or_else(|| Operation::new().document(&params, &mut generator));
+
+            if let Value::Object(item) = paths.entry(path).or_insert_with(|| json!({})) {
+                item.insert(key, operation);
+            }
+        }
+
+        let mut info = Map::new();
+
+        info.insert("title".into(), json!(self.title));
+        info.insert("version".into(), json!(self.version));
+
+        if let Some(description) = &self.description {
+            info.insert("description".into(), json!(description));
+        }
+
+        json!({
+            "openapi": "3.0.3",
+            "info": info,
+            "paths": paths,
+            "components": { "schemas": generator.take_definitions(true) },
+        })
+    }
+
+    ///
+    /// Registers the document and Swagger UI routes on a [Router].
+    ///
+    /// The document is generated once, from the routes registered so far, so mount it
+    /// after every other route. The Swagger UI page loads its assets from the `unpkg`
+    /// CDN.
+    ///
+    pub fn mount(&self, router: &mut Router) {
+        let document = serde_json::to_vec(&self.document(router)).unwrap_or_default();
+        let page = swagger_ui(&self.title, &self.spec_path);
+
+        router
+            .get(&self.spec_path, move |_| {
+                Response::new(200).bytes("application/json", document.clone())
+            })
+            .get(&self.docs_path, move |_| Response::new(200).html(&page));
+    }
+}
+
+///
+/// Converts a route pattern to an OpenAPI path (`/users/:id` to `/users/{id}`), with
+/// the names of its parameters.
+///
+fn openapi_path(pattern: &str) -> (String, Vec<String>) {
+    let mut params = Vec::new();
+
+    let path = pattern
+        .split('/')
+        .map(|segment| match segment.strip_prefix([':', '*']) {
+            Some(name) => {
+                params.push(name.to_string());
+                format!("{{{}}}", name)
+            }
+            None => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+
+    (path, params)
+}
+
+///
+/// Swagger UI page rendering the document served on `spec_path`.
+///
+fn swagger_ui(title: &str, spec_path: &str) -> String {
+    let title = title
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+
+    format!(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+<div id="swagger-ui"></div>
+<script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+<script>SwaggerUIBundle({{ url: {url}, dom_id: "#swagger-ui" }});</script>
+</body>
+</html>
+"##,
+        title = title,
+        url = json!(spec_path),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::test_request;
+    use serde::Serialize;
+
+    #[derive(Serialize, JsonSchema)]
+    #[allow(dead_code)]
+    struct User {
+        id: u64,
+        name: String,
+    }
+
+    #[test]
+    fn test_openapi_document() {
+        let mut router = Router::new();
+
+        router
+            .get("/users/:id", |_| Response::new(200))
+            .post("/users", |_| Response::new(201))
+            .get("/files/*path", |_| Response::new(200));
+
+        OpenApi::new("Users", "1.0.0")
+            .operation(
+                HttpMethod::GET,
+                "users/:id/",
+                Operation::new()
+                    .summary("Get a user")
+                    .response::<User>(200)
+                    .empty_response(404),
+            )
+            .operation(
+                HttpMethod::POST,
+                "/users",
+                Operation::new().request::<User>().response::<User>(201),
+            )
+            .mount(&mut router);
+
+        let res = router.handle(test_request(HttpMethod::GET, "/openapi.json"));
+        let document: Value = serde_json::from_slice(res.body.as_bytes().unwrap()).unwrap();
+
+        let get = &document["paths"]["/users/{id}"]["get"];
+
+        assert_eq!(get["summary"], "Get a user");
+        assert_eq!(get["parameters"][0]["name"], "id");
+        assert_eq!(get["parameters"][0]["in"], "path");
+        assert_eq!(
+            get["responses"]["200"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/User"
+        );
+        assert_eq!(get["responses"]["404"]["description"], "Not Found");
+
+        let post = &document["paths"]["/users"]["post"];
+
+        assert_eq!(
+            post["requestBody"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/User"
+        );
+
+        // Undocumented routes are still listed
+        assert_eq!(
+            document["paths"]["/files/{path}"]["get"]["responses"]["200"]["description"],
+            "OK"
+        );
+        assert_eq!(
+            document["components"]["schemas"]["User"]["properties"]["name"]["type"],
+            "string"
+        );
+        assert!(document["paths"]["/openapi.json"].is_null());
+
+        let res = router.handle(test_request(HttpMethod::GET, "/docs"));
+
+        assert_eq!(res.status, 200);
+        assert!(String::from_utf8_lossy(res.body.as_bytes().unwrap())
+            .contains(r#"url: "/openapi.json""#));
+    }
+}
@@ -0,0 +1,183 @@
+//!
+//! A fixed size pool of worker threads used by [crate::server::Server::serve], and the
+//! count of connections it is handling.
+//!
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Arc, Condvar, Mutex, MutexGuard},
+    thread,
+};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+///
+/// Fixed size pool of worker threads pulling jobs from a shared queue.
+///
+pub(crate) struct ThreadPool {
+    workers: Vec<thread::JoinHandle<()>>,
+    sender: Option<mpsc::Sender<Job>>,
+}
+
+impl ThreadPool {
+    ///
+    /// Spawns a pool with `size` worker threads.
+    ///
+    /// `👉 Note:` A `size` of `0` is treated as `1`
+    ///
+    pub(crate) fn new(size: usize) -> ThreadPool {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..size.max(1))
+            .map(|_| {
+                let receiver = Arc::clone(&receiver);
+
+                thread::spawn(move || loop {
+                    // Release the lock before running the job so other workers can pick up work
+                    let job = match receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => return,
+                    };
+
+                    // A panicking job must not shrink the pool
+                    match job {
+                        Ok(job) => {
+                            let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                        }
+                        Err(_) => return,
+                    }
+                })
+            })
+            .collect();
+
+        ThreadPool {
+            workers,
+            sender: Some(sender),
+        }
+    }
+
+    ///
+    /// Queues a job to run on the next free worker.
+    ///
+    pub(crate) fn execute<F: FnOnce() + Send + 'static>(&self, job: F) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(Box::new(job));
+        }
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        // Closing the channel makes every worker exit once the queue is drained
+        drop(self.sender.take());
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+///
+/// Number of connections open at once, bounded by [crate::server::Server::max_connections].
+///
+#[derive(Debug, Default)]
+pub(crate) struct Connections {
+    open: Mutex<usize>,
+    closed: Condvar,
+}
+
+impl Connections {
+    fn open(&self) -> MutexGuard<'_, usize> {
+        self.open.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    ///
+    /// Blocks until fewer than `max` connections are open.
+    ///
+    pub(crate) fn wait_below(&self, max: usize) {
+        let mut open = self.open();
+
+        while *open >= max {
+            open = self.closed.wait(open).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    ///
+    /// Counts a new connection, unless `max` are already open.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<OpenConnection>` -> Guard uncounting the connection when dropped, or
+    ///   None if at capacity
+    ///
+    pub(crate) fn try_open(self: &Arc<Self>, max: usize) -> Option<OpenConnection> {
+        let mut open = self.open();
+
+        if *open >= max {
+            return None;
+        }
+
+        *open += 1;
+
+        Some(OpenConnection(Arc::clone(self)))
+    }
+}
+
+///
+/// A connection counted in [Connections], until dropped.
+///
+#[derive(Debug)]
+pub(crate) struct OpenConnection(Arc<Connections>);
+
+impl Drop for OpenConnection {
+    fn drop(&mut self) {
+        *self.0.open() -= 1;
+        self.0.closed.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_pool_runs_all_jobs() {
+        let counter = Arc::new(AtomicUsize::new(0));
+
+        {
+            let pool = ThreadPool::new(4);
+
+            for _ in 0..32 {
+                let counter = Arc::clone(&counter);
+
+                pool.execute(move || {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                });
+            }
+        }
+
+        assert_eq!(counter.load(Ordering::SeqCst), 32);
+    }
+
+    #[test]
+    fn test_connections_limit() {
+        let connections = Arc::new(Connections::default());
+
+        let first = connections.try_open(2).unwrap();
+        let _second = connections.try_open(2).unwrap();
+
+        assert!(connections.try_open(2).is_none());
+
+        let waiter = {
+            let connections = Arc::clone(&connections);
+            thread::spawn(move || connections.wait_below(2))
+        };
+
+        drop(first);
+        waiter.join().unwrap();
+
+        assert!(connections.try_open(2).is_some());
+    }
+}
@@ -0,0 +1,224 @@
+//!
+//! Token-bucket rate limiting per client.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::rate_limit::RateLimit;
+//! use http_rs::router::Router;
+//! use std::time::Duration;
+//!
+//! let mut router = Router::new();
+//!
+//! // 100 requests per minute per client IP, in bursts of up to 100
+//! router.layer(RateLimit::new(100, Duration::from_secs(60)));
+//!
+//! // Or per API key
+//! router.layer(
+//!     RateLimit::new(10, Duration::from_secs(1))
+//!         .key(|req| req.headers.get("X-Api-Key").map(str::to_string)),
+//! );
+//! ```
+//!
+
+use crate::middleware::{Middleware, Next};
+use crate::server::{Request, Response};
+use crate::status::StatusCode;
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+///
+/// Callback extracting the key requests are counted under.
+///
+type KeyFn = Box<dyn Fn(&Request) -> Option<String> + Send + Sync>;
+
+///
+/// Tokens left for one key, refilled continuously over time.
+///
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[derive(Debug)]
+struct Buckets {
+    buckets: HashMap<String, Bucket>,
+    pruned: Instant,
+}
+
+///
+/// [Middleware] limiting how many requests each client can make over a window,
+/// answering 429 with `Retry-After` once exceeded.
+///
+/// Every key has a bucket of `requests` tokens refilled at `requests / window`, so
+/// clients may burst up to `requests` and are then held to the average rate. Requests
+/// are keyed by client IP unless [RateLimit::key] is set, and requests without a key
+/// are not limited.
+///
+/// Buckets are shared by all worker threads behind a [Mutex].
+///
+pub struct RateLimit {
+    capacity: f64,
+    rate: f64,
+    window: Duration,
+    key: KeyFn,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimit {
+    ///
+    /// Creates the rate limiting [Middleware].
+    ///
+    /// # Arguments
+    ///
+    /// * `requests` -> Requests allowed per window, also the largest burst
+    /// * `window` -> Duration over which `requests` are allowed
+    ///
+    pub fn new(requests: u32, window: Duration) -> RateLimit {
+        let capacity = f64::from(requests.max(1));
+
+        RateLimit {
+            capacity,
+            rate: capacity / window.as_secs_f64().max(f64::EPSILON),
+            window,
+            key: Box::new(|req| req.client_ip().map(|ip| ip.to_string())),
+            buckets: Mutex::new(Buckets {
+                buckets: HashMap::new(),
+                pruned: Instant::now(),
+            }),
+        }
+    }
+
+    ///
+    /// Sets a custom key extractor, replacing the client IP.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` -> Callback returning the key of a request, or None to let it through
+    ///   unlimited
+    ///
+    pub fn key<F>(mut self, key: F) -> RateLimit
+    where
+        F: Fn(&Request) -> Option<String> + Send + Sync + 'static,
+    {
+        self.key = Box::new(key);
+
+        self
+    }
+
+    fn buckets(&self) -> MutexGuard<'_, Buckets> {
+        self.buckets.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    ///
+    /// Takes a token from the bucket of `key`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Duration>` -> Ok if the request is allowed, or the time until a
+    ///   token is available
+    ///
+    fn acquire(&self, key: String, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets();
+
+        // Idle buckets are full again, forget them so the map doesn't grow unbounded
+        if now.duration_since(buckets.pruned) >= self.window {
+            buckets
+                .buckets
+                .retain(|_, bucket| now.duration_since(bucket.updated) < self.window);
+            buckets.pruned = now;
+        }
+
+        let bucket = buckets.buckets.entry(key).or_insert(Bucket {
+            tokens: self.capacity,
+            updated: now,
+        });
+
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+
+            return Ok(());
+        }
+
+        Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+    }
+}
+
+impl Middleware for RateLimit {
+    fn handle(&self, req: Request, next: Next<'_>) -> Response {
+        let Some(key) = (self.key)(&req) else {
+            return next.run(req);
+        };
+
+        match self.acquire(key, Instant::now()) {
+            Ok(()) => next.run(req),
+            Err(wait) => {
+                let status = StatusCode::TOO_MANY_REQUESTS;
+                let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+
+                Response::new(status)
+                    .header("Retry-After", &retry_after.to_string())
+                    .json(&status.canonical_reason())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::router::Router;
+    use crate::server::{test_request, HttpMethod};
+
+    fn request(ip: &str) -> Request {
+        let mut req = test_request(HttpMethod::GET, "/");
+        req.remote_addr = Some(format!("{}:5000", ip).parse().unwrap());
+
+        req
+    }
+
+    #[test]
+    fn test_rate_limit_per_ip() {
+        let mut router = Router::new();
+
+        router
+            .layer(RateLimit::new(2, Duration::from_secs(60)))
+            .get("/", |_| Response::new(200));
+
+        assert_eq!(router.handle(request("10.0.0.1")).status, 200);
+        assert_eq!(router.handle(request("10.0.0.1")).status, 200);
+
+        let res = router.handle(request("10.0.0.1"));
+
+        assert_eq!(res.status, 429);
+        assert_eq!(res.headers.get("Retry-After"), Some("30"));
+
+        // Other clients have their own bucket
+        assert_eq!(router.handle(request("10.0.0.2")).status, 200);
+    }
+
+    #[test]
+    fn test_rate_limit_refill() {
+        let limit = RateLimit::new(1, Duration::from_secs(8));
+        let now = Instant::now();
+
+        assert_eq!(limit.acquire("a".to_string(), now), Ok(()));
+        assert_eq!(
+            limit.acquire("a".to_string(), now + Duration::from_secs(4)),
+            Err(Duration::from_secs(4))
+        );
+        assert_eq!(
+            limit.acquire("a".to_string(), now + Duration::from_secs(8)),
+            Ok(())
+        );
+    }
+}
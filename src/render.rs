@@ -0,0 +1,397 @@
+//!
+//! Server-side template rendering behind a [TemplateEngine] trait, with a built-in
+//! substitution engine and adapters for `tera` and `askama` behind their features.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::render::{RenderError, Templates};
+//! use http_rs::router::Router;
+//! use http_rs::server::{Request, Response};
+//! use serde::Serialize;
+//! use std::sync::Arc;
+//!
+//! #[derive(Serialize)]
+//! struct Page {
+//!     title: String,
+//!     user: User,
+//! }
+//!
+//! #[derive(Serialize)]
+//! struct User {
+//!     name: String,
+//! }
+//!
+//! // templates/profile.html: `<h1>{{ title }}</h1><p>Hello {{ user.name }}</p>`
+//! let templates = Arc::new(Templates::new().dir("templates"));
+//! let mut router = Router::new();
+//!
+//! router.get("/profile", move |_: Request| -> Result<Response, RenderError> {
+//!     let page = Page {
+//!         title: "Profile".to_string(),
+//!         user: User { name: "<Alice>".to_string() },
+//!     };
+//!
+//!     Response::new(200).render(&*templates, "profile.html", &page)
+//! });
+//! ```
+//!
+
+use crate::{response::ResponseError, server::Response, static_files};
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Component, Path, PathBuf},
+};
+
+///
+/// Renders named templates with a context, see [Response::render].
+///
+pub trait TemplateEngine: Send + Sync {
+    ///
+    /// Renders the template `name` with `context`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` -> Name of the template (e.g., "page.html")
+    /// * `context` -> Values available to the template, usually an object
+    ///
+    /// # Returns
+    ///
+    /// * `Result<String, RenderError>` -> The rendered text, or why rendering failed
+    ///
+    fn render(&self, name: &str, context: &Value) -> Result<String, RenderError>;
+}
+
+///
+/// Why a template couldn't be rendered (e.g., it doesn't exist or uses an undefined
+/// variable).
+///
+/// Answers `500` when returned from a handler, without the details, which are meant for
+/// logs.
+///
+#[derive(Debug)]
+pub struct RenderError {
+    template: String,
+    message: String,
+}
+
+impl RenderError {
+    ///
+    /// Creates a [RenderError], for [TemplateEngine] implementations.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` -> Name of the template that failed
+    /// * `message` -> What went wrong
+    ///
+    pub fn new(template: &str, message: impl fmt::Display) -> RenderError {
+        RenderError {
+            template: template.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    ///
+    /// Returns the name of the template that failed.
+    ///
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to render {}: {}", self.template, self.message)
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+impl ResponseError for RenderError {}
+
+///
+/// Built-in [TemplateEngine] substituting `{{ name }}` with HTML-escaped values and
+/// `{{{ name }}}` with raw ones.
+///
+/// Names are paths into the context (e.g., `user.name`, `items.0`). Strings render as
+/// is, numbers and booleans as text, `null` as nothing and arrays and objects as `JSON`.
+/// An undefined name fails the rendering rather than silently rendering nothing.
+///
+/// Templates are added from strings, or read from a directory on every render so edits
+/// show up without restarting.
+///
+#[derive(Debug, Default)]
+pub struct Templates {
+    dir: Option<PathBuf>,
+    sources: HashMap<String, String>,
+}
+
+impl Templates {
+    ///
+    /// Creates an engine without templates.
+    ///
+    pub fn new() -> Templates {
+        Templates::default()
+    }
+
+    ///
+    /// Reads templates not added with [Templates::add] from files below `dir`, the name
+    /// being the relative path (e.g., "emails/welcome.html").
+    ///
+    pub fn dir(mut self, dir: impl Into<PathBuf>) -> Templates {
+        self.dir = Some(dir.into());
+
+        self
+    }
+
+    ///
+    /// Adds a template from a string, taking precedence over a file of the same name.
+    ///
+    pub fn add(mut self, name: &str, source: &str) -> Templates {
+        self.sources.insert(name.to_string(), source.to_string());
+
+        self
+    }
+
+    fn source(&self, name: &str) -> Result<String, RenderError> {
+        if let Some(source) = self.sources.get(name) {
+            return Ok(source.clone());
+        }
+
+        let not_found = || RenderError::new(name, "template not found");
+        let dir = self.dir.as_ref().ok_or_else(not_found)?;
+
+        // Names must stay below the directory
+        let relative = Path::new(name);
+        if !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(not_found());
+        }
+
+        fs::read_to_string(dir.join(relative)).map_err(|e| RenderError::new(name, e))
+    }
+}
+
+impl TemplateEngine for Templates {
+    fn render(&self, name: &str, context: &Value) -> Result<String, RenderError> {
+        let source = self.source(name)?;
+        let mut rendered = String::with_capacity(source.len());
+        let mut rest = source.as_str();
+
+        while let Some(start) = rest.find("{{") {
+            rendered.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let (raw, open, close) = match rest.starts_with("{{{") {
+                true => (true, "{{{", "}}}"),
+                false => (false, "{{", "}}"),
+            };
+
+            let end = rest
+                .find(close)
+                .ok_or_else(|| RenderError::new(name, format!("unclosed `{}`", open)))?;
+
+            let path = rest[open.len()..end].trim();
+            let value = lookup(context, path)
+                .ok_or_else(|| RenderError::new(name, format!("undefined `{}`", path)))?;
+
+            let text = match value {
+                Value::String(text) => text.clone(),
+                Value::Null => String::new(),
+                value => value.to_string(),
+            };
+
+            match raw {
+                true => rendered.push_str(&text),
+                false => rendered.push_str(&static_files::escape_html(&text)),
+            }
+
+            rest = &rest[end + close.len()..];
+        }
+
+        rendered.push_str(rest);
+
+        Ok(rendered)
+    }
+}
+
+///
+/// Follows a dotted path through objects and arrays.
+///
+fn lookup<'a>(context: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(context, |value, key| match value {
+        Value::Object(map) => map.get(key),
+        Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+///
+/// Renders templates of a `tera` instance (requires the `tera` feature). The context
+/// must serialize to an object.
+///
+#[cfg(feature = "tera")]
+impl TemplateEngine for tera::Tera {
+    fn render(&self, name: &str, context: &Value) -> Result<String, RenderError> {
+        let context =
+            tera::Context::from_value(context.clone()).map_err(|e| RenderError::new(name, e))?;
+
+        tera::Tera::render(self, name, &context).map_err(|e| {
+            // The top-level error only names the template, the cause is in the sources
+            let mut message = e.to_string();
+            let mut source = std::error::Error::source(&e);
+
+            while let Some(cause) = source {
+                message = format!("{}: {}", message, cause);
+                source = cause.source();
+            }
+
+            RenderError::new(name, message)
+        })
+    }
+}
+
+impl Response {
+    ///
+    /// Renders a template into the [Response] body, typed from the template name's
+    /// extension (e.g., `text/html` for "page.html").
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` -> The [TemplateEngine] (e.g., [Templates], or a `tera::Tera`)
+    /// * `name` -> Name of the template
+    /// * `context` -> Values available to the template. **Must implement Serialize.**
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Response, RenderError>` -> The modified response, or why rendering
+    ///   failed, which handlers can return as a `500`
+    ///
+    pub fn render<E, T>(self, engine: &E, name: &str, context: &T) -> Result<Response, RenderError>
+    where
+        E: TemplateEngine + ?Sized,
+        T: Serialize,
+    {
+        let context = serde_json::to_value(context).map_err(|e| RenderError::new(name, e))?;
+        let rendered = engine.render(name, &context)?;
+        let content_type = static_files::guess_mime(Path::new(name));
+
+        Ok(self.bytes(content_type, rendered.into_bytes()))
+    }
+
+    ///
+    /// Renders an `askama` template into the [Response] body as HTML (requires the
+    /// `askama` feature).
+    ///
+    /// # Arguments
+    ///
+    /// * `template` -> The compiled template, holding its context
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Response, RenderError>` -> The modified response, or why rendering
+    ///   failed, which handlers can return as a `500`
+    ///
+    #[cfg(feature = "askama")]
+    pub fn render_askama<T: askama::Template>(self, template: &T) -> Result<Response, RenderError> {
+        let rendered = template
+            .render()
+            .map_err(|e| RenderError::new(std::any::type_name::<T>(), e))?;
+
+        Ok(self.html(&rendered))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TemplateEngine, Templates};
+    use crate::server::Response;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_templates() {
+        let dir = std::env::temp_dir().join(format!("http_rs_templates_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("page.html"), "<h1>{{ title }}</h1>{{{ footer }}}").unwrap();
+
+        let templates = Templates::new().dir(&dir).add(
+            "list.txt",
+            "{{ items.1.name }} of {{count}}, {{ missing_ok }}",
+        );
+
+        let context = json!({
+            "title": "<Tom & Jerry>",
+            "footer": "<footer/>",
+            "items": [{ "name": "a" }, { "name": "b" }],
+            "count": 2,
+            "missing_ok": null,
+        });
+
+        let res = Response::new(200)
+            .render(&templates, "page.html", &context)
+            .unwrap();
+        assert_eq!(
+            res.headers.get("Content-Type"),
+            Some("text/html; charset=utf-8")
+        );
+        assert_eq!(
+            res.body.as_bytes().unwrap(),
+            b"<h1>&lt;Tom &amp; Jerry&gt;</h1><footer/>"
+        );
+
+        assert_eq!(templates.render("list.txt", &context).unwrap(), "b of 2, ");
+
+        let err = templates.render("list.txt", &json!({})).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Failed to render list.txt: undefined `items.1.name`"
+        );
+
+        assert!(templates.render("../page.html", &context).is_err());
+        assert!(templates.render("other.html", &context).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "tera")]
+    #[test]
+    fn test_render_tera() {
+        let mut tera = tera::Tera::default();
+        tera.add_raw_template("hello.html", "Hello {{ name }}{% if admin %}!{% endif %}")
+            .unwrap();
+
+        let res = Response::new(200)
+            .render(
+                &tera,
+                "hello.html",
+                &json!({ "name": "<Bob>", "admin": true }),
+            )
+            .unwrap();
+        assert_eq!(res.body.as_bytes().unwrap(), b"Hello &lt;Bob&gt;!");
+
+        let err = Response::new(200)
+            .render(&tera, "hello.html", &json!({}))
+            .unwrap_err();
+        assert!(err.to_string().contains("name"));
+    }
+
+    #[cfg(feature = "askama")]
+    #[test]
+    fn test_render_askama() {
+        #[derive(askama::Template)]
+        #[template(source = "Hi {{ name }}", ext = "html")]
+        struct Hello<'a> {
+            name: &'a str,
+        }
+
+        let res = Response::new(200)
+            .render_askama(&Hello { name: "<Eve>" })
+            .unwrap();
+        assert_eq!(res.body.as_bytes().unwrap(), b"Hi &#60;Eve&#62;");
+    }
+}
@@ -0,0 +1,260 @@
+//!
+//! Conversions of handler return values into responses, so handlers can return
+//! whatever is convenient and propagate errors with `?`.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::extract::Json;
+//! use http_rs::response::ResponseError;
+//! use http_rs::router::Router;
+//! use http_rs::server::{Request, Response};
+//! use http_rs::status::StatusCode;
+//! use serde::Deserialize;
+//! use std::fmt;
+//!
+//! #[derive(Deserialize)]
+//! struct Login {
+//!     user: String,
+//! }
+//!
+//! #[derive(Debug)]
+//! enum AppError {
+//!     UnknownUser,
+//!     Invalid(http_rs::Error),
+//! }
+//!
+//! impl fmt::Display for AppError {
+//!     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+//!         match self {
+//!             AppError::UnknownUser => f.write_str("Unknown user"),
+//!             AppError::Invalid(e) => e.fmt(f),
+//!         }
+//!     }
+//! }
+//!
+//! impl From<http_rs::Error> for AppError {
+//!     fn from(e: http_rs::Error) -> AppError {
+//!         AppError::Invalid(e)
+//!     }
+//! }
+//!
+//! // Rendered as `{"error": "..."}` with the status
+//! impl ResponseError for AppError {
+//!     fn status(&self) -> StatusCode {
+//!         match self {
+//!             AppError::UnknownUser => StatusCode::UNAUTHORIZED,
+//!             AppError::Invalid(e) => e.status(),
+//!         }
+//!     }
+//! }
+//!
+//! let mut router = Router::new();
+//!
+//! router.post("/login", |req: Request| -> Result<Response, AppError> {
+//!     let Json(login) = req.extract::<Json<Login>>()?;
+//!
+//!     match login.user.as_str() {
+//!         "admin" => Ok(Response::new(200).json(&"Welcome")),
+//!         _ => Err(AppError::UnknownUser),
+//!     }
+//! });
+//!
+//! router.get("/health", |_| StatusCode::NO_CONTENT);
+//! router.get("/hello", |_| (StatusCode::ACCEPTED, "Hello"));
+//! ```
+//!
+
+use crate::{error::Error, extract::Json, server::Response, status::StatusCode};
+use serde::Serialize;
+use std::{fmt, io};
+
+///
+/// Values a handler can return, converted into the [Response] sent to the client.
+///
+/// Implemented for [Response], [StatusCode] (an empty body), `String` and `&'static str`
+/// (`text/plain`), [Json] (`application/json`), `(StatusCode, T)` overriding the status
+/// of `T`, and `Result<T, E>` where `E` is a [ResponseError].
+///
+pub trait IntoResponse {
+    ///
+    /// Converts the value into a [Response].
+    ///
+    fn into_response(self) -> Response;
+}
+
+///
+/// Errors a handler can return, rendered as a [Response] with their [status] and a
+/// `JSON` body `{"error": "..."}` holding their message.
+///
+/// `👉 Note:` Server errors (`5xx`) only show the status reason, so internal details
+/// (paths, queries, ...) don't leak to clients. Override [error_response] to change that
+///
+/// [status]: ResponseError::status
+/// [error_response]: ResponseError::error_response
+///
+pub trait ResponseError: fmt::Display {
+    ///
+    /// Returns the status to answer with, `500 Internal Server Error` by default.
+    ///
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+
+    ///
+    /// Renders the error as a [Response].
+    ///
+    fn error_response(&self) -> Response {
+        let status = self.status();
+
+        let message = match status.is_server_error() {
+            true => status
+                .canonical_reason()
+                .unwrap_or("Server Error")
+                .to_string(),
+            false => self.to_string(),
+        };
+
+        Response::new(status).json(&serde_json::json!({ "error": message }))
+    }
+}
+
+impl IntoResponse for Response {
+    fn into_response(self) -> Response {
+        self
+    }
+}
+
+impl IntoResponse for StatusCode {
+    fn into_response(self) -> Response {
+        Response::new(self)
+    }
+}
+
+impl IntoResponse for String {
+    fn into_response(self) -> Response {
+        Response::new(200).text(&self)
+    }
+}
+
+impl IntoResponse for &'static str {
+    fn into_response(self) -> Response {
+        Response::new(200).text(self)
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        Response::new(200).json(&self.0)
+    }
+}
+
+impl<T: IntoResponse> IntoResponse for (StatusCode, T) {
+    fn into_response(self) -> Response {
+        let mut res = self.1.into_response();
+        res.status = self.0.as_u16();
+        res.reason = None;
+
+        res
+    }
+}
+
+impl<T: IntoResponse, E: ResponseError> IntoResponse for Result<T, E> {
+    fn into_response(self) -> Response {
+        match self {
+            Ok(value) => value.into_response(),
+            Err(e) => e.error_response(),
+        }
+    }
+}
+
+///
+/// Answers with [Error::status], e.g. `400` for an invalid [Json] body.
+///
+impl ResponseError for Error {
+    fn status(&self) -> StatusCode {
+        Error::status(self)
+    }
+}
+
+///
+/// Answers `404` for missing files, `403` for denied access and `500` otherwise.
+///
+impl ResponseError for io::Error {
+    fn status(&self) -> StatusCode {
+        match self.kind() {
+            io::ErrorKind::NotFound => StatusCode::NOT_FOUND,
+            io::ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IntoResponse, ResponseError};
+    use crate::{
+        error::Error,
+        extract::Json,
+        router::Router,
+        server::{test_request, HttpMethod, Request, Response},
+        status::StatusCode,
+    };
+    use std::{fmt, io};
+
+    #[derive(Debug)]
+    struct Database;
+
+    impl fmt::Display for Database {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("connection to db.internal:5432 refused")
+        }
+    }
+
+    impl ResponseError for Database {}
+
+    fn body(res: &Response) -> &str {
+        std::str::from_utf8(res.body.as_bytes().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_into_response() {
+        let mut router = Router::new();
+        router.get("/status", |_| StatusCode::NO_CONTENT);
+        router.get("/text", |_| (StatusCode::CREATED, "created"));
+        router.get("/json", |_| Json(vec![1, 2]));
+        router.get("/db", |_| -> Result<Response, Database> { Err(Database) });
+        router.post("/users", |req: Request| -> Result<String, Error> {
+            let Json(name) = req.extract::<Json<String>>()?;
+
+            Ok(name)
+        });
+
+        let get = |target| router.handle(test_request(HttpMethod::GET, target));
+
+        assert_eq!(get("/status").status, 204);
+
+        let res = get("/text");
+        assert_eq!((res.status, body(&res)), (201, "created"));
+        assert_eq!(
+            res.headers.get("Content-Type"),
+            Some("text/plain; charset=utf-8")
+        );
+
+        assert_eq!(body(&get("/json")), "[1,2]");
+
+        let res = get("/db");
+        assert_eq!(res.status, 500);
+        assert_eq!(body(&res), r#"{"error":"Internal Server Error"}"#);
+
+        let res = router.handle(test_request(HttpMethod::POST, "/users"));
+        assert_eq!(res.status, 415);
+        assert_eq!(
+            body(&res),
+            r#"{"error":"Unsupported media type, expected application/json"}"#
+        );
+
+        let res = Err::<Response, _>(io::Error::from(io::ErrorKind::NotFound)).into_response();
+        assert_eq!(res.status, 404);
+    }
+}
@@ -0,0 +1,792 @@
+//!
+//! Request routing with path parameters.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::router::Router;
+//! use http_rs::server::{Request, Response};
+//!
+//! let mut router = Router::new();
+//!
+//! router.get("/users/:id", |req: Request| {
+//!     let id = req.params.get("id").cloned().unwrap_or_default();
+//!
+//!     Response::new(200).json(&id)
+//! });
+//! ```
+//!
+
+use crate::body::Body;
+use crate::middleware::{Middleware, Next};
+use crate::response::IntoResponse;
+use crate::server::{percent_decode, HttpMethod, Params, Request, Response};
+use std::{sync::Arc, time::Duration};
+
+///
+/// Anything that can turn a [Request] into a [Response].
+///
+/// Implemented for every `Fn(Request) -> R` closure or function where `R` is
+/// [IntoResponse] (e.g., a [Response], or a `Result` with a
+/// [crate::response::ResponseError]), so handlers rarely need to implement this by hand.
+///
+pub trait Handler: Send + Sync + 'static {
+    ///
+    /// Handles the [Request] and produces a [Response].
+    ///
+    fn call(&self, req: Request) -> Response;
+
+    ///
+    /// Returns the [RouteLimits] overriding the server's for the body of `req`, asked
+    /// once its head is parsed and before any of its body is read.
+    ///
+    fn route_limits(&self, _req: &Request) -> Option<RouteLimits> {
+        None
+    }
+}
+
+impl<F, R> Handler for F
+where
+    F: Fn(Request) -> R + Send + Sync + 'static,
+    R: IntoResponse,
+{
+    fn call(&self, req: Request) -> Response {
+        self(req).into_response()
+    }
+}
+
+///
+/// A single segment of a route pattern.
+///
+enum Segment {
+    ///
+    /// Literal segment which must match exactly (e.g., `users`)
+    ///
+    Static(String),
+
+    ///
+    /// Named segment which matches anything (e.g., `:id`)
+    ///
+    Param(String),
+
+    ///
+    /// Final segment which matches the rest of the path, possibly empty (e.g., `*path`)
+    ///
+    CatchAll(String),
+}
+
+///
+/// [Handler] of a nested route, run behind the [Middleware] of the [Router] it was
+/// registered on.
+///
+struct Layered {
+    middleware: Arc<[Box<dyn Middleware>]>,
+    handler: Box<dyn Handler>,
+}
+
+impl Handler for Layered {
+    fn call(&self, req: Request) -> Response {
+        Next::new(&self.middleware, &|req| self.handler.call(req)).run(req)
+    }
+}
+
+///
+/// Body size and read timeout overriding those of the [crate::server::Server] for the
+/// requests matching a route pattern, see [Router::limits].
+///
+/// The body size and read timeout apply while the body is read, over HTTP/1.x as well
+/// as on HTTP/2 streams, where the timeout bounds each pause between `DATA` frames.
+///
+/// # Example
+///
+/// ```rust, no_run
+/// use http_rs::router::{RouteLimits, Router};
+/// use std::time::Duration;
+///
+/// let mut router = Router::new();
+///
+/// router.limits(
+///     "/upload",
+///     RouteLimits::new()
+///         .max_body_size(1024 * 1024 * 1024)
+///         .read_timeout(Duration::from_secs(120)),
+/// );
+/// ```
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RouteLimits {
+    pub(crate) max_body_size: Option<usize>,
+    pub(crate) read_timeout: Option<Duration>,
+}
+
+impl RouteLimits {
+    ///
+    /// Creates [RouteLimits] keeping the server's limits until one is set.
+    ///
+    pub fn new() -> RouteLimits {
+        RouteLimits::default()
+    }
+
+    ///
+    /// Sets the largest request body accepted on the route, see
+    /// [crate::server::Server::max_body_size].
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` -> Maximum body size in bytes, after chunked decoding
+    ///
+    pub fn max_body_size(mut self, bytes: usize) -> RouteLimits {
+        self.max_body_size = Some(bytes);
+
+        self
+    }
+
+    ///
+    /// Sets how long each read of a request body may block on the route, see
+    /// [crate::server::Server::read_timeout].
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` -> Maximum time a single body read may block
+    ///
+    pub fn read_timeout(mut self, timeout: Duration) -> RouteLimits {
+        self.read_timeout = Some(timeout).filter(|t| !t.is_zero());
+
+        self
+    }
+}
+
+///
+/// A registered route, its parsed pattern and the [Handler] it dispatches to.
+///
+struct Route {
+    method: HttpMethod,
+    segments: Vec<Segment>,
+    handler: Box<dyn Handler>,
+}
+
+///
+/// Dispatches [Request]s to [Handler]s by [HttpMethod] and route pattern.
+///
+/// Patterns are `/` separated, segments starting with `:` capture the matching path
+/// segment into [Request::params], and a final segment starting with `*` captures the
+/// rest of the path. Routes are matched in registration order, and
+/// other routers can be mounted under a prefix with [Router::nest].
+///
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+    limits: Vec<(Vec<Segment>, RouteLimits)>,
+    middleware: Vec<Box<dyn Middleware>>,
+    not_found: Option<Box<dyn Handler>>,
+}
+
+impl Router {
+    ///
+    /// Creates an empty [Router].
+    ///
+    pub fn new() -> Router {
+        Router {
+            routes: Vec::new(),
+            limits: Vec::new(),
+            middleware: Vec::new(),
+            not_found: None,
+        }
+    }
+
+    ///
+    /// Registers a [Handler] for the given [HttpMethod] and route pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` -> The [HttpMethod] to match
+    /// * `pattern` -> Route pattern (e.g., "/users/:id" or "/static/*path")
+    /// * `handler` -> The [Handler] invoked on a match
+    ///
+    pub fn route<H: Handler>(
+        &mut self,
+        method: HttpMethod,
+        pattern: &str,
+        handler: H,
+    ) -> &mut Router {
+        self.routes.push(Route {
+            method,
+            segments: parse_pattern(pattern),
+            handler: Box::new(handler),
+        });
+
+        self
+    }
+
+    ///
+    /// Registers a [Handler] for `GET` requests on the route pattern.
+    ///
+    pub fn get<H: Handler>(&mut self, pattern: &str, handler: H) -> &mut Router {
+        self.route(HttpMethod::GET, pattern, handler)
+    }
+
+    ///
+    /// Registers a [Handler] for `POST` requests on the route pattern.
+    ///
+    pub fn post<H: Handler>(&mut self, pattern: &str, handler: H) -> &mut Router {
+        self.route(HttpMethod::POST, pattern, handler)
+    }
+
+    ///
+    /// Registers a [Handler] for `PUT` requests on the route pattern.
+    ///
+    pub fn put<H: Handler>(&mut self, pattern: &str, handler: H) -> &mut Router {
+        self.route(HttpMethod::PUT, pattern, handler)
+    }
+
+    ///
+    /// Registers a [Handler] for `DELETE` requests on the route pattern.
+    ///
+    pub fn delete<H: Handler>(&mut self, pattern: &str, handler: H) -> &mut Router {
+        self.route(HttpMethod::DELETE, pattern, handler)
+    }
+
+    ///
+    /// Registers a [Handler] for `PATCH` requests on the route pattern.
+    ///
+    pub fn patch<H: Handler>(&mut self, pattern: &str, handler: H) -> &mut Router {
+        self.route(HttpMethod::PATCH, pattern, handler)
+    }
+
+    ///
+    /// Sets the [Handler] for requests matching no route, replacing the default JSON
+    /// `404`.
+    ///
+    /// `👉 Note:` A nested [Router]'s not-found handler is dropped by [Router::nest],
+    /// only the one of the outermost router is used
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` -> The [Handler] invoked when nothing matches
+    ///
+    pub fn not_found<H: Handler>(&mut self, handler: H) -> &mut Router {
+        self.not_found = Some(Box::new(handler));
+
+        self
+    }
+
+    ///
+    /// Overrides the server's body size limit and body read timeout for requests
+    /// matching the route pattern, whatever their method (e.g., large uploads only on
+    /// `/upload`). The first matching pattern applies, in registration order.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` -> Route pattern (e.g., "/upload" or "/files/*path")
+    /// * `limits` -> The [RouteLimits] applied to matching requests
+    ///
+    pub fn limits(&mut self, pattern: &str, limits: RouteLimits) -> &mut Router {
+        self.limits.push((parse_pattern(pattern), limits));
+
+        self
+    }
+
+    ///
+    /// Mounts the routes of another [Router] under a path prefix.
+    ///
+    /// The nested routes and [RouteLimits] keep their place in the registration order,
+    /// and their handlers see the full path in [Request::route]. The nested [Router]'s own
+    /// [Middleware] only wraps requests matching one of its routes, inside the
+    /// middleware of this one.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` -> Path prefix, which may contain `:param` segments (e.g., "/api/v1")
+    /// * `router` -> The [Router] whose routes are mounted
+    ///
+    /// # Example
+    ///
+    /// ```rust, no_run
+    /// use http_rs::router::Router;
+    /// use http_rs::server::Response;
+    ///
+    /// let mut users = Router::new();
+    /// users.get("/", |_| Response::new(200)).get("/:id", |_| Response::new(200));
+    ///
+    /// let mut router = Router::new();
+    /// router.nest("/api/v1/users", users);
+    /// ```
+    ///
+    pub fn nest(&mut self, prefix: &str, router: Router) -> &mut Router {
+        let middleware: Arc<[Box<dyn Middleware>]> = router.middleware.into();
+
+        for route in router.routes {
+            let mut segments = parse_pattern(prefix);
+            segments.extend(route.segments);
+
+            let handler: Box<dyn Handler> = match middleware.is_empty() {
+                true => route.handler,
+                false => Box::new(Layered {
+                    middleware: Arc::clone(&middleware),
+                    handler: route.handler,
+                }),
+            };
+
+            self.routes.push(Route {
+                method: route.method,
+                segments,
+                handler,
+            });
+        }
+
+        for (pattern, limits) in router.limits {
+            let mut segments = parse_pattern(prefix);
+            segments.extend(pattern);
+
+            self.limits.push((segments, limits));
+        }
+
+        self
+    }
+
+    ///
+    /// Wraps every request handled by this [Router] in a [Middleware], including
+    /// requests which match no route.
+    ///
+    /// Middleware runs in registration order, the first one added is the outermost.
+    ///
+    /// # Arguments
+    ///
+    /// * `middleware` -> The [Middleware] to add to the chain
+    ///
+    pub fn layer<M: Middleware>(&mut self, middleware: M) -> &mut Router {
+        self.middleware.push(Box::new(middleware));
+
+        self
+    }
+
+    ///
+    /// Lists the registered routes as `(method, pattern)` pairs, in registration order
+    /// and with nested routes under their full pattern (e.g., `/api/v1/users/:id`).
+    ///
+    pub fn routes(&self) -> impl Iterator<Item = (&HttpMethod, String)> {
+        self.routes
+            .iter()
+            .map(|route| (&route.method, format_pattern(&route.segments)))
+    }
+
+    ///
+    /// Runs the [Request] through the [Middleware] chain and dispatches it to the first
+    /// matching route, filling in [Request::params]. Paths matched only by routes of
+    /// other methods get a `405` listing them in `Allow`, and anything else a `404`.
+    ///
+    /// `HEAD` requests without a `HEAD` route run the matching `GET` handler, and the
+    /// body of every `HEAD` response is dropped while its headers, `Content-Length`
+    /// included, are kept.
+    ///
+    pub fn handle(&self, req: Request) -> Response {
+        let is_head = req.method == HttpMethod::HEAD;
+        let mut res = Next::new(&self.middleware, &|req| self.dispatch(req)).run(req);
+
+        if is_head {
+            res.body = Body::default();
+        }
+
+        res
+    }
+
+    ///
+    /// Dispatches the [Request] to the first matching route.
+    ///
+    fn dispatch(&self, mut req: Request) -> Response {
+        let found = self
+            .find(&req.method, &req.route)
+            .or_else(|| match req.method {
+                HttpMethod::HEAD => self.find(&HttpMethod::GET, &req.route),
+                _ => None,
+            });
+
+        match found {
+            Some((route, params)) => {
+                req.params = params;
+
+                route.handler.call(req)
+            }
+            None => match self.allowed_methods(&req.route) {
+                allowed if allowed.is_empty() => match &self.not_found {
+                    Some(handler) => handler.call(req),
+                    None => Response::new(404).json(&"Not Found"),
+                },
+                allowed => Response::new(405)
+                    .header("Allow", &allowed.join(", "))
+                    .json(&"Method Not Allowed"),
+            },
+        }
+    }
+
+    ///
+    /// Lists the methods of every route whose pattern matches `path`, in registration
+    /// order, with `HEAD` implied by `GET`.
+    ///
+    fn allowed_methods(&self, path: &str) -> Vec<&str> {
+        let mut allowed = Vec::new();
+
+        for route in &self.routes {
+            if match_segments(&route.segments, path).is_none() {
+                continue;
+            }
+
+            let implied = match route.method {
+                HttpMethod::GET => Some(HttpMethod::HEAD.as_str()),
+                _ => None,
+            };
+
+            for method in std::iter::once(route.method.as_str()).chain(implied) {
+                if !allowed.contains(&method) {
+                    allowed.push(method);
+                }
+            }
+        }
+
+        allowed
+    }
+
+    ///
+    /// Finds the first route registered for `method` whose pattern matches `path`.
+    ///
+    fn find(&self, method: &HttpMethod, path: &str) -> Option<(&Route, Params)> {
+        self.routes
+            .iter()
+            .filter(|route| route.method == *method)
+            .find_map(|route| Some((route, match_segments(&route.segments, path)?)))
+    }
+}
+
+impl Handler for Router {
+    fn call(&self, req: Request) -> Response {
+        self.handle(req)
+    }
+
+    fn route_limits(&self, req: &Request) -> Option<RouteLimits> {
+        self.limits
+            .iter()
+            .find(|(segments, _)| match_segments(segments, &req.route).is_some())
+            .map(|(_, limits)| *limits)
+    }
+}
+
+///
+/// Splits a route pattern into its [Segment]s.
+///
+/// # Panics
+///
+/// If a `*` catch-all segment isn't the last one
+///
+fn parse_pattern(pattern: &str) -> Vec<Segment> {
+    let segments: Vec<_> = pattern
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            if let Some(name) = s.strip_prefix(':') {
+                return Segment::Param(name.to_string());
+            }
+
+            match s.strip_prefix('*') {
+                Some(name) => Segment::CatchAll(name.to_string()),
+                None => Segment::Static(s.to_string()),
+            }
+        })
+        .collect();
+
+    let catch_all = segments
+        .iter()
+        .position(|s| matches!(s, Segment::CatchAll(_)));
+
+    assert!(
+        catch_all.is_none_or(|i| i == segments.len() - 1),
+        "catch-all segment must be the last one in route pattern {:?}",
+        pattern
+    );
+
+    segments
+}
+
+///
+/// Formats pattern [Segment]s back into a route pattern, as [Router::routes] lists them.
+///
+fn format_pattern(segments: &[Segment]) -> String {
+    if segments.is_empty() {
+        return "/".to_string();
+    }
+
+    segments
+        .iter()
+        .map(|segment| match segment {
+            Segment::Static(s) => format!("/{}", s),
+            Segment::Param(name) => format!("/:{}", name),
+            Segment::CatchAll(name) => format!("/*{}", name),
+        })
+        .collect()
+}
+
+///
+/// Normalizes a route pattern to the form listed by [Router::routes] (e.g., `users/:id/`
+/// to `/users/:id`).
+///
+#[cfg(feature = "openapi")]
+pub(crate) fn normalize_pattern(pattern: &str) -> String {
+    format_pattern(&parse_pattern(pattern))
+}
+
+///
+/// Matches a request path against pattern [Segment]s.
+///
+/// # Returns
+///
+/// * `Option<Params>` -> Captured [Params] on a match, or None otherwise
+///
+fn match_segments(segments: &[Segment], path: &str) -> Option<Params> {
+    let mut parts = path.split('/').filter(|s| !s.is_empty());
+    let mut params = Params::new();
+
+    for segment in segments {
+        if let Segment::CatchAll(name) = segment {
+            // The remainder keeps `%2F` and `%25` encoded, as in [Request::route]
+            if !name.is_empty() {
+                params.insert(name.clone(), parts.collect::<Vec<_>>().join("/"));
+            }
+
+            return Some(params);
+        }
+
+        // Only `%2F` and `%25` are left encoded in the route, see [Request::route]
+        let part = parts.next()?;
+        let part = percent_decode(part).unwrap_or_else(|| part.to_string());
+
+        match segment {
+            Segment::Static(s) if *s == part => {}
+            Segment::Static(_) => return None,
+            Segment::Param(name) => {
+                params.insert(name.clone(), part);
+            }
+            Segment::CatchAll(_) => unreachable!(),
+        }
+    }
+
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some(params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::{test_request, Server};
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::thread;
+
+    #[test]
+    fn test_router_params() {
+        let mut router = Router::new();
+
+        router.get("/users/:id/posts/:post", |req: Request| {
+            Response::new(200).json(&req.params)
+        });
+
+        let res = router.handle(test_request(HttpMethod::GET, "/users/7/posts/42"));
+        let params: Params = serde_json::from_slice(res.body.as_bytes().unwrap()).unwrap();
+
+        assert_eq!(res.status, 200);
+        assert_eq!(params.get("id"), Some(&"7".to_string()));
+        assert_eq!(params.get("post"), Some(&"42".to_string()));
+
+        // Encoded slashes stay inside their segment
+        let res = router.handle(test_request(HttpMethod::GET, "/users/a%2Fb/posts/c%20d"));
+        let params: Params = serde_json::from_slice(res.body.as_bytes().unwrap()).unwrap();
+
+        assert_eq!(params.get("id"), Some(&"a/b".to_string()));
+        assert_eq!(params.get("post"), Some(&"c d".to_string()));
+    }
+
+    #[test]
+    fn test_router_not_found() {
+        let mut router = Router::new();
+
+        router.get("/users", |_| Response::new(200));
+
+        assert_eq!(
+            router
+                .handle(test_request(HttpMethod::GET, "/users"))
+                .status,
+            200
+        );
+        assert_eq!(
+            router
+                .handle(test_request(HttpMethod::POST, "/missing"))
+                .status,
+            404
+        );
+        assert_eq!(
+            router
+                .handle(test_request(HttpMethod::GET, "/users/1"))
+                .status,
+            404
+        );
+    }
+
+    #[test]
+    fn test_router_custom_not_found() {
+        let mut router = Router::new();
+
+        router
+            .get("/users", |_| Response::new(200))
+            .not_found(|req: Request| Response::new(404).json(&format!("No {}", req.route)));
+
+        let res = router.handle(test_request(HttpMethod::GET, "/posts"));
+
+        assert_eq!(res.status, 404);
+        assert_eq!(res.body.as_bytes(), Some(&b"\"No /posts\""[..]));
+    }
+
+    #[test]
+    fn test_router_method_not_allowed() {
+        let mut router = Router::new();
+
+        router
+            .get("/users/:id", |_| Response::new(200))
+            .delete("/users/:id", |_| Response::new(204))
+            .put("/users/*rest", |_| Response::new(200));
+
+        let res = router.handle(test_request(HttpMethod::POST, "/users/1"));
+
+        assert_eq!(res.status, 405);
+        assert_eq!(res.headers.get("Allow"), Some("GET, HEAD, DELETE, PUT"));
+    }
+
+    #[test]
+    fn test_router_head_falls_back_to_get() {
+        let mut router = Router::new();
+
+        router.get("/users", |_| Response::new(200).json(&"users"));
+
+        let res = router.handle(test_request(HttpMethod::HEAD, "/users"));
+
+        assert_eq!(res.status, 200);
+        assert_eq!(res.headers.get("Content-Length"), Some("7"));
+        assert_eq!(res.body.as_bytes(), Some(&b""[..]));
+    }
+
+    #[test]
+    fn test_router_catch_all() {
+        let mut router = Router::new();
+
+        router
+            .get("/static/*path", |req: Request| {
+                Response::new(200).json(&req.params["path"])
+            })
+            .get("/*", |_| Response::new(200).json(&"index.html"));
+
+        let body = |route| router.handle(test_request(HttpMethod::GET, route)).body;
+
+        assert_eq!(
+            body("/static/css/a%20b.css").as_bytes(),
+            Some(&b"\"css/a b.css\""[..])
+        );
+        assert_eq!(body("/static").as_bytes(), Some(&b"\"\""[..]));
+        assert_eq!(
+            body("/app/settings").as_bytes(),
+            Some(&b"\"index.html\""[..])
+        );
+        assert_eq!(body("/").as_bytes(), Some(&b"\"index.html\""[..]));
+    }
+
+    #[test]
+    #[should_panic(expected = "catch-all segment must be the last one")]
+    fn test_router_catch_all_not_last() {
+        Router::new().get("/*path/edit", |_| Response::new(200));
+    }
+
+    #[test]
+    fn test_router_nest() {
+        let mut users = Router::new();
+
+        users
+            .layer(|req: Request, next: Next| next.run(req).set_header("X-Users", "1"))
+            .get("/", |_| Response::new(200).json(&"list"))
+            .get("/:id", |req: Request| Response::new(200).json(&req.params));
+
+        let mut api = Router::new();
+        api.nest("/users", users);
+
+        let mut router = Router::new();
+
+        router
+            .nest("/api/:version", api)
+            .get("/health", |_| Response::new(200));
+
+        let res = router.handle(test_request(HttpMethod::GET, "/api/v1/users"));
+
+        assert_eq!(res.body.as_bytes(), Some(&b"\"list\""[..]));
+        assert_eq!(res.headers.get("X-Users"), Some("1"));
+
+        let res = router.handle(test_request(HttpMethod::GET, "/api/v2/users/7"));
+        let params: Params = serde_json::from_slice(res.body.as_bytes().unwrap()).unwrap();
+
+        assert_eq!(params.get("version"), Some(&"v2".to_string()));
+        assert_eq!(params.get("id"), Some(&"7".to_string()));
+
+        // The nested middleware only wraps nested routes
+        let res = router.handle(test_request(HttpMethod::GET, "/health"));
+
+        assert_eq!(res.status, 200);
+        assert!(!res.headers.contains_key("X-Users"));
+    }
+
+    #[test]
+    fn test_router_limits() {
+        let mut uploads = Router::new();
+        uploads
+            .limits("/:file", RouteLimits::new().max_body_size(64))
+            .put("/:file", |req: Request| {
+                Response::new(200).json(&req.body.len())
+            });
+
+        let mut router = Router::new();
+        router
+            .nest("/upload", uploads)
+            .put("/notes", |_| Response::new(200));
+
+        let server = Server::new("127.0.0.1:0")
+            .unwrap()
+            .workers(1)
+            .max_body_size(8);
+        let addr = server.local_addr().unwrap();
+
+        thread::spawn(move || server.serve(router));
+
+        let send = |route: &str| {
+            let mut client = TcpStream::connect(addr).unwrap();
+            let body = "a".repeat(32);
+
+            write!(
+                client,
+                "PUT {} HTTP/1.1\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+                route,
+                body.len(),
+                body
+            )
+            .unwrap();
+
+            let mut response = String::new();
+            client.read_to_string(&mut response).unwrap();
+
+            response
+        };
+
+        let response = send("/upload/a.txt");
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.ends_with("\r\n\r\n32"));
+
+        // Other routes keep the server's limit
+        assert!(send("/notes").starts_with("HTTP/1.1 413 "));
+    }
+}
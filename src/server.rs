@@ -4,8 +4,9 @@
 //! # Example
 //!
 //! ```rust, no_run
-//! use http_rs::server::{Server, Request, Response, HttpMethod};
-//! use std::io::BufReader;
+//! use http_rs::extract::Json;
+//! use http_rs::router::Router;
+//! use http_rs::server::{HttpMethod, Request, Response, Server};
 //! use serde::{Deserialize, Serialize};
 //!
 //! #[derive(Serialize, Deserialize)]
@@ -16,452 +17,3825 @@
 //!
 //! fn main() -> std::io::Result<()> {
 //!     let server = Server::new("127.0.0.1:8080")?;
+//!     let mut router = Router::new();
 //!
-//!     for stream in server.listen() {
-//!         match stream {
-//!             Ok(mut stream) => {
-//!                 let buf = BufReader::new(stream.try_clone().unwrap());
-//!
-//!                 if let Ok(req) = Request::new(buf) {
-//!                     let response = match (req.method, req.route.as_str()) {
-//!                         (HttpMethod::POST, "/users") => {
-//!                             if let Some(user) = req.get_json::<User>() {
-//!                                 Response::new(201).json(&user)
-//!                             } else {
-//!                                 Response::new(400).json(&"Invalid JSON")
-//!                             }
-//!                         }
-//!                         _ => Response::new(404).json(&"Not Found"),
-//!                     };
-//!
-//!                     if let Err(e) = response.send(&mut stream) {
-//!                         eprintln!("Failed to send response: {}", e);
-//!                     }
-//!                 }
-//!             }
-//!             Err(e) => eprintln!("Connection failed: {}", e),
+//!     // Any `Fn(Request) -> Response` closure is a `Handler`, see `IntoResponse` for
+//!     // other return types
+//!     router.route(HttpMethod::POST, "/users", |req: Request| {
+//!         match req.extract::<Json<User>>() {
+//!             Ok(Json(user)) => Response::new(201).json(&user),
+//!             // 400 or 415 with a `JSON` description of the error
+//!             Err(e) => e.into(),
 //!         }
-//!     }
+//!     });
 //!
-//!     Ok(())
+//!     server.serve(router)
 //! }
 //! ```
 //!
 
+#[cfg(feature = "http2")]
+use crate::h2;
+use crate::{
+    body::{self, Body, Trailers},
+    date,
+    error::{is_timeout, Error},
+    events,
+    extensions::Extensions,
+    extract::Query,
+    forwarded,
+    header::HeaderMap,
+    pool::{Connections, ThreadPool},
+    router::Handler,
+    session::Session,
+    state::AppState,
+    static_files::guess_mime,
+    status::StatusCode,
+    stream::Stream,
+};
+#[cfg(feature = "tls")]
+use crate::{
+    certificates::Certificates,
+    client_cert::{ClientAuth, ClientCertificate},
+    tls,
+};
 use serde::{Deserialize, Serialize};
 use serde_json;
 use std::{
     collections::HashMap,
+    fmt, fs,
     io::{self, prelude::*, BufReader},
-    net::{TcpListener, TcpStream},
+    net::{IpAddr, Shutdown, SocketAddr, TcpListener},
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    str::FromStr,
+    sync::{mpsc, Arc},
+    thread,
+    time::{Duration, Instant},
 };
 
 ///
-/// Represents HTTP methods supported by the server.
+/// Represents HTTP request methods.
 ///
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum HttpMethod {
     GET,
     POST,
+    PUT,
+    DELETE,
+    PATCH,
+    HEAD,
+    OPTIONS,
+    TRACE,
+    CONNECT,
+
+    ///
+    /// Any other (extension) method, e.g. `PROPFIND`, stored as sent
+    ///
+    Other(String),
+}
+
+impl HttpMethod {
+    ///
+    /// Returns the method name as sent on the request line.
+    ///
+    pub fn as_str(&self) -> &str {
+        match self {
+            HttpMethod::GET => "GET",
+            HttpMethod::POST => "POST",
+            HttpMethod::PUT => "PUT",
+            HttpMethod::DELETE => "DELETE",
+            HttpMethod::PATCH => "PATCH",
+            HttpMethod::HEAD => "HEAD",
+            HttpMethod::OPTIONS => "OPTIONS",
+            HttpMethod::TRACE => "TRACE",
+            HttpMethod::CONNECT => "CONNECT",
+            HttpMethod::Other(method) => method,
+        }
+    }
+}
+
+impl fmt::Display for HttpMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+///
+/// Parses a method name, case-sensitively as required by RFC 9110. Unknown but
+/// well-formed names become [HttpMethod::Other].
+///
+impl FromStr for HttpMethod {
+    type Err = Error;
+
+    fn from_str(method: &str) -> Result<HttpMethod, Error> {
+        Ok(match method {
+            "GET" => HttpMethod::GET,
+            "POST" => HttpMethod::POST,
+            "PUT" => HttpMethod::PUT,
+            "DELETE" => HttpMethod::DELETE,
+            "PATCH" => HttpMethod::PATCH,
+            "HEAD" => HttpMethod::HEAD,
+            "OPTIONS" => HttpMethod::OPTIONS,
+            "TRACE" => HttpMethod::TRACE,
+            "CONNECT" => HttpMethod::CONNECT,
+            _ if is_token(method) => HttpMethod::Other(method.to_string()),
+            _ => return Err(Error::InvalidMethod),
+        })
+    }
+}
+
+///
+/// Represents HTTP protocol versions supported by the server.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HttpVersion {
+    Http10,
+    Http11,
+
+    ///
+    /// HTTP/2, negotiated with ALPN on TLS listeners (requires the `http2` feature)
+    ///
+    Http2,
+}
+
+impl fmt::Display for HttpVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpVersion::Http10 => f.write_str("HTTP/1.0"),
+            HttpVersion::Http11 => f.write_str("HTTP/1.1"),
+            HttpVersion::Http2 => f.write_str("HTTP/2.0"),
+        }
+    }
+}
+
+///
+/// Parses an `HTTP/x.y` version token, case-sensitively as required by RFC 9112.
+///
+/// Later HTTP/1 minor versions are read as HTTP/1.1, the highest one supported. Other
+/// well-formed versions (e.g., `HTTP/2.0` sent with prior knowledge) fail with
+/// [Error::UnsupportedVersion], and anything else with [Error::BadRequestLine].
+///
+impl FromStr for HttpVersion {
+    type Err = Error;
+
+    fn from_str(version: &str) -> Result<HttpVersion, Error> {
+        let is_digit = |s: &str| s.len() == 1 && s.as_bytes()[0].is_ascii_digit();
+
+        let (major, minor) = version
+            .strip_prefix("HTTP/")
+            .and_then(|numbers| numbers.split_once('.'))
+            .filter(|(major, minor)| is_digit(major) && is_digit(minor))
+            .ok_or(Error::BadRequestLine)?;
+
+        match (major, minor) {
+            ("1", "0") => Ok(HttpVersion::Http10),
+            ("1", _) => Ok(HttpVersion::Http11),
+            _ => Err(Error::UnsupportedVersion),
+        }
+    }
 }
 
 ///
-/// Alias for HTTP headers as KV pairs.
+/// Represents the form of the request-target sent in the request line.
 ///
-pub type Headers = HashMap<String, String>;
+#[derive(Debug, Clone, PartialEq)]
+pub enum RequestTarget {
+    ///
+    /// Absolute path with optional query (e.g., `/users?id=1`)
+    ///
+    Origin(String),
+
+    ///
+    /// Complete URI, as sent to proxies (e.g., `http://example.com/users`)
+    ///
+    Absolute(String),
+
+    ///
+    /// Host and port only, as sent with `CONNECT` (e.g., `example.com:443`)
+    ///
+    Authority(String),
+
+    ///
+    /// A lone `*`, as sent with server-wide `OPTIONS`
+    ///
+    Asterisk,
+}
+
+///
+/// Formats the target as it appeared in the request line.
+///
+impl fmt::Display for RequestTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequestTarget::Origin(target)
+            | RequestTarget::Absolute(target)
+            | RequestTarget::Authority(target) => f.write_str(target),
+            RequestTarget::Asterisk => f.write_str("*"),
+        }
+    }
+}
 
 ///
 /// Alias for URL query params as KV pairs.
 ///
 pub type QueryParams = HashMap<String, String>;
 
+///
+/// Alias for route path params as KV pairs.
+///
+pub type Params = HashMap<String, String>;
+
+///
+/// How the body of a [Request] is delimited on the wire.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum BodyFraming {
+    ///
+    /// Exactly this many bytes follow the headers
+    ///
+    Length(usize),
+
+    ///
+    /// `Transfer-Encoding: chunked`
+    ///
+    Chunked,
+}
+
 ///
 /// Representation of HTTP request
 ///
 #[derive(Debug)]
 pub struct Request {
     ///
-    /// The requested route/path
+    /// The requested route/path, percent-decoded except for `%2F` and `%25` which are
+    /// kept encoded so path segments stay intact
+    ///
+    /// `👉 Note:` Empty for [RequestTarget::Authority] and [RequestTarget::Asterisk]
     ///
     pub route: String,
 
+    ///
+    /// The raw [RequestTarget] from the request line
+    ///
+    pub target: RequestTarget,
+
     ///
     /// The [HttpMethod] used
     ///
     pub method: HttpMethod,
 
+    ///
+    /// The [HttpVersion] advertised in the request line
+    ///
+    pub version: HttpVersion,
+
     ///
     /// HTTP request [Headers]
     ///
-    pub headers: Headers,
+    pub headers: HeaderMap,
 
     ///
     /// Parsed [QueryParams] from the URL
     ///
     pub query_params: QueryParams,
 
+    ///
+    /// [Params] captured from the matched route pattern
+    ///
+    /// `👉 Note:` Only populated when dispatched through a [crate::router::Router]
+    ///
+    pub params: Params,
+
     ///
     /// Request body as raw bytes
     ///
     pub body: Vec<u8>,
+
+    ///
+    /// Trailer fields sent after a chunked body (e.g., a checksum of it)
+    ///
+    /// `👉 Note:` Empty unless the body was chunked and ended with trailers. They
+    /// aren't merged into [Request::headers], as senders may put anything there
+    ///
+    pub trailers: HeaderMap,
+
+    ///
+    /// Address of the connected client
+    ///
+    /// `👉 Note:` Only populated for requests read by [Server::serve], and the address of
+    /// the last proxy when behind one, see [Request::client_ip]
+    ///
+    pub remote_addr: Option<SocketAddr>,
+
+    ///
+    /// Local address the connection was accepted on, telling apart the listeners of a
+    /// [Server] bound to several addresses (see [Server::bind])
+    ///
+    /// `👉 Note:` Only populated for requests read by [Server::serve]
+    ///
+    pub local_addr: Option<SocketAddr>,
+
+    ///
+    /// Whether the connection is encrypted, see [Request::is_tls]
+    ///
+    pub(crate) tls: bool,
+
+    ///
+    /// Client address resolved through [Server::trusted_proxies], see [Request::client_ip]
+    ///
+    pub(crate) client_ip: Option<IpAddr>,
+
+    ///
+    /// [Session] attached by the [crate::session::Sessions] middleware
+    ///
+    pub(crate) session: Option<Session>,
+
+    ///
+    /// Shared values registered with [Server::with_state]
+    ///
+    pub(crate) state: AppState,
+
+    ///
+    /// Typed per-request data, see [Request::extensions]
+    ///
+    pub(crate) extensions: Extensions,
 }
 
 ///
 /// Representation of HTTP response
 ///
-#[derive(Serialize)]
+#[derive(Debug)]
 pub struct Response {
     ///
     /// HTTP status code
     ///
-    status: u16,
+    pub(crate) status: u16,
+
+    ///
+    /// Custom reason phrase, overriding the standard one for `status`
+    ///
+    pub(crate) reason: Option<String>,
 
     ///
-    /// Response [Headers]
+    /// Response headers as name/value pairs, kept in insertion order
     ///
-    headers: Headers,
+    pub(crate) headers: HeaderMap,
 
     ///
-    /// Response body as a string
+    /// Response [Body], either buffered or streamed
+    ///
+    pub(crate) body: Body,
+
     ///
-    /// `👉 Note:` Only json is supported
+    /// Trailer fields sent after a streamed body, see [Response::trailers]
     ///
-    body: String,
+    pub(crate) trailers: Option<Trailers>,
 }
 
 ///
 /// HTTP Implementation which handles TCP connections
 ///
 pub struct Server {
-    listener: TcpListener,
-}
-
-impl Server {
     ///
-    /// Creates a new HTTP server bound to the specified address.
+    /// Listening sockets, the first one bound by the constructor and any others added
+    /// with [Server::bind]
     ///
-    /// # Arguments
+    pub(crate) listeners: Vec<Listener>,
+
     ///
-    /// * `addr` -> Address to bind to (e.g., "0.0.0.0:8080")
+    /// Whether `TCP_NODELAY` is set on accepted connections
     ///
-    /// # Returns
+    pub(crate) nodelay: bool,
+
     ///
-    /// * `io::Result<Server>` -> The server instance or an [std::io] error
+    /// Number of worker threads used by [Server::serve]
     ///
-    /// # Example
+    workers: usize,
+
     ///
-    /// ```rust, no_run
-    /// use http_rs::server::Server;
-    /// 
-    /// let server = Server::new("127.0.0.1:8080");
-    /// ```
+    /// Idle timeout for persistent connections, `None` closes after each response
     ///
-    pub fn new(addr: &str) -> io::Result<Server> {
-        let listener = TcpListener::bind(addr)?;
+    keep_alive: Option<Duration>,
 
-        Ok(Server { listener })
-    }
+    ///
+    /// Budget for receiving a request line and headers
+    ///
+    header_timeout: Option<Duration>,
 
     ///
-    /// Returns an iterator over incoming TCP connections.
+    /// Timeout for each read of a request body
     ///
-    /// # Returns
+    read_timeout: Option<Duration>,
+
     ///
-    /// `io::Result<TcpStream>` -> An iterator yielding for each incoming connection
-    /// or an [std::io] error
+    /// Timeout for each write of a response
     ///
-    pub fn listen(&self) -> impl Iterator<Item = io::Result<TcpStream>> + '_ {
-        self.listener.incoming()
-    }
-}
+    write_timeout: Option<Duration>,
 
-impl Request {
     ///
-    /// Creates a new [Request] instance by parsing an incoming [TcpStream] yielded by [Server::listen]
+    /// Builds the responses to requests that fail before reaching the handler
     ///
-    /// # Arguments
+    error_handler: Arc<ErrorHandler>,
+
     ///
-    /// * `stream` -> A buffered [TcpStream] containing the [Request]
+    /// Decides whether to accept the body of `Expect: 100-continue` requests
     ///
-    /// # Returns
+    expect: Option<Arc<ExpectHandler>>,
+
     ///
-    /// * `io::Result<Request>` -> A Result containing the parsed [Request] or an [std::io] error
+    /// Value of the `Server` header added to responses, see [Server::server_header]
     ///
-    pub fn new(mut stream: BufReader<TcpStream>) -> io::Result<Request> {
-        // Parse the request line (e.g., "GET /path HTTP/1.1")
-        let request_line = Request::read_line(&mut stream)?;
+    server_header: Option<String>,
 
-        let mut parts = request_line.split_ascii_whitespace();
+    ///
+    /// Proxies allowed to report the client address, see [Server::trusted_proxies]
+    ///
+    pub(crate) trusted_proxies: Arc<[IpAddr]>,
 
-        // Parse HTTP method
-        let method = match parts.next().unwrap_or("") {
-            "GET" => HttpMethod::GET,
-            "POST" => HttpMethod::POST,
-            _ => {
-                return Err(io::Error::new(
-                    io::ErrorKind::NotFound,
-                    "Invalid HTTP method",
-                ))
-            }
-        };
+    ///
+    /// Most connections handled at once and what happens to the ones beyond, see
+    /// [Server::max_connections]
+    ///
+    max_connections: Option<(usize, Overload)>,
 
-        // Parse route and query parameters
-        let full_route = parts.next().unwrap_or("").to_string();
-        let (route, query_params) = parse_url(&full_route);
+    ///
+    /// Size limits enforced while parsing requests
+    ///
+    limits: Limits,
 
-        let mut headers = Headers::new();
+    ///
+    /// Shared values handed to every request, see [Server::with_state]
+    ///
+    pub(crate) state: AppState,
+}
 
-        loop {
-            let line = Request::read_line(&mut stream)?;
+///
+/// What [Server::serve] does with new connections once [Server::max_connections] are
+/// open.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Overload {
+    ///
+    /// Stop accepting until a connection closes, new ones waiting in the kernel's listen
+    /// backlog (see [crate::builder::ServerBuilder::backlog])
+    ///
+    Wait,
 
-            if line.is_empty() {
-                break;
-            }
+    ///
+    /// Accept them only to answer `503 Service Unavailable` with a `Retry-After` header
+    /// and close
+    ///
+    Reject { retry_after: Duration },
+}
 
-            if let Some((name, value)) = line.split_once(": ") {
-                headers.insert(name.to_string(), value.to_string());
-            }
-        }
+///
+/// A listening socket of a [Server], with the TLS configuration of its connections.
+///
+#[derive(Debug)]
+pub(crate) struct Listener {
+    pub(crate) socket: TcpListener,
 
-        // Extract `Content-Length` from [Request] body if present
-        let content_length = headers
-            .get("Content-Length")
-            .and_then(|len| len.parse::<usize>().ok())
-            .unwrap_or(0);
+    ///
+    /// TLS configuration, set when bound with [Server::new_tls] or [Server::bind_tls]
+    ///
+    #[cfg(feature = "tls")]
+    pub(crate) tls: Option<Arc<rustls::ServerConfig>>,
 
-        let mut body = vec![0; content_length];
+    ///
+    /// Certificates the TLS configuration serves
+    ///
+    #[cfg(feature = "tls")]
+    certificates: Option<Certificates>,
+}
 
-        if content_length > 0 {
-            stream.read_exact(&mut body)?;
+impl Listener {
+    pub(crate) fn new(socket: TcpListener) -> Listener {
+        Listener {
+            socket,
+            #[cfg(feature = "tls")]
+            tls: None,
+            #[cfg(feature = "tls")]
+            certificates: None,
         }
-
-        Ok(Request {
-            method,
-            route,
-            headers,
-            query_params,
-            body,
-        })
     }
 
     ///
-    /// Reads a single line from the [TcpStream].
+    /// Serves TLS connections with the given default certificate chain and private key.
     ///
-    fn read_line(stream: &mut BufReader<TcpStream>) -> io::Result<String> {
-        let mut line = String::new();
-        stream.read_line(&mut line)?;
+    #[cfg(feature = "tls")]
+    pub(crate) fn set_tls(
+        &mut self,
+        cert_path: &std::path::Path,
+        key_path: &std::path::Path,
+    ) -> io::Result<()> {
+        let certificates = Certificates::new(cert_path, key_path)?;
+
+        self.tls = Some(tls::server_config(&certificates)?);
+        self.certificates = Some(certificates);
 
-        Ok(line.trim().to_string())
+        Ok(())
     }
 
     ///
-    /// Attempts to parse the [Request] body as `JSON` into the specified type `T`.
-    ///
-    /// # Parameters
-    ///
-    /// * `T` -> The type to deserialize the `JSON` into. **Must implement Deserialize.**
+    /// Creates a [Listener] accepting on another socket with the same TLS configuration.
     ///
-    /// # Returns
+    pub(crate) fn sibling(&self, socket: TcpListener) -> Listener {
+        Listener {
+            socket,
+            #[cfg(feature = "tls")]
+            tls: self.tls.clone(),
+            #[cfg(feature = "tls")]
+            certificates: self.certificates.clone(),
+        }
+    }
+
+    fn try_clone(&self) -> io::Result<Listener> {
+        Ok(self.sibling(self.socket.try_clone()?))
+    }
+
     ///
-    /// * `Option<T>` -> The parsed `JSON` data or None if parsing fails
+    /// Accepts the next connection, wrapping it into a [Stream] and starting a TLS
+    /// session if configured.
     ///
-    pub fn get_json<T: for<'a> Deserialize<'a>>(&self) -> Option<T> {
-        serde_json::from_slice(&self.body).ok()
+    fn accept(&self, nodelay: bool) -> io::Result<Stream> {
+        let (stream, _) = self.socket.accept()?;
+
+        if nodelay {
+            stream.set_nodelay(true)?;
+        }
+
+        #[cfg(feature = "tls")]
+        if let Some(config) = &self.tls {
+            let conn = rustls::ServerConnection::new(Arc::clone(config)).map_err(tls::tls_error)?;
+
+            return Ok(Stream::Tls(Arc::new(std::sync::Mutex::new(
+                rustls::StreamOwned::new(conn, stream),
+            ))));
+        }
+
+        Ok(Stream::Tcp(stream))
     }
 }
 
-impl Response {
+impl Server {
     ///
-    /// Creates a new [Response] with the specified status code.
+    /// Creates a new HTTP server bound to the specified address.
     ///
     /// # Arguments
     ///
-    /// * `status` -> HTTP status code (e.g., 200, 404)
+    /// * `addr` -> Address to bind to (e.g., "0.0.0.0:8080")
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<Server>` -> The server instance or an [std::io] error
     ///
     /// # Example
     ///
     /// ```rust, no_run
-    /// use http_rs::server::Response;
-    /// 
-    /// let response = Response::new(200);
+    /// use http_rs::server::Server;
+    ///
+    /// let server = Server::new("127.0.0.1:8080");
     /// ```
     ///
-    pub fn new(status: u16) -> Response {
-        let mut headers = Headers::new();
+    pub fn new(addr: &str) -> io::Result<Server> {
+        Ok(Server::from_listener(TcpListener::bind(addr)?))
+    }
 
-        headers.insert("Content-Type".to_string(), "application/json".to_string());
+    ///
+    /// Creates a server with the default settings accepting on an already bound listener.
+    ///
+    pub(crate) fn from_listener(listener: TcpListener) -> Server {
+        let workers = thread::available_parallelism().map_or(4, |n| n.get());
 
-        Response {
-            status,
-            headers,
-            body: String::new(),
+        Server {
+            listeners: vec![Listener::new(listener)],
+            nodelay: false,
+            workers,
+            keep_alive: Some(Duration::from_secs(5)),
+            header_timeout: Some(Duration::from_secs(10)),
+            read_timeout: Some(Duration::from_secs(30)),
+            write_timeout: Some(Duration::from_secs(30)),
+            error_handler: Arc::new(default_error),
+            expect: None,
+            server_header: Some("http_rs".to_string()),
+            trusted_proxies: Arc::new([]),
+            max_connections: None,
+            limits: Limits::default(),
+            state: AppState::default(),
         }
     }
 
     ///
-    /// Sets the [Response] body as `JSON` and returns the modified response.
+    /// Creates a new HTTPS server bound to the specified address.
+    ///
+    /// Every connection yielded by [Server::listen] is wrapped in a TLS session, the
+    /// handshake happens on first read or write of the [Stream].
+    ///
+    /// With the `http2` feature, [Server::serve] speaks HTTP/2 to clients that negotiate
+    /// it through ALPN, and HTTP/1.1 to the others.
+    ///
+    /// `👉 Note:` Requires the `tls` feature
     ///
     /// # Arguments
     ///
-    /// * `data` -> Data to be serialized to `JSON`. **Must implement Serialize.**
+    /// * `addr` -> Address to bind to (e.g., "0.0.0.0:8443")
+    /// * `cert_path` -> Path to the PEM encoded certificate chain
+    /// * `key_path` -> Path to the PEM encoded private key
     ///
     /// # Returns
     ///
-    /// Modified [Response] with `JSON` body and updated `Content-Length` header
+    /// * `io::Result<Server>` -> The server instance or an [std::io] error
+    ///
+    /// # Example
     ///
-    pub fn json<T: Serialize>(mut self, data: &T) -> Response {
-        self.body = serde_json::to_string(data).unwrap_or_default();
+    /// ```rust, no_run
+    /// use http_rs::server::Server;
+    ///
+    /// let server = Server::new_tls("0.0.0.0:8443", "cert.pem", "key.pem");
+    /// ```
+    ///
+    #[cfg(feature = "tls")]
+    pub fn new_tls(
+        addr: &str,
+        cert_path: impl AsRef<std::path::Path>,
+        key_path: impl AsRef<std::path::Path>,
+    ) -> io::Result<Server> {
+        let mut server = Server::new(addr)?;
 
-        self.headers
-            .insert("Content-Length".to_string(), self.body.len().to_string());
+        server.listeners[0].set_tls(cert_path.as_ref(), key_path.as_ref())?;
 
-        self
+        Ok(server)
     }
 
     ///
-    /// Sends the [Response] over the [TcpStream].
+    /// Sets the number of worker threads used by [Server::serve].
+    ///
+    /// Defaults to the available parallelism of the machine.
     ///
     /// # Arguments
     ///
-    /// * `stream` -> The [TcpStream] to write the response to
+    /// * `workers` -> Number of threads handling connections concurrently
     ///
-    /// # Returns
+    pub fn workers(mut self, workers: usize) -> Server {
+        self.workers = workers;
+
+        self
+    }
+
+    ///
+    /// Sets how long [Server::serve] keeps an idle connection open waiting for the
+    /// next [Request], or disables persistent connections with `None`.
+    ///
+    /// Defaults to 5 seconds.
+    ///
+    /// `👉 Note:` Like the other timeouts, it doesn't run while a handler is working on a
+    /// [Request], so long polls (see [crate::long_poll]) may wait longer
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` -> Idle timeout between requests on the same connection
+    ///
+    pub fn keep_alive(mut self, timeout: Option<Duration>) -> Server {
+        self.keep_alive = timeout.filter(|t| !t.is_zero());
+
+        self
+    }
+
+    ///
+    /// Sets how long [Server::serve] waits for the request line and headers of a
+    /// [Request], counted from its first byte. The first request of a connection must
+    /// also start within it. Slow clients exceeding it get a `408` and are disconnected.
+    ///
+    /// Defaults to 10 seconds, `None` waits forever.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` -> Maximum time to receive a request head
+    ///
+    pub fn header_timeout(mut self, timeout: Option<Duration>) -> Server {
+        self.header_timeout = timeout.filter(|t| !t.is_zero());
+
+        self
+    }
+
+    ///
+    /// Sets how long [Server::serve] waits on each read of a request body before
+    /// answering `408` and disconnecting.
+    ///
+    /// Defaults to 30 seconds, `None` waits forever.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` -> Maximum time a single body read may block
+    ///
+    pub fn read_timeout(mut self, timeout: Option<Duration>) -> Server {
+        self.read_timeout = timeout.filter(|t| !t.is_zero());
+
+        self
+    }
+
+    ///
+    /// Sets how long [Server::serve] waits on each write of a response before giving
+    /// up on the connection, so clients that stop reading don't pin a worker.
+    ///
+    /// Defaults to 30 seconds, `None` waits forever.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` -> Maximum time a single write may block
+    ///
+    pub fn write_timeout(mut self, timeout: Option<Duration>) -> Server {
+        self.write_timeout = timeout.filter(|t| !t.is_zero());
+
+        self
+    }
+
+    ///
+    /// Limits how many connections [Server::serve] handles at once, so a traffic spike
+    /// can't exhaust file descriptors.
+    ///
+    /// Connections waiting for a worker count as open, as do idle persistent ones.
+    ///
+    /// Defaults to no limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `max` -> Most connections open at once
+    /// * `overload` -> What to do with new connections when `max` are open
+    ///
+    /// # Example
+    ///
+    /// ```rust, no_run
+    /// use http_rs::server::{Overload, Server};
+    /// use std::time::Duration;
+    ///
+    /// let server = Server::new("127.0.0.1:8080").unwrap().max_connections(
+    ///     1024,
+    ///     Overload::Reject {
+    ///         retry_after: Duration::from_secs(5),
+    ///     },
+    /// );
+    /// ```
+    ///
+    pub fn max_connections(mut self, max: usize, overload: Overload) -> Server {
+        self.max_connections = Some((max.max(1), overload));
+
+        self
+    }
+
+    ///
+    /// Sets the largest request body [Server::serve] accepts, larger bodies are answered
+    /// with `413` before being read.
+    ///
+    /// Defaults to 10 MiB, and can be overridden per route with
+    /// [crate::router::Router::limits].
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` -> Maximum body size in bytes, after chunked decoding
+    ///
+    pub fn max_body_size(mut self, bytes: usize) -> Server {
+        self.limits.body = bytes;
+
+        self
+    }
+
+    ///
+    /// Sets how many header fields a request may carry before [Server::serve] answers
+    /// with `431`.
+    ///
+    /// Defaults to 100.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` -> Maximum number of header lines
+    ///
+    pub fn max_headers(mut self, count: usize) -> Server {
+        self.limits.headers = count;
+
+        self
+    }
+
+    ///
+    /// Sets the longest header line [Server::serve] accepts before answering with `431`.
+    ///
+    /// Defaults to 8 KiB.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` -> Maximum length of a single header line
+    ///
+    pub fn max_header_line(mut self, bytes: usize) -> Server {
+        self.limits.header_line = bytes;
+
+        self
+    }
+
+    ///
+    /// Sets the longest request target [Server::serve] accepts before answering with `414`.
+    ///
+    /// Defaults to 8 KiB.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` -> Maximum length of the URI on the request line
+    ///
+    pub fn max_uri_length(mut self, bytes: usize) -> Server {
+        self.limits.uri = bytes;
+
+        self
+    }
+
+    ///
+    /// Sets how [Server::serve] answers requests that can't be handled, such as
+    /// malformed requests (`400`), exceeded limits (`413`, `414`, `431`), timeouts
+    /// (`408`), refused expectations (`417`), unsupported versions (`505`) or handlers
+    /// that panicked (`500`).
+    ///
+    /// Defaults to a JSON string of the reason phrase.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` -> Callback given the [StatusCode] and a description of the error
+    ///
+    /// # Example
+    ///
+    /// ```rust, no_run
+    /// use http_rs::server::{Response, Server};
+    ///
+    /// let server = Server::new("127.0.0.1:8080").unwrap().error_handler(|status, message| {
+    ///     Response::new(status).text(&format!("{}: {}", status.as_u16(), message))
+    /// });
+    /// ```
+    ///
+    pub fn error_handler<F>(mut self, handler: F) -> Server
+    where
+        F: Fn(StatusCode, &str) -> Response + Send + Sync + 'static,
+    {
+        self.error_handler = Arc::new(handler);
+
+        self
+    }
+
+    ///
+    /// Additionally listens on the specified address, connections accepted on every
+    /// address are served by the same handler.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` -> Address to bind to (e.g., "[::]:8080")
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<Server>` -> The server instance or an [std::io] error
+    ///
+    /// # Example
+    ///
+    /// ```rust, no_run
+    /// use http_rs::server::Server;
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let server = Server::new("0.0.0.0:8080")?.bind("[::]:8080")?;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    pub fn bind(mut self, addr: &str) -> io::Result<Server> {
+        self.listeners.push(Listener::new(TcpListener::bind(addr)?));
+
+        Ok(self)
+    }
+
+    ///
+    /// Additionally listens for HTTPS connections on the specified address, e.g. to serve
+    /// HTTP and HTTPS from the same [Server].
+    ///
+    /// `👉 Note:` Requires the `tls` feature
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` -> Address to bind to (e.g., "0.0.0.0:8443")
+    /// * `cert_path` -> Path to the PEM encoded certificate chain
+    /// * `key_path` -> Path to the PEM encoded private key
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<Server>` -> The server instance or an [std::io] error
+    ///
+    #[cfg(feature = "tls")]
+    pub fn bind_tls(
+        mut self,
+        addr: &str,
+        cert_path: impl AsRef<std::path::Path>,
+        key_path: impl AsRef<std::path::Path>,
+    ) -> io::Result<Server> {
+        let mut listener = Listener::new(TcpListener::bind(addr)?);
+        listener.set_tls(cert_path.as_ref(), key_path.as_ref())?;
+
+        self.listeners.push(listener);
+
+        Ok(self)
+    }
+
+    ///
+    /// Asks TLS clients for a certificate, verified against the given CA bundle during the
+    /// handshake. The certificate a client authenticated with is then available to
+    /// handlers through [Request::client_certificate].
+    ///
+    /// Applies to the TLS listeners bound so far, see [Server::new_tls] and
+    /// [Server::bind_tls].
+    ///
+    /// `👉 Note:` Requires the `tls` feature
+    ///
+    /// # Arguments
+    ///
+    /// * `ca_path` -> Path to the PEM encoded CA certificates client certificates chain to
+    /// * `auth` -> Whether connections without a certificate are refused
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<Server>` -> The server instance or an [std::io] error, also when no
+    ///   listener serves TLS
+    ///
+    /// # Example
+    ///
+    /// ```rust, no_run
+    /// use http_rs::{client_cert::ClientAuth, server::Server};
+    ///
+    /// let server = Server::new_tls("0.0.0.0:8443", "cert.pem", "key.pem")
+    ///     .and_then(|server| server.client_auth("ca.pem", ClientAuth::Required));
+    /// ```
+    ///
+    #[cfg(feature = "tls")]
+    pub fn client_auth(
+        mut self,
+        ca_path: impl AsRef<std::path::Path>,
+        auth: ClientAuth,
+    ) -> io::Result<Server> {
+        let mut found = false;
+
+        for listener in &mut self.listeners {
+            if let Some(config) = &listener.tls {
+                listener.tls = Some(tls::with_client_auth(config, ca_path.as_ref(), auth)?);
+                found = true;
+            }
+        }
+
+        if !found {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no TLS listener to authenticate clients on",
+            ));
+        }
+
+        Ok(self)
+    }
+
+    ///
+    /// Serves another certificate to TLS clients asking for `hostname` through SNI, the
+    /// one given to [Server::new_tls] or [Server::bind_tls] remaining the default for
+    /// other hostnames and clients not sending SNI.
+    ///
+    /// Applies to the TLS listeners bound so far.
+    ///
+    /// `👉 Note:` Requires the `tls` feature
+    ///
+    /// # Arguments
+    ///
+    /// * `hostname` -> Hostname to serve the certificate for, or a wildcard covering one
+    ///   label (e.g., "*.example.com")
+    /// * `cert_path` -> Path to the PEM encoded certificate chain
+    /// * `key_path` -> Path to the PEM encoded private key
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<Server>` -> The server instance or an [std::io] error, also when no
+    ///   listener serves TLS
+    ///
+    #[cfg(feature = "tls")]
+    pub fn sni_certificate(
+        self,
+        hostname: &str,
+        cert_path: impl AsRef<std::path::Path>,
+        key_path: impl AsRef<std::path::Path>,
+    ) -> io::Result<Server> {
+        let certificates = self.certificates();
+
+        if certificates.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no TLS listener to serve the certificate on",
+            ));
+        }
+
+        for certificates in certificates {
+            certificates.add(hostname, cert_path.as_ref(), key_path.as_ref())?;
+        }
+
+        Ok(self)
+    }
+
+    ///
+    /// Returns the [Certificates] of each TLS listener, in the order they were bound, to
+    /// reload them from disk once renewed.
+    ///
+    /// `👉 Note:` Requires the `tls` feature
+    ///
+    /// # Example
+    ///
+    /// ```rust, no_run
+    /// use http_rs::server::Server;
+    ///
+    /// let server = Server::new_tls("0.0.0.0:8443", "cert.pem", "key.pem").unwrap();
+    /// let certificates = server.certificates();
+    ///
+    /// // e.g., from a SIGHUP handler or an admin endpoint
+    /// for certificates in &certificates {
+    ///     certificates.reload().unwrap();
+    /// }
+    /// ```
+    ///
+    #[cfg(feature = "tls")]
+    pub fn certificates(&self) -> Vec<Certificates> {
+        self.listeners
+            .iter()
+            .filter_map(|listener| listener.certificates.clone())
+            .collect()
+    }
+
+    ///
+    /// Sets a check run on requests sending `Expect: 100-continue` before their body is
+    /// read, answering `417` when it returns `false` so the client doesn't upload a body
+    /// that would be refused anyway (e.g., unauthenticated or unsupported uploads).
+    ///
+    /// Otherwise [Server::serve] answers `100 Continue` to such requests, unless their
+    /// `Content-Length` already exceeds [Server::max_body_size] (`413`).
+    ///
+    /// # Arguments
+    ///
+    /// * `check` -> Callback given the [Request] with its head only
+    ///
+    /// # Example
+    ///
+    /// ```rust, no_run
+    /// use http_rs::server::Server;
+    ///
+    /// let server = Server::new("127.0.0.1:8080")
+    ///     .unwrap()
+    ///     .expect_continue(|req| req.headers.contains_key("Authorization"));
+    /// ```
+    ///
+    pub fn expect_continue<F>(mut self, check: F) -> Server
+    where
+        F: Fn(&Request) -> bool + Send + Sync + 'static,
+    {
+        self.expect = Some(Arc::new(check));
+
+        self
+    }
+
+    ///
+    /// Sets the `Server` header [Server::serve] adds to responses that don't set their
+    /// own, or omits it with `None`.
+    ///
+    /// Defaults to `http_rs`.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` -> Product identifying the server (e.g., `my-api/2.1`)
+    ///
+    pub fn server_header(mut self, value: Option<&str>) -> Server {
+        self.server_header = value.map(str::to_string);
+
+        self
+    }
+
+    ///
+    /// Returns the local address the server is bound to, the first one if it listens on
+    /// several (see [Server::local_addrs]).
+    ///
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listeners[0].socket.local_addr()
+    }
+
+    ///
+    /// Returns every local address the server is bound to, in the order they were bound.
+    ///
+    pub fn local_addrs(&self) -> io::Result<Vec<SocketAddr>> {
+        let mut addrs = Vec::with_capacity(self.listeners.len());
+
+        for listener in &self.listeners {
+            let addr = listener.socket.local_addr()?;
+
+            // Acceptors share their address (see [crate::builder::ServerBuilder::acceptors])
+            if !addrs.contains(&addr) {
+                addrs.push(addr);
+            }
+        }
+
+        Ok(addrs)
+    }
+
+    ///
+    /// Returns an iterator over incoming connections.
+    ///
+    /// When bound to several addresses, each one is accepted on its own thread and the
+    /// connections are yielded in the order they arrive. Each thread waits for its
+    /// connection to be taken before accepting the next one.
+    ///
+    /// # Returns
+    ///
+    /// `io::Result<Stream>` -> An iterator yielding for each incoming connection
+    /// or an [std::io] error
+    ///
+    pub fn listen(&self) -> impl Iterator<Item = io::Result<Stream>> + '_ {
+        let nodelay = self.nodelay;
+
+        let receiver = (self.listeners.len() > 1).then(|| {
+            let (sender, receiver) = mpsc::sync_channel(0);
+
+            for listener in &self.listeners {
+                let sender = sender.clone();
+
+                match listener.try_clone() {
+                    Ok(listener) => {
+                        thread::spawn(
+                            move || {
+                                while sender.send(listener.accept(nodelay)).is_ok() {}
+                            },
+                        );
+                    }
+                    Err(e) => {
+                        let _ = sender.send(Err(e));
+                    }
+                }
+            }
+
+            receiver
+        });
+
+        std::iter::from_fn(move || match &receiver {
+            Some(receiver) => receiver.recv().ok(),
+            None => Some(self.listeners[0].accept(nodelay)),
+        })
+    }
+
+    ///
+    /// Collects the settings every connection is served with.
+    ///
+    pub(crate) fn shared(&self) -> Shared {
+        Shared {
+            timeouts: Timeouts {
+                keep_alive: self.keep_alive,
+                header: self.header_timeout,
+                read: self.read_timeout,
+                write: self.write_timeout,
+            },
+            limits: self.limits,
+            errors: Arc::clone(&self.error_handler),
+            trusted_proxies: Arc::clone(&self.trusted_proxies),
+            state: self.state.clone(),
+            expect: self.expect.clone(),
+            server_header: self.server_header.clone(),
+        }
+    }
+
+    ///
+    /// Accepts connections forever, handing each one to a worker thread which parses
+    /// the [Request], dispatches it to `handler` and sends back the [Response].
+    ///
+    /// Each bound address, and each acceptor (see
+    /// [crate::builder::ServerBuilder::acceptors]), is accepted on by its own loop.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` -> The [Handler] invoked for every request (e.g., a [crate::router::Router])
+    ///
+    /// # Example
+    ///
+    /// ```rust, no_run
+    /// use http_rs::router::Router;
+    /// use http_rs::server::{Response, Server};
+    ///
+    /// fn main() -> std::io::Result<()> {
+    ///     let mut router = Router::new();
+    ///     router.get("/", |_| Response::new(200).json(&"Hello"));
+    ///
+    ///     Server::new("127.0.0.1:8080")?.workers(8).serve(router)
+    /// }
+    /// ```
+    ///
+    pub fn serve<H: Handler>(&self, handler: H) -> io::Result<()> {
+        let pool = ThreadPool::new(self.workers);
+        let handler = Arc::new(handler);
+        let shared = Arc::new(self.shared());
+
+        let connections = Arc::new(Connections::default());
+        let (max, overload) = self.max_connections.unwrap_or((usize::MAX, Overload::Wait));
+
+        let accept_loop = |listener: &Listener| loop {
+            // At capacity, connections are left to queue up in the kernel backlog
+            if overload == Overload::Wait {
+                connections.wait_below(max);
+            }
+
+            match listener.accept(self.nodelay) {
+                Ok(stream) => {
+                    // Another accept loop may have taken the last slot meanwhile
+                    let open = loop {
+                        match connections.try_open(max) {
+                            Some(open) => break Some(open),
+                            None if overload == Overload::Wait => connections.wait_below(max),
+                            None => break None,
+                        }
+                    };
+
+                    let Some(open) = open else {
+                        if let Overload::Reject { retry_after } = overload {
+                            reject_overloaded(stream, retry_after, &shared);
+                        }
+
+                        continue;
+                    };
+
+                    let handler = Arc::clone(&handler);
+                    let shared = Arc::clone(&shared);
+
+                    pool.execute(move || {
+                        handle_connection(stream, handler.as_ref(), &shared);
+                        drop(open);
+                    });
+                }
+                Err(e) => events::accept_failed(&e),
+            }
+        };
+
+        // Every listener (each address, each acceptor) runs its own accept loop
+        match self.listeners.as_slice() {
+            [listener] => accept_loop(listener),
+            listeners => {
+                thread::scope(|scope| {
+                    for listener in listeners {
+                        scope.spawn(|| accept_loop(listener));
+                    }
+                });
+
+                Ok(())
+            }
+        }
+    }
+}
+
+///
+/// Connection timeouts applied by [Server::serve].
+///
+#[derive(Clone, Copy)]
+pub(crate) struct Timeouts {
+    pub(crate) keep_alive: Option<Duration>,
+    pub(crate) header: Option<Duration>,
+    pub(crate) read: Option<Duration>,
+    pub(crate) write: Option<Duration>,
+}
+
+///
+/// Settings of a [Server] shared by every connection handled by [Server::serve].
+///
+pub(crate) struct Shared {
+    pub(crate) timeouts: Timeouts,
+    pub(crate) limits: Limits,
+    pub(crate) errors: Arc<ErrorHandler>,
+    trusted_proxies: Arc<[IpAddr]>,
+    state: AppState,
+    expect: Option<Arc<ExpectHandler>>,
+    server_header: Option<String>,
+}
+
+///
+/// Size limits applied while parsing a [Request].
+///
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Limits {
+    pub(crate) body: usize,
+    pub(crate) headers: usize,
+    pub(crate) header_line: usize,
+    pub(crate) uri: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            body: 10 * 1024 * 1024,
+            headers: 100,
+            header_line: 8 * 1024,
+            uri: 8 * 1024,
+        }
+    }
+}
+
+///
+/// Serves [Request]s from the [Stream] until the client closes the connection,
+/// asks for it to be closed, stays idle longer than the keep-alive timeout, or is too
+/// slow to send a request.
+///
+/// Pipelined requests, sent before the previous ones are answered, wait in the buffer
+/// and are answered one at a time in order. Those behind a request closing the
+/// connection, or one rejected before its body was read, are dropped unanswered.
+///
+fn handle_connection<H: Handler>(mut stream: Stream, handler: &H, shared: &Shared) {
+    let (timeouts, errors) = (shared.timeouts, shared.errors.as_ref());
+
+    let mut buf = match stream.try_clone() {
+        Ok(stream) => BufReader::new(stream),
+        Err(_) => return,
+    };
+
+    if stream.set_write_timeout(timeouts.write).is_err() {
+        return;
+    }
+
+    // Completed within the header timeout, so a client certificate is known upfront
+    #[cfg(feature = "tls")]
+    if stream
+        .set_read_timeout(timeouts.header)
+        .and_then(|_| stream.handshake())
+        .is_err()
+    {
+        return;
+    }
+
+    // Clients that negotiated HTTP/2 during the TLS handshake speak it from the start
+    #[cfg(feature = "http2")]
+    if stream.alpn_protocol().as_deref() == Some(b"h2") {
+        return h2::serve(stream, handler, shared);
+    }
+
+    let peer = Peer::of(&stream);
+    let remote_addr = peer.remote_addr;
+
+    let mut first = true;
+
+    loop {
+        // Wait for the request to start, an idle persistent connection is simply closed
+        let idle = if first {
+            timeouts.header
+        } else {
+            timeouts.keep_alive
+        };
+
+        if stream.set_read_timeout(idle).is_err() {
+            return;
+        }
+
+        match buf.fill_buf() {
+            Ok([]) => return,
+            Ok(_) => {}
+            Err(e) if first && is_timeout(&e) => {
+                let _ = finish(request_timeout(errors), shared, false).send_to(&mut stream);
+                return;
+            }
+            Err(_) => return,
+        }
+
+        first = false;
+
+        // Set when the request is rejected, its remaining bytes being left unread
+        let mut rejected = false;
+        let mut head = false;
+
+        let (mut response, persist) = match read_request(&mut buf, handler, shared) {
+            Ok(mut req) => {
+                let persist = timeouts.keep_alive.is_some() && req.keep_alive();
+                attach(&mut req, &peer, shared);
+
+                head = req.method == HttpMethod::HEAD;
+
+                let version = req.version;
+
+                // The connection is closed after a panic as its state is unknown
+                match dispatch(handler, req) {
+                    // HTTP/1.0 has no chunked encoding, a streamed body is delimited by
+                    // closing the connection instead
+                    Some(mut response)
+                        if version == HttpVersion::Http10 && response.body.is_stream() =>
+                    {
+                        response.headers.remove("Transfer-Encoding");
+                        (response, false)
+                    }
+                    Some(response) => (response, persist),
+                    None => (error_response(errors, 500), false),
+                }
+            }
+            // The connection failed or was closed, there is no one to answer
+            Err(Error::Io(e)) if e.kind() != io::ErrorKind::InvalidData && !is_timeout(&e) => {
+                return
+            }
+            Err(e) if e.status() == StatusCode::REQUEST_TIMEOUT => (request_timeout(errors), false),
+            Err(e) => {
+                events::request_rejected(&e, remote_addr);
+                rejected = true;
+                (error_response(errors, e.status().as_u16()), false)
+            }
+        };
+
+        // Whatever the handler answered, a `HEAD` response ends with its headers, or
+        // the next request on the connection would be read from the body
+        if head {
+            response.body = Body::default();
+        }
+
+        // Handlers may close the connection by answering `Connection: close`
+        let persist = persist && !has_token(&response.headers, "Connection", "close");
+
+        if let Err(e) = finish(response, shared, persist).send_to(&mut stream) {
+            events::send_failed(&e, remote_addr);
+            return;
+        }
+
+        if rejected {
+            linger(&stream, &mut buf);
+        }
+
+        if !persist {
+            return;
+        }
+    }
+}
+
+///
+/// What is known about a connection once accepted, shared by the requests it carries.
+///
+pub(crate) struct Peer {
+    pub(crate) remote_addr: Option<SocketAddr>,
+    local_addr: Option<SocketAddr>,
+    tls: bool,
+    #[cfg(feature = "tls")]
+    certificate: Option<ClientCertificate>,
+}
+
+impl Peer {
+    ///
+    /// Reads the addresses of a connection, and the client certificate of a TLS one
+    /// whose handshake completed.
+    ///
+    pub(crate) fn of(stream: &Stream) -> Peer {
+        Peer {
+            remote_addr: stream.peer_addr().ok(),
+            local_addr: stream.local_addr().ok(),
+            tls: stream.is_tls(),
+            #[cfg(feature = "tls")]
+            certificate: stream
+                .peer_certificate()
+                .and_then(ClientCertificate::from_der),
+        }
+    }
+}
+
+///
+/// Fills in what a [Request] read from a connection knows about it: the peer and local
+/// addresses, whether it is encrypted and the client certificate, the client behind
+/// trusted proxies, and the shared state.
+///
+pub(crate) fn attach(req: &mut Request, peer: &Peer, shared: &Shared) {
+    req.remote_addr = peer.remote_addr;
+    req.local_addr = peer.local_addr;
+    req.tls = peer.tls;
+    req.client_ip = peer
+        .remote_addr
+        .map(|addr| forwarded::client_ip(&req.headers, addr.ip(), &shared.trusted_proxies));
+    req.state = shared.state.clone();
+
+    #[cfg(feature = "tls")]
+    if let Some(certificate) = &peer.certificate {
+        req.extensions.insert(certificate.clone());
+    }
+}
+
+///
+/// Runs the [Handler] on a [Request], catching panics so they don't take the worker down.
+///
+/// # Returns
+///
+/// * `Option<Response>` -> The handler's response, or None if it panicked
+///
+pub(crate) fn dispatch<H: Handler>(handler: &H, req: Request) -> Option<Response> {
+    let (method, route) = (req.method.clone(), req.route.clone());
+
+    panic::catch_unwind(AssertUnwindSafe(|| handler.call(req)))
+        .map_err(|panic| events::handler_panicked(panic.as_ref(), method.as_str(), &route))
+        .ok()
+}
+
+///
+/// Adds the headers [Server::serve] sends on every [Response]: `Date`, `Server` unless
+/// disabled, and `Connection` telling whether the connection stays open.
+///
+/// `Date` and `Server` headers set by the handler are kept. Empty bodies get a
+/// `Content-Length: 0`, as a response without length is read until the connection closes.
+///
+fn finish(mut response: Response, shared: &Shared, persist: bool) -> Response {
+    let connection = if persist { "keep-alive" } else { "close" };
+
+    let bodiless = response.status < 200 || response.status == 204 || response.status == 304;
+    let delimited = response.headers.contains_key("Content-Length")
+        || response.headers.contains_key("Transfer-Encoding");
+
+    if !bodiless && !delimited && response.body.as_bytes().is_some_and(<[u8]>::is_empty) {
+        response.headers.insert("Content-Length", "0");
+    }
+
+    stamp(response, shared).set_header("Connection", connection)
+}
+
+///
+/// Adds the `Date` and `Server` headers of [finish], for connections whose persistence
+/// isn't told by a header.
+///
+pub(crate) fn stamp(mut response: Response, shared: &Shared) -> Response {
+    if !response.headers.contains_key("Date") {
+        response.headers.insert("Date", date::now());
+    }
+
+    if let Some(server) = &shared.server_header {
+        if !response.headers.contains_key("Server") {
+            response.headers.insert("Server", server);
+        }
+    }
+
+    response
+}
+
+///
+/// Whether a comma separated header lists `token`, case-insensitively.
+///
+fn has_token(headers: &HeaderMap, name: &str, token: &str) -> bool {
+    headers
+        .get_all(name)
+        .flat_map(|v| v.split(','))
+        .any(|t| t.trim().eq_ignore_ascii_case(token))
+}
+
+///
+/// How long, and for how many bytes, a connection is drained by [linger].
+///
+const LINGER_TIMEOUT: Duration = Duration::from_secs(1);
+const LINGER_BYTES: usize = 64 * 1024;
+
+///
+/// Drains what the client still sends after its request was rejected, before the
+/// connection is closed.
+///
+/// Closing a socket with unread data makes it send a reset, which can discard the error
+/// response before the client reads it. Shutting down the write side first lets the
+/// client see the response end, and reading stops once it closes or after
+/// [LINGER_TIMEOUT].
+///
+fn linger(stream: &Stream, buf: &mut BufReader<Stream>) {
+    if stream.shutdown(Shutdown::Write).is_err() {
+        return;
+    }
+
+    let deadline = Instant::now() + LINGER_TIMEOUT;
+    let mut drained = 0;
+    let mut sink = [0; 4096];
+
+    while drained < LINGER_BYTES {
+        let left = deadline.saturating_duration_since(Instant::now());
+
+        if left.is_zero() || stream.set_read_timeout(Some(left)).is_err() {
+            return;
+        }
+
+        match buf.read(&mut sink) {
+            Ok(0) | Err(_) => return,
+            Ok(n) => drained += n,
+        }
+    }
+}
+
+///
+/// Reads a [Request] within the header budget, then reads its body with the per-read
+/// timeout and the limits of its route, first answering `100 Continue` if the client
+/// waits for it.
+///
+fn read_request<H: Handler>(
+    buf: &mut BufReader<Stream>,
+    handler: &H,
+    shared: &Shared,
+) -> Result<Request, Error> {
+    let timeouts = &shared.timeouts;
+    let deadline = timeouts.header.map(|t| Instant::now() + t);
+
+    buf.get_ref().set_read_timeout(timeouts.header)?;
+    let mut req = Request::read_head(buf, &shared.limits, deadline)?;
+
+    // The route may override the body limits, see [crate::router::Router::limits]
+    let route = handler.route_limits(&req).unwrap_or_default();
+    let limits = &Limits {
+        body: route.max_body_size.unwrap_or(shared.limits.body),
+        ..shared.limits
+    };
+
+    if let Some(expectation) = req.headers.get("Expect") {
+        if !expectation.trim().eq_ignore_ascii_case("100-continue") {
+            return Err(Error::ExpectationFailed);
+        }
+
+        // HTTP/1.0 clients don't know interim responses, and bodiless requests have
+        // nothing to wait for
+        let framing = req.body_framing()?;
+
+        if req.version == HttpVersion::Http11 && framing != BodyFraming::Length(0) {
+            if matches!(framing, BodyFraming::Length(len) if len > limits.body) {
+                return Err(Error::BodyTooLarge);
+            }
+
+            if let Some(expect) = &shared.expect {
+                if !expect(&req) {
+                    return Err(Error::ExpectationFailed);
+                }
+            }
+
+            let stream = buf.get_mut();
+            stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+            stream.flush()?;
+        }
+    }
+
+    buf.get_ref()
+        .set_read_timeout(route.read_timeout.or(timeouts.read))?;
+    req.read_body(buf, limits)?;
+
+    Ok(req)
+}
+
+///
+/// Callback deciding whether a request sending `Expect: 100-continue` may send its body,
+/// see [Server::expect_continue].
+///
+type ExpectHandler = dyn Fn(&Request) -> bool + Send + Sync;
+
+///
+/// Callback building the [Response] to a request that failed, see [Server::error_handler].
+///
+pub(crate) type ErrorHandler = dyn Fn(StatusCode, &str) -> Response + Send + Sync;
+
+///
+/// Default [ErrorHandler], answering with the message as a JSON string.
+///
+fn default_error(status: StatusCode, message: &str) -> Response {
+    Response::new(status).json(&message)
+}
+
+///
+/// Builds the [Response] to a request rejected with `status`, described by its reason.
+///
+pub(crate) fn error_response(errors: &ErrorHandler, status: u16) -> Response {
+    errors(StatusCode::from(status), status_text(status))
+}
+
+///
+/// How long answering a connection refused by [Overload::Reject] may block accepting.
+///
+const REJECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+///
+/// Answers `503` with `Retry-After` on a connection beyond [Server::max_connections]
+/// and closes it, without reading its request.
+///
+fn reject_overloaded(mut stream: Stream, retry_after: Duration, shared: &Shared) {
+    // Sent from the accepting thread, so a client that doesn't read must not stall it
+    if stream.set_write_timeout(Some(REJECT_TIMEOUT)).is_err() {
+        return;
+    }
+
+    let retry_after = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    let res =
+        error_response(shared.errors.as_ref(), 503).header("Retry-After", &retry_after.to_string());
+
+    if finish(res, shared, false).send_to(&mut stream).is_ok() {
+        let _ = stream.shutdown(Shutdown::Write);
+    }
+}
+
+///
+/// Builds the `408` sent to clients that are too slow, always closing the connection.
+///
+fn request_timeout(errors: &ErrorHandler) -> Response {
+    error_response(errors, 408).set_header("Connection", "close")
+}
+
+impl Request {
+    ///
+    /// Creates a new [Request] instance by parsing it from any buffered reader, such as a
+    /// [Stream] yielded by [Server::listen], a unix socket or a byte slice.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` -> A buffered reader containing the [Request]
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Request, Error>` -> A Result containing the parsed [Request] or the [Error]
+    ///   making it invalid
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use http_rs::server::Request;
+    ///
+    /// let raw = b"GET /users?page=2 HTTP/1.1\r\nHost: localhost\r\n\r\n";
+    /// let req = Request::new(&raw[..]).unwrap();
+    ///
+    /// assert_eq!(req.route, "/users");
+    /// ```
+    ///
+    pub fn new<R: BufRead>(mut stream: R) -> Result<Request, Error> {
+        Request::read_from(&mut stream)
+    }
+
+    ///
+    /// Parses the next [Request] from a borrowed buffered reader, leaving any bytes
+    /// after it in the buffer so further requests can be read from the same connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` -> A buffered reader positioned at the start of a [Request]
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Request, Error>` -> A Result containing the parsed [Request] or the [Error]
+    ///   making it invalid, an [Error::Io] of kind [io::ErrorKind::UnexpectedEof] if the
+    ///   peer closed the connection
+    ///
+    pub fn read_from<R: BufRead>(stream: &mut R) -> Result<Request, Error> {
+        let limits = Limits::default();
+
+        let mut req = Request::read_head(stream, &limits, None)?;
+        req.read_body(stream, &limits)?;
+
+        Ok(req)
+    }
+
+    ///
+    /// Parses the request line and headers of a [Request], leaving the body unread
+    /// and empty.
+    ///
+    /// Fails with [Error::Timeout] if `deadline` passes before the head is complete,
+    /// checked after every line so trickling bytes can't extend it, and with the matching
+    /// [Error] if the head is malformed or breaks one of the [Limits].
+    ///
+    pub(crate) fn read_head<R: BufRead>(
+        stream: &mut R,
+        limits: &Limits,
+        deadline: Option<Instant>,
+    ) -> Result<Request, Error> {
+        let read_line = |stream: &mut R, max: usize, too_long: Error| {
+            let line = Request::read_line(stream, max, too_long)?;
+
+            match deadline {
+                Some(deadline) if Instant::now() > deadline => Err(Error::Timeout),
+                _ => Ok(line),
+            }
+        };
+
+        // Parse the request line (e.g., "GET /path HTTP/1.1"), leaving room for the
+        // method and version around the URI
+        let request_line = read_line(stream, limits.uri + 64, Error::UriTooLong)?;
+
+        let mut parts = request_line.split_ascii_whitespace();
+
+        // Parse HTTP method
+        let method = parts.next().unwrap_or("").parse::<HttpMethod>()?;
+
+        // Parse route and query parameters
+        let (target, route, query_params) =
+            Request::split_target(parts.next().unwrap_or(""), limits)?;
+
+        // Parse protocol version, HTTP/0.9 style requests carry no version token
+        let version = parts
+            .next()
+            .ok_or(Error::BadRequestLine)?
+            .parse::<HttpVersion>()?;
+
+        if parts.next().is_some() {
+            return Err(Error::BadRequestLine);
+        }
+
+        let mut headers = HeaderMap::new();
+
+        loop {
+            let line = read_line(stream, limits.header_line, Error::HeaderTooLarge)?;
+
+            if line.is_empty() {
+                break;
+            }
+
+            Request::parse_field(&mut headers, &line, limits)?;
+        }
+
+        Ok(Request::from_head(
+            method,
+            version,
+            (target, route, query_params),
+            headers,
+        ))
+    }
+
+    ///
+    /// Adds a header or trailer field line (e.g., "Host: localhost") to `fields`.
+    ///
+    fn parse_field(fields: &mut HeaderMap, line: &str, limits: &Limits) -> Result<(), Error> {
+        if fields.len() == limits.headers {
+            return Err(Error::HeaderTooLarge);
+        }
+
+        // A line starting with whitespace continues the previous one (obs-fold),
+        // which recipients may join differently, so it is rejected
+        if line.starts_with([' ', '\t']) {
+            return Err(Error::BadHeader);
+        }
+
+        // No whitespace is allowed between the name and the colon
+        match line.split_once(':') {
+            Some((name, value)) if is_token(name) => fields.append(name, value.trim()),
+            _ => return Err(Error::BadHeader),
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Parses a request-target (e.g., `/users?id=1`) into the [RequestTarget], its
+    /// decoded route and its query parameters.
+    ///
+    pub(crate) fn split_target(
+        raw_target: &str,
+        limits: &Limits,
+    ) -> Result<(RequestTarget, String, QueryParams), Error> {
+        if raw_target.len() > limits.uri {
+            return Err(Error::UriTooLong);
+        }
+
+        let target = parse_target(raw_target)?;
+
+        let (route, query_params) = match &target {
+            RequestTarget::Origin(raw) => parse_url(raw)?,
+            RequestTarget::Absolute(raw) => parse_url(absolute_path(raw))?,
+            RequestTarget::Authority(_) | RequestTarget::Asterisk => {
+                (String::new(), QueryParams::new())
+            }
+        };
+
+        Ok((target, route, query_params))
+    }
+
+    ///
+    /// Creates a bodiless [Request] from its parsed head, as returned by
+    /// [Request::split_target] for the target.
+    ///
+    pub(crate) fn from_head(
+        method: HttpMethod,
+        version: HttpVersion,
+        (target, route, query_params): (RequestTarget, String, QueryParams),
+        headers: HeaderMap,
+    ) -> Request {
+        Request {
+            method,
+            version,
+            route,
+            target,
+            headers,
+            query_params,
+            params: Params::new(),
+            body: Vec::new(),
+            trailers: HeaderMap::new(),
+            session: None,
+            remote_addr: None,
+            local_addr: None,
+            tls: false,
+            client_ip: None,
+            state: AppState::default(),
+            extensions: Extensions::new(),
+        }
+    }
+
+    ///
+    /// Reads the body following the head according to [Request::body_framing],
+    /// failing with [Error::BodyTooLarge] if it is larger than allowed.
+    ///
+    pub(crate) fn read_body<R: BufRead>(
+        &mut self,
+        stream: &mut R,
+        limits: &Limits,
+    ) -> Result<(), Error> {
+        self.body = match self.body_framing()? {
+            BodyFraming::Chunked => {
+                let (body, trailers) = Request::read_chunked(stream, limits)?;
+                self.trailers = trailers;
+
+                body
+            }
+            BodyFraming::Length(content_length) if content_length > limits.body => {
+                return Err(Error::BodyTooLarge)
+            }
+            BodyFraming::Length(content_length) => {
+                let mut body = vec![0; content_length];
+
+                if content_length > 0 {
+                    stream.read_exact(&mut body)?;
+                }
+
+                body
+            }
+        };
+
+        Ok(())
+    }
+
+    ///
+    /// Determines how the body following the headers is delimited.
+    ///
+    pub(crate) fn body_framing(&self) -> Result<BodyFraming, Error> {
+        let codings = self
+            .headers
+            .get_all("Transfer-Encoding")
+            .collect::<Vec<_>>()
+            .join(",");
+
+        // Proxies disagreeing on which header wins is how requests get smuggled, so a
+        // request with both is rejected rather than having `Transfer-Encoding` win
+        if !codings.is_empty() && self.headers.contains_key("Content-Length") {
+            return Err(Error::ConflictingFraming);
+        }
+
+        if is_chunked(&codings) {
+            return Ok(BodyFraming::Chunked);
+        }
+
+        if !codings.is_empty() {
+            return Err(Error::UnsupportedTransferCoding);
+        }
+
+        // Repeated `Content-Length` values must all agree, anything else is ambiguous
+        let mut content_length = None;
+
+        for value in self
+            .headers
+            .get_all("Content-Length")
+            .flat_map(|v| v.split(','))
+            .map(str::trim)
+        {
+            // Only digits, `parse` alone would also take a sign
+            if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(Error::InvalidContentLength);
+            }
+
+            let len = value
+                .parse::<usize>()
+                .map_err(|_| Error::InvalidContentLength)?;
+
+            if content_length.is_some_and(|l| l != len) {
+                return Err(Error::InvalidContentLength);
+            }
+
+            content_length = Some(len);
+        }
+
+        Ok(BodyFraming::Length(content_length.unwrap_or(0)))
+    }
+
+    ///
+    /// Decodes a `Transfer-Encoding: chunked` body, returning it with the fields of the
+    /// trailer section.
+    ///
+    /// Chunk extensions are ignored, trailer fields follow the same rules as headers.
+    ///
+    pub(crate) fn read_chunked<R: BufRead>(
+        stream: &mut R,
+        limits: &Limits,
+    ) -> Result<(Vec<u8>, HeaderMap), Error> {
+        let mut body = Vec::new();
+
+        loop {
+            // Chunk size line (e.g., "1a;ext=value")
+            let line = Request::read_line(stream, limits.header_line, Error::BadChunkedEncoding)?;
+            let size = chunk_size(&line)?;
+
+            if size == 0 {
+                break;
+            }
+
+            if size > limits.body - body.len() {
+                return Err(Error::BodyTooLarge);
+            }
+
+            let start = body.len();
+            body.resize(start + size, 0);
+            stream.read_exact(&mut body[start..])?;
+
+            // Every chunk's data is terminated by CRLF
+            if !Request::read_line(stream, limits.header_line, Error::BadChunkedEncoding)?
+                .is_empty()
+            {
+                return Err(Error::BadChunkedEncoding);
+            }
+        }
+
+        Ok((body, Request::read_trailers(stream, limits)?))
+    }
+
+    ///
+    /// Reads the trailer section following the last chunk, up to its empty line.
+    ///
+    pub(crate) fn read_trailers<R: BufRead>(
+        stream: &mut R,
+        limits: &Limits,
+    ) -> Result<HeaderMap, Error> {
+        let mut trailers = HeaderMap::new();
+
+        loop {
+            let line = Request::read_line(stream, limits.header_line, Error::HeaderTooLarge)?;
+
+            if line.is_empty() {
+                return Ok(trailers);
+            }
+
+            Request::parse_field(&mut trailers, &line, limits)?;
+        }
+    }
+
+    ///
+    /// Reads a single line from the [Stream] without its line ending, failing with
+    /// `too_long` if it is longer than `max` bytes.
+    ///
+    /// The line is scanned for in the reader's buffer and copied out once, so a line
+    /// already buffered costs a single allocation.
+    ///
+    pub(crate) fn read_line<R: BufRead>(
+        stream: &mut R,
+        max: usize,
+        too_long: Error,
+    ) -> Result<String, Error> {
+        let mut line = Vec::new();
+
+        // Room for the CRLF, so an exactly `max` long line still fits
+        let cap = max + 2;
+
+        loop {
+            let available = match stream.fill_buf() {
+                Ok(available) => available,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(Error::Io(e)),
+            };
+
+            if available.is_empty() {
+                if line.is_empty() {
+                    return Err(Error::Io(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Connection closed",
+                    )));
+                }
+
+                // The last line of a closed stream needs no line ending
+                break;
+            }
+
+            let window = &available[..available.len().min(cap - line.len())];
+
+            match window.iter().position(|&b| b == b'\n') {
+                Some(i) => {
+                    line.extend_from_slice(&window[..=i]);
+                    stream.consume(i + 1);
+
+                    break;
+                }
+                None => {
+                    let n = window.len();
+                    line.extend_from_slice(window);
+                    stream.consume(n);
+
+                    if line.len() == cap {
+                        return Err(too_long);
+                    }
+                }
+            }
+        }
+
+        let mut line = String::from_utf8(line).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            )
+        })?;
+
+        // Leading whitespace is kept, it is meaningful at the start of a header line
+        line.truncate(line.trim_end().len());
+
+        Ok(line)
+    }
+
+    ///
+    /// Whether the client asked to keep the connection open after this [Request].
+    ///
+    /// HTTP/1.1 connections are persistent unless `Connection: close` is sent,
+    /// HTTP/1.0 ones only when `Connection: keep-alive` is sent, and HTTP/2 ones always.
+    ///
+    pub fn keep_alive(&self) -> bool {
+        let has_token = |token: &str| has_token(&self.headers, "Connection", token);
+
+        match self.version {
+            HttpVersion::Http11 => !has_token("close"),
+            HttpVersion::Http10 => has_token("keep-alive"),
+            HttpVersion::Http2 => true,
+        }
+    }
+
+    ///
+    /// Whether the [Request] arrived over TLS, i.e. on a listener created with
+    /// [Server::new_tls] or [Server::bind_tls].
+    ///
+    /// `👉 Note:` Only reflects the connection to the server, not whether a proxy in front
+    /// of it terminated TLS
+    ///
+    pub fn is_tls(&self) -> bool {
+        self.tls
+    }
+
+    ///
+    /// Attempts to parse the [Request] body as `JSON` into the specified type `T`.
+    ///
+    /// # Parameters
+    ///
+    /// * `T` -> The type to deserialize the `JSON` into. **Must implement Deserialize.**
+    ///
+    /// # Returns
+    ///
+    /// * `Option<T>` -> The parsed `JSON` data or None if parsing fails
+    ///
+    #[deprecated(note = "use `req.json::<T>()`, which reports why parsing failed")]
+    pub fn get_json<T: for<'a> Deserialize<'a>>(&self) -> Option<T> {
+        serde_json::from_slice(&self.body).ok()
+    }
+
+    ///
+    /// Attempts to parse the [Request] body as `application/x-www-form-urlencoded` into
+    /// the specified type `T`, percent-decoding names and values and reading `+` as a space.
+    ///
+    /// `👉 Note:` Prefer `req.extract::<Form<T>>()` (see [crate::extract::Form]), which
+    /// checks the `Content-Type` and reports why parsing failed
+    ///
+    /// # Parameters
+    ///
+    /// * `T` -> The type to deserialize the form into (e.g., `HashMap<String, String>`).
+    ///   **Must implement Deserialize.**
+    ///
+    /// # Returns
+    ///
+    /// * `Option<T>` -> The parsed form data or None if parsing fails
+    ///
+    pub fn get_form<T: for<'a> Deserialize<'a>>(&self) -> Option<T> {
+        serde_urlencoded::from_bytes(&self.body).ok()
+    }
+
+    ///
+    /// Attempts to deserialize the [QueryParams] into the specified type `T`.
+    ///
+    /// Values are coerced into the field types (numbers, bools, ...), and missing
+    /// parameters are allowed for `Option` and `#[serde(default)]` fields.
+    ///
+    /// `👉 Note:` Shorthand for the preferred `req.extract::<Query<T>>()` (see
+    /// [crate::extract::Query]), which reports why parsing failed
+    ///
+    /// # Parameters
+    ///
+    /// * `T` -> The type to deserialize the query into. **Must implement Deserialize.**
+    ///
+    /// # Returns
+    ///
+    /// * `Option<T>` -> The parsed query or None if parsing fails
+    ///
+    /// # Example
+    ///
+    /// ```rust, no_run
+    /// use http_rs::extract::Query;
+    /// use http_rs::server::{Request, Response};
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Page {
+    ///     #[serde(default)]
+    ///     page: u32,
+    ///     search: Option<String>,
+    /// }
+    ///
+    /// fn handler(req: Request) -> Response {
+    ///     match req.extract::<Query<Page>>() {
+    ///         Ok(Query(query)) => Response::new(200).json(&query.page),
+    ///         Err(e) => e.into(),
+    ///     }
+    /// }
+    /// ```
+    ///
+    pub fn get_query<T: for<'a> Deserialize<'a>>(&self) -> Option<T> {
+        self.extract::<Query<T>>().ok().map(|Query(query)| query)
+    }
+}
+
+impl Response {
+    ///
+    /// Creates a new [Response] with the specified status code.
+    ///
+    /// # Arguments
+    ///
+    /// * `status` -> HTTP status code, as a [StatusCode] or `u16` (e.g., 200, 404)
+    ///
+    /// # Example
+    ///
+    /// ```rust, no_run
+    /// use http_rs::server::Response;
+    /// use http_rs::status::StatusCode;
+    ///
+    /// let response = Response::new(200);
+    /// let response = Response::new(StatusCode::NOT_FOUND);
+    /// ```
+    ///
+    pub fn new(status: impl Into<StatusCode>) -> Response {
+        let status = status.into().as_u16();
+        let mut headers = HeaderMap::new();
+        headers.insert("Content-Type", "application/json");
+
+        Response {
+            status,
+            reason: None,
+            headers,
+            body: Body::default(),
+            trailers: None,
+        }
+    }
+
+    ///
+    /// Sets a custom reason phrase for the status line and returns the modified response.
+    ///
+    /// `👉 Note:` A phrase with characters other than tabs, spaces and visible ones (e.g.,
+    /// CR or LF) is rejected, the standard one being kept
+    ///
+    /// # Arguments
+    ///
+    /// * `reason` -> Reason phrase sent instead of the standard one
+    ///
+    /// # Example
+    ///
+    /// ```rust, no_run
+    /// use http_rs::server::Response;
+    ///
+    /// let response = Response::new(299).reason("Custom");
+    /// ```
+    ///
+    pub fn reason(mut self, reason: &str) -> Response {
+        // HTAB, SP, VCHAR or obs-text, as non-ASCII characters are only made of the latter
+        let valid = reason
+            .bytes()
+            .all(|b| b == b'\t' || b == b' ' || b.is_ascii_graphic() || !b.is_ascii());
+
+        if valid {
+            self.reason = Some(reason.to_string());
+        }
+
+        self
+    }
+
+    ///
+    /// Sets the [Response] body as `JSON` and returns the modified response.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` -> Data to be serialized to `JSON`. **Must implement Serialize.**
+    ///
+    /// # Returns
+    ///
+    /// Modified [Response] with `JSON` body and updated `Content-Length` header
+    ///
+    pub fn json<T: Serialize>(self, data: &T) -> Response {
+        let body = serde_json::to_vec(data).unwrap_or_default();
+
+        self.bytes("application/json", body)
+    }
+
+    ///
+    /// Sets the [Response] body as plain text and returns the modified response.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` -> The text sent as `text/plain; charset=utf-8`
+    ///
+    /// # Example
+    ///
+    /// ```rust, no_run
+    /// use http_rs::server::Response;
+    ///
+    /// let response = Response::new(200).header("X-Trace", "abc").text("hi");
+    /// ```
+    ///
+    pub fn text(self, text: &str) -> Response {
+        self.bytes("text/plain; charset=utf-8", text.as_bytes().to_vec())
+    }
+
+    ///
+    /// Sets the [Response] body as HTML and returns the modified response.
+    ///
+    /// # Arguments
+    ///
+    /// * `html` -> The markup sent as `text/html; charset=utf-8`
+    ///
+    pub fn html(self, html: &str) -> Response {
+        self.bytes("text/html; charset=utf-8", html.as_bytes().to_vec())
+    }
+
+    ///
+    /// Sets the [Response] body to raw bytes of the given type and returns the modified
+    /// response.
+    ///
+    /// # Arguments
+    ///
+    /// * `content_type` -> Value of the `Content-Type` header (e.g., "image/png")
+    /// * `bytes` -> The body
+    ///
+    pub fn bytes(self, content_type: &str, bytes: Vec<u8>) -> Response {
+        self.set_header("Content-Type", content_type)
+            .body(Body::Full(bytes))
+    }
+
+    ///
+    /// Sets the [Response] body without touching `Content-Type`, and returns the
+    /// modified response. Framing headers follow the [Body]: `Content-Length` for full
+    /// and file bodies, `Transfer-Encoding: chunked` for streamed ones.
+    ///
+    /// # Arguments
+    ///
+    /// * `body` -> Anything convertible into a [Body] (e.g., `Vec<u8>`, `String`, `&str`)
+    ///
+    pub fn body(mut self, body: impl Into<Body>) -> Response {
+        self.body = body.into();
+
+        let len = match &self.body {
+            Body::Full(bytes) => Some(bytes.len() as u64),
+            Body::File { len, .. } => Some(*len),
+            Body::Stream(_) | Body::Producer(_) => None,
+        };
+
+        match len {
+            Some(len) => {
+                self.headers.remove("Transfer-Encoding");
+                self.headers.insert("Content-Length", len.to_string());
+            }
+            None => {
+                self.headers.remove("Content-Length");
+                self.headers.insert("Transfer-Encoding", "chunked");
+            }
+        }
+
+        self
+    }
+
+    ///
+    /// Streams the [Response] body from a reader using `Transfer-Encoding: chunked`,
+    /// so large payloads are sent without being loaded into memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` -> Source of the body, read until EOF while sending
+    ///
+    /// # Example
+    ///
+    /// ```rust, no_run
+    /// use http_rs::server::Response;
+    /// use std::fs::File;
+    ///
+    /// let file = File::open("dump.bin").unwrap();
+    /// let response = Response::new(200)
+    ///     .set_header("Content-Type", "application/octet-stream")
+    ///     .stream(file);
+    /// ```
+    ///
+    pub fn stream<R: Read + Send + 'static>(self, reader: R) -> Response {
+        self.body(Body::Stream(Box::new(reader)))
+    }
+
+    ///
+    /// Declares trailer fields sent after a streamed body, produced once the whole body
+    /// was sent (e.g., `Server-Timing`, a checksum computed while streaming).
+    ///
+    /// The names are announced in the `Trailer` header, and only the declared fields are
+    /// sent. Trailers need a chunked body (see [Response::stream]) over HTTP/1.1, or
+    /// HTTP/2, and are dropped otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `names` -> Names of the fields the producer may return
+    /// * `produce` -> Returns the fields, called after the last chunk
+    ///
+    /// # Example
+    ///
+    /// ```rust, no_run
+    /// use http_rs::header::HeaderMap;
+    /// use http_rs::server::Response;
+    /// use std::{fs::File, time::Instant};
+    ///
+    /// let start = Instant::now();
+    /// let response = Response::new(200)
+    ///     .stream(File::open("report.csv").unwrap())
+    ///     .trailers(&["Server-Timing"], move || {
+    ///         let mut fields = HeaderMap::new();
+    ///         let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+    ///         fields.insert("Server-Timing", format!("total;dur={:.1}", elapsed));
+    ///
+    ///         fields
+    ///     });
+    /// ```
+    ///
+    pub fn trailers<F>(mut self, names: &[&str], produce: F) -> Response
+    where
+        F: FnOnce() -> HeaderMap + Send + 'static,
+    {
+        self.headers.insert("Trailer", names.join(", "));
+        self.trailers = Some(Trailers::new(
+            names.iter().map(|name| name.to_string()).collect(),
+            Box::new(produce),
+        ));
+
+        self
+    }
+
+    ///
+    /// Sets the [Response] body to a file and returns the modified response.
+    ///
+    /// `Content-Type` is guessed from the extension and `Content-Length` taken from the
+    /// file's metadata. The file is read while sending rather than loaded into memory,
+    /// and [Server::serve] has the kernel copy it straight to plain TCP connections
+    /// (`sendfile` on Linux).
+    ///
+    /// # Arguments
+    ///
+    /// * `path` -> Path of the file to send
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<Response>` -> The modified response, or an [std::io] error if the
+    ///   file can't be opened or isn't a regular file
+    ///
+    /// # Example
+    ///
+    /// ```rust, no_run
+    /// use http_rs::server::Response;
+    ///
+    /// let response = Response::new(200).file("assets/video.mp4").unwrap();
+    /// ```
+    ///
+    pub fn file(self, path: impl AsRef<Path>) -> io::Result<Response> {
+        let path = path.as_ref();
+        let file = fs::File::open(path)?;
+        let metadata = file.metadata()?;
+
+        if !metadata.is_file() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "not a regular file",
+            ));
+        }
+
+        Ok(self
+            .set_header("Content-Type", guess_mime(path))
+            .body(Body::File {
+                file,
+                len: metadata.len(),
+            }))
+    }
+
+    ///
+    /// Sets a header on the [Response], replacing every existing value of that name.
+    ///
+    /// Shorthand for [Response::set_header] when building a response.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` -> Header name, matched case-insensitively
+    /// * `value` -> Header value
+    ///
+    pub fn header(self, name: &str, value: &str) -> Response {
+        self.set_header(name, value)
+    }
+
+    ///
+    /// Sets a header on the [Response], replacing every existing value of that name.
+    ///
+    /// `👉 Note:` CR, LF and NUL are stripped from the value, and a name which isn't a
+    /// token is ignored, so a header can't split the response
+    ///
+    /// # Arguments
+    ///
+    /// * `name` -> Header name, matched case-insensitively
+    /// * `value` -> Header value
+    ///
+    /// # Example
+    ///
+    /// ```rust, no_run
+    /// use http_rs::server::Response;
+    ///
+    /// let response = Response::new(200).set_header("Cache-Control", "no-cache");
+    /// ```
+    ///
+    pub fn set_header(mut self, name: &str, value: &str) -> Response {
+        self.headers.insert(name, value);
+
+        self
+    }
+
+    ///
+    /// Appends a header value to the [Response], keeping any existing values of that
+    /// name so headers like `Set-Cookie`, `Link` or `Via` can be sent multiple times.
+    ///
+    /// `👉 Note:` CR, LF and NUL are stripped from the value, and a name which isn't a
+    /// token is ignored, so a header can't split the response
+    ///
+    /// # Arguments
+    ///
+    /// * `name` -> Header name
+    /// * `value` -> Header value
+    ///
+    /// # Example
+    ///
+    /// ```rust, no_run
+    /// use http_rs::server::Response;
+    ///
+    /// let response = Response::new(200)
+    ///     .append_header("Set-Cookie", "a=1")
+    ///     .append_header("Set-Cookie", "b=2");
+    /// ```
+    ///
+    pub fn append_header(mut self, name: &str, value: &str) -> Response {
+        self.headers.append(name, value);
+
+        self
+    }
+
+    ///
+    /// Sends the [Response] to any writer, such as a [Stream], a [TcpStream], a unix
+    /// socket or an in-memory buffer.
+    ///
+    /// The writer is flushed once the body is written, so buffered writers can be passed
+    /// as is. Streamed bodies are chunk-encoded as long as `Transfer-Encoding: chunked`
+    /// is set, which [Server::serve] removes for HTTP/1.0 clients.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` -> The writer to write the response to
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<()>` -> Ok if the response was sent successfully or an [std::io] error
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use http_rs::server::Response;
+    ///
+    /// let mut raw = Vec::new();
+    /// Response::new(204).send(&mut raw).unwrap();
+    ///
+    /// assert!(raw.starts_with(b"HTTP/1.1 204 No Content\r\n"));
+    /// ```
+    ///
+    pub fn send<W: Write>(self, stream: &mut W) -> io::Result<()> {
+        let chunked = self
+            .headers
+            .get("Transfer-Encoding")
+            .is_some_and(is_chunked);
+
+        with_head_buffer(|head| {
+            self.write_head(head);
+
+            match self.body {
+                // Head and body in one write, without copying the body next to the head
+                Body::Full(bytes) => body::write_all_vectored(
+                    stream,
+                    &mut [io::IoSlice::new(head), io::IoSlice::new(&bytes)],
+                )?,
+                body => {
+                    stream.write_all(head)?;
+                    body.write_to(stream, chunked, self.trailers)?;
+                }
+            }
+
+            stream.flush()
+        })
+    }
+
+    ///
+    /// Sends the [Response] over a client connection as [Response::send] does, with file
+    /// bodies handed to [Stream::send_file].
+    ///
+    pub(crate) fn send_to(mut self, stream: &mut Stream) -> io::Result<()> {
+        match std::mem::take(&mut self.body) {
+            Body::File { file, len } => with_head_buffer(|head| {
+                self.write_head(head);
+                stream.write_all(head)?;
+                stream.send_file(file, len)?;
+                stream.flush()
+            }),
+            body => {
+                self.body = body;
+                self.send(stream)
+            }
+        }
+    }
+
+    ///
+    /// Appends the status line and headers, including the blank line ending them, to
+    /// `out` without intermediate allocations.
+    ///
+    pub(crate) fn write_head(&self, out: &mut Vec<u8>) {
+        let status_text = self
+            .reason
+            .as_deref()
+            .unwrap_or_else(|| status_text(self.status));
+
+        // Writing to a Vec can't fail
+        let _ = write!(out, "HTTP/1.1 {} {}\r\n", self.status, status_text);
+
+        for (k, v) in self.headers.iter() {
+            let _ = write!(out, "{}: {}\r\n", k, v);
+        }
+
+        out.extend_from_slice(b"\r\n");
+    }
+}
+
+///
+/// Largest head buffer kept for reuse, ones grown past it (by huge headers) are dropped.
+///
+const HEAD_BUFFER_KEPT: usize = 16 * 1024;
+
+thread_local! {
+    ///
+    /// Buffer heads are formatted into, reused by every response a thread sends.
+    ///
+    static HEAD_BUFFER: std::cell::Cell<Vec<u8>> = const { std::cell::Cell::new(Vec::new()) };
+}
+
+///
+/// Runs `f` with the thread's empty head buffer.
+///
+fn with_head_buffer<T>(f: impl FnOnce(&mut Vec<u8>) -> T) -> T {
+    // Taken rather than borrowed, so a nested send (e.g., from a streamed body) just
+    // gets a fresh one
+    let mut buf = HEAD_BUFFER.take();
+    buf.clear();
+
+    let result = f(&mut buf);
+
+    if buf.capacity() <= HEAD_BUFFER_KEPT {
+        HEAD_BUFFER.set(buf);
+    }
+
+    result
+}
+
+///
+/// Whether a string is a non-empty RFC 9110 `token`, the syntax of method names,
+/// header names and media types.
+///
+pub(crate) fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+///
+/// Parses the size of a chunk from its size line (e.g., `1a;ext=value`).
+///
+pub(crate) fn chunk_size(line: &str) -> Result<usize, Error> {
+    let size = line.split(';').next().unwrap_or("").trim();
+
+    // Only hex digits, `from_str_radix` alone would also take a sign
+    if size.is_empty() || !size.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(Error::BadChunkedEncoding);
+    }
+
+    usize::from_str_radix(size, 16).map_err(|_| Error::BadChunkedEncoding)
+}
+
+///
+/// Whether `chunked` is the final coding of a `Transfer-Encoding` value.
+///
+pub(crate) fn is_chunked(codings: &str) -> bool {
+    codings
+        .rsplit(',')
+        .next()
+        .is_some_and(|coding| coding.trim().eq_ignore_ascii_case("chunked"))
+}
+
+///
+/// Returns the standard reason phrase for a status code.
+///
+fn status_text(status: u16) -> &'static str {
+    StatusCode::from(status)
+        .canonical_reason()
+        .unwrap_or("Unknown")
+}
+
+///
+/// Parses a URL string into a decoded route and [QueryParams].
+///
+/// # Arguments
+///
+/// * `raw_route` -> The raw URL string to parse
+///
+/// # Returns
+///
+/// `Result<(String, QueryParams), Error>` -> Tuple containing the route string and a
+/// HashMap of decoded [QueryParams], or [Error::InvalidTarget] on invalid percent-encoding
+///
+pub(crate) fn parse_url(raw_route: &str) -> Result<(String, QueryParams), Error> {
+    let (path, query) = raw_route.split_once('?').unwrap_or((raw_route, ""));
+
+    let route = decode_path(path).ok_or(Error::InvalidTarget)?;
+
+    let query_params = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+
+            match (decode_query(name), decode_query(value)) {
+                (Some(name), Some(value)) => Ok((name, value)),
+                _ => Err(Error::InvalidTarget),
+            }
+        })
+        .collect::<Result<_, Error>>()?;
+
+    Ok((route, query_params))
+}
+
+///
+/// Decodes `%XX` sequences, failing on malformed sequences or non UTF-8 results.
+///
+/// # Returns
+///
+/// * `Option<String>` -> The decoded string, or None if it is invalid
+///
+pub(crate) fn percent_decode(s: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = tail.get(..2)?;
+
+            // Only hex digits, `from_str_radix` alone would also take a sign
+            if !hex.iter().all(u8::is_ascii_hexdigit) {
+                return None;
+            }
+
+            bytes.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+///
+/// Decodes a path segment by segment, re-encoding `%` and `/` inside segments so
+/// an encoded slash can't create a new segment.
+///
+fn decode_path(path: &str) -> Option<String> {
+    let segments = path
+        .split('/')
+        .map(|segment| {
+            let decoded = percent_decode(segment)?;
+
+            Some(decoded.replace('%', "%25").replace('/', "%2F"))
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(segments.join("/"))
+}
+
+///
+/// Decodes a query string name or value, where `+` stands for a space.
+///
+fn decode_query(s: &str) -> Option<String> {
+    percent_decode(&s.replace('+', " "))
+}
+
+///
+/// Classifies a raw request-target into one of the [RequestTarget] forms.
+///
+/// # Arguments
+///
+/// * `raw_target` -> The request-target as it appears in the request line
+///
+/// # Returns
+///
+/// * `Result<RequestTarget, Error>` -> The classified target or [Error::InvalidTarget]
+///   if it matches none of the forms
+///
+fn parse_target(raw_target: &str) -> Result<RequestTarget, Error> {
+    if raw_target == "*" {
+        return Ok(RequestTarget::Asterisk);
+    }
+
+    if raw_target.starts_with('/') {
+        return Ok(RequestTarget::Origin(raw_target.to_string()));
+    }
+
+    if let Some((scheme, rest)) = raw_target.split_once("://") {
+        if scheme.is_empty() || rest.is_empty() {
+            return Err(Error::InvalidTarget);
+        }
+
+        return Ok(RequestTarget::Absolute(raw_target.to_string()));
+    }
+
+    // Authority form must be exactly `host:port`
+    match raw_target.rsplit_once(':') {
+        Some((host, port))
+            if !host.is_empty() && !host.contains('/') && port.parse::<u16>().is_ok() =>
+        {
+            Ok(RequestTarget::Authority(raw_target.to_string()))
+        }
+        _ => Err(Error::InvalidTarget),
+    }
+}
+
+///
+/// Returns the path and query portion of an absolute-form URI, defaulting to `/`.
+///
+fn absolute_path(uri: &str) -> &str {
+    let rest = uri.split_once("://").map_or(uri, |(_, rest)| rest);
+
+    match rest.find(['/', '?']) {
+        Some(idx) if rest[idx..].starts_with('/') => &rest[idx..],
+        _ => "/",
+    }
+}
+
+///
+/// Builds a bare [Request] for unit tests without going through a [Stream].
+///
+#[cfg(test)]
+pub(crate) fn test_request(method: HttpMethod, raw_target: &str) -> Request {
+    let target = parse_target(raw_target).unwrap();
+    let (route, query_params) = parse_url(raw_target).unwrap();
+
+    Request {
+        route,
+        target,
+        method,
+        version: HttpVersion::Http11,
+        headers: HeaderMap::new(),
+        query_params,
+        params: Params::new(),
+        body: Vec::new(),
+        trailers: HeaderMap::new(),
+        session: None,
+        remote_addr: None,
+        local_addr: None,
+        tls: false,
+        client_ip: None,
+        state: AppState::default(),
+        extensions: Extensions::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use std::io::{self, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct User {
+        id: u32,
+        name: String,
+    }
+
     ///
-    /// * `io::Result<()>` -> Ok if the response was sent successfully or an [std::io] error
+    /// Helper function to create a mock TCP stream with a given request string
     ///
-    pub fn send(self, stream: &mut TcpStream) -> io::Result<()> {
-        let status_text = match self.status {
-            200 => "OK",
-            201 => "Created",
-            400 => "Bad Request",
-            404 => "Not Found",
-            500 => "Internal Server Error",
-            _ => "Unknown",
-        };
+    fn create_mock_stream(request: &str) -> io::Result<(Stream, Stream)> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+
+        let client = TcpStream::connect(addr)?;
+        let (server, _) = listener.accept()?;
+
+        let request_owned = request.to_string();
+        let client_clone = client.try_clone()?;
+
+        thread::spawn(move || {
+            let mut client = client_clone;
+
+            client.write_all(request_owned.as_bytes()).unwrap();
+
+            // Flush after writing
+            client.flush().unwrap();
+        });
+
+        Ok((server.try_clone()?.into(), server.into()))
+    }
+
+    ///
+    /// Helper function to send a [Response] into a buffer and collect the raw bytes
+    ///
+    fn send_to_string(response: Response) -> io::Result<String> {
+        let mut raw = Vec::new();
+        response.send(&mut raw)?;
+
+        String::from_utf8(raw).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    #[test]
+    fn test_send_over_stream() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        let mut server = Stream::from(server);
+
+        Response::new(200).text("hi").send(&mut server).unwrap();
+        drop(server);
+
+        let mut raw = String::new();
+        client.read_to_string(&mut raw).unwrap();
+
+        assert!(raw.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(raw.ends_with("\r\n\r\nhi"));
+    }
+
+    #[test]
+    fn test_request_parsing_get() {
+        let request = "GET /test?key=value HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        let (server, stream) = create_mock_stream(request).unwrap();
+
+        let buf = BufReader::new(stream);
+        let parsed_request = Request::new(buf).unwrap();
+
+        assert_eq!(parsed_request.method, HttpMethod::GET);
+        assert_eq!(parsed_request.route, "/test");
+        assert_eq!(
+            parsed_request.query_params.get("key"),
+            Some(&"value".to_string())
+        );
+        assert_eq!(parsed_request.headers.get("host"), Some("localhost"));
+        assert_eq!(parsed_request.body.len(), 0);
+
+        // Close the server stream
+        drop(server);
+    }
+
+    #[test]
+    fn test_parse_url() {
+        let (route, query_params) = parse_url("/path?key1=value1&key2=value2").unwrap();
+
+        assert_eq!(route, "/path");
+        assert_eq!(query_params.get("key1"), Some(&"value1".to_string()));
+        assert_eq!(query_params.get("key2"), Some(&"value2".to_string()));
+    }
+
+    #[test]
+    fn test_parse_url_decoding() {
+        let (route, query_params) =
+            parse_url("/files/my%20docs/a%2Fb?q=hello%20world+again&k%C3%A9y=a%3Db").unwrap();
+
+        assert_eq!(route, "/files/my docs/a%2Fb");
+        assert_eq!(
+            query_params.get("q"),
+            Some(&"hello world again".to_string())
+        );
+        assert_eq!(query_params.get("kéy"), Some(&"a=b".to_string()));
+
+        assert!(parse_url("/bad%zz").is_err());
+        assert!(parse_url("/ok?q=%E2%28").is_err());
+        assert!(parse_url("/ok?q=%4").is_err());
+        assert!(parse_url("/ok?q=%+5").is_err());
+        assert!(parse_url("/%-1").is_err());
+        assert_eq!(percent_decode("%+5"), None);
+        assert_eq!(percent_decode("%4a%4A"), Some("JJ".to_string()));
+    }
+
+    #[test]
+    fn test_parse_target_forms() {
+        assert_eq!(
+            parse_target("/users?id=1").unwrap(),
+            RequestTarget::Origin("/users?id=1".to_string())
+        );
+        assert_eq!(
+            parse_target("http://example.com/users").unwrap(),
+            RequestTarget::Absolute("http://example.com/users".to_string())
+        );
+        assert_eq!(
+            parse_target("example.com:443").unwrap(),
+            RequestTarget::Authority("example.com:443".to_string())
+        );
+        assert_eq!(parse_target("*").unwrap(), RequestTarget::Asterisk);
+
+        assert!(parse_target("").is_err());
+        assert!(parse_target("example.com").is_err());
+    }
+
+    #[test]
+    fn test_absolute_form_route() {
+        let request = "GET http://example.com/users?page=2 HTTP/1.1\r\n\r\n";
+        let (_, stream) = create_mock_stream(request).unwrap();
+
+        let buf = BufReader::new(stream);
+        let parsed_request = Request::new(buf).unwrap();
+
+        assert_eq!(parsed_request.route, "/users");
+        assert_eq!(
+            parsed_request.query_params.get("page"),
+            Some(&"2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_response_reason_phrase() {
+        let raw = send_to_string(Response::new(404)).unwrap();
+        assert!(raw.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+        let raw = send_to_string(Response::new(299).reason("Custom")).unwrap();
+        assert!(raw.starts_with("HTTP/1.1 299 Custom\r\n"));
+
+        let raw = send_to_string(Response::new(200).reason("OK\r\nSet-Cookie: a=1")).unwrap();
+        assert!(raw.starts_with("HTTP/1.1 200 OK\r\n") && !raw.contains("Set-Cookie"));
+
+        let raw = send_to_string(Response::new(200).reason("Fine\0")).unwrap();
+        assert!(raw.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+
+    #[test]
+    fn test_response_header_order() {
+        let raw = send_to_string(Response::new(200).json(&"hi").json(&"hello")).unwrap();
+
+        assert_eq!(
+            raw,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 7\r\n\r\n\"hello\""
+        );
+    }
+
+    #[test]
+    fn test_append_and_set_header() {
+        let response = Response::new(204)
+            .append_header("Set-Cookie", "a=1")
+            .append_header("Set-Cookie", "b=2")
+            .append_header("Via", "proxy-1")
+            .append_header("Via", "proxy-2")
+            .set_header("via", "proxy-3");
+
+        let raw = send_to_string(response).unwrap();
+
+        assert!(raw.contains("Set-Cookie: a=1\r\nSet-Cookie: b=2\r\n"));
+        assert!(raw.contains("Via: proxy-3\r\n"));
+        assert!(!raw.contains("proxy-1") && !raw.contains("proxy-2"));
+    }
+
+    #[test]
+    fn test_serve_header_injection() {
+        let server = Server::new("127.0.0.1:0").unwrap().workers(1);
+        let addr = server.local_addr().unwrap();
+
+        // The decoded route turns `%0D%0A` into CRLF
+        thread::spawn(move || {
+            server.serve(|req: Request| Response::new(302).header("Location", &req.route))
+        });
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(b"GET /a%0D%0ASet-Cookie:%20admin=1 HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .unwrap();
+
+        let mut raw = String::new();
+        client.read_to_string(&mut raw).unwrap();
+
+        assert!(raw.starts_with("HTTP/1.1 302 Found\r\n"));
+        assert!(raw.contains("\r\nLocation: /aSet-Cookie: admin=1\r\n"));
+        assert!(!raw.contains("\r\nSet-Cookie"));
+    }
+
+    #[test]
+    fn test_serve_concurrent_connections() {
+        let server = Server::new("127.0.0.1:0").unwrap().workers(2);
+        let addr = server.local_addr().unwrap();
+
+        thread::spawn(move || server.serve(|_| Response::new(200).json(&"ok")));
+
+        // A client which never sends anything must not block the next one
+        let _idle = TcpStream::connect(addr).unwrap();
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .unwrap();
+
+        let mut raw = String::new();
+        client.read_to_string(&mut raw).unwrap();
+
+        assert!(raw.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(raw.ends_with("\"ok\""));
+    }
+
+    #[test]
+    fn test_serve_keep_alive() {
+        let server = Server::new("127.0.0.1:0").unwrap().workers(1);
+        let addr = server.local_addr().unwrap();
+
+        thread::spawn(move || server.serve(|req: Request| Response::new(200).json(&req.route)));
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(client.try_clone().unwrap());
+
+        client.write_all(b"GET /first HTTP/1.1\r\n\r\n").unwrap();
+
+        let mut head = String::new();
+        while !head.ends_with("\r\n\r\n") {
+            reader.read_line(&mut head).unwrap();
+        }
+        let mut body = [0; 8];
+        reader.read_exact(&mut body).unwrap();
+
+        assert!(head.contains("Connection: keep-alive\r\n"));
+        assert_eq!(&body, b"\"/first\"");
+
+        // The same connection is reused, and closed once asked to
+        client
+            .write_all(b"GET /second HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .unwrap();
+
+        let mut raw = String::new();
+        reader.read_to_string(&mut raw).unwrap();
+
+        assert!(raw.contains("Connection: close\r\n"));
+        assert!(raw.ends_with("\"/second\""));
+    }
+
+    #[test]
+    fn test_serve_keep_alive_empty_body() {
+        let server = Server::new("127.0.0.1:0").unwrap().workers(1);
+        let addr = server.local_addr().unwrap();
+
+        thread::spawn(move || {
+            server.serve(|req: Request| match req.route.as_str() {
+                "/moved" => Response::new(302).header("Location", "/"),
+                "/none" => Response::new(204),
+                _ => Response::new(200),
+            })
+        });
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        let mut reader = BufReader::new(client.try_clone().unwrap());
+
+        // Each response must end with its head, or the next one is never reached
+        for (target, status) in [("/", 200), ("/moved", 302), ("/none", 204), ("/", 200)] {
+            client
+                .write_all(format!("GET {} HTTP/1.1\r\n\r\n", target).as_bytes())
+                .unwrap();
+
+            let mut head = String::new();
+            while !head.ends_with("\r\n\r\n") {
+                reader.read_line(&mut head).unwrap();
+            }
+
+            assert!(head.starts_with(&format!("HTTP/1.1 {} ", status)));
+            assert!(head.contains("Connection: keep-alive\r\n"));
+            assert_eq!(
+                head.contains("Content-Length: 0\r\n"),
+                status != 204,
+                "{}",
+                head
+            );
+        }
+    }
+
+    ///
+    /// Splits raw `Content-Length` delimited responses into their heads and bodies.
+    ///
+    fn split_responses(mut raw: &str, head_requests: &[usize]) -> Vec<(String, String)> {
+        let mut responses = Vec::new();
+
+        while !raw.is_empty() {
+            let end = raw.find("\r\n\r\n").unwrap() + 4;
+            let head = &raw[..end];
+            assert!(
+                head.starts_with("HTTP/1.1 "),
+                "stray bytes before {:?}",
+                head
+            );
+
+            let len = match head_requests.contains(&responses.len()) {
+                true => 0,
+                false => head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("Content-Length: "))
+                    .unwrap()
+                    .parse::<usize>()
+                    .unwrap(),
+            };
+
+            responses.push((head.to_string(), raw[end..end + len].to_string()));
+            raw = &raw[end + len..];
+        }
+
+        responses
+    }
+
+    #[test]
+    fn test_serve_pipelined() {
+        let server = Server::new("127.0.0.1:0").unwrap().workers(1);
+        let addr = server.local_addr().unwrap();
+
+        // A plain handler, not a Router, so the server itself must drop `HEAD` bodies
+        thread::spawn(move || {
+            server.serve(|req: Request| {
+                let body = String::from_utf8_lossy(&req.body).to_string();
+
+                Response::new(200).text(&format!("{} {} {}", req.method, req.route, body))
+            })
+        });
+
+        let mut client = TcpStream::connect(addr).unwrap();
+
+        // Every request is sent before reading any response, the last one after the
+        // connection is asked to close
+        client
+            .write_all(
+                b"GET /a HTTP/1.1\r\n\r\n\
+                  HEAD /h HTTP/1.1\r\n\r\n\
+                  POST /b HTTP/1.1\r\nContent-Length: 3\r\n\r\nxyz\
+                  GET /c HTTP/1.1\r\nConnection: close\r\n\r\n\
+                  GET /d HTTP/1.1\r\n\r\n",
+            )
+            .unwrap();
+
+        let mut raw = String::new();
+        client.read_to_string(&mut raw).unwrap();
+
+        let responses = split_responses(&raw, &[1]);
+        let bodies: Vec<&str> = responses.iter().map(|(_, body)| body.as_str()).collect();
+
+        assert_eq!(bodies, ["GET /a ", "", "POST /b xyz", "GET /c "]);
+        assert!(responses[1].0.contains("Content-Length: 8\r\n"));
+        assert!(responses[2].0.contains("Connection: keep-alive\r\n"));
+        assert!(responses[3].0.contains("Connection: close\r\n"));
+    }
+
+    #[test]
+    fn test_serve_pipelined_after_rejection() {
+        let server = Server::new("127.0.0.1:0")
+            .unwrap()
+            .workers(1)
+            .max_body_size(4);
+        let addr = server.local_addr().unwrap();
+
+        thread::spawn(move || server.serve(|req: Request| Response::new(200).json(&req.route)));
+
+        let mut client = TcpStream::connect(addr).unwrap();
+
+        // The rejected request's body can't be skipped reliably, so the requests queued
+        // behind it aren't answered
+        client
+            .write_all(
+                b"GET /ok HTTP/1.1\r\n\r\n\
+                  POST /big HTTP/1.1\r\nContent-Length: 10\r\n\r\n0123456789\
+                  GET /after HTTP/1.1\r\n\r\n",
+            )
+            .unwrap();
+
+        let mut raw = String::new();
+        client.read_to_string(&mut raw).unwrap();
+
+        let responses = split_responses(&raw, &[]);
+
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0].1, "\"/ok\"");
+        assert!(responses[1].0.starts_with("HTTP/1.1 413 "));
+        assert!(responses[1].0.contains("Connection: close\r\n"));
+    }
+
+    #[test]
+    fn test_serve_http10() {
+        let server = Server::new("127.0.0.1:0").unwrap().workers(1);
+        let addr = server.local_addr().unwrap();
+
+        thread::spawn(move || {
+            server.serve(|req: Request| match req.route.as_str() {
+                "/stream" => Response::new(200).stream(io::Cursor::new(b"streamed".to_vec())),
+                _ => Response::new(200).text("full"),
+            })
+        });
+
+        // Persistent only when asked for
+        let mut client = TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(client.try_clone().unwrap());
+
+        client
+            .write_all(b"GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\n")
+            .unwrap();
+
+        let mut head = String::new();
+        while !head.ends_with("\r\n\r\n") {
+            reader.read_line(&mut head).unwrap();
+        }
+        let mut body = [0; 4];
+        reader.read_exact(&mut body).unwrap();
+
+        assert!(head.contains("Connection: keep-alive\r\n"));
+        assert_eq!(&body, b"full");
+
+        // Streamed bodies aren't chunked but end with the connection
+        client
+            .write_all(b"GET /stream HTTP/1.0\r\nConnection: keep-alive\r\n\r\n")
+            .unwrap();
+
+        let mut raw = String::new();
+        reader.read_to_string(&mut raw).unwrap();
+
+        assert!(raw.contains("Connection: close\r\n"));
+        assert!(!raw.contains("Transfer-Encoding"));
+        assert!(raw.ends_with("\r\n\r\nstreamed"));
+
+        // And closed by default
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(b"GET / HTTP/1.0\r\n\r\n").unwrap();
+
+        let mut raw = String::new();
+        client.read_to_string(&mut raw).unwrap();
+
+        assert!(raw.contains("Connection: close\r\n"));
+        assert!(raw.ends_with("full"));
+    }
+
+    #[test]
+    fn test_serve_default_headers() {
+        let server = Server::new("127.0.0.1:0")
+            .unwrap()
+            .workers(1)
+            .server_header(Some("test/1.0"));
+        let addr = server.local_addr().unwrap();
+
+        thread::spawn(move || {
+            server.serve(|_| Response::new(200).header("Connection", "close").text("bye"))
+        });
+
+        // The handler closes the connection although the client wants to keep it
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+
+        let mut raw = String::new();
+        client.read_to_string(&mut raw).unwrap();
+
+        let date = raw
+            .lines()
+            .find_map(|line| line.strip_prefix("Date: "))
+            .unwrap();
+
+        assert!(date::parse_http_date(date).is_some());
+        assert!(raw.contains("Server: test/1.0\r\n"));
+        assert!(raw.contains("Connection: close\r\n"));
+        assert!(!raw.contains("keep-alive"));
+        assert!(raw.ends_with("bye"));
+    }
+
+    #[test]
+    fn test_serve_file() {
+        let path = std::env::temp_dir().join(format!("http_rs_file_{}.txt", std::process::id()));
+        let content = "0123456789".repeat(10_000);
+        fs::write(&path, &content).unwrap();
+
+        assert!(Response::new(200).file(std::env::temp_dir()).is_err());
+
+        let server = Server::new("127.0.0.1:0").unwrap().workers(1);
+        let addr = server.local_addr().unwrap();
+        let file = path.clone();
+
+        thread::spawn(move || server.serve(move |_| Response::new(200).file(&file).unwrap()));
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .unwrap();
+
+        let mut raw = String::new();
+        client.read_to_string(&mut raw).unwrap();
+
+        assert!(raw.contains("Content-Type: text/plain; charset=utf-8\r\n"));
+        assert!(raw.contains("Content-Length: 100000\r\n"));
+        assert!(raw.ends_with(&format!("\r\n\r\n{}", content)));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_serve_max_connections_reject() {
+        let server = Server::new("127.0.0.1:0")
+            .unwrap()
+            .workers(2)
+            .max_connections(
+                1,
+                Overload::Reject {
+                    retry_after: Duration::from_millis(1500),
+                },
+            );
+        let addr = server.local_addr().unwrap();
 
-        let response = format!(
-            "HTTP/1.1 {} {}\r\n{}\r\n\r\n{}",
-            self.status,
-            status_text,
-            self.headers
-                .iter()
-                .map(|(k, v)| format!("{}: {}", k, v))
-                .collect::<Vec<_>>()
-                .join("\r\n"),
-            self.body,
-        );
+        thread::spawn(move || server.serve(|_| Response::new(200).text("ok")));
+
+        // A persistent connection takes the only slot
+        let mut first = TcpStream::connect(addr).unwrap();
+        first.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        first.read_exact(&mut [0; 12]).unwrap();
+
+        let mut second = TcpStream::connect(addr).unwrap();
+
+        let mut raw = String::new();
+        second.read_to_string(&mut raw).unwrap();
 
-        stream.write_all(response.as_bytes())
+        assert!(raw.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+        assert!(raw.contains("Retry-After: 2\r\n"));
+        assert!(raw.contains("Connection: close\r\n"));
     }
-}
 
-///
-/// Parses a URL string into a route and [QueryParams].
-///
-/// # Arguments
-///
-/// * `raw_route` -> The raw URL string to parse
-///
-/// # Returns
-///
-/// `(String, QueryParams)` -> Tuple containing the route string and a
-/// HashMap of [QueryParams]
-///
-fn parse_url(raw_route: &str) -> (String, QueryParams) {
-    if let Some((path, query)) = raw_route.split_once('?') {
-        let query_params = query
-            .split('&')
-            .filter_map(|pair| {
-                let mut parts = pair.split('=');
+    #[test]
+    fn test_serve_max_connections_wait() {
+        let server = Server::new("127.0.0.1:0")
+            .unwrap()
+            .workers(2)
+            .max_connections(1, Overload::Wait);
+        let addr = server.local_addr().unwrap();
 
-                Some((
-                    parts.next()?.to_string(),
-                    parts.next().unwrap_or("").to_string(),
-                ))
-            })
-            .collect();
+        thread::spawn(move || server.serve(|_| Response::new(200).text("ok")));
 
-        (path.to_string(), query_params)
-    } else {
-        (raw_route.to_string(), HashMap::new())
+        let mut first = TcpStream::connect(addr).unwrap();
+        first.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        first.read_exact(&mut [0; 12]).unwrap();
+
+        // Queued in the backlog until the first connection closes
+        let mut second = TcpStream::connect(addr).unwrap();
+        second
+            .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        second
+            .set_read_timeout(Some(Duration::from_millis(300)))
+            .unwrap();
+
+        assert!(second.read(&mut [0; 1]).is_err());
+
+        drop(first);
+        second.set_read_timeout(None).unwrap();
+
+        let mut raw = String::new();
+        second.read_to_string(&mut raw).unwrap();
+
+        assert!(raw.starts_with("HTTP/1.1 200 OK\r\n"));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use serde::{Deserialize, Serialize};
-    use std::io::{self, BufReader, Write};
-    use std::net::{TcpListener, TcpStream};
-    use std::thread;
+    #[test]
+    fn test_serve_header_timeout() {
+        let server = Server::new("127.0.0.1:0")
+            .unwrap()
+            .workers(2)
+            .header_timeout(Some(Duration::from_millis(200)));
+        let addr = server.local_addr().unwrap();
 
-    #[derive(Serialize, Deserialize, PartialEq, Debug)]
-    struct User {
-        id: u32,
-        name: String,
+        thread::spawn(move || server.serve(|_| Response::new(200)));
+
+        // A client that never sends anything
+        let mut idle = TcpStream::connect(addr).unwrap();
+
+        // A client trickling header lines to stretch the head forever
+        let mut slow = TcpStream::connect(addr).unwrap();
+        slow.write_all(b"GET / HTTP/1.1\r\n").unwrap();
+
+        for _ in 0..5 {
+            thread::sleep(Duration::from_millis(60));
+            let _ = slow.write_all(b"X-Slow: 1\r\n");
+        }
+
+        for client in [&mut idle, &mut slow] {
+            let mut raw = String::new();
+            client.read_to_string(&mut raw).unwrap();
+
+            assert!(raw.starts_with("HTTP/1.1 408 Request Timeout\r\n"));
+        }
     }
 
-    ///
-    /// Helper function to create a mock TCP stream with a given request string
-    ///
-    fn create_mock_stream(request: &str) -> io::Result<(TcpStream, TcpStream)> {
-        let listener = TcpListener::bind("127.0.0.1:0")?;
-        let addr = listener.local_addr()?;
+    #[test]
+    fn test_serve_error_handler() {
+        let server = Server::new("127.0.0.1:0")
+            .unwrap()
+            .workers(1)
+            .error_handler(|status, message| {
+                Response::new(status).json(&format!("{} ({})", message, status.as_u16()))
+            });
+        let addr = server.local_addr().unwrap();
 
-        let client = TcpStream::connect(addr)?;
-        let (server, _) = listener.accept()?;
+        thread::spawn(move || server.serve(|_| Response::new(200)));
 
-        let request_owned = request.to_string();
-        let client_clone = client.try_clone()?;
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(b"GET / HTTP/1.1\r\nBad Header\r\n\r\n")
+            .unwrap();
+
+        let mut raw = String::new();
+        client.read_to_string(&mut raw).unwrap();
+
+        assert!(raw.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert!(raw.ends_with("\"Bad Request (400)\""));
+    }
+
+    #[test]
+    fn test_serve_multiple_addresses() {
+        let server = Server::new("127.0.0.1:0")
+            .unwrap()
+            .bind("127.0.0.1:0")
+            .unwrap()
+            .workers(2);
+        let addrs = server.local_addrs().unwrap();
+
+        assert_eq!(addrs.len(), 2);
+        assert_eq!(server.local_addr().unwrap(), addrs[0]);
 
         thread::spawn(move || {
-            let mut client = client_clone;
+            server.serve(|req: Request| {
+                Response::new(200).json(&(req.local_addr.unwrap().port(), req.is_tls()))
+            })
+        });
 
-            client.write_all(request_owned.as_bytes()).unwrap();
+        for addr in addrs {
+            let mut client = TcpStream::connect(addr).unwrap();
+            client
+                .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+                .unwrap();
 
-            // Flush after writing
-            client.flush().unwrap();
+            let mut raw = String::new();
+            client.read_to_string(&mut raw).unwrap();
+
+            assert!(raw.ends_with(&format!("[{},false]", addr.port())));
+        }
+    }
+
+    #[test]
+    fn test_serve_expect_continue() {
+        let server = Server::new("127.0.0.1:0")
+            .unwrap()
+            .workers(1)
+            .expect_continue(|req| req.route != "/forbidden");
+        let addr = server.local_addr().unwrap();
+
+        thread::spawn(move || server.serve(|req: Request| Response::new(200).text(&req.route)));
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(
+                b"POST /upload HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 2\r\n\r\n",
+            )
+            .unwrap();
+
+        let mut interim = [0; 25];
+        client.read_exact(&mut interim).unwrap();
+
+        assert_eq!(&interim, b"HTTP/1.1 100 Continue\r\n\r\n");
+
+        client
+            .write_all(
+                b"hiPOST /forbidden HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 2\r\n\r\n",
+            )
+            .unwrap();
+
+        let mut raw = String::new();
+        client.read_to_string(&mut raw).unwrap();
+
+        let (first, second) = raw.split_once("/upload").unwrap();
+
+        assert!(first.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(second.starts_with("HTTP/1.1 417 Expectation Failed\r\n"));
+    }
+
+    #[test]
+    fn test_serve_malformed_requests() {
+        let server = Server::new("127.0.0.1:0")
+            .unwrap()
+            .workers(1)
+            .max_header_line(64);
+        let addr = server.local_addr().unwrap();
+
+        thread::spawn(move || server.serve(|_| Response::new(200)));
+
+        let send = |raw: &[u8]| {
+            let mut client = TcpStream::connect(addr).unwrap();
+            client.write_all(raw).unwrap();
+
+            let mut response = String::new();
+            client.read_to_string(&mut response).unwrap();
+
+            response
+        };
+
+        let long_header = format!("GET / HTTP/1.1\r\nX-Big: {}\r\n\r\n", "a".repeat(200));
+
+        for (raw, status) in [
+            (&b"GARBAGE\r\n\r\n"[..], "400 Bad Request"),
+            (
+                b"GET / HTTP/1.1\r\nX-Bytes: \xff\xfe\r\n\r\n",
+                "400 Bad Request",
+            ),
+            (b"GET / HTTP/2.0\r\n\r\n", "505 HTTP Version Not Supported"),
+            (
+                long_header.as_bytes(),
+                "431 Request Header Fields Too Large",
+            ),
+        ] {
+            let response = send(raw);
+
+            assert!(
+                response.starts_with(&format!("HTTP/1.1 {}\r\n", status)),
+                "{}",
+                response
+            );
+            assert!(response.contains("Connection: close\r\n"));
+        }
+    }
+
+    #[test]
+    fn test_serve_handler_panic() {
+        let server = Server::new("127.0.0.1:0").unwrap().workers(1);
+        let addr = server.local_addr().unwrap();
+
+        thread::spawn(move || {
+            server.serve(|req: Request| match req.route.as_str() {
+                "/panic" => panic!("handler bug"),
+                _ => Response::new(200),
+            })
         });
 
-        Ok((server.try_clone()?, server))
+        let get = |route: &str| {
+            let mut client = TcpStream::connect(addr).unwrap();
+            write!(
+                client,
+                "GET {} HTTP/1.1\r\nConnection: close\r\n\r\n",
+                route
+            )
+            .unwrap();
+
+            let mut raw = String::new();
+            client.read_to_string(&mut raw).unwrap_or_default();
+
+            raw
+        };
+
+        assert!(get("/panic").starts_with("HTTP/1.1 500 Internal Server Error\r\n"));
+
+        // The only worker survived the panic
+        assert!(get("/").starts_with("HTTP/1.1 200 OK\r\n"));
     }
 
     #[test]
-    fn test_request_parsing_get() {
-        let request = "GET /test?key=value HTTP/1.1\r\nHost: localhost\r\n\r\n";
-        let (server, stream) = create_mock_stream(request).unwrap();
+    fn test_request_limits() {
+        let limits = Limits {
+            body: 4,
+            headers: 2,
+            header_line: 32,
+            uri: 8,
+        };
 
-        let buf = BufReader::new(stream);
-        let parsed_request = Request::new(buf).unwrap();
+        let status = |raw: &str| {
+            let mut raw = raw.as_bytes();
+            let err = Request::read_head(&mut raw, &limits, None)
+                .and_then(|mut req| req.read_body(&mut raw, &limits).map(|_| req))
+                .unwrap_err();
 
-        assert_eq!(parsed_request.method, HttpMethod::GET);
-        assert_eq!(parsed_request.route, "/test");
+            err.status().as_u16()
+        };
+
+        assert_eq!(status("GET /a-long-path HTTP/1.1\r\n\r\n"), 414);
         assert_eq!(
-            parsed_request.query_params.get("key"),
-            Some(&"value".to_string())
+            status("GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\nC: 3\r\n\r\n"),
+            431
         );
         assert_eq!(
-            parsed_request.headers.get("Host"),
-            Some(&"localhost".to_string())
+            status("GET / HTTP/1.1\r\nX-Long: 0123456789abcdef0123456789\r\n\r\n"),
+            431
+        );
+        assert_eq!(
+            status("POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello"),
+            413
+        );
+        assert_eq!(
+            status("POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n3\r\ndef\r\n0\r\n\r\n"),
+            413
         );
-        assert_eq!(parsed_request.body.len(), 0);
 
-        // Close the server stream
-        drop(server);
+        let mut raw = &b"POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\nhiya"[..];
+        let mut req = Request::read_head(&mut raw, &limits, None).unwrap();
+        req.read_body(&mut raw, &limits).unwrap();
+
+        assert_eq!(req.body, b"hiya");
     }
 
     #[test]
-    fn test_parse_url() {
-        let (route, query_params) = parse_url("/path?key1=value1&key2=value2");
+    fn test_response_builder_bodies() {
+        let raw = send_to_string(Response::new(200).header("X-Trace", "abc").text("hi")).unwrap();
 
-        assert_eq!(route, "/path");
-        assert_eq!(query_params.get("key1"), Some(&"value1".to_string()));
-        assert_eq!(query_params.get("key2"), Some(&"value2".to_string()));
+        assert_eq!(
+            raw,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nX-Trace: abc\r\nContent-Length: 2\r\n\r\nhi"
+        );
+
+        let res = Response::new(200).html("<p>hi</p>");
+        assert_eq!(
+            res.headers.get("Content-Type"),
+            Some("text/html; charset=utf-8")
+        );
+
+        let res = Response::new(200).bytes("image/png", vec![0x89, b'P']);
+        assert_eq!(res.headers.get("Content-Type"), Some("image/png"));
+        assert_eq!(res.body.as_bytes(), Some(&[0x89, b'P'][..]));
+
+        // A plain body keeps the default content type
+        let res = Response::new(200).body("raw");
+        assert_eq!(res.headers.get("Content-Length"), Some("3"));
+        assert_eq!(res.headers.get("Content-Type"), Some("application/json"));
+    }
+
+    #[test]
+    fn test_streamed_response() {
+        let reader = io::Cursor::new(b"streamed".to_vec());
+        let raw = send_to_string(Response::new(200).json(&"ignored").stream(reader)).unwrap();
+
+        assert!(raw.contains("Transfer-Encoding: chunked\r\n"));
+        assert!(!raw.contains("Content-Length"));
+        assert!(raw.ends_with("\r\n\r\n8\r\nstreamed\r\n0\r\n\r\n"));
+    }
+
+    #[test]
+    fn test_response_trailers() {
+        let reader = io::Cursor::new(b"streamed".to_vec());
+        let response =
+            Response::new(200)
+                .stream(reader)
+                .trailers(&["Server-Timing", "Digest"], || {
+                    let mut fields = HeaderMap::new();
+                    fields.insert("Server-Timing", "total;dur=1");
+                    fields.insert("X-Undeclared", "dropped");
+
+                    fields
+                });
+
+        let raw = send_to_string(response).unwrap();
+
+        assert!(raw.contains("Trailer: Server-Timing, Digest\r\n"));
+        assert!(raw.ends_with("8\r\nstreamed\r\n0\r\nServer-Timing: total;dur=1\r\n\r\n"));
+
+        // Without a chunked body there's nowhere to put them
+        let raw = send_to_string(
+            Response::new(200)
+                .text("full")
+                .trailers(&["Digest"], || panic!("not produced")),
+        )
+        .unwrap();
+        assert!(raw.ends_with("\r\n\r\nfull"));
+    }
+
+    #[test]
+    fn test_request_from_bytes() {
+        let mut raw =
+            &b"POST /a HTTP/1.1\r\nContent-Length: 2\r\n\r\nhiGET /b HTTP/1.1\r\n\r\n"[..];
+
+        let first = Request::read_from(&mut raw).unwrap();
+        let second = Request::read_from(&mut raw).unwrap();
+
+        assert_eq!(
+            (first.route.as_str(), first.body.as_slice()),
+            ("/a", &b"hi"[..])
+        );
+        assert_eq!(second.route, "/b");
+        assert!(raw.is_empty());
     }
 
     #[test]
     fn test_invalid_method() {
-        let request = "INVALID /path HTTP/1.1\r\n\r\n";
+        let request = "GET(1) /path HTTP/1.1\r\n\r\n";
         let (_, stream) = create_mock_stream(request).unwrap();
 
         let buf = BufReader::new(stream);
@@ -470,6 +3844,270 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_method_parsing() {
+        for name in [
+            "GET", "POST", "PUT", "DELETE", "PATCH", "HEAD", "OPTIONS", "TRACE", "CONNECT",
+        ] {
+            let method = name.parse::<HttpMethod>().unwrap();
+
+            assert!(!matches!(method, HttpMethod::Other(_)));
+            assert_eq!(method.to_string(), name);
+        }
+
+        assert_eq!(
+            "PROPFIND".parse::<HttpMethod>().unwrap(),
+            HttpMethod::Other("PROPFIND".to_string())
+        );
+        assert!("".parse::<HttpMethod>().is_err());
+        assert!("GET /".parse::<HttpMethod>().is_err());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_request_parsing_post_json() {
+        let request =
+            "POST /users HTTP/1.1\r\nContent-Length: 23\r\n\r\n{\"id\":1,\"name\":\"Alice\"}";
+        let (_, stream) = create_mock_stream(request).unwrap();
+
+        let buf = BufReader::new(stream);
+        let parsed_request = Request::new(buf).unwrap();
+
+        assert_eq!(parsed_request.method, HttpMethod::POST);
+        assert_eq!(parsed_request.version, HttpVersion::Http11);
+        assert_eq!(
+            parsed_request.get_json::<User>(),
+            Some(User {
+                id: 1,
+                name: "Alice".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let request = "GET /path HTTP/2.0\r\n\r\n";
+        let (_, stream) = create_mock_stream(request).unwrap();
+
+        let buf = BufReader::new(stream);
+        let result = Request::new(buf);
+
+        assert!(matches!(result, Err(Error::UnsupportedVersion)));
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(
+            "HTTP/1.0".parse::<HttpVersion>().unwrap(),
+            HttpVersion::Http10
+        );
+        assert_eq!(
+            "HTTP/1.1".parse::<HttpVersion>().unwrap(),
+            HttpVersion::Http11
+        );
+        assert_eq!(
+            "HTTP/1.2".parse::<HttpVersion>().unwrap(),
+            HttpVersion::Http11
+        );
+
+        for unsupported in ["HTTP/2.0", "HTTP/3.0", "HTTP/0.9"] {
+            assert!(matches!(
+                unsupported.parse::<HttpVersion>(),
+                Err(Error::UnsupportedVersion)
+            ));
+        }
+
+        for garbage in ["http/1.1", "HTTP/1", "HTTP/1.10", "HTTP/x.y", "FOO"] {
+            assert!(matches!(
+                garbage.parse::<HttpVersion>(),
+                Err(Error::BadRequestLine)
+            ));
+        }
+
+        let result = Request::new(&b"GET / HTTP/1.1 extra\r\n\r\n"[..]);
+
+        assert!(matches!(result, Err(Error::BadRequestLine)));
+    }
+
+    #[test]
+    fn test_missing_version() {
+        let request = "GET /path\r\n\r\n";
+        let (_, stream) = create_mock_stream(request).unwrap();
+
+        let buf = BufReader::new(stream);
+        let result = Request::new(buf);
+
+        assert!(matches!(result, Err(Error::BadRequestLine)));
+    }
+
+    #[test]
+    fn test_chunked_body() {
+        let request = "POST /upload HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+            5;ext=1\r\nhello\r\n7\r\n, world\r\n0\r\nChecksum: abc\r\n\r\n";
+        let (_, stream) = create_mock_stream(request).unwrap();
+
+        let mut buf = BufReader::new(stream);
+        let parsed_request = Request::read_from(&mut buf).unwrap();
+
+        assert_eq!(parsed_request.body, b"hello, world");
+        assert_eq!(parsed_request.trailers.get("checksum"), Some("abc"));
+        assert!(!parsed_request.headers.contains_key("Checksum"));
+        assert!(buf.buffer().is_empty());
+
+        let mut folded = &b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+            0\r\nChecksum: a\r\n b\r\n\r\n"[..];
+        assert!(matches!(
+            Request::read_from(&mut folded),
+            Err(Error::BadHeader)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_chunk_size() {
+        let request = "POST /upload HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n";
+        let (_, stream) = create_mock_stream(request).unwrap();
+
+        let buf = BufReader::new(stream);
+        let result = Request::new(buf);
+
+        assert!(matches!(result, Err(Error::BadChunkedEncoding)));
+    }
+
+    #[test]
+    fn test_read_line_across_refills() {
+        // A 4 byte buffer makes every line span several refills
+        let mut stream = BufReader::with_capacity(4, &b"Host: example.com  \r\nlast"[..]);
+
+        let line = Request::read_line(&mut stream, 64, Error::HeaderTooLarge).unwrap();
+        assert_eq!(line, "Host: example.com");
+        assert_eq!(
+            Request::read_line(&mut stream, 64, Error::HeaderTooLarge).unwrap(),
+            "last"
+        );
+        assert!(matches!(
+            Request::read_line(&mut stream, 64, Error::HeaderTooLarge),
+            Err(Error::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof
+        ));
+
+        // Exactly `max` bytes fit with the CRLF, one more doesn't
+        let mut stream = BufReader::with_capacity(4, &b"abcdef\r\nabcdefg\r\n"[..]);
+        assert_eq!(
+            Request::read_line(&mut stream, 6, Error::HeaderTooLarge).unwrap(),
+            "abcdef"
+        );
+        assert!(matches!(
+            Request::read_line(&mut stream, 6, Error::HeaderTooLarge),
+            Err(Error::HeaderTooLarge)
+        ));
+    }
+
+    #[test]
+    fn test_get_form() {
+        let mut req = test_request(HttpMethod::POST, "/users");
+        req.body = b"id=7&name=Jane+Doe%21".to_vec();
+
+        assert_eq!(
+            req.get_form::<User>(),
+            Some(User {
+                id: 7,
+                name: "Jane Doe!".to_string()
+            })
+        );
+
+        let form = req.get_form::<HashMap<String, String>>().unwrap();
+        assert_eq!(form.get("name"), Some(&"Jane Doe!".to_string()));
+
+        req.body = b"id=seven".to_vec();
+        assert_eq!(req.get_form::<User>(), None);
+    }
+
+    #[test]
+    fn test_header_case_and_repeats() {
+        let mut raw =
+            &b"POST / HTTP/1.1\r\ncontent-length: 2\r\nAccept: a\r\naccept:b\r\n\r\nhi"[..];
+        let limits = Limits::default();
+
+        let mut req = Request::read_head(&mut raw, &limits, None).unwrap();
+        req.read_body(&mut raw, &limits).unwrap();
+
+        assert_eq!(req.body, b"hi");
+        assert_eq!(
+            req.headers.get_all("ACCEPT").collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+
+        // Conflicting lengths are ambiguous and rejected
+        let mut raw = &b"POST / HTTP/1.1\r\nContent-Length: 2\r\nContent-Length: 3\r\n\r\nhi"[..];
+        let req = Request::read_head(&mut raw, &limits, None).unwrap();
+
+        assert!(req.body_framing().is_err());
+    }
+
+    #[test]
+    fn test_smuggling_defenses() {
+        let limits = Limits::default();
+        let framing = |raw: &[u8]| {
+            let mut raw = raw;
+            Request::read_head(&mut raw, &limits, None)?.body_framing()
+        };
+
+        assert!(matches!(
+            framing(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\nContent-Length: 3\r\n\r\n"),
+            Err(Error::ConflictingFraming)
+        ));
+        assert!(matches!(
+            framing(b"POST / HTTP/1.1\r\nContent-Length: 3, 4\r\n\r\n"),
+            Err(Error::InvalidContentLength)
+        ));
+        assert!(matches!(
+            framing(b"POST / HTTP/1.1\r\nContent-Length: +3\r\n\r\n"),
+            Err(Error::InvalidContentLength)
+        ));
+        assert!(matches!(
+            framing(b"POST / HTTP/1.1\r\nContent-Length: 3\r\nContent-Length: 3\r\n\r\n"),
+            Ok(BodyFraming::Length(3))
+        ));
+
+        // Folded header lines
+        assert!(matches!(
+            framing(b"POST / HTTP/1.1\r\nX-Note: a\r\n Transfer-Encoding: chunked\r\n\r\n"),
+            Err(Error::BadHeader)
+        ));
+        assert!(matches!(
+            framing(b"POST / HTTP/1.1\r\nX-Note: a\r\n\tb\r\n\r\n"),
+            Err(Error::BadHeader)
+        ));
+
+        assert!(matches!(chunk_size("+a"), Err(Error::BadChunkedEncoding)));
+        assert_eq!(chunk_size("1A;ext").unwrap(), 26);
+    }
+
+    #[test]
+    fn test_get_query() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Search {
+            q: String,
+            page: u32,
+            exact: bool,
+            limit: Option<u8>,
+        }
+
+        let req = test_request(HttpMethod::GET, "/search?q=hello%20world&page=2&exact=true");
+
+        assert_eq!(
+            req.get_query::<Search>(),
+            Some(Search {
+                q: "hello world".to_string(),
+                page: 2,
+                exact: true,
+                limit: None
+            })
+        );
+
+        let req = test_request(HttpMethod::GET, "/search?q=a&page=two&exact=false");
+        assert_eq!(req.get_query::<Search>(), None);
+    }
+
     #[test]
     fn test_missing_content_length() {
         let request = "POST /path HTTP/1.1\r\n\r\n";
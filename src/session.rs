@@ -0,0 +1,434 @@
+//!
+//! Server-side sessions keyed by a signed cookie.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::router::Router;
+//! use http_rs::server::{Request, Response};
+//! use http_rs::session::{MemoryStore, Sessions};
+//!
+//! let mut router = Router::new();
+//!
+//! router.layer(Sessions::new(MemoryStore::new(), b"a long random secret key"));
+//!
+//! router.get("/visits", |req: Request| {
+//!     let session = req.session().unwrap();
+//!     let visits = session.get::<u32>("visits").unwrap_or(0) + 1;
+//!
+//!     session.set("visits", &visits);
+//!
+//!     Response::new(200).json(&visits)
+//! });
+//! ```
+//!
+
+use crate::cookie::{Cookie, SameSite};
+use crate::middleware::{Middleware, Next};
+use crate::server::{Request, Response};
+use hmac::{Hmac, Mac};
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::Sha256;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+///
+/// Alias for the values stored in a session as KV pairs.
+///
+pub type SessionData = HashMap<String, serde_json::Value>;
+
+///
+/// Storage backend for [SessionData], keyed by session ID.
+///
+pub trait SessionStore: Send + Sync + 'static {
+    ///
+    /// Loads the data of a session, or None if it does not exist.
+    ///
+    fn load(&self, id: &str) -> Option<SessionData>;
+
+    ///
+    /// Stores the data of a session, replacing what was there.
+    ///
+    fn save(&self, id: &str, data: SessionData);
+
+    ///
+    /// Deletes a session.
+    ///
+    fn destroy(&self, id: &str);
+}
+
+///
+/// In-memory [SessionStore], sessions are lost when the process exits.
+///
+#[derive(Default)]
+pub struct MemoryStore {
+    sessions: Mutex<HashMap<String, SessionData>>,
+}
+
+impl MemoryStore {
+    ///
+    /// Creates an empty [MemoryStore].
+    ///
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    fn sessions(&self) -> MutexGuard<'_, HashMap<String, SessionData>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl SessionStore for MemoryStore {
+    fn load(&self, id: &str) -> Option<SessionData> {
+        self.sessions().get(id).cloned()
+    }
+
+    fn save(&self, id: &str, data: SessionData) {
+        self.sessions().insert(id.to_string(), data);
+    }
+
+    fn destroy(&self, id: &str) {
+        self.sessions().remove(id);
+    }
+}
+
+#[derive(Debug)]
+struct SessionState {
+    id: String,
+    data: SessionData,
+    modified: bool,
+    destroyed: bool,
+}
+
+///
+/// Handle to the session of the current [Request], see [Request::session].
+///
+/// Changes are persisted to the [SessionStore] by the [Sessions] middleware once the
+/// handler returns.
+///
+#[derive(Debug, Clone)]
+pub struct Session {
+    state: Arc<Mutex<SessionState>>,
+}
+
+impl Session {
+    fn new(id: String, data: SessionData) -> Session {
+        Session {
+            state: Arc::new(Mutex::new(SessionState {
+                id,
+                data,
+                modified: false,
+                destroyed: false,
+            })),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, SessionState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    ///
+    /// Returns the session ID.
+    ///
+    pub fn id(&self) -> String {
+        self.state().id.clone()
+    }
+
+    ///
+    /// Reads a value from the session, deserializing it into `T`.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<T>` -> The value, or None if missing or of a different type
+    ///
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let value = self.state().data.get(key)?.clone();
+
+        serde_json::from_value(value).ok()
+    }
+
+    ///
+    /// Stores a value in the session, replacing any previous value of that key.
+    ///
+    pub fn set<T: Serialize>(&self, key: &str, value: &T) {
+        let Ok(value) = serde_json::to_value(value) else {
+            return;
+        };
+
+        let mut state = self.state();
+
+        state.data.insert(key.to_string(), value);
+        state.modified = true;
+    }
+
+    ///
+    /// Removes a value from the session.
+    ///
+    pub fn remove(&self, key: &str) {
+        let mut state = self.state();
+
+        if state.data.remove(key).is_some() {
+            state.modified = true;
+        }
+    }
+
+    ///
+    /// Deletes the session from the store and expires its cookie (e.g., on logout).
+    ///
+    pub fn destroy(&self) {
+        let mut state = self.state();
+
+        state.data.clear();
+        state.destroyed = true;
+    }
+}
+
+impl Request {
+    ///
+    /// Returns the [Session] attached by the [Sessions] middleware.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&Session>` -> The session, or None if the middleware is not installed
+    ///
+    pub fn session(&self) -> Option<&Session> {
+        self.session.as_ref()
+    }
+}
+
+///
+/// [Middleware] loading or creating a [Session] for every request.
+///
+/// The session ID travels in an `HttpOnly` cookie signed with HMAC-SHA256, so clients
+/// can't forge IDs. A cookie is only issued once the session holds data.
+///
+pub struct Sessions<S: SessionStore> {
+    store: S,
+    secret: Vec<u8>,
+    cookie_name: String,
+    secure: bool,
+    max_age: Option<Duration>,
+}
+
+impl<S: SessionStore> Sessions<S> {
+    ///
+    /// Creates the session [Middleware].
+    ///
+    /// # Arguments
+    ///
+    /// * `store` -> The [SessionStore] holding session data
+    /// * `secret` -> Key used to sign session IDs, keep it private and long
+    ///
+    pub fn new(store: S, secret: &[u8]) -> Sessions<S> {
+        Sessions {
+            store,
+            secret: secret.to_vec(),
+            cookie_name: "session".to_string(),
+            secure: false,
+            max_age: None,
+        }
+    }
+
+    ///
+    /// Sets the name of the session cookie. Defaults to `session`.
+    ///
+    pub fn cookie_name(mut self, name: &str) -> Sessions<S> {
+        self.cookie_name = name.to_string();
+
+        self
+    }
+
+    ///
+    /// Marks the session cookie `Secure`, only sending it over HTTPS.
+    ///
+    pub fn secure(mut self, secure: bool) -> Sessions<S> {
+        self.secure = secure;
+
+        self
+    }
+
+    ///
+    /// Sets `Max-Age` on the session cookie. Defaults to a browser-session cookie.
+    ///
+    pub fn max_age(mut self, max_age: Duration) -> Sessions<S> {
+        self.max_age = Some(max_age);
+
+        self
+    }
+
+    fn mac(&self) -> Hmac<Sha256> {
+        Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts keys of any size")
+    }
+
+    ///
+    /// Signs a session ID into a cookie value (`<id>.<signature>`).
+    ///
+    fn sign(&self, id: &str) -> String {
+        let mut mac = self.mac();
+        mac.update(id.as_bytes());
+
+        format!("{}.{}", id, hex(&mac.finalize().into_bytes()))
+    }
+
+    ///
+    /// Verifies a signed cookie value and returns the session ID it carries.
+    ///
+    fn verify<'a>(&self, value: &'a str) -> Option<&'a str> {
+        let (id, signature) = value.rsplit_once('.')?;
+        let signature = unhex(signature)?;
+
+        let mut mac = self.mac();
+        mac.update(id.as_bytes());
+        mac.verify_slice(&signature).ok()?;
+
+        Some(id)
+    }
+
+    fn cookie(&self, value: &str) -> Cookie {
+        let cookie = Cookie::new(&self.cookie_name, value)
+            .path("/")
+            .http_only(true)
+            .secure(self.secure)
+            .same_site(SameSite::Lax);
+
+        match self.max_age {
+            Some(max_age) => cookie.max_age(max_age),
+            None => cookie,
+        }
+    }
+}
+
+impl<S: SessionStore> Middleware for Sessions<S> {
+    fn handle(&self, mut req: Request, next: Next<'_>) -> Response {
+        let existing = req.cookies().get(&self.cookie_name).and_then(|value| {
+            let id = self.verify(value)?;
+
+            Some((id.to_string(), self.store.load(id)?))
+        });
+
+        let is_new = existing.is_none();
+        let (id, data) = existing.unwrap_or_else(|| (new_session_id(), SessionData::new()));
+
+        let session = Session::new(id, data);
+        req.session = Some(session.clone());
+
+        let res = next.run(req);
+        let state = session.state();
+
+        if state.destroyed {
+            self.store.destroy(&state.id);
+
+            return match is_new {
+                true => res,
+                false => res.set_cookie(self.cookie("").max_age(Duration::ZERO)),
+            };
+        }
+
+        if !state.modified {
+            return res;
+        }
+
+        self.store.save(&state.id, state.data.clone());
+
+        match is_new {
+            true => res.set_cookie(self.cookie(&self.sign(&state.id))),
+            false => res,
+        }
+    }
+}
+
+///
+/// Generates a random 256-bit session ID, hex encoded.
+///
+fn new_session_id() -> String {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).expect("OS random number generator unavailable");
+
+    hex(&bytes)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::router::Router;
+    use crate::server::{test_request, HttpMethod};
+
+    fn counter_router() -> Router {
+        let mut router = Router::new();
+
+        router
+            .layer(Sessions::new(MemoryStore::new(), b"secret"))
+            .get("/count", |req: Request| {
+                let session = req.session().unwrap();
+                let count = session.get::<u32>("count").unwrap_or(0) + 1;
+
+                session.set("count", &count);
+
+                Response::new(200).json(&count)
+            });
+
+        router
+    }
+
+    fn set_cookie(res: &Response) -> Option<String> {
+        res.headers
+            .iter()
+            .find(|(k, _)| *k == "Set-Cookie")
+            .map(|(_, v)| v.split(';').next().unwrap().to_string())
+    }
+
+    #[test]
+    fn test_session_persists_between_requests() {
+        let router = counter_router();
+
+        let res = router.handle(test_request(HttpMethod::GET, "/count"));
+        let cookie = set_cookie(&res).unwrap();
+
+        assert_eq!(res.body.as_bytes(), Some(&b"1"[..]));
+
+        let mut req = test_request(HttpMethod::GET, "/count");
+        req.headers.insert("Cookie".to_string(), cookie);
+
+        let res = router.handle(req);
+
+        assert_eq!(res.body.as_bytes(), Some(&b"2"[..]));
+        assert_eq!(set_cookie(&res), None);
+    }
+
+    #[test]
+    fn test_session_rejects_forged_id() {
+        let router = counter_router();
+
+        let res = router.handle(test_request(HttpMethod::GET, "/count"));
+        let cookie = set_cookie(&res).unwrap();
+
+        // Keep the signature but swap the ID
+        let (_, signature) = cookie.rsplit_once('.').unwrap();
+        let forged = format!("session={}.{}", "0".repeat(64), signature);
+
+        let mut req = test_request(HttpMethod::GET, "/count");
+        req.headers.insert("Cookie".to_string(), forged);
+
+        let res = router.handle(req);
+
+        assert_eq!(res.body.as_bytes(), Some(&b"1"[..]));
+    }
+}
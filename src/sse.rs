@@ -0,0 +1,312 @@
+//!
+//! Server-Sent Events, pushing a stream of events to the client over a single response.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::router::Router;
+//! use http_rs::server::{Request, Response};
+//! use http_rs::sse::Event;
+//! use std::{thread, time::Duration};
+//!
+//! let mut router = Router::new();
+//!
+//! router.get("/ticks", |_req: Request| {
+//!     let (res, events) = Response::sse();
+//!
+//!     thread::spawn(move || {
+//!         for i in 0.. {
+//!             // Fails once the client is gone
+//!             if events.send(Event::new(i.to_string()).event("tick")).is_err() {
+//!                 break;
+//!             }
+//!
+//!             thread::sleep(Duration::from_secs(1));
+//!         }
+//!     });
+//!
+//!     res
+//! });
+//! ```
+//!
+
+use crate::server::Response;
+use std::{
+    fmt::Write as _,
+    io::{self, Read},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    time::Duration,
+};
+
+///
+/// Default interval after which an idle stream sends a keep-alive comment.
+///
+const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+///
+/// A single message of an event stream.
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Event {
+    data: String,
+    event: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl Event {
+    ///
+    /// Creates an [Event] carrying `data`, sent as one `data:` line per line of text.
+    ///
+    pub fn new(data: impl Into<String>) -> Event {
+        Event {
+            data: data.into(),
+            ..Event::default()
+        }
+    }
+
+    ///
+    /// Creates an [Event] carrying a value serialized as JSON.
+    ///
+    /// # Returns
+    ///
+    /// * `serde_json::Result<Event>` -> The [Event] or a serialization error
+    ///
+    pub fn json<T: serde::Serialize>(value: &T) -> serde_json::Result<Event> {
+        Ok(Event::new(serde_json::to_string(value)?))
+    }
+
+    ///
+    /// Sets the event type (`event:`), dispatched to `addEventListener` listeners in
+    /// the browser instead of `onmessage`.
+    ///
+    pub fn event(mut self, event: &str) -> Event {
+        self.event = Some(single_line(event));
+
+        self
+    }
+
+    ///
+    /// Sets the event ID (`id:`), sent back by the browser as `Last-Event-ID` when it
+    /// reconnects.
+    ///
+    pub fn id(mut self, id: &str) -> Event {
+        self.id = Some(single_line(id));
+
+        self
+    }
+
+    ///
+    /// Sets how long the browser waits before reconnecting (`retry:`).
+    ///
+    pub fn retry(mut self, retry: Duration) -> Event {
+        self.retry = Some(retry);
+
+        self
+    }
+
+    ///
+    /// Formats the [Event] in the `text/event-stream` wire format.
+    ///
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = String::new();
+
+        if let Some(event) = &self.event {
+            let _ = writeln!(out, "event: {}", event);
+        }
+
+        if let Some(id) = &self.id {
+            let _ = writeln!(out, "id: {}", id);
+        }
+
+        if let Some(retry) = self.retry {
+            let _ = writeln!(out, "retry: {}", retry.as_millis());
+        }
+
+        for line in self.data.split('\n') {
+            let _ = writeln!(out, "data: {}", line.strip_suffix('\r').unwrap_or(line));
+        }
+
+        out.push('\n');
+        out.into_bytes()
+    }
+}
+
+///
+/// Handle used to push events into an event stream, see [Response::sse].
+///
+/// It can be cloned and moved to other threads. The stream ends once every sender is
+/// dropped.
+///
+#[derive(Debug, Clone)]
+pub struct EventSender {
+    sender: Sender<Vec<u8>>,
+}
+
+impl EventSender {
+    ///
+    /// Sends an [Event] to the client.
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<()>` -> Ok if the event was queued, or a `BrokenPipe` error once the
+    ///   client has disconnected
+    ///
+    pub fn send(&self, event: Event) -> io::Result<()> {
+        self.send_raw(event.to_bytes())
+    }
+
+    ///
+    /// Sends a comment line, ignored by the browser (e.g., to keep proxies from timing
+    /// out the connection).
+    ///
+    pub fn comment(&self, comment: &str) -> io::Result<()> {
+        self.send_raw(format!(": {}\n\n", single_line(comment)).into_bytes())
+    }
+
+    fn send_raw(&self, bytes: Vec<u8>) -> io::Result<()> {
+        self.sender
+            .send(bytes)
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "client disconnected"))
+    }
+}
+
+///
+/// Streamed body of an event stream, fed by an [EventSender].
+///
+/// Sends a keep-alive comment whenever no event arrives within the keep-alive interval,
+/// which also reveals a disconnected client when the write fails.
+///
+struct EventStream {
+    receiver: Receiver<Vec<u8>>,
+    keep_alive: Duration,
+    pending: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for EventStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.pending.len() {
+            self.pending = match self.receiver.recv_timeout(self.keep_alive) {
+                Ok(bytes) => bytes,
+                Err(RecvTimeoutError::Timeout) => b": keep-alive\n\n".to_vec(),
+                Err(RecvTimeoutError::Disconnected) => return Ok(0),
+            };
+            self.pos = 0;
+        }
+
+        let n = buf.len().min(self.pending.len() - self.pos);
+
+        buf[..n].copy_from_slice(&self.pending[self.pos..self.pos + n]);
+        self.pos += n;
+
+        Ok(n)
+    }
+}
+
+impl Response {
+    ///
+    /// Creates a `text/event-stream` [Response] whose events are pushed through the
+    /// returned [EventSender], sending a keep-alive comment every 15 seconds of silence.
+    ///
+    /// `👉 Note:` The connection stays open (and its worker busy) until every
+    /// [EventSender] is dropped, so send events from another thread and return the
+    /// [Response] right away.
+    ///
+    /// # Returns
+    ///
+    /// * `(Response, EventSender)` -> The response to return from the handler and the
+    ///   sender feeding it
+    ///
+    pub fn sse() -> (Response, EventSender) {
+        Response::sse_with_keep_alive(DEFAULT_KEEP_ALIVE)
+    }
+
+    ///
+    /// Same as [Response::sse] with a custom keep-alive interval.
+    ///
+    /// # Arguments
+    ///
+    /// * `keep_alive` -> Idle time after which a keep-alive comment is sent
+    ///
+    pub fn sse_with_keep_alive(keep_alive: Duration) -> (Response, EventSender) {
+        let (sender, receiver) = mpsc::channel();
+
+        let stream = EventStream {
+            receiver,
+            keep_alive,
+            pending: Vec::new(),
+            pos: 0,
+        };
+
+        let res = Response::new(200)
+            .header("Content-Type", "text/event-stream")
+            .header("Cache-Control", "no-cache")
+            .stream(stream);
+
+        (res, EventSender { sender })
+    }
+}
+
+///
+/// Strips line breaks from a field value, which would otherwise start a new field.
+///
+fn single_line(value: &str) -> String {
+    value.replace(['\r', '\n'], "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::Body;
+
+    fn read_all(res: Response) -> String {
+        let mut out = Vec::new();
+        res.body.write_to(&mut out, true, None).unwrap();
+
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_sse_event_format() {
+        let (res, events) = Response::sse();
+
+        assert_eq!(res.headers.get("Content-Type"), Some("text/event-stream"));
+
+        events
+            .send(
+                Event::new("line 1\nline 2")
+                    .event("update")
+                    .id("7")
+                    .retry(Duration::from_secs(3)),
+            )
+            .unwrap();
+        events.comment("hi").unwrap();
+        drop(events);
+
+        assert_eq!(
+            read_all(res),
+            "3b\r\nevent: update\nid: 7\nretry: 3000\ndata: line 1\ndata: line 2\n\n\r\n\
+             6\r\n: hi\n\n\r\n\
+             0\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn test_sse_keep_alive_and_disconnect() {
+        let (mut res, events) = Response::sse_with_keep_alive(Duration::from_millis(10));
+
+        let Body::Stream(mut reader) = std::mem::take(&mut res.body) else {
+            panic!("expected a streamed body");
+        };
+
+        let mut buf = [0; 64];
+        let n = reader.read(&mut buf).unwrap();
+
+        assert_eq!(&buf[..n], b": keep-alive\n\n");
+
+        drop(reader);
+
+        assert!(events.send(Event::new("gone")).is_err());
+    }
+}
@@ -0,0 +1,133 @@
+//!
+//! Shared application state handed to every request.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::router::Router;
+//! use http_rs::server::{Request, Response, Server};
+//! use std::sync::atomic::{AtomicU64, Ordering};
+//!
+//! struct Config {
+//!     greeting: String,
+//! }
+//!
+//! fn main() -> std::io::Result<()> {
+//!     let mut router = Router::new();
+//!
+//!     router.get("/", |req: Request| {
+//!         let config = req.state::<Config>().unwrap();
+//!         let hits = req.state::<AtomicU64>().unwrap().fetch_add(1, Ordering::Relaxed);
+//!
+//!         Response::new(200).json(&format!("{} #{}", config.greeting, hits))
+//!     });
+//!
+//!     Server::new("127.0.0.1:8080")?
+//!         .with_state(Config { greeting: "Hello".to_string() })
+//!         .with_state(AtomicU64::new(0))
+//!         .serve(router)
+//! }
+//! ```
+//!
+
+use crate::server::{Request, Server};
+use std::{
+    any::{Any, TypeId},
+    fmt,
+    sync::Arc,
+};
+
+///
+/// Values registered with [Server::with_state], one per type, shared by all requests.
+///
+#[derive(Clone, Default)]
+pub(crate) struct AppState(Arc<[Arc<dyn Any + Send + Sync>]>);
+
+impl AppState {
+    ///
+    /// Returns the value of type `T`, if one was registered.
+    ///
+    pub(crate) fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.0.iter().find_map(|value| value.downcast_ref::<T>())
+    }
+
+    ///
+    /// Returns a copy with `value` added, replacing any previous value of type `T`.
+    ///
+    pub(crate) fn with<T: Send + Sync + 'static>(&self, value: T) -> AppState {
+        let mut values: Vec<_> = self
+            .0
+            .iter()
+            .filter(|v| v.as_ref().type_id() != TypeId::of::<T>())
+            .cloned()
+            .collect();
+
+        values.push(Arc::new(value));
+
+        AppState(values.into())
+    }
+}
+
+impl fmt::Debug for AppState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AppState")
+            .field("values", &self.0.len())
+            .finish()
+    }
+}
+
+impl Server {
+    ///
+    /// Registers a value shared by every request handled by [Server::serve], retrieved
+    /// with [Request::state]. One value is kept per type, registering the same type
+    /// again replaces it.
+    ///
+    /// `👉 Note:` The value is shared behind an `Arc` and handed out as `&T`, so it
+    /// needn't be `Clone` but must be `Send + Sync`. Use interior mutability (e.g., a
+    /// `Mutex` or atomics) for anything handlers modify.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` -> The shared value (e.g., a config or a database pool)
+    ///
+    pub fn with_state<T: Send + Sync + 'static>(mut self, state: T) -> Server {
+        self.state = self.state.with(state);
+
+        self
+    }
+}
+
+impl Request {
+    ///
+    /// Returns the shared value of type `T` registered with [Server::with_state].
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&T>` -> The value, or None if no value of that type was registered or
+    ///   the request wasn't read by [Server::serve]
+    ///
+    pub fn state<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.state.get::<T>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::{test_request, HttpMethod};
+
+    #[test]
+    fn test_state_lookup_by_type() {
+        let state = AppState::default()
+            .with(1u32)
+            .with("config".to_string())
+            .with(2u32);
+
+        let mut req = test_request(HttpMethod::GET, "/");
+        req.state = state;
+
+        assert_eq!(req.state::<u32>(), Some(&2));
+        assert_eq!(req.state::<String>().map(String::as_str), Some("config"));
+        assert_eq!(req.state::<u64>(), None);
+    }
+}
@@ -0,0 +1,1119 @@
+//!
+//! Serving files from a directory tree.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::server::Server;
+//! use http_rs::static_files::StaticFiles;
+//!
+//! fn main() -> std::io::Result<()> {
+//!     Server::new("127.0.0.1:8080")?.serve(StaticFiles::new("./public"))
+//! }
+//! ```
+//!
+
+use crate::body::Body;
+use crate::conditional;
+use crate::date;
+use crate::negotiation::{self, Representations};
+use crate::router::Handler;
+use crate::server::{percent_decode, HttpMethod, Request, Response};
+use serde::Serialize;
+use std::{
+    fs,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Component, Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+///
+/// [Handler] serving files below a root directory, mapping URL paths to file paths.
+///
+/// Directories are served through their `index.html`, or listed if
+/// [StaticFiles::listing] is enabled, and anything missing, outside the root, or
+/// requested with a method other than `GET` or `HEAD` gets a `404`. The body of a `HEAD`
+/// response is dropped by the server, its headers being those of the `GET` one.
+///
+/// Files are sent without being loaded into memory, see [Response::file]. They carry
+/// `Last-Modified` and an `ETag` derived from their modification time and size, so
+/// conditional requests for unchanged files get a `304`. `Range` requests get a `206`
+/// with only the requested bytes, or a `416` if none of them exist.
+///
+pub struct StaticFiles {
+    root: PathBuf,
+    prefix: String,
+    listing: bool,
+    spa: bool,
+    spa_exclude: Vec<String>,
+    precompressed: bool,
+}
+
+///
+/// Content codings of precompressed siblings and their file extensions, preferred in
+/// this order when the client accepts them equally.
+///
+const PRECOMPRESSED: [(&str, &str); 2] = [("br", "br"), ("gzip", "gz")];
+
+impl StaticFiles {
+    ///
+    /// Creates a [StaticFiles] handler serving the `root` directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` -> Directory to serve files from
+    ///
+    pub fn new(root: impl Into<PathBuf>) -> StaticFiles {
+        StaticFiles {
+            root: root.into(),
+            prefix: String::new(),
+            listing: false,
+            spa: false,
+            spa_exclude: Vec::new(),
+            precompressed: false,
+        }
+    }
+
+    ///
+    /// Strips a URL prefix before mapping the path to a file, so `/static/app.js`
+    /// serves `<root>/app.js` with a prefix of `/static`.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` -> URL prefix to strip (e.g., "/static")
+    ///
+    pub fn prefix(mut self, prefix: &str) -> StaticFiles {
+        self.prefix = prefix.trim_end_matches('/').to_string();
+
+        self
+    }
+
+    ///
+    /// Lists the contents of directories without an `index.html`, as an HTML page or as
+    /// `JSON` depending on the request's `Accept` header.
+    ///
+    /// Entries show their name, size and modification time. Hidden entries (starting
+    /// with `.`) are left out.
+    ///
+    /// Defaults to `false`, such directories getting a `404`.
+    ///
+    pub fn listing(mut self, listing: bool) -> StaticFiles {
+        self.listing = listing;
+
+        self
+    }
+
+    ///
+    /// Serves a single-page app, answering unmatched page requests with the root
+    /// `index.html` so the app's client-side router can take over.
+    ///
+    /// Only `GET` and `HEAD` requests whose `Accept` lists `text/html` fall back, and not for paths
+    /// with a file extension (e.g., a missing `/app.js`) or under a prefix excluded with
+    /// [StaticFiles::spa_exclude], which still get a `404`.
+    ///
+    /// Defaults to `false`.
+    ///
+    pub fn spa(mut self, spa: bool) -> StaticFiles {
+        self.spa = spa;
+
+        self
+    }
+
+    ///
+    /// Keeps a URL prefix from falling back to `index.html` in [StaticFiles::spa] mode,
+    /// so unknown API routes answer `404` rather than the app.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` -> URL prefix to exclude (e.g., "/api")
+    ///
+    pub fn spa_exclude(mut self, prefix: &str) -> StaticFiles {
+        self.spa_exclude
+            .push(prefix.trim_end_matches('/').to_string());
+
+        self
+    }
+
+    ///
+    /// Serves the precompressed sibling of a file (e.g., `app.js.br` or `app.js.gz` for
+    /// `app.js`) when the client's `Accept-Encoding` allows it, with `Content-Encoding`
+    /// set, so assets compressed at build time cost nothing to compress per request.
+    ///
+    /// `br` is preferred over `gzip` when accepted equally, and the file itself is served
+    /// to clients accepting neither. Responses for files with siblings carry
+    /// `Vary: Accept-Encoding`.
+    ///
+    /// Defaults to `false`.
+    ///
+    pub fn precompressed(mut self, precompressed: bool) -> StaticFiles {
+        self.precompressed = precompressed;
+
+        self
+    }
+
+    ///
+    /// Maps a URL path to a file below the root.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<PathBuf>` -> Path to an existing file, or None if it is missing or
+    ///   would escape the root
+    ///
+    pub fn resolve(&self, route: &str) -> Option<PathBuf> {
+        let mut path = self.map(route)?;
+
+        if path.is_dir() {
+            path.push("index.html");
+        }
+
+        self.contain(&path).filter(|path| path.is_file())
+    }
+
+    ///
+    /// Maps a URL path to a directory below the root, for listing.
+    ///
+    fn directory(&self, route: &str) -> Option<PathBuf> {
+        self.map(route)
+            .and_then(|path| self.contain(&path))
+            .filter(|path| path.is_dir())
+    }
+
+    ///
+    /// Returns the `index.html` an unmatched request falls back to in
+    /// [StaticFiles::spa] mode, if it qualifies.
+    ///
+    fn spa_index(&self, req: &Request) -> Option<PathBuf> {
+        let relative = req.route.strip_prefix(self.prefix.as_str())?;
+        let under = |prefix: &str| {
+            req.route
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        };
+
+        let qualifies = self.spa
+            && (relative.is_empty() || relative.starts_with('/'))
+            // Assets are requested by file name, a missing one is a real 404
+            && !relative.rsplit('/').next().unwrap_or("").contains('.')
+            && !self.spa_exclude.iter().any(|prefix| under(prefix))
+            && req
+                .accepted_types()
+                .iter()
+                .any(|media| media.value == "text/html" && media.quality > 0.0);
+
+        if !qualifies {
+            return None;
+        }
+
+        self.resolve(&self.prefix)
+    }
+
+    ///
+    /// Returns the precompressed siblings of a file with their content coding, in
+    /// [StaticFiles::precompressed] mode.
+    ///
+    fn variants(&self, path: &Path) -> Vec<(&'static str, PathBuf)> {
+        if !self.precompressed {
+            return Vec::new();
+        }
+
+        PRECOMPRESSED
+            .iter()
+            .filter_map(|(coding, ext)| {
+                let mut sibling = path.as_os_str().to_owned();
+                sibling.push(".");
+                sibling.push(ext);
+
+                self.contain(Path::new(&sibling))
+                    .filter(|sibling| sibling.is_file())
+                    .map(|sibling| (*coding, sibling))
+            })
+            .collect()
+    }
+
+    ///
+    /// Maps a URL path to a path below the root, rejecting any segment that isn't a
+    /// plain file name.
+    ///
+    fn map(&self, route: &str) -> Option<PathBuf> {
+        let relative = route.strip_prefix(self.prefix.as_str())?;
+
+        // The prefix must end on a segment boundary ("/static" must not match "/staticfoo")
+        if !relative.is_empty() && !relative.starts_with('/') {
+            return None;
+        }
+
+        let mut path = self.root.clone();
+
+        for segment in relative.split('/').filter(|s| !s.is_empty()) {
+            // An encoded `/` must not reach the filesystem as a separator
+            let segment = percent_decode(segment)?;
+
+            // Only plain file names are allowed, which rules out `..`, `.` and absolute paths
+            let mut components = Path::new(&segment).components();
+
+            match (components.next(), components.next()) {
+                (Some(Component::Normal(name)), None) if !segment.contains('\\') => path.push(name),
+                _ => return None,
+            }
+        }
+
+        Some(path)
+    }
+
+    ///
+    /// Resolves a path to its canonical form, if it exists and stays below the root.
+    ///
+    fn contain(&self, path: &Path) -> Option<PathBuf> {
+        // Symlinks may still point outside the root
+        let root = fs::canonicalize(&self.root).ok()?;
+        let path = fs::canonicalize(path).ok()?;
+
+        path.starts_with(&root).then_some(path)
+    }
+
+    ///
+    /// Lists a directory for [StaticFiles::listing], as HTML or `JSON`.
+    ///
+    fn list(&self, req: &Request, dir: &Path) -> Response {
+        let Ok(read_dir) = fs::read_dir(dir) else {
+            return Response::new(404).json(&"Not Found");
+        };
+
+        let mut entries: Vec<Entry> = read_dir
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let name = entry.file_name().into_string().ok()?;
+                // Follows symlinks, like serving does
+                let metadata = fs::metadata(entry.path()).ok()?;
+
+                (!name.starts_with('.')).then(|| Entry {
+                    name,
+                    directory: metadata.is_dir(),
+                    size: metadata.len(),
+                    modified: metadata.modified().ok().map(date::http_date),
+                })
+            })
+            .collect();
+
+        // Directories first, then by name
+        entries.sort_by(|a, b| b.directory.cmp(&a.directory).then(a.name.cmp(&b.name)));
+
+        let base = format!("{}/", req.route.trim_end_matches('/'));
+        let title = escape_html(&base);
+
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Index of {0}</title></head>\n\
+             <body>\n<h1>Index of {0}</h1>\n<table>\n\
+             <tr><th>Name</th><th>Size</th><th>Last modified</th></tr>\n",
+            title
+        );
+
+        // Links are absolute so they work whether or not the route ends with `/`
+        if let Some((parent, _)) = base.trim_end_matches('/').rsplit_once('/') {
+            if parent.len() >= self.prefix.len() {
+                html.push_str(&format!(
+                    "<tr><td><a href=\"{}/\">../</a></td><td></td><td></td></tr>\n",
+                    escape_html(parent)
+                ));
+            }
+        }
+
+        for entry in &entries {
+            let slash = if entry.directory { "/" } else { "" };
+            let size = if entry.directory {
+                "-".to_string()
+            } else {
+                entry.size.to_string()
+            };
+
+            html.push_str(&format!(
+                "<tr><td><a href=\"{}{}{}\">{}{}</a></td><td>{}</td><td>{}</td></tr>\n",
+                escape_html(&base),
+                percent_encode(&entry.name),
+                slash,
+                escape_html(&entry.name),
+                slash,
+                size,
+                entry.modified.as_deref().unwrap_or("-"),
+            ));
+        }
+
+        html.push_str("</table>\n</body>\n</html>\n");
+
+        Representations::new()
+            .html(&html)
+            .json(&entries)
+            .respond(req)
+    }
+}
+
+impl Handler for StaticFiles {
+    fn call(&self, req: Request) -> Response {
+        if !matches!(req.method, HttpMethod::GET | HttpMethod::HEAD) {
+            return Response::new(404).json(&"Not Found");
+        }
+
+        let path = match self.resolve(&req.route) {
+            Some(path) => path,
+            None => match (self.directory(&req.route), self.spa_index(&req)) {
+                (Some(dir), _) if self.listing => return self.list(&req, &dir),
+                (_, Some(index)) => index,
+                _ => return Response::new(404).json(&"Not Found"),
+            },
+        };
+
+        // Typed after the file itself, whichever variant is sent
+        let mime = guess_mime(&path);
+
+        let variants = self.variants(&path);
+        let variant = req
+            .headers
+            .get("Accept-Encoding")
+            .and_then(|accept_encoding| accepted_variant(accept_encoding, &variants));
+
+        let path = variant.map_or(path, |(_, sibling)| sibling.clone());
+
+        // Validators and bytes both come from this handle, so a file replaced meanwhile
+        // can't have its old ETag sent with its new content
+        let Ok(file) = fs::File::open(&path) else {
+            return Response::new(404).json(&"Not Found");
+        };
+
+        let Ok(metadata) = file.metadata() else {
+            return Response::new(404).json(&"Not Found");
+        };
+
+        let len = metadata.len();
+        let mut res = Response::new(200).header("Accept-Ranges", "bytes");
+
+        if !variants.is_empty() {
+            res = res.vary("Accept-Encoding");
+        }
+
+        if let Some((coding, _)) = variant {
+            res = res.header("Content-Encoding", coding);
+        }
+
+        let modified = metadata.modified().ok();
+
+        if let Some(modified) = modified {
+            let since_epoch = modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+
+            // Nanoseconds tell apart edits within the same second, which resumed
+            // downloads would otherwise mix up
+            res = res
+                .header("Last-Modified", &date::http_date(modified))
+                .header(
+                    "ETag",
+                    &format!(
+                        "\"{:x}.{:x}-{:x}\"",
+                        since_epoch.as_secs(),
+                        since_epoch.subsec_nanos(),
+                        len
+                    ),
+                );
+        }
+
+        // Revalidation is answered from the metadata alone, without reading the file
+        let res = conditional::evaluate(&req, res);
+
+        if res.status == 304 {
+            return res;
+        }
+
+        let ranges = match req.headers.get("Range") {
+            Some(range) if if_range_matches(&req, &res, modified) => parse_ranges(range, len),
+            _ => Ranges::Full,
+        };
+
+        let res = match ranges {
+            Ranges::Full => Ok(res
+                .set_header("Content-Type", mime)
+                .body(Body::File { file, len })),
+            Ranges::Satisfiable(ranges) => partial_content(res, file, mime, len, &ranges),
+            Ranges::Unsatisfiable => {
+                return Response::new(416)
+                    .header("Content-Range", &format!("bytes */{}", len))
+                    .json(&"Range Not Satisfiable")
+            }
+        };
+
+        res.unwrap_or_else(|_| Response::new(404).json(&"Not Found"))
+    }
+}
+
+///
+/// Picks the precompressed variant the client's `Accept-Encoding` prefers, unless it
+/// prefers the uncompressed file (`identity`).
+///
+fn accepted_variant<'a>(
+    accept_encoding: &str,
+    variants: &'a [(&'static str, PathBuf)],
+) -> Option<&'a (&'static str, PathBuf)> {
+    let preferences = negotiation::parse(accept_encoding);
+
+    let quality = |coding: &str| {
+        preferences
+            .iter()
+            .find(|p| p.value == coding || (coding == "gzip" && p.value == "x-gzip"))
+            .or_else(|| preferences.iter().find(|p| p.value == "*"))
+            .map_or(0.0, |p| p.quality)
+    };
+
+    // Only an explicitly listed `identity` competes, it is acceptable but not preferred
+    let identity = preferences
+        .iter()
+        .find(|p| p.value == "identity")
+        .map_or(0.0, |p| p.quality);
+
+    let mut best: Option<(&'a (&'static str, PathBuf), f32)> = None;
+
+    for variant in variants {
+        let quality = quality(variant.0);
+
+        // Ties keep the earlier, better compressing coding
+        if quality > 0.0 && quality >= identity && best.is_none_or(|(_, q)| quality > q) {
+            best = Some((variant, quality));
+        }
+    }
+
+    best.map(|(variant, _)| variant)
+}
+
+///
+/// One entry of a directory listing.
+///
+#[derive(Debug, Serialize)]
+struct Entry {
+    name: String,
+    directory: bool,
+    size: u64,
+    modified: Option<String>,
+}
+
+///
+/// Escapes text for use in HTML content and quoted attributes.
+///
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+///
+/// Percent-encodes a file name for use as a URL path segment.
+///
+fn percent_encode(name: &str) -> String {
+    name.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+///
+/// Outcome of evaluating a `Range` header against a file.
+///
+#[derive(Debug, PartialEq)]
+enum Ranges {
+    ///
+    /// The header is malformed or not in bytes, so the whole file is sent
+    ///
+    Full,
+
+    ///
+    /// Inclusive byte ranges to send, in the requested order
+    ///
+    Satisfiable(Vec<(u64, u64)>),
+
+    ///
+    /// No range overlaps the file
+    ///
+    Unsatisfiable,
+}
+
+///
+/// Most ranges accepted in one request, more are treated as a malformed header to keep
+/// clients from turning one request into thousands of tiny reads.
+///
+const MAX_RANGES: usize = 16;
+
+///
+/// Parses a `Range` header (e.g., `bytes=0-499, -500`) for a file of `len` bytes.
+///
+fn parse_ranges(header: &str, len: u64) -> Ranges {
+    let Some(specs) = header.trim().strip_prefix("bytes=") else {
+        return Ranges::Full;
+    };
+
+    let mut ranges = Vec::new();
+
+    for spec in specs.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let Some((start, end)) = spec.split_once('-') else {
+            return Ranges::Full;
+        };
+
+        let range = match (start.parse::<u64>(), end.parse::<u64>()) {
+            // `-500` is the last 500 bytes
+            (Err(_), Ok(suffix)) if start.is_empty() => {
+                (suffix > 0 && len > 0).then(|| (len - suffix.min(len), len - 1))
+            }
+            // `500-` is everything from byte 500
+            (Ok(start), Err(_)) if end.is_empty() => (start < len).then(|| (start, len - 1)),
+            (Ok(start), Ok(end)) if start <= end => {
+                (start < len).then(|| (start, end.min(len - 1)))
+            }
+            _ => return Ranges::Full,
+        };
+
+        ranges.extend(range);
+    }
+
+    match ranges.len() {
+        0 => Ranges::Unsatisfiable,
+        n if n > MAX_RANGES => Ranges::Full,
+        _ => Ranges::Satisfiable(ranges),
+    }
+}
+
+///
+/// Whether the `If-Range` precondition, if any, still matches the file, meaning the
+/// client's partial copy is current and the `Range` can be honored. Otherwise the whole
+/// file is sent, rather than the requested bytes of a different version.
+///
+fn if_range_matches(req: &Request, res: &Response, modified: Option<SystemTime>) -> bool {
+    let Some(if_range) = req.headers.get("If-Range") else {
+        return true;
+    };
+
+    match if_range.trim() {
+        // A weak entity tag never matches, as the bytes may differ
+        tag if tag.starts_with("W/") => false,
+        tag if tag.starts_with('"') => res.headers.get("ETag") == Some(tag),
+        // A date must match exactly, and is only trusted once the file is older than
+        // its one second resolution, a file changed twice in that second sharing it
+        date => {
+            let settled = modified.is_some_and(|modified| {
+                SystemTime::now()
+                    .duration_since(modified)
+                    .is_ok_and(|age| age >= Duration::from_secs(1))
+            });
+
+            settled && res.headers.get("Last-Modified") == Some(date)
+        }
+    }
+}
+
+///
+/// Builds the `206` [Response] for the given ranges of a file, as a `multipart/byteranges`
+/// body when there are several.
+///
+/// The ranges are streamed from the file while the response is sent, none of them
+/// being loaded into memory.
+///
+fn partial_content(
+    mut res: Response,
+    mut file: fs::File,
+    mime: &str,
+    len: u64,
+    ranges: &[(u64, u64)],
+) -> io::Result<Response> {
+    res.status = 206;
+
+    if let [(start, end)] = *ranges {
+        file.seek(SeekFrom::Start(start))?;
+
+        return Ok(res
+            .header("Content-Range", &format!("bytes {}-{}/{}", start, end, len))
+            .set_header("Content-Type", mime)
+            .body(Body::File {
+                file,
+                len: end - start + 1,
+            }));
+    }
+
+    let mut random = [0u8; 12];
+    getrandom::getrandom(&mut random).expect("OS random number generator unavailable");
+
+    let boundary: String = random.iter().map(|b| format!("{:02x}", b)).collect();
+    let content_type = format!("multipart/byteranges; boundary={}", boundary);
+    let (mime, ranges) = (mime.to_string(), ranges.to_vec());
+
+    let body = Body::from_fn(move |out| {
+        for (start, end) in ranges {
+            write!(
+                out,
+                "--{}\r\nContent-Type: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                boundary, mime, start, end, len
+            )?;
+
+            file.seek(SeekFrom::Start(start))?;
+
+            // The file shrank since its length was read
+            if io::copy(&mut (&file).take(end - start + 1), out)? != end - start + 1 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+
+            out.write_all(b"\r\n")?;
+        }
+
+        write!(out, "--{}--\r\n", boundary)
+    });
+
+    Ok(res.set_header("Content-Type", &content_type).body(body))
+}
+
+///
+/// Guesses the `Content-Type` of a file from its extension.
+///
+/// # Returns
+///
+/// * `&'static str` -> The MIME type, or `application/octet-stream` if unknown
+///
+pub fn guess_mime(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+
+    match ext.as_deref() {
+        Some("html" | "htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js" | "mjs") => "text/javascript; charset=utf-8",
+        Some("json" | "map") => "application/json",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("csv") => "text/csv; charset=utf-8",
+        Some("xml") => "application/xml",
+        Some("md") => "text/markdown; charset=utf-8",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("ico") => "image/x-icon",
+        Some("webp") => "image/webp",
+        Some("avif") => "image/avif",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("ttf") => "font/ttf",
+        Some("otf") => "font/otf",
+        Some("mp3") => "audio/mpeg",
+        Some("ogg") => "audio/ogg",
+        Some("wav") => "audio/wav",
+        Some("mp4") => "video/mp4",
+        Some("webm") => "video/webm",
+        Some("pdf") => "application/pdf",
+        Some("zip") => "application/zip",
+        Some("gz") => "application/gzip",
+        Some("wasm") => "application/wasm",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        compression::Compression,
+        router::Router,
+        server::{test_request, Server},
+    };
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::thread;
+
+    fn body(res: Response) -> Vec<u8> {
+        let mut out = Vec::new();
+        res.body.write_to(&mut out, false, None).unwrap();
+
+        out
+    }
+
+    fn fixture(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("http_rs_{}_{}", name, std::process::id()));
+
+        fs::create_dir_all(dir.join("public/docs")).unwrap();
+        fs::write(dir.join("public/app.js"), "console.log(1)").unwrap();
+        fs::write(dir.join("public/docs/index.html"), "<h1>Docs</h1>").unwrap();
+        fs::write(dir.join("secret.txt"), "secret").unwrap();
+
+        dir
+    }
+
+    #[test]
+    fn test_static_files_serving() {
+        let dir = fixture("static");
+        let files = StaticFiles::new(dir.join("public")).prefix("/static");
+
+        let res = files.call(test_request(HttpMethod::GET, "/static/app.js"));
+
+        assert_eq!(res.status, 200);
+        assert_eq!(
+            res.headers.get("Content-Type"),
+            Some("text/javascript; charset=utf-8")
+        );
+        assert_eq!(res.headers.get("Content-Length"), Some("14"));
+
+        // Revalidating with the ETag skips the body
+        let mut req = test_request(HttpMethod::GET, "/static/app.js");
+        req.headers
+            .insert("If-None-Match", res.headers.get("ETag").unwrap());
+
+        assert_eq!(files.call(req).status, 304);
+        assert_eq!(body(res), b"console.log(1)");
+
+        let res = files.call(test_request(HttpMethod::GET, "/static/docs/"));
+        assert_eq!(body(res), b"<h1>Docs</h1>");
+
+        let res = files.call(test_request(HttpMethod::GET, "/static/missing.js"));
+        assert_eq!(res.status, 404);
+
+        let res = files.call(test_request(HttpMethod::POST, "/static/app.js"));
+        assert_eq!(res.status, 404);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_static_files_head() {
+        let dir = fixture("head");
+        let files = StaticFiles::new(dir.join("public"));
+
+        let server = Server::new("127.0.0.1:0").unwrap().workers(1);
+        let addr = server.local_addr().unwrap();
+
+        thread::spawn(move || server.serve(files));
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(b"HEAD /app.js HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .unwrap();
+
+        let mut raw = String::new();
+        client.read_to_string(&mut raw).unwrap();
+
+        assert!(raw.starts_with("HTTP/1.1 200 OK\r\n"), "{}", raw);
+        assert!(raw.contains("\r\nContent-Length: 14\r\n"));
+        assert!(raw.ends_with("\r\n\r\n"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_static_files_precompressed() {
+        let dir = fixture("precompressed");
+        fs::write(dir.join("public/app.js.gz"), "gzipped").unwrap();
+        fs::write(dir.join("public/app.js.br"), "brotli").unwrap();
+
+        let files = StaticFiles::new(dir.join("public")).precompressed(true);
+        let get = |accept_encoding: Option<&str>| {
+            let mut req = test_request(HttpMethod::GET, "/app.js");
+
+            if let Some(accept_encoding) = accept_encoding {
+                req.headers.insert("Accept-Encoding", accept_encoding);
+            }
+
+            files.call(req)
+        };
+
+        let res = get(Some("gzip, deflate, br"));
+        assert_eq!(res.headers.get("Content-Encoding"), Some("br"));
+        assert_eq!(
+            res.headers.get("Content-Type"),
+            Some("text/javascript; charset=utf-8")
+        );
+        assert_eq!(res.headers.get("Vary"), Some("Accept-Encoding"));
+        assert_eq!(body(res), b"brotli");
+
+        let res = get(Some("br;q=0.5, gzip"));
+        assert_eq!(res.headers.get("Content-Encoding"), Some("gzip"));
+        assert_eq!(body(res), b"gzipped");
+
+        // Refused codings and clients without Accept-Encoding get the file itself
+        for accept_encoding in [None, Some("br;q=0, gzip;q=0"), Some("identity, gzip;q=0.5")] {
+            let res = get(accept_encoding);
+            assert_eq!(res.headers.get("Content-Encoding"), None);
+            assert_eq!(res.headers.get("Vary"), Some("Accept-Encoding"));
+            assert_eq!(body(res), b"console.log(1)");
+        }
+
+        // Off by default
+        let mut req = test_request(HttpMethod::GET, "/app.js");
+        req.headers.insert("Accept-Encoding", "br, gzip");
+
+        let res = StaticFiles::new(dir.join("public")).call(req);
+        assert_eq!(res.headers.get("Vary"), None);
+        assert_eq!(body(res), b"console.log(1)");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_static_files_listing() {
+        let dir = fixture("listing");
+        fs::write(dir.join("public/docs/<b>&.txt"), "12345").unwrap();
+        fs::write(dir.join("public/.env"), "SECRET=1").unwrap();
+
+        let files = StaticFiles::new(dir.join("public")).prefix("/static");
+
+        // Off by default
+        assert_eq!(
+            files.call(test_request(HttpMethod::GET, "/static")).status,
+            404
+        );
+
+        let files = files.listing(true);
+        let res = files.call(test_request(HttpMethod::GET, "/static"));
+        let html = String::from_utf8(res.body.as_bytes().unwrap().to_vec()).unwrap();
+
+        assert_eq!(res.status, 200);
+        assert!(html.contains("<a href=\"/static/docs/\">docs/</a>"));
+        assert!(html.contains("<a href=\"/static/app.js\">app.js</a></td><td>14</td>"));
+        assert!(!html.contains(".env"));
+        assert!(!html.contains("../"));
+
+        let mut req = test_request(HttpMethod::GET, "/static/docs/");
+        req.headers.insert("Accept", "application/json");
+        fs::remove_file(dir.join("public/docs/index.html")).unwrap();
+
+        let res = files.call(req);
+        let entries: serde_json::Value =
+            serde_json::from_slice(res.body.as_bytes().unwrap()).unwrap();
+
+        assert_eq!(res.headers.get("Content-Type"), Some("application/json"));
+        assert_eq!(entries[0]["name"], "<b>&.txt");
+        assert_eq!(entries[0]["size"], 5);
+
+        let res = files.call(test_request(HttpMethod::GET, "/static/docs/"));
+        let html = String::from_utf8(res.body.as_bytes().unwrap().to_vec()).unwrap();
+
+        assert!(html.contains("<a href=\"/static/docs/%3Cb%3E%26.txt\">&lt;b&gt;&amp;.txt</a>"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_static_files_spa() {
+        let dir = fixture("spa");
+        fs::write(dir.join("public/index.html"), "<div id=\"app\"></div>").unwrap();
+
+        let files = StaticFiles::new(dir.join("public"))
+            .spa(true)
+            .spa_exclude("/api");
+
+        let request = |route: &str, accept: &str| {
+            let mut req = test_request(HttpMethod::GET, route);
+            req.headers.insert("Accept", accept);
+
+            files.call(req)
+        };
+
+        let html = "text/html,application/xhtml+xml,*/*;q=0.8";
+        let res = request("/users/42", html);
+
+        assert_eq!(res.status, 200);
+        assert_eq!(body(res), b"<div id=\"app\"></div>");
+
+        // Existing files are still served
+        assert_eq!(body(request("/app.js", html)), b"console.log(1)");
+
+        assert_eq!(request("/missing.js", html).status, 404);
+        assert_eq!(request("/api/users", html).status, 404);
+        assert_eq!(request("/users/42", "application/json").status, 404);
+        assert_eq!(request("/users/42", "*/*").status, 404);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_ranges() {
+        assert_eq!(
+            parse_ranges("bytes=0-4", 10),
+            Ranges::Satisfiable(vec![(0, 4)])
+        );
+        assert_eq!(
+            parse_ranges("bytes=8-, -3, 5-100", 10),
+            Ranges::Satisfiable(vec![(8, 9), (7, 9), (5, 9)])
+        );
+        assert_eq!(parse_ranges("bytes=10-20", 10), Ranges::Unsatisfiable);
+        assert_eq!(parse_ranges("bytes=-0", 10), Ranges::Unsatisfiable);
+        assert_eq!(parse_ranges("bytes=5-2", 10), Ranges::Full);
+        assert_eq!(parse_ranges("items=0-1", 10), Ranges::Full);
+    }
+
+    #[test]
+    fn test_static_files_ranges() {
+        let dir = fixture("ranges");
+        let files = StaticFiles::new(dir.join("public"));
+
+        let request = |range: &str| {
+            let mut req = test_request(HttpMethod::GET, "/app.js");
+            req.headers.insert("Range", range);
+
+            files.call(req)
+        };
+
+        let res = request("bytes=0-6");
+
+        assert_eq!(res.status, 206);
+        assert_eq!(res.headers.get("Content-Range"), Some("bytes 0-6/14"));
+        assert_eq!(res.headers.get("Content-Length"), Some("7"));
+        assert_eq!(body(res), b"console");
+
+        let res = request("bytes=0-0,-2");
+        let content_type = res.headers.get("Content-Type").unwrap();
+        let boundary = content_type.split_once("boundary=").unwrap().1.to_string();
+
+        assert_eq!(res.status, 206);
+        assert_eq!(
+            String::from_utf8(body(res)).unwrap(),
+            format!(
+                "--{b}\r\nContent-Type: text/javascript; charset=utf-8\r\nContent-Range: bytes 0-0/14\r\n\r\nc\r\n\
+                 --{b}\r\nContent-Type: text/javascript; charset=utf-8\r\nContent-Range: bytes 12-13/14\r\n\r\n1)\r\n\
+                 --{b}--\r\n",
+                b = boundary
+            )
+        );
+
+        // Large ranges are streamed from the file rather than buffered
+        let res = request("bytes=1-12");
+
+        assert!(matches!(res.body, Body::File { len: 12, .. }));
+        assert_eq!(body(res), b"onsole.log(1");
+
+        let res = request("bytes=0-1,3-");
+
+        assert!(matches!(res.body, Body::Producer(_)));
+        assert!(String::from_utf8(body(res))
+            .unwrap()
+            .contains("Content-Range: bytes 3-13/14\r\n\r\nsole.log(1)\r\n"));
+
+        let res = request("bytes=100-");
+
+        assert_eq!(res.status, 416);
+        assert_eq!(res.headers.get("Content-Range"), Some("bytes */14"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_static_files_compressed_ranges() {
+        let dir = fixture("compressed_ranges");
+        fs::write(dir.join("public/app.js"), "console.log(1);\n".repeat(200)).unwrap();
+
+        let mut router = Router::new();
+        router
+            .layer(Compression::new().min_size(64))
+            .get("/*path", StaticFiles::new(dir.join("public")));
+
+        let request = |headers: &[(&str, &str)]| {
+            let mut req = test_request(HttpMethod::GET, "/app.js");
+            req.headers.insert("Accept-Encoding", "gzip");
+
+            for (name, value) in headers {
+                req.headers.insert(*name, *value);
+            }
+
+            router.handle(req)
+        };
+
+        let res = request(&[]);
+        let etag = res.headers.get("ETag").unwrap().to_string();
+        assert_eq!(res.headers.get("Content-Encoding"), Some("gzip"));
+        assert!(etag.ends_with("-gzip\""));
+        assert_eq!(res.headers.get("Accept-Ranges"), None);
+
+        // A range counts bytes of the file, so it is sent uncompressed
+        let identity = router.handle(test_request(HttpMethod::GET, "/app.js"));
+        let identity_etag = identity.headers.get("ETag").unwrap().to_string();
+        assert_ne!(identity_etag, etag);
+
+        let res = request(&[("Range", "bytes=0-15"), ("If-Range", &identity_etag)]);
+        assert_eq!(res.status, 206);
+        assert_eq!(res.headers.get("Content-Encoding"), None);
+        assert_eq!(res.headers.get("Content-Range"), Some("bytes 0-15/3200"));
+
+        // A partial compressed copy can't be resumed from file offsets
+        let res = request(&[("Range", "bytes=0-15"), ("If-Range", &etag)]);
+        assert_eq!(res.status, 200);
+        assert_eq!(res.headers.get("Content-Encoding"), Some("gzip"));
+        assert_eq!(res.headers.get("Content-Range"), None);
+
+        let res = request(&[("If-None-Match", &etag)]);
+        assert_eq!(res.status, 304);
+        assert_eq!(res.headers.get("ETag"), Some(etag.as_str()));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_static_files_if_range() {
+        let dir = fixture("if_range");
+        let files = StaticFiles::new(dir.join("public"));
+
+        let request = |if_range: &str| {
+            let mut req = test_request(HttpMethod::GET, "/app.js");
+            req.headers.insert("Range", "bytes=0-6");
+            req.headers.insert("If-Range", if_range);
+
+            files.call(req)
+        };
+
+        let res = files.call(test_request(HttpMethod::GET, "/app.js"));
+        let etag = res.headers.get("ETag").unwrap().to_string();
+        let last_modified = res.headers.get("Last-Modified").unwrap().to_string();
+
+        assert_eq!(request(&etag).status, 206);
+        assert_eq!(request("\"stale\"").status, 200);
+        assert_eq!(request(&format!("W/{}", etag)).status, 200);
+
+        // Just written, so the date may be shared by another version
+        assert_eq!(request(&last_modified).status, 200);
+
+        let modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        fs::File::options()
+            .write(true)
+            .open(dir.join("public/app.js"))
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        assert_eq!(request(&date::http_date(modified)).status, 206);
+
+        // A resumed download of a changed file gets all of it
+        fs::write(dir.join("public/app.js"), "console.log(22)").unwrap();
+
+        let res = request(&etag);
+        assert_eq!(res.status, 200);
+        assert_eq!(body(res), b"console.log(22)");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_static_files_traversal() {
+        let dir = fixture("traversal");
+        let files = StaticFiles::new(dir.join("public"));
+
+        assert!(files.resolve("/../secret.txt").is_none());
+        assert!(files.resolve("/docs/../../secret.txt").is_none());
+        assert!(files.resolve("/..\\secret.txt").is_none());
+        assert!(files.resolve("/docs%2F..%2F..%2Fsecret.txt").is_none());
+        assert!(files.resolve("/app.js").is_some());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,264 @@
+//!
+//! Connection abstraction over plain TCP and TLS streams, so [crate::server::Request]
+//! parsing and [crate::server::Response] sending work the same on both.
+//!
+
+use crate::body;
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    net::{Shutdown, SocketAddr, TcpStream},
+    time::Duration,
+};
+
+#[cfg(feature = "tls")]
+use std::sync::{Arc, Mutex};
+
+///
+/// Alias for a server side TLS session over a [TcpStream].
+///
+#[cfg(feature = "tls")]
+pub type TlsStream = rustls::StreamOwned<rustls::ServerConnection, TcpStream>;
+
+///
+/// A client connection yielded by [crate::server::Server::listen].
+///
+pub enum Stream {
+    ///
+    /// Plain text TCP connection
+    ///
+    Tcp(TcpStream),
+
+    ///
+    /// TLS connection, shared between clones so reads and writes can use separate handles
+    ///
+    #[cfg(feature = "tls")]
+    Tls(Arc<Mutex<TlsStream>>),
+}
+
+impl Stream {
+    ///
+    /// Creates a new handle to the same connection.
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<Stream>` -> The cloned [Stream] or an [std::io] error
+    ///
+    pub fn try_clone(&self) -> io::Result<Stream> {
+        match self {
+            Stream::Tcp(stream) => Ok(Stream::Tcp(stream.try_clone()?)),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => Ok(Stream::Tls(Arc::clone(stream))),
+        }
+    }
+
+    ///
+    /// Returns the address of the connected peer.
+    ///
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.with_tcp(|tcp| tcp.peer_addr())
+    }
+
+    ///
+    /// Returns the local address the connection was accepted on.
+    ///
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.with_tcp(|tcp| tcp.local_addr())
+    }
+
+    ///
+    /// Shuts down the read, write or both halves of the underlying [TcpStream].
+    ///
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.with_tcp(|tcp| tcp.shutdown(how))
+    }
+
+    ///
+    /// Sets the read timeout of the underlying [TcpStream], `None` blocks indefinitely.
+    ///
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.with_tcp(|tcp| tcp.set_read_timeout(timeout))
+    }
+
+    ///
+    /// Sets the write timeout of the underlying [TcpStream], `None` blocks indefinitely.
+    ///
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.with_tcp(|tcp| tcp.set_write_timeout(timeout))
+    }
+
+    ///
+    /// Whether the connection is encrypted with TLS.
+    ///
+    pub fn is_tls(&self) -> bool {
+        match self {
+            Stream::Tcp(_) => false,
+            #[cfg(feature = "tls")]
+            Stream::Tls(_) => true,
+        }
+    }
+
+    ///
+    /// Completes the TLS handshake, which otherwise happens on first read or write.
+    /// Nothing to do for plain TCP connections.
+    ///
+    #[cfg(feature = "tls")]
+    pub(crate) fn handshake(&self) -> io::Result<()> {
+        if let Stream::Tls(stream) = self {
+            let mut stream = lock(stream)?;
+            let TlsStream { conn, sock } = &mut *stream;
+
+            while conn.is_handshaking() {
+                conn.complete_io(sock)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Returns the protocol the client negotiated with ALPN during the handshake (e.g.,
+    /// `h2`), if any.
+    ///
+    #[cfg(feature = "http2")]
+    pub(crate) fn alpn_protocol(&self) -> Option<Vec<u8>> {
+        match self {
+            Stream::Tcp(_) => None,
+            Stream::Tls(stream) => lock(stream).ok()?.conn.alpn_protocol().map(<[u8]>::to_vec),
+        }
+    }
+
+    ///
+    /// Returns the DER encoded certificate the client authenticated with during the
+    /// handshake, if any, see [crate::server::Server::client_auth].
+    ///
+    #[cfg(feature = "tls")]
+    pub(crate) fn peer_certificate(&self) -> Option<Vec<u8>> {
+        match self {
+            Stream::Tcp(_) => None,
+            Stream::Tls(stream) => {
+                let stream = lock(stream).ok()?;
+                let chain = stream.conn.peer_certificates()?;
+
+                chain.first().map(|cert| cert.to_vec())
+            }
+        }
+    }
+
+    ///
+    /// Sends `len` bytes of a file over the connection.
+    ///
+    /// Plain TCP connections hand the copy to the kernel (`sendfile` on Linux), TLS ones
+    /// encrypt it in user space.
+    ///
+    pub(crate) fn send_file(&mut self, file: File, len: u64) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => body::copy_file(file, len, stream),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => body::copy_file(file, len, &mut *lock(stream)?),
+        }
+    }
+
+    ///
+    /// Runs `f` against the underlying [TcpStream].
+    ///
+    fn with_tcp<T>(&self, f: impl FnOnce(&TcpStream) -> io::Result<T>) -> io::Result<T> {
+        match self {
+            Stream::Tcp(stream) => f(stream),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => f(&lock(stream)?.sock),
+        }
+    }
+}
+
+///
+/// Locks a shared [TlsStream], surfacing a poisoned lock as an [std::io] error.
+///
+#[cfg(feature = "tls")]
+fn lock(stream: &Mutex<TlsStream>) -> io::Result<std::sync::MutexGuard<'_, TlsStream>> {
+    stream
+        .lock()
+        .map_err(|_| io::Error::other("TLS stream lock poisoned"))
+}
+
+impl From<TcpStream> for Stream {
+    fn from(stream: TcpStream) -> Stream {
+        Stream::Tcp(stream)
+    }
+}
+
+///
+/// Reads decrypted bytes from a shared [TlsStream].
+///
+/// The socket is waited on without holding the lock, so writes through other handles
+/// (e.g., HTTP/2 responses) go on while the connection is idle.
+///
+#[cfg(feature = "tls")]
+fn read_tls(stream: &Mutex<TlsStream>, buf: &mut [u8]) -> io::Result<usize> {
+    loop {
+        let sock = {
+            let mut tls = lock(stream)?;
+
+            match tls.conn.reader().read(buf) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                read => return read,
+            }
+
+            tls.sock.try_clone()?
+        };
+
+        // Blocks until a record arrives or the client closes, within the read timeout
+        sock.peek(&mut [0])?;
+
+        let mut tls = lock(stream)?;
+        let TlsStream { conn, sock } = &mut *tls;
+
+        conn.read_tls(sock)?;
+
+        if let Err(e) = conn.process_new_packets() {
+            // Tells the client why, as the alert is queued rather than sent
+            let _ = conn.write_tls(sock);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, e));
+        }
+
+        while conn.wants_write() {
+            conn.write_tls(sock)?;
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.read(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => read_tls(stream, buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.write(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => lock(stream)?.write(buf),
+        }
+    }
+
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.write_vectored(bufs),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => lock(stream)?.write_vectored(bufs),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.flush(),
+            #[cfg(feature = "tls")]
+            Stream::Tls(stream) => lock(stream)?.flush(),
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//!
+//! TLS configuration for HTTPS listeners, enabled with the `tls` feature.
+//!
+
+use crate::{certificates::Certificates, client_cert::ClientAuth};
+use rustls::{
+    crypto::ring,
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+    server::WebPkiClientVerifier,
+    sign::CertifiedKey,
+    InconsistentKeys, RootCertStore, ServerConfig,
+};
+use std::{io, path::Path, sync::Arc};
+
+///
+/// Converts any TLS or PEM error into an [std::io] error.
+///
+pub(crate) fn tls_error<E: std::fmt::Display>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+///
+/// Loads a PEM encoded certificate chain from disk.
+///
+pub(crate) fn load_certs(path: &Path) -> io::Result<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .map_err(tls_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(tls_error)?;
+
+    if certs.is_empty() {
+        return Err(tls_error("No certificates found"));
+    }
+
+    Ok(certs)
+}
+
+///
+/// Loads a PEM encoded private key (PKCS#1, PKCS#8 or SEC1) from disk.
+///
+pub(crate) fn load_key(path: &Path) -> io::Result<PrivateKeyDer<'static>> {
+    PrivateKeyDer::from_pem_file(path).map_err(tls_error)
+}
+
+///
+/// Loads a PEM certificate chain and the private key it was issued for.
+///
+/// # Arguments
+///
+/// * `cert_path` -> Path to the PEM encoded certificate chain
+/// * `key_path` -> Path to the PEM encoded private key
+///
+/// # Returns
+///
+/// * `io::Result<Arc<CertifiedKey>>` -> The certified key or an [std::io] error
+///
+pub(crate) fn certified_key(cert_path: &Path, key_path: &Path) -> io::Result<Arc<CertifiedKey>> {
+    let certs = load_certs(cert_path)?;
+    let key = ring::default_provider()
+        .key_provider
+        .load_private_key(load_key(key_path)?)
+        .map_err(tls_error)?;
+
+    let certified = CertifiedKey::new(certs, key);
+
+    match certified.keys_match() {
+        Ok(()) | Err(rustls::Error::InconsistentKeys(InconsistentKeys::Unknown)) => {
+            Ok(Arc::new(certified))
+        }
+        Err(e) => Err(tls_error(e)),
+    }
+}
+
+///
+/// Builds a rustls [ServerConfig] serving the given [Certificates].
+///
+/// # Arguments
+///
+/// * `certificates` -> The certificates, selected by SNI hostname
+///
+/// # Returns
+///
+/// * `io::Result<Arc<ServerConfig>>` -> The shared config or an [std::io] error
+///
+pub(crate) fn server_config(certificates: &Certificates) -> io::Result<Arc<ServerConfig>> {
+    let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(tls_error)?
+        .with_no_client_auth()
+        .with_cert_resolver(Arc::new(certificates.clone()));
+
+    // Offer HTTP/2 to clients that support it, HTTP/1.1 remaining the fallback
+    #[cfg(feature = "http2")]
+    let config = {
+        let mut config = config;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        config
+    };
+
+    Ok(Arc::new(config))
+}
+
+///
+/// Rebuilds a [ServerConfig] to verify client certificates against a PEM CA bundle,
+/// keeping its server certificate and ALPN protocols.
+///
+/// # Arguments
+///
+/// * `config` -> The config to rebuild
+/// * `ca_path` -> Path to the PEM encoded CA certificates client certificates chain to
+/// * `auth` -> Whether a client certificate is required
+///
+/// # Returns
+///
+/// * `io::Result<Arc<ServerConfig>>` -> The shared config or an [std::io] error
+///
+pub(crate) fn with_client_auth(
+    config: &ServerConfig,
+    ca_path: &Path,
+    auth: ClientAuth,
+) -> io::Result<Arc<ServerConfig>> {
+    let mut roots = RootCertStore::empty();
+
+    for cert in load_certs(ca_path)? {
+        roots.add(cert).map_err(tls_error)?;
+    }
+
+    let provider = Arc::new(ring::default_provider());
+    let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone());
+    let verifier = match auth {
+        ClientAuth::Required => verifier,
+        ClientAuth::Optional => verifier.allow_unauthenticated(),
+    };
+
+    let mut rebuilt = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(tls_error)?
+        .with_client_cert_verifier(verifier.build().map_err(tls_error)?)
+        .with_cert_resolver(config.cert_resolver.clone());
+
+    rebuilt.alpn_protocols = config.alpn_protocols.clone();
+
+    Ok(Arc::new(rebuilt))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::{Response, Server};
+    use rustls::{pki_types::ServerName, ClientConfig, ClientConnection, RootCertStore};
+    use std::{
+        io::{Read, Write},
+        net::TcpStream,
+        sync::Arc,
+        thread,
+    };
+
+    #[test]
+    fn test_tls_round_trip() {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+
+        let dir = std::env::temp_dir().join(format!("http_rs_tls_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("cert.pem"), cert.cert.pem()).unwrap();
+        std::fs::write(dir.join("key.pem"), cert.key_pair.serialize_pem()).unwrap();
+
+        let server = Server::new_tls("127.0.0.1:0", dir.join("cert.pem"), dir.join("key.pem"))
+            .unwrap()
+            .workers(1);
+        let addr = server.local_addr().unwrap();
+
+        thread::spawn(move || server.serve(|_| Response::new(200).json(&"secure")));
+
+        let mut roots = RootCertStore::empty();
+        roots.add(cert.cert.der().clone()).unwrap();
+
+        let config = ClientConfig::builder_with_provider(Arc::new(super::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        let conn =
+            ClientConnection::new(Arc::new(config), ServerName::try_from("localhost").unwrap())
+                .unwrap();
+        let mut client = rustls::StreamOwned::new(conn, TcpStream::connect(addr).unwrap());
+
+        client
+            .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .unwrap();
+
+        let mut raw = Vec::new();
+        let _ = client.read_to_end(&mut raw);
+        let raw = String::from_utf8_lossy(&raw);
+
+        assert!(raw.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(raw.ends_with("\"secure\""));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,199 @@
+//!
+//! Request instrumentation through `tracing` spans and/or `log` records.
+//!
+//! `👉 Note:` Requires the `tracing` or `log` feature
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::router::Router;
+//! use http_rs::trace::Trace;
+//! use std::time::Duration;
+//!
+//! let mut router = Router::new();
+//!
+//! // A span per request, and a warning for requests slower than 500ms
+//! router.layer(Trace::new().slow_threshold(Duration::from_millis(500)));
+//! ```
+//!
+
+use crate::events::TARGET;
+use crate::middleware::{Middleware, Next};
+use crate::server::{Request, Response};
+use std::time::{Duration, Instant};
+
+///
+/// Default latency above which a request is reported as slow.
+///
+const DEFAULT_SLOW_THRESHOLD: Duration = Duration::from_secs(1);
+
+///
+/// [Middleware] instrumenting every request.
+///
+/// With the `tracing` feature, each request runs inside a `request` span with `method`
+/// and `path` fields, `status` and `latency_ms` being recorded once the handler
+/// answers. With the `log` feature, a record is logged per request with the same
+/// fields. Either way, requests slower than [Trace::slow_threshold] are reported at
+/// warning level.
+///
+/// Parse errors and handler panics are reported by [crate::server::Server::serve]
+/// itself under the same `http_rs` target, as they never reach the middleware.
+///
+/// Install it as the first layer so the span covers the whole chain.
+///
+#[derive(Debug, Clone)]
+pub struct Trace {
+    slow_threshold: Duration,
+}
+
+impl Trace {
+    ///
+    /// Creates the instrumentation [Middleware].
+    ///
+    pub fn new() -> Trace {
+        Trace {
+            slow_threshold: DEFAULT_SLOW_THRESHOLD,
+        }
+    }
+
+    ///
+    /// Sets the latency above which a request is reported as slow.
+    ///
+    /// Defaults to 1 second.
+    ///
+    pub fn slow_threshold(mut self, threshold: Duration) -> Trace {
+        self.slow_threshold = threshold;
+
+        self
+    }
+}
+
+impl Default for Trace {
+    fn default() -> Trace {
+        Trace::new()
+    }
+}
+
+impl Middleware for Trace {
+    fn handle(&self, req: Request, next: Next<'_>) -> Response {
+        let method = req.method.to_string();
+        let path = req.route.clone();
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            target: TARGET,
+            "request",
+            method = %method,
+            path = %path,
+            status = tracing::field::Empty,
+            latency_ms = tracing::field::Empty,
+        );
+
+        #[cfg(feature = "tracing")]
+        let _entered = span.enter();
+
+        let start = Instant::now();
+        let res = next.run(req);
+        let latency = start.elapsed();
+
+        let status = res.status;
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        let slow = latency > self.slow_threshold;
+
+        #[cfg(feature = "tracing")]
+        {
+            span.record("status", status);
+            span.record("latency_ms", latency_ms);
+
+            if slow {
+                tracing::warn!(target: TARGET, "slow request");
+            } else {
+                tracing::info!(target: TARGET, "request completed");
+            }
+        }
+
+        #[cfg(feature = "log")]
+        {
+            let level = if slow {
+                log::Level::Warn
+            } else {
+                log::Level::Info
+            };
+
+            log::log!(
+                target: TARGET,
+                level,
+                "{} {} {} {:.3}ms{}",
+                method,
+                path,
+                status,
+                latency_ms,
+                if slow { " (slow)" } else { "" }
+            );
+        }
+
+        res
+    }
+}
+
+#[cfg(all(test, feature = "log"))]
+mod tests {
+    use super::*;
+    use crate::router::Router;
+    use crate::server::{test_request, HttpMethod};
+    use std::sync::Mutex;
+
+    static RECORDS: Mutex<Vec<(log::Level, String)>> = Mutex::new(Vec::new());
+
+    struct Capture;
+
+    impl log::Log for Capture {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.target() == TARGET
+        }
+
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                let line = record.args().to_string();
+                RECORDS.lock().unwrap().push((record.level(), line));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn test_trace_log_records() {
+        log::set_logger(&Capture).unwrap();
+        log::set_max_level(log::LevelFilter::Info);
+
+        let mut router = Router::new();
+
+        router
+            .layer(Trace::new().slow_threshold(Duration::from_millis(20)))
+            .get("/fast", |_| Response::new(204))
+            .get("/slow", |_| {
+                std::thread::sleep(Duration::from_millis(40));
+                Response::new(200)
+            });
+
+        router.handle(test_request(HttpMethod::GET, "/fast"));
+        router.handle(test_request(HttpMethod::GET, "/slow"));
+
+        // Other tests log concurrently through the same global logger
+        let records: Vec<_> = RECORDS
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, line)| line.starts_with("GET /fast") || line.starts_with("GET /slow"))
+            .cloned()
+            .collect();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].0, log::Level::Info);
+        assert!(records[0].1.starts_with("GET /fast 204 "));
+        assert_eq!(records[1].0, log::Level::Warn);
+        assert!(records[1].1.starts_with("GET /slow 200 "));
+        assert!(records[1].1.ends_with("ms (slow)"));
+    }
+}
@@ -0,0 +1,166 @@
+//!
+//! `XML` request and response bodies through serde, enabled with the `xml` feature.
+//!
+//! # Example
+//!
+//! ```rust, no_run
+//! use http_rs::server::{Request, Response};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Invoice {
+//!     #[serde(rename = "@id")]
+//!     id: u32,
+//!     amount: f64,
+//! }
+//!
+//! // `<Invoice id="7"><amount>12.5</amount></Invoice>` in, the same out
+//! fn handler(req: Request) -> Response {
+//!     match req.get_xml::<Invoice>() {
+//!         Ok(invoice) => Response::new(200).xml(&invoice),
+//!         Err(e) => e.into(),
+//!     }
+//! }
+//! ```
+//!
+
+use crate::{
+    error::{Error, Result},
+    extract::{media_type, FromRequest},
+    response::IntoResponse,
+    server::{Request, Response},
+};
+use serde::{Deserialize, Serialize};
+
+///
+/// An `XML` body deserialized into `T`, requiring `Content-Type: application/xml` (or
+/// `text/xml`, or a `+xml` type such as `application/soap+xml`), and sent as
+/// `application/xml` when returned from a handler.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Xml<T>(pub T);
+
+///
+/// Whether a media type is one of the `XML` ones.
+///
+fn is_xml(media_type: &str) -> bool {
+    let media_type = media_type.to_ascii_lowercase();
+
+    media_type == "application/xml"
+        || media_type == "text/xml"
+        || (media_type.starts_with("application/") && media_type.ends_with("+xml"))
+}
+
+impl<T: for<'a> Deserialize<'a>> FromRequest for Xml<T> {
+    fn from_request(req: &Request) -> Result<Xml<T>> {
+        if !media_type(req).is_some_and(is_xml) {
+            return Err(Error::UnsupportedMediaType("application/xml"));
+        }
+
+        req.get_xml().map(Xml)
+    }
+}
+
+impl<T: Serialize> IntoResponse for Xml<T> {
+    fn into_response(self) -> Response {
+        Response::new(200).xml(&self.0)
+    }
+}
+
+impl Request {
+    ///
+    /// Deserializes the [Request] body as `XML` into `T`, whatever its `Content-Type`.
+    ///
+    /// Attributes map to fields renamed with an `@` prefix (e.g.,
+    /// `#[serde(rename = "@id")]`) and text content to a `$text` field, see
+    /// [quick_xml::de].
+    ///
+    /// # Parameters
+    ///
+    /// * `T` -> The type to deserialize the `XML` into. **Must implement Deserialize.**
+    ///
+    /// # Returns
+    ///
+    /// * `Result<T>` -> The parsed value, or [Error::Xml] which converts into a `400`
+    ///   [Response]
+    ///
+    pub fn get_xml<T: for<'a> Deserialize<'a>>(&self) -> Result<T> {
+        Ok(quick_xml::de::from_reader(&self.body[..])?)
+    }
+}
+
+impl Response {
+    ///
+    /// Sets the [Response] body as `XML`, with an `<?xml ...?>` declaration, and returns
+    /// the modified response.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` -> Data to be serialized to `XML`, the root element named after its
+    ///   type. **Must implement Serialize.**
+    ///
+    /// # Returns
+    ///
+    /// Modified [Response] with an `application/xml; charset=utf-8` body, empty if `data`
+    /// can't be represented as `XML` (e.g., a bare sequence)
+    ///
+    pub fn xml<T: Serialize>(self, data: &T) -> Response {
+        let body = quick_xml::se::to_string(data)
+            .map(|xml| format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>{}", xml))
+            .unwrap_or_default();
+
+        self.bytes("application/xml; charset=utf-8", body.into_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Xml;
+    use crate::{
+        error::Error,
+        server::{test_request, HttpMethod, Response},
+    };
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Invoice {
+        #[serde(rename = "@id")]
+        id: u32,
+        amount: f64,
+    }
+
+    #[test]
+    fn test_xml_round_trip() {
+        let invoice = Invoice {
+            id: 7,
+            amount: 12.5,
+        };
+
+        let res = Response::new(200).xml(&invoice);
+        assert_eq!(
+            res.headers.get("Content-Type"),
+            Some("application/xml; charset=utf-8")
+        );
+
+        let mut req = test_request(HttpMethod::POST, "/invoices");
+        req.body = res.body.as_bytes().unwrap().to_vec();
+        assert_eq!(
+            String::from_utf8_lossy(&req.body),
+            r#"<?xml version="1.0" encoding="UTF-8"?><Invoice id="7"><amount>12.5</amount></Invoice>"#
+        );
+        assert_eq!(req.get_xml::<Invoice>().unwrap(), invoice);
+
+        assert!(matches!(
+            req.extract::<Xml<Invoice>>(),
+            Err(Error::UnsupportedMediaType(_))
+        ));
+
+        req.headers.insert("Content-Type", "application/soap+xml");
+        assert_eq!(req.extract::<Xml<Invoice>>().unwrap().0, invoice);
+
+        req.body = b"<Invoice id=\"x\"/>".to_vec();
+        let err = req.get_xml::<Invoice>().unwrap_err();
+        assert!(matches!(err, Error::Xml(_)));
+        assert_eq!(Response::from(err).status, 400);
+    }
+}